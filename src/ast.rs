@@ -58,13 +58,18 @@ pub enum Statement {
         attributes: Vec<Attribute>,
         /// Generic type parameters: `fn foo<T, U>()` or `fn foo<T: Trait>()`
         type_params: Vec<TypeParam>,
+        /// `async fn`: calls return a task handle instead of running inline
+        is_async: bool,
     },
 
     /// Type alias declaration: `type Name = Type;`
+    /// or refinement type: `type Port = Int where self > 0 && self < 65536`
     TypeAlias {
         name: String,
         type_params: Vec<TypeParam>,
         target: TypeExpr,
+        /// Predicate over `self` that values of this type must satisfy
+        refinement: Option<Expression>,
     },
 
     /// Struct declaration
@@ -236,6 +241,12 @@ pub enum Expression {
         index: Box<Expression>,
     },
 
+    /// Optional chaining: `obj?.field`, `obj?.[index]`, `obj?.method(args)`
+    OptionalChain {
+        object: Box<Expression>,
+        access: ChainAccess,
+    },
+
     /// Array literal
     Array(Vec<Expression>),
 
@@ -303,6 +314,17 @@ pub enum Expression {
     Try(Box<Expression>),
 }
 
+/// The access performed by an optional chaining step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ChainAccess {
+    Field(String),
+    Index(Box<Expression>),
+    Method {
+        method: String,
+        arguments: Vec<Expression>,
+    },
+}
+
 /// Binary operators
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum BinaryOp {
@@ -371,6 +393,8 @@ pub enum StringPart {
     Literal(String),
     /// Expression to interpolate
     Expr(Expression),
+    /// Expression with a format spec (`{price:.2}`), see `crate::format`
+    Formatted { expr: Expression, spec: String },
 }
 
 /// Template string part (triple-quoted strings with {{}} syntax)
@@ -508,6 +532,25 @@ pub enum Pattern {
 
     /// Array pattern
     Array(Vec<Pattern>),
+
+    /// Or-pattern `1 | 2 | 3`
+    Or(Vec<Pattern>),
+
+    /// Range pattern `200..299` or `200..=299`
+    Range {
+        start: Expression,
+        end: Expression,
+        inclusive: bool,
+    },
+
+    /// Binding pattern `name @ pattern`
+    Binding { name: String, pattern: Box<Pattern> },
+
+    /// Map pattern `{ "type": "card", "amount": a }` (extra keys are allowed)
+    Map(Vec<(String, Pattern)>),
+
+    /// Rest element inside an array pattern: `..` or `..name`
+    Rest(Option<String>),
 }
 
 /// Attribute/annotation
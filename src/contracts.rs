@@ -13,11 +13,19 @@
 //!
 //! - `result`: Refers to the return value in postconditions
 //! - `old(expr)`: Refers to the value of an expression before function execution
+//!
+//! # Enforcement Modes
+//!
+//! Each kind of clause can be set to `enforce`, `log`, `sample(N%)` or `off`
+//! (see [`ContractConfig`]), so production deployments can trade strictness
+//! for cost while still counting how often contracts fire.
 
 use crate::ast::Expression;
 use crate::error::{IntentError, Result};
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
 /// A contract specification containing all contract clauses for a function
 #[derive(Debug, Clone)]
@@ -115,6 +123,157 @@ pub struct ContractResult {
     pub actual_value: Option<String>,
 }
 
+/// Detailed, power-assert style report of a failed contract clause
+///
+/// Captures the runtime value of every sub-expression of the failing
+/// condition, the arguments the function was called with, and any `old()`
+/// snapshots, so the failure can be explained without re-running the code.
+#[derive(Debug, Clone)]
+pub struct ContractViolation {
+    pub clause_type: ContractType,
+    /// Function (or struct, for invariants) the clause belongs to
+    pub owner: String,
+    /// The failing condition as source code
+    pub condition: String,
+    /// Sub-expressions of the condition with their values, outermost first
+    pub values: Vec<(String, String)>,
+    /// Function arguments as (parameter, value) pairs
+    pub arguments: Vec<(String, String)>,
+    /// Values captured by `old()` before the function body ran
+    pub old_values: Vec<(String, String)>,
+}
+
+impl ContractViolation {
+    pub fn new(clause_type: ContractType, owner: &str, condition: &str) -> Self {
+        ContractViolation {
+            clause_type,
+            owner: owner.to_string(),
+            condition: condition.to_string(),
+            values: Vec::new(),
+            arguments: Vec::new(),
+            old_values: Vec::new(),
+        }
+    }
+
+    /// One-line summary, e.g. `Postcondition failed in 'divide': result * b == a`
+    pub fn headline(&self) -> String {
+        match self.clause_type {
+            ContractType::Precondition => {
+                format!(
+                    "Precondition failed in '{}': {}",
+                    self.owner, self.condition
+                )
+            }
+            ContractType::Postcondition => {
+                format!(
+                    "Postcondition failed in '{}': {}",
+                    self.owner, self.condition
+                )
+            }
+            ContractType::Invariant => {
+                format!(
+                    "Invariant violated for '{}': {}",
+                    self.owner, self.condition
+                )
+            }
+        }
+    }
+
+    /// Multi-line breakdown of sub-expression values, arguments and old() snapshots
+    pub fn details(&self) -> String {
+        let mut lines = Vec::new();
+        for (expr, value) in &self.values {
+            lines.push(format!("  {} = {}", expr, value));
+        }
+        if !self.arguments.is_empty() {
+            let args: Vec<String> = self
+                .arguments
+                .iter()
+                .map(|(name, value)| format!("{} = {}", name, value))
+                .collect();
+            lines.push(format!("  arguments: {}", args.join(", ")));
+        }
+        if !self.old_values.is_empty() {
+            let olds: Vec<String> = self
+                .old_values
+                .iter()
+                .map(|(expr, value)| format!("{} = {}", expr, value))
+                .collect();
+            lines.push(format!("  old: {}", olds.join(", ")));
+        }
+        lines.join("\n")
+    }
+
+    /// JSON representation used in HTTP error bodies and intent check output
+    pub fn to_json(&self) -> serde_json::Value {
+        let pairs = |items: &[(String, String)], key: &str| {
+            items
+                .iter()
+                .map(|(k, v)| serde_json::json!({ key: k, "value": v }))
+                .collect::<Vec<_>>()
+        };
+        serde_json::json!({
+            "kind": self.clause_type.to_string().to_lowercase(),
+            "function": self.owner,
+            "condition": self.condition,
+            "values": pairs(&self.values, "expression"),
+            "arguments": pairs(&self.arguments, "name"),
+            "old": pairs(&self.old_values, "expression"),
+        })
+    }
+
+    /// Render the `contract` object of an HTTP error body (see [`ContractViolation::to_json`])
+    /// as the same multi-line breakdown produced by [`ContractViolation::details`]
+    pub fn details_from_json(contract: &serde_json::Value) -> Option<String> {
+        let condition = contract.get("condition")?.as_str()?;
+        let section = |key: &str, label: &str| -> Vec<String> {
+            contract
+                .get(key)
+                .and_then(|v| v.as_array())
+                .map(|items| {
+                    items
+                        .iter()
+                        .map(|item| {
+                            format!(
+                                "{} = {}",
+                                item.get(label).and_then(|v| v.as_str()).unwrap_or_default(),
+                                item.get("value")
+                                    .and_then(|v| v.as_str())
+                                    .unwrap_or_default()
+                            )
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let mut lines = vec![format!("Contract: {}", condition)];
+        for line in section("values", "expression") {
+            lines.push(format!("  {}", line));
+        }
+        let args = section("arguments", "name");
+        if !args.is_empty() {
+            lines.push(format!("  arguments: {}", args.join(", ")));
+        }
+        let olds = section("old", "expression");
+        if !olds.is_empty() {
+            lines.push(format!("  old: {}", olds.join(", ")));
+        }
+        Some(lines.join("\n"))
+    }
+}
+
+impl fmt::Display for ContractViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let details = self.details();
+        if details.is_empty() {
+            write!(f, "{}", self.headline())
+        } else {
+            write!(f, "{}\n{}", self.headline(), details)
+        }
+    }
+}
+
 /// Type of contract clause
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ContractType {
@@ -133,44 +292,291 @@ impl fmt::Display for ContractType {
     }
 }
 
+/// How violations of one kind of contract clause are handled at runtime
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnforcementMode {
+    /// Evaluate every clause; a violation is an error (HTTP 400/500)
+    Enforce,
+    /// Evaluate every clause; a violation emits a structured warning and execution continues
+    Log,
+    /// Evaluate roughly N% of clauses; violations are logged like `Log`
+    Sample(u8),
+    /// Do not evaluate clauses of this kind
+    Off,
+}
+
+impl std::str::FromStr for EnforcementMode {
+    type Err = String;
+
+    /// Parse `enforce`, `log`, `off` or `sample(N%)` (the `%` is optional)
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        match s.as_str() {
+            "enforce" => Ok(EnforcementMode::Enforce),
+            "log" => Ok(EnforcementMode::Log),
+            "off" => Ok(EnforcementMode::Off),
+            _ => {
+                let pct = s
+                    .strip_prefix("sample(")
+                    .and_then(|rest| rest.strip_suffix(')'))
+                    .map(|n| n.trim().trim_end_matches('%').trim())
+                    .and_then(|n| n.parse::<u8>().ok())
+                    .filter(|n| *n <= 100);
+                match pct {
+                    Some(n) => Ok(EnforcementMode::Sample(n)),
+                    None => Err(format!(
+                        "invalid contract mode '{}' (expected enforce, log, sample(N%) or off)",
+                        s
+                    )),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for EnforcementMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnforcementMode::Enforce => write!(f, "enforce"),
+            EnforcementMode::Log => write!(f, "log"),
+            EnforcementMode::Sample(n) => write!(f, "sample({}%)", n),
+            EnforcementMode::Off => write!(f, "off"),
+        }
+    }
+}
+
+/// Contract enforcement configuration, one mode per clause kind
+///
+/// Read from the `[contracts]` table of `ntnt.toml`:
+///
+/// ```toml
+/// [contracts]
+/// requires = "enforce"
+/// ensures = "sample(10%)"
+/// invariant = "log"
+/// metrics_path = "/_ntnt/contracts"
+/// ```
+///
+/// With `NTNT_ENV=production` the defaults keep preconditions enforced (they
+/// validate client input) but only log postcondition and invariant failures.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractConfig {
+    pub requires: EnforcementMode,
+    pub ensures: EnforcementMode,
+    pub invariant: EnforcementMode,
+    /// Path on the HTTP server that reports contract statistics as JSON
+    pub metrics_path: Option<String>,
+}
+
+impl ContractConfig {
+    /// Development defaults: every clause is enforced
+    pub fn development() -> Self {
+        ContractConfig {
+            requires: EnforcementMode::Enforce,
+            ensures: EnforcementMode::Enforce,
+            invariant: EnforcementMode::Enforce,
+            metrics_path: None,
+        }
+    }
+
+    /// Production defaults: preconditions enforced, everything else logged
+    pub fn production() -> Self {
+        ContractConfig {
+            requires: EnforcementMode::Enforce,
+            ensures: EnforcementMode::Log,
+            invariant: EnforcementMode::Log,
+            metrics_path: None,
+        }
+    }
+
+    /// Defaults for the current `NTNT_ENV`
+    pub fn from_env() -> Self {
+        let is_production = std::env::var("NTNT_ENV")
+            .map(|v| v == "production" || v == "prod")
+            .unwrap_or(false);
+        if is_production {
+            Self::production()
+        } else {
+            Self::development()
+        }
+    }
+
+    /// Apply the `[contracts]` table of a parsed `ntnt.toml` on top of these defaults
+    pub fn apply_toml(&mut self, config: &toml::Value) -> Result<()> {
+        let Some(table) = config.get("contracts") else {
+            return Ok(());
+        };
+        for (key, mode) in [
+            ("requires", &mut self.requires),
+            ("ensures", &mut self.ensures),
+            ("invariant", &mut self.invariant),
+        ] {
+            if let Some(value) = table.get(key).and_then(|v| v.as_str()) {
+                *mode = value.parse().map_err(|e| {
+                    IntentError::RuntimeError(format!("ntnt.toml [contracts] {}: {}", key, e))
+                })?;
+            }
+        }
+        if let Some(path) = table.get("metrics_path").and_then(|v| v.as_str()) {
+            self.metrics_path = Some(path.to_string());
+        }
+        Ok(())
+    }
+
+    /// The mode for a kind of clause
+    pub fn mode(&self, kind: ContractType) -> EnforcementMode {
+        match kind {
+            ContractType::Precondition => self.requires,
+            ContractType::Postcondition => self.ensures,
+            ContractType::Invariant => self.invariant,
+        }
+    }
+}
+
+impl Default for ContractConfig {
+    fn default() -> Self {
+        Self::development()
+    }
+}
+
+/// Counters for one kind of contract clause
+#[derive(Debug, Default)]
+pub struct ClauseMetrics {
+    /// Clauses evaluated
+    pub checks: AtomicU64,
+    /// Clauses not evaluated because the mode is `off` or the call was not sampled
+    pub skipped: AtomicU64,
+    /// Clauses that evaluated to false
+    pub violations: AtomicU64,
+}
+
+impl ClauseMetrics {
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "checks": self.checks.load(Ordering::Relaxed),
+            "skipped": self.skipped.load(Ordering::Relaxed),
+            "violations": self.violations.load(Ordering::Relaxed),
+        })
+    }
+
+    fn reset(&self) {
+        self.checks.store(0, Ordering::Relaxed);
+        self.skipped.store(0, Ordering::Relaxed);
+        self.violations.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Thread-safe contract statistics, shared with the HTTP server's metrics endpoint
+#[derive(Debug, Default)]
+pub struct ContractMetrics {
+    pub requires: ClauseMetrics,
+    pub ensures: ClauseMetrics,
+    pub invariant: ClauseMetrics,
+}
+
+impl ContractMetrics {
+    pub fn clause(&self, kind: ContractType) -> &ClauseMetrics {
+        match kind {
+            ContractType::Precondition => &self.requires,
+            ContractType::Postcondition => &self.ensures,
+            ContractType::Invariant => &self.invariant,
+        }
+    }
+
+    /// Total (checks, violations) across all clause kinds
+    pub fn totals(&self) -> (usize, usize) {
+        let all = [&self.requires, &self.ensures, &self.invariant];
+        let checks: u64 = all.iter().map(|m| m.checks.load(Ordering::Relaxed)).sum();
+        let violations: u64 = all
+            .iter()
+            .map(|m| m.violations.load(Ordering::Relaxed))
+            .sum();
+        (checks as usize, violations as usize)
+    }
+
+    /// JSON body served on the metrics endpoint
+    pub fn to_json(&self, config: &ContractConfig) -> serde_json::Value {
+        let (checks, violations) = self.totals();
+        serde_json::json!({
+            "checks": checks,
+            "violations": violations,
+            "requires": { "mode": config.requires.to_string(), "stats": self.requires.to_json() },
+            "ensures": { "mode": config.ensures.to_string(), "stats": self.ensures.to_json() },
+            "invariant": { "mode": config.invariant.to_string(), "stats": self.invariant.to_json() },
+        })
+    }
+}
+
 /// Contract checker for runtime verification
 pub struct ContractChecker {
     /// Named contracts that can be referenced
     contracts: HashMap<String, ContractSpec>,
 
-    /// Whether contract checking is enabled
-    enabled: bool,
+    /// Enforcement mode per clause kind
+    config: ContractConfig,
 
     /// Callback for approval requests
     #[allow(clippy::type_complexity)]
     approval_handler: Option<Box<dyn Fn(&str) -> bool>>,
 
-    /// Contract violation count for statistics
-    violation_count: usize,
-
-    /// Contract check count for statistics
-    check_count: usize,
+    /// Check and violation counts for statistics
+    metrics: Arc<ContractMetrics>,
 }
 
 impl ContractChecker {
     pub fn new() -> Self {
         ContractChecker {
             contracts: HashMap::new(),
-            enabled: true,
+            config: ContractConfig::default(),
             approval_handler: None,
-            violation_count: 0,
-            check_count: 0,
+            metrics: Arc::new(ContractMetrics::default()),
         }
     }
 
     /// Enable or disable contract checking
+    ///
+    /// Enabling enforces every kind of clause; disabling turns them all off.
     pub fn set_enabled(&mut self, enabled: bool) {
-        self.enabled = enabled;
+        let mode = if enabled {
+            EnforcementMode::Enforce
+        } else {
+            EnforcementMode::Off
+        };
+        self.config.requires = mode;
+        self.config.ensures = mode;
+        self.config.invariant = mode;
     }
 
-    /// Check if contract checking is enabled
+    /// Check if contract checking is enabled for any kind of clause
     pub fn is_enabled(&self) -> bool {
-        self.enabled
+        [
+            self.config.requires,
+            self.config.ensures,
+            self.config.invariant,
+        ]
+        .iter()
+        .any(|m| *m != EnforcementMode::Off)
+    }
+
+    /// Replace the enforcement configuration
+    pub fn set_config(&mut self, config: ContractConfig) {
+        self.config = config;
+    }
+
+    /// Current enforcement configuration
+    pub fn config(&self) -> &ContractConfig {
+        &self.config
+    }
+
+    /// Shared statistics handle (for the metrics endpoint)
+    pub fn metrics(&self) -> Arc<ContractMetrics> {
+        Arc::clone(&self.metrics)
+    }
+
+    /// Record statistics into an existing handle (pool workers share the
+    /// first interpreter's metrics endpoint)
+    pub fn share_metrics(&mut self, metrics: Arc<ContractMetrics>) {
+        self.metrics = metrics;
     }
 
     /// Register a named contract
@@ -200,28 +606,85 @@ impl ContractChecker {
         }
     }
 
-    /// Verify a precondition and return a detailed result
-    pub fn check_precondition(
+    /// Decide whether a clause of this kind should be evaluated on this call
+    ///
+    /// Counts the clause as checked or skipped.
+    pub fn should_check(&mut self, kind: ContractType) -> bool {
+        let check = match self.config.mode(kind) {
+            EnforcementMode::Enforce | EnforcementMode::Log => true,
+            EnforcementMode::Sample(pct) => rand::random::<f64>() * 100.0 < pct as f64,
+            EnforcementMode::Off => false,
+        };
+        let metrics = self.metrics.clause(kind);
+        if check {
+            metrics.checks.fetch_add(1, Ordering::Relaxed);
+        } else {
+            metrics.skipped.fetch_add(1, Ordering::Relaxed);
+        }
+        check
+    }
+
+    /// Record a failed clause and decide whether it should abort execution
+    ///
+    /// Returns `true` in `enforce` mode. In `log` and `sample` modes a structured
+    /// warning is written to stderr and `false` is returned.
+    pub fn report_violation(&mut self, violation: &ContractViolation) -> bool {
+        self.metrics
+            .clause(violation.clause_type)
+            .violations
+            .fetch_add(1, Ordering::Relaxed);
+
+        match self.config.mode(violation.clause_type) {
+            EnforcementMode::Enforce => true,
+            mode => {
+                let warning = serde_json::json!({
+                    "level": "warn",
+                    "event": "contract_violation",
+                    "mode": mode.to_string(),
+                    "message": violation.headline(),
+                    "contract": violation.to_json(),
+                });
+                eprintln!("{}", warning);
+                false
+            }
+        }
+    }
+
+    /// Check an already-evaluated clause against the configured mode
+    fn check_clause(
         &mut self,
+        kind: ContractType,
         condition: &str,
         result: bool,
         message: Option<&str>,
     ) -> Result<()> {
-        self.check_count += 1;
-
-        if !self.enabled {
+        if !self.should_check(kind) || result {
             return Ok(());
         }
 
-        if !result {
-            self.violation_count += 1;
-            let msg = message
-                .map(|s| s.to_string())
-                .unwrap_or_else(|| format!("Precondition failed: {}", condition));
-            return Err(IntentError::ContractViolation(msg));
+        let violation = ContractViolation::new(kind, "<anonymous>", condition);
+        if !self.report_violation(&violation) {
+            return Ok(());
         }
 
-        Ok(())
+        let msg = message
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| match kind {
+                ContractType::Precondition => format!("Precondition failed: {}", condition),
+                ContractType::Postcondition => format!("Postcondition failed: {}", condition),
+                ContractType::Invariant => format!("Invariant violated: {}", condition),
+            });
+        Err(IntentError::ContractViolation(msg))
+    }
+
+    /// Verify a precondition and return a detailed result
+    pub fn check_precondition(
+        &mut self,
+        condition: &str,
+        result: bool,
+        message: Option<&str>,
+    ) -> Result<()> {
+        self.check_clause(ContractType::Precondition, condition, result, message)
     }
 
     /// Verify a postcondition and return a detailed result
@@ -231,21 +694,7 @@ impl ContractChecker {
         result: bool,
         message: Option<&str>,
     ) -> Result<()> {
-        self.check_count += 1;
-
-        if !self.enabled {
-            return Ok(());
-        }
-
-        if !result {
-            self.violation_count += 1;
-            let msg = message
-                .map(|s| s.to_string())
-                .unwrap_or_else(|| format!("Postcondition failed: {}", condition));
-            return Err(IntentError::ContractViolation(msg));
-        }
-
-        Ok(())
+        self.check_clause(ContractType::Postcondition, condition, result, message)
     }
 
     /// Verify an invariant and return a detailed result
@@ -255,32 +704,19 @@ impl ContractChecker {
         result: bool,
         message: Option<&str>,
     ) -> Result<()> {
-        self.check_count += 1;
-
-        if !self.enabled {
-            return Ok(());
-        }
-
-        if !result {
-            self.violation_count += 1;
-            let msg = message
-                .map(|s| s.to_string())
-                .unwrap_or_else(|| format!("Invariant violated: {}", condition));
-            return Err(IntentError::ContractViolation(msg));
-        }
-
-        Ok(())
+        self.check_clause(ContractType::Invariant, condition, result, message)
     }
 
-    /// Get contract statistics
+    /// Get contract statistics as (checks, violations)
     pub fn stats(&self) -> (usize, usize) {
-        (self.check_count, self.violation_count)
+        self.metrics.totals()
     }
 
     /// Reset statistics
     pub fn reset_stats(&mut self) {
-        self.check_count = 0;
-        self.violation_count = 0;
+        self.metrics.requires.reset();
+        self.metrics.ensures.reset();
+        self.metrics.invariant.reset();
     }
 }
 
@@ -400,6 +836,107 @@ mod tests {
         assert_eq!(violations, 1);
     }
 
+    #[test]
+    fn test_enforcement_mode_parse() {
+        assert_eq!("enforce".parse(), Ok(EnforcementMode::Enforce));
+        assert_eq!("LOG".parse(), Ok(EnforcementMode::Log));
+        assert_eq!("off".parse(), Ok(EnforcementMode::Off));
+        assert_eq!("sample(10%)".parse(), Ok(EnforcementMode::Sample(10)));
+        assert_eq!("sample(25)".parse(), Ok(EnforcementMode::Sample(25)));
+        assert!("sample(150%)".parse::<EnforcementMode>().is_err());
+        assert!("strict".parse::<EnforcementMode>().is_err());
+    }
+
+    #[test]
+    fn test_contract_config_from_toml() {
+        let toml: toml::Value = r#"
+            [contracts]
+            ensures = "sample(5%)"
+            invariant = "off"
+            metrics_path = "/_ntnt/contracts"
+        "#
+        .parse()
+        .unwrap();
+        let mut config = ContractConfig::production();
+        config.apply_toml(&toml).unwrap();
+        assert_eq!(config.requires, EnforcementMode::Enforce);
+        assert_eq!(config.ensures, EnforcementMode::Sample(5));
+        assert_eq!(config.invariant, EnforcementMode::Off);
+        assert_eq!(config.metrics_path.as_deref(), Some("/_ntnt/contracts"));
+
+        let bad: toml::Value = "[contracts]\nrequires = \"maybe\"".parse().unwrap();
+        assert!(ContractConfig::default().apply_toml(&bad).is_err());
+    }
+
+    #[test]
+    fn test_contract_checker_modes() {
+        let mut checker = ContractChecker::new();
+        checker.set_config(ContractConfig {
+            requires: EnforcementMode::Log,
+            ensures: EnforcementMode::Sample(0),
+            invariant: EnforcementMode::Sample(100),
+            metrics_path: None,
+        });
+
+        // Log mode records the violation but does not fail
+        assert!(checker.check_precondition("x > 0", false, None).is_ok());
+        // sample(0%) never evaluates
+        assert!(checker.check_postcondition("r > 0", false, None).is_ok());
+        // sample(100%) always evaluates; sampled violations are logged
+        assert!(checker.check_invariant("y > 0", false, None).is_ok());
+
+        let metrics = checker.metrics();
+        assert_eq!(metrics.requires.violations.load(Ordering::Relaxed), 1);
+        assert_eq!(metrics.ensures.skipped.load(Ordering::Relaxed), 1);
+        assert_eq!(metrics.invariant.checks.load(Ordering::Relaxed), 1);
+        assert_eq!(checker.stats(), (2, 2));
+
+        let json = metrics.to_json(checker.config());
+        assert_eq!(json["requires"]["mode"], "log");
+        assert_eq!(json["violations"], 2);
+    }
+
+    #[test]
+    fn test_contract_violation_report() {
+        let mut violation =
+            ContractViolation::new(ContractType::Postcondition, "divide", "result * b == a");
+        violation
+            .values
+            .push(("result".to_string(), "3".to_string()));
+        violation.values.push(("b".to_string(), "0".to_string()));
+        violation.arguments.push(("a".to_string(), "7".to_string()));
+        violation.arguments.push(("b".to_string(), "0".to_string()));
+
+        assert_eq!(
+            violation.headline(),
+            "Postcondition failed in 'divide': result * b == a"
+        );
+        let text = violation.to_string();
+        assert!(text.contains("  result = 3"));
+        assert!(text.contains("  b = 0"));
+        assert!(text.contains("arguments: a = 7, b = 0"));
+        assert!(!text.contains("old:"));
+    }
+
+    #[test]
+    fn test_contract_violation_json_roundtrip() {
+        let mut violation = ContractViolation::new(ContractType::Precondition, "f", "x > 0");
+        violation.values.push(("x".to_string(), "-1".to_string()));
+        violation
+            .old_values
+            .push(("old(x)".to_string(), "-1".to_string()));
+
+        let json = violation.to_json();
+        assert_eq!(json["kind"], "precondition");
+        assert_eq!(json["values"][0]["expression"], "x");
+        assert_eq!(json["values"][0]["value"], "-1");
+
+        let details = ContractViolation::details_from_json(&json).unwrap();
+        assert!(details.starts_with("Contract: x > 0"));
+        assert!(details.contains("  x = -1"));
+        assert!(details.contains("old: old(x) = -1"));
+    }
+
     #[test]
     fn test_old_values() {
         let mut old = OldValues::new();
@@ -0,0 +1,545 @@
+//! Format specs for `format()` and string interpolation
+//!
+//! A spec is the text after `:` in a placeholder, as in `"{price:.2}"` or
+//! `format("{:>10} {:,}", name, count)`:
+//!
+//! ```text
+//! [[fill]align][+][#][0][width][,][.precision][type]
+//!
+//! align      <  left   >  right   ^  center (numbers default to right, everything else to left)
+//! +          always show the sign of a number
+//! #          0x / 0b / 0o prefix for the x, X, b and o types
+//! 0          pad numbers with zeros after the sign
+//! ,          group thousands: 1,234,567
+//! precision  digits after the decimal point
+//! type       x X (hex), b (binary), o (octal) for Ints; e (exponent), % (percent) for numbers
+//! ```
+//!
+//! `format()` templates use `{}` for the next argument and `{N}` for argument
+//! N (from 0), each optionally followed by `:spec`. Named values are
+//! formatted by interpolation instead: `"{total:,.2}"`. `{{` and `}}` are
+//! literal braces.
+
+use crate::interpreter::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+impl Align {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '<' => Some(Align::Left),
+            '>' => Some(Align::Right),
+            '^' => Some(Align::Center),
+            _ => None,
+        }
+    }
+}
+
+/// Presentation type (the last character of a spec)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatKind {
+    Hex,
+    UpperHex,
+    Binary,
+    Octal,
+    Exponent,
+    Percent,
+}
+
+impl FormatKind {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            'x' => Some(FormatKind::Hex),
+            'X' => Some(FormatKind::UpperHex),
+            'b' => Some(FormatKind::Binary),
+            'o' => Some(FormatKind::Octal),
+            'e' => Some(FormatKind::Exponent),
+            '%' => Some(FormatKind::Percent),
+            _ => None,
+        }
+    }
+
+    fn symbol(self) -> char {
+        match self {
+            FormatKind::Hex => 'x',
+            FormatKind::UpperHex => 'X',
+            FormatKind::Binary => 'b',
+            FormatKind::Octal => 'o',
+            FormatKind::Exponent => 'e',
+            FormatKind::Percent => '%',
+        }
+    }
+
+    fn is_radix(self) -> bool {
+        matches!(
+            self,
+            FormatKind::Hex | FormatKind::UpperHex | FormatKind::Binary | FormatKind::Octal
+        )
+    }
+}
+
+/// A parsed format spec
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormatSpec {
+    pub fill: Option<char>,
+    pub align: Option<Align>,
+    pub plus: bool,
+    pub alternate: bool,
+    pub zero: bool,
+    pub width: Option<usize>,
+    pub grouping: bool,
+    pub precision: Option<usize>,
+    pub kind: Option<FormatKind>,
+}
+
+impl FormatSpec {
+    /// Parse the text after the `:` of a placeholder
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let chars: Vec<char> = spec.chars().collect();
+        let mut result = FormatSpec::default();
+        let mut i = 0;
+
+        if chars.len() >= 2 && Align::from_char(chars[1]).is_some() {
+            result.fill = Some(chars[0]);
+            result.align = Align::from_char(chars[1]);
+            i = 2;
+        } else if let Some(align) = chars.first().and_then(|c| Align::from_char(*c)) {
+            result.align = Some(align);
+            i = 1;
+        }
+        if chars.get(i) == Some(&'+') {
+            result.plus = true;
+            i += 1;
+        }
+        if chars.get(i) == Some(&'#') {
+            result.alternate = true;
+            i += 1;
+        }
+        if chars.get(i) == Some(&'0') {
+            result.zero = true;
+            i += 1;
+        }
+        result.width = read_number(&chars, &mut i);
+        if chars.get(i) == Some(&',') {
+            result.grouping = true;
+            i += 1;
+        }
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+            result.precision =
+                Some(read_number(&chars, &mut i).ok_or("expected digits after '.'".to_string())?);
+        }
+        if let Some(kind) = chars.get(i).and_then(|c| FormatKind::from_char(*c)) {
+            result.kind = Some(kind);
+            i += 1;
+        }
+        if let Some(c) = chars.get(i) {
+            return Err(format!("unexpected '{}'", c));
+        }
+
+        match result.kind {
+            Some(kind) if kind.is_radix() && result.precision.is_some() => {
+                return Err(format!("precision cannot be used with '{}'", kind.symbol()))
+            }
+            Some(kind) if kind.is_radix() && result.grouping => {
+                return Err(format!("',' cannot be used with '{}'", kind.symbol()))
+            }
+            _ => {}
+        }
+        if result.alternate && !result.kind.is_some_and(FormatKind::is_radix) {
+            return Err("'#' needs one of the types x, X, b or o".to_string());
+        }
+        Ok(result)
+    }
+
+    /// The first part of the spec that only makes sense for numbers
+    pub fn numeric_feature(&self) -> Option<String> {
+        if let Some(kind) = self.kind {
+            Some(format!("type '{}'", kind.symbol()))
+        } else if self.grouping {
+            Some("','".to_string())
+        } else if self.plus {
+            Some("'+'".to_string())
+        } else if self.zero {
+            Some("'0'".to_string())
+        } else {
+            self.precision.map(|precision| format!("'.{}'", precision))
+        }
+    }
+
+    /// Whether the spec only accepts Ints (x, X, b, o)
+    pub fn requires_int(&self) -> bool {
+        self.kind.is_some_and(FormatKind::is_radix)
+    }
+
+    /// Format a value according to this spec
+    pub fn apply(&self, value: &Value) -> Result<String, String> {
+        match value {
+            Value::Int(n) => Ok(self.format_int(*n)),
+            Value::Float(f) if self.requires_int() => Err(format!(
+                "type '{}' requires an Int, got Float {}",
+                self.kind.map(FormatKind::symbol).unwrap_or('x'),
+                f
+            )),
+            Value::Float(f) => Ok(self.format_float(*f)),
+            other => {
+                if let Some(feature) = self.numeric_feature() {
+                    return Err(format!(
+                        "{} requires a number, got {}",
+                        feature,
+                        other.type_name()
+                    ));
+                }
+                Ok(self.pad(other.to_string(), Align::Left))
+            }
+        }
+    }
+
+    fn format_int(&self, n: i64) -> String {
+        let Some(kind) = self.kind.filter(|k| k.is_radix()) else {
+            if self.precision.is_some() || self.kind.is_some() {
+                return self.format_float(n as f64);
+            }
+            let mut digits = n.unsigned_abs().to_string();
+            if self.grouping {
+                digits = group_thousands(&digits);
+            }
+            return self.pad_number(self.sign(n < 0), "", digits);
+        };
+        let abs = n.unsigned_abs();
+        let (digits, prefix) = match kind {
+            FormatKind::Hex => (format!("{:x}", abs), "0x"),
+            FormatKind::UpperHex => (format!("{:X}", abs), "0x"),
+            FormatKind::Binary => (format!("{:b}", abs), "0b"),
+            _ => (format!("{:o}", abs), "0o"),
+        };
+        let prefix = if self.alternate { prefix } else { "" };
+        self.pad_number(self.sign(n < 0), prefix, digits)
+    }
+
+    fn format_float(&self, f: f64) -> String {
+        if !f.is_finite() {
+            let text = if f.is_nan() {
+                "NaN".to_string()
+            } else {
+                "inf".to_string()
+            };
+            return self.pad_number(self.sign(f < 0.0), "", text);
+        }
+        let abs = f.abs();
+        let digits = match self.kind {
+            Some(FormatKind::Percent) => {
+                format!("{:.*}%", self.precision.unwrap_or(0), abs * 100.0)
+            }
+            Some(FormatKind::Exponent) => match self.precision {
+                Some(p) => format!("{:.*e}", p, abs),
+                None => format!("{:e}", abs),
+            },
+            _ => match self.precision {
+                Some(p) => format!("{:.*}", p, abs),
+                None => Value::Float(abs).to_string(),
+            },
+        };
+        let digits = if self.grouping {
+            // Only the whole part before the decimal point (or exponent) is grouped
+            let split = digits
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(digits.len());
+            format!("{}{}", group_thousands(&digits[..split]), &digits[split..])
+        } else {
+            digits
+        };
+        // Don't show "-0.00" for values that round to zero
+        let negative = f < 0.0 && digits.chars().any(|c| c.is_ascii_digit() && c != '0');
+        self.pad_number(self.sign(negative), "", digits)
+    }
+
+    fn sign(&self, negative: bool) -> &'static str {
+        if negative {
+            "-"
+        } else if self.plus {
+            "+"
+        } else {
+            ""
+        }
+    }
+
+    fn pad_number(&self, sign: &str, prefix: &str, digits: String) -> String {
+        if self.zero && self.align.is_none() {
+            let len = sign.len() + prefix.len() + digits.chars().count();
+            let zeros = self.width.unwrap_or(0).saturating_sub(len);
+            return format!("{}{}{}{}", sign, prefix, "0".repeat(zeros), digits);
+        }
+        self.pad(format!("{}{}{}", sign, prefix, digits), Align::Right)
+    }
+
+    fn pad(&self, text: String, default_align: Align) -> String {
+        let len = text.chars().count();
+        let Some(width) = self.width.filter(|w| *w > len) else {
+            return text;
+        };
+        let fill = self.fill.unwrap_or(' ');
+        let padding = width - len;
+        let (left, right) = match self.align.unwrap_or(default_align) {
+            Align::Left => (0, padding),
+            Align::Right => (padding, 0),
+            Align::Center => (padding / 2, padding - padding / 2),
+        };
+        let repeat = |n: usize| std::iter::repeat_n(fill, n).collect::<String>();
+        format!("{}{}{}", repeat(left), text, repeat(right))
+    }
+}
+
+fn read_number(chars: &[char], i: &mut usize) -> Option<usize> {
+    let start = *i;
+    while chars.get(*i).is_some_and(|c| c.is_ascii_digit()) {
+        *i += 1;
+    }
+    if *i == start {
+        return None;
+    }
+    chars[start..*i].iter().collect::<String>().parse().ok()
+}
+
+fn group_thousands(digits: &str) -> String {
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+/// Which argument a template placeholder refers to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field {
+    /// `{}`: the argument after the previous `{}`
+    Next,
+    /// `{2}`: an argument by position
+    Index(usize),
+}
+
+/// A piece of a parsed `format()` template
+#[derive(Debug, Clone, PartialEq)]
+pub enum Piece {
+    Literal(String),
+    Placeholder { field: Field, spec: FormatSpec },
+}
+
+/// Parse a `format()` template into literal text and placeholders
+pub fn parse_template(template: &str) -> Result<Vec<Piece>, String> {
+    let mut pieces = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '}' => return Err("unmatched '}' (use '}}' for a literal brace)".to_string()),
+            '{' => {
+                let mut inner = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => inner.push(c),
+                        None => {
+                            return Err("unclosed '{' (use '{{' for a literal brace)".to_string())
+                        }
+                    }
+                }
+                let (name, spec) = match inner.split_once(':') {
+                    Some((name, spec)) => (name.trim(), spec),
+                    None => (inner.trim(), ""),
+                };
+                let field = if name.is_empty() {
+                    Field::Next
+                } else if name.chars().all(|c| c.is_ascii_digit()) {
+                    Field::Index(
+                        name.parse()
+                            .map_err(|_| format!("invalid index '{}'", name))?,
+                    )
+                } else {
+                    return Err(format!(
+                        "invalid placeholder '{{{}}}' (use {{}} or {{N}}; interpolate named values directly, e.g. \"{{total:.2}}\")",
+                        inner
+                    ));
+                };
+                let spec = FormatSpec::parse(spec).map_err(|e| {
+                    format!("invalid format spec '{}' in '{{{}}}': {}", spec, inner, e)
+                })?;
+                if !literal.is_empty() {
+                    pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                }
+                pieces.push(Piece::Placeholder { field, spec });
+            }
+            c => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        pieces.push(Piece::Literal(literal));
+    }
+    Ok(pieces)
+}
+
+/// How many arguments a template uses
+pub fn argument_count(pieces: &[Piece]) -> usize {
+    let mut next = 0;
+    let mut needed = 0;
+    for piece in pieces {
+        match piece {
+            Piece::Placeholder {
+                field: Field::Next, ..
+            } => {
+                next += 1;
+                needed = needed.max(next);
+            }
+            Piece::Placeholder {
+                field: Field::Index(i),
+                ..
+            } => needed = needed.max(i + 1),
+            Piece::Literal(_) => {}
+        }
+    }
+    needed
+}
+
+/// Fill a template with arguments. Every argument must be used.
+pub fn format_values(template: &str, args: &[Value]) -> Result<String, String> {
+    let pieces = parse_template(template)?;
+    let needed = argument_count(&pieces);
+    if args.len() != needed {
+        return Err(format!(
+            "the template uses {} argument{} but {} {} given",
+            needed,
+            if needed == 1 { "" } else { "s" },
+            args.len(),
+            if args.len() == 1 { "was" } else { "were" }
+        ));
+    }
+
+    let mut out = String::new();
+    let mut next = 0;
+    for piece in &pieces {
+        match piece {
+            Piece::Literal(text) => out.push_str(text),
+            Piece::Placeholder { field, spec } => {
+                let index = match field {
+                    Field::Next => {
+                        next += 1;
+                        next - 1
+                    }
+                    Field::Index(i) => *i,
+                };
+                let text = spec
+                    .apply(&args[index])
+                    .map_err(|e| format!("argument {} cannot be formatted: {}", index, e))?;
+                out.push_str(&text);
+            }
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fmt(spec: &str, value: Value) -> String {
+        FormatSpec::parse(spec).unwrap().apply(&value).unwrap()
+    }
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(fmt(".2", Value::Float(1.23456)), "1.23");
+        assert_eq!(fmt(".2", Value::Int(5)), "5.00");
+        assert_eq!(fmt(",", Value::Int(1234567)), "1,234,567");
+        assert_eq!(fmt(",", Value::Int(-999)), "-999");
+        assert_eq!(fmt(",.2", Value::Float(-1234567.891)), "-1,234,567.89");
+        assert_eq!(fmt("+", Value::Int(7)), "+7");
+        assert_eq!(fmt("08.3", Value::Float(-3.5)), "-003.500");
+        assert_eq!(fmt("x", Value::Int(255)), "ff");
+        assert_eq!(fmt("#06X", Value::Int(255)), "0x00FF");
+        assert_eq!(fmt("b", Value::Int(5)), "101");
+        assert_eq!(fmt(".1%", Value::Float(0.256)), "25.6%");
+        assert_eq!(fmt(".2e", Value::Float(1234.5)), "1.23e3");
+        assert_eq!(fmt(".1", Value::Float(-0.01)), "0.0");
+    }
+
+    #[test]
+    fn test_alignment() {
+        assert_eq!(fmt(">8", Value::String("abc".to_string())), "     abc");
+        assert_eq!(fmt("8", Value::String("abc".to_string())), "abc     ");
+        assert_eq!(fmt("8", Value::Int(42)), "      42");
+        assert_eq!(fmt("*^9", Value::String("mid".to_string())), "***mid***");
+        assert_eq!(fmt("<6,", Value::Int(1000)), "1,000 ");
+        assert_eq!(fmt(">3", Value::String("longer".to_string())), "longer");
+    }
+
+    #[test]
+    fn test_invalid_specs() {
+        assert!(FormatSpec::parse(".").is_err());
+        assert!(FormatSpec::parse("10q").is_err());
+        assert!(FormatSpec::parse(".2x").is_err());
+        assert!(FormatSpec::parse("#").is_err());
+        assert!(FormatSpec::parse(",b").is_err());
+        let spec = FormatSpec::parse(",").unwrap();
+        assert!(spec.apply(&Value::String("x".to_string())).is_err());
+        let spec = FormatSpec::parse(".2").unwrap();
+        assert!(spec.apply(&Value::String("str".to_string())).is_err());
+        let spec = FormatSpec::parse("x").unwrap();
+        assert!(spec.apply(&Value::Float(1.5)).is_err());
+    }
+
+    #[test]
+    fn test_templates() {
+        let args = [
+            Value::String("Widget".to_string()),
+            Value::Float(9.5),
+            Value::Int(12000),
+        ];
+        assert_eq!(
+            format_values("{:<8}|{:>7.2}|{:,}", &args).unwrap(),
+            "Widget  |   9.50|12,000"
+        );
+        assert_eq!(
+            format_values("{2} {0} {1:.1} {{literal}}", &args).unwrap(),
+            "12000 Widget 9.5 {literal}"
+        );
+    }
+
+    #[test]
+    fn test_template_errors() {
+        let one = [Value::Int(1)];
+        assert!(format_values("{} {}", &one)
+            .unwrap_err()
+            .contains("uses 2 arguments"));
+        assert!(format_values("none", &one)
+            .unwrap_err()
+            .contains("uses 0 arguments"));
+        assert!(format_values("{name}", &one)
+            .unwrap_err()
+            .contains("invalid placeholder"));
+        assert!(format_values("{", &one).unwrap_err().contains("unclosed"));
+        assert!(format_values("}", &one).unwrap_err().contains("unmatched"));
+        assert!(format_values("{:q}", &one)
+            .unwrap_err()
+            .contains("invalid format spec"));
+        let text = [Value::String("x".to_string())];
+        assert!(format_values("{:,}", &text)
+            .unwrap_err()
+            .contains("argument 0 cannot be formatted"));
+    }
+}
@@ -179,7 +179,9 @@ pub fn execute(primitive: &Primitive, ctx: &mut Context, port: u16) -> ExecuteRe
             value,
         } => execute_invariant_check(invariant_id, value, ctx),
 
-        Primitive::Check { op, path, expected } => do_execute_check(op, path, expected, ctx),
+        Primitive::Check { op, path, expected } => {
+            with_contract_details(do_execute_check(op, path, expected, ctx), ctx)
+        }
     }
 }
 
@@ -189,7 +191,9 @@ pub fn execute(primitive: &Primitive, ctx: &mut Context, port: u16) -> ExecuteRe
 /// data and you just need to verify assertions.
 pub fn execute_check(primitive: &Primitive, ctx: &Context) -> ExecuteResult {
     match primitive {
-        Primitive::Check { op, path, expected } => do_execute_check(op, path, expected, ctx),
+        Primitive::Check { op, path, expected } => {
+            with_contract_details(do_execute_check(op, path, expected, ctx), ctx)
+        }
         _ => ExecuteResult::fail(
             "Invalid primitive",
             "execute_check only handles Check primitives",
@@ -527,6 +531,30 @@ fn execute_read_file(path: &str, ctx: &mut Context) -> ExecuteResult {
     }
 }
 
+/// Append contract violation details to a failed check.
+///
+/// When a handler fails a contract, the server responds with a JSON body
+/// containing a `contract` object (see `ContractViolation::to_json`). Showing
+/// its sub-expression values next to the failed check explains *why* the
+/// request failed instead of just reporting the status code.
+fn with_contract_details(mut result: ExecuteResult, ctx: &Context) -> ExecuteResult {
+    if result.passed {
+        return result;
+    }
+    let details = ctx
+        .get_string("response.body")
+        .and_then(|body| serde_json::from_str::<serde_json::Value>(body).ok())
+        .and_then(|json| {
+            json.get("contract")
+                .and_then(crate::contracts::ContractViolation::details_from_json)
+        });
+    if let (Some(details), Some(message)) = (details, result.message.as_mut()) {
+        message.push('\n');
+        message.push_str(&details);
+    }
+    result
+}
+
 /// Execute a check against the context (internal implementation)
 fn do_execute_check(op: &CheckOp, path: &str, expected: &Value, ctx: &Context) -> ExecuteResult {
     let actual = ctx.get(path);
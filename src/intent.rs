@@ -2442,6 +2442,22 @@ pub fn run_assertions_ial(
         .collect()
 }
 
+/// Failure message for a status assertion, including contract violation
+/// details when the response body carries them
+fn status_failure_message(expected: u16, status: u16, body: &str) -> String {
+    let message = format!("Expected status {}, got {}", expected, status);
+    let details = serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|json| {
+            json.get("contract")
+                .and_then(crate::contracts::ContractViolation::details_from_json)
+        });
+    match details {
+        Some(details) => format!("{}\n{}", message, details),
+        None => message,
+    }
+}
+
 /// Direct assertion execution (fallback when IAL vocabulary lookup fails)
 fn run_assertion_legacy(
     assertion: &Assertion,
@@ -2459,7 +2475,7 @@ fn run_assertion_legacy(
                 message: if passed {
                     None
                 } else {
-                    Some(format!("Expected status {}, got {}", expected, status))
+                    Some(status_failure_message(*expected, status, body))
                 },
             }
         }
@@ -4746,7 +4762,7 @@ fn run_assertions(
                     message: if passed {
                         None
                     } else {
-                        Some(format!("Expected status {}, got {}", expected, status))
+                        Some(status_failure_message(*expected, status, body))
                     },
                 }
             }
@@ -11,8 +11,15 @@
 //! - `result` to reference the return value in postconditions
 
 use crate::ast::*;
-use crate::contracts::{ContractChecker, OldValues, StoredValue};
+use crate::contracts::{
+    ContractChecker, ContractConfig, ContractType, ContractViolation, EnforcementMode, OldValues,
+    StoredValue,
+};
 use crate::error::{IntentError, Result};
+use crate::stdlib::concurrent::SerializedValue;
+use crate::stdlib::http_server::RouteOptions;
+use crate::stdlib::http_server_async::ListenOptions;
+use crate::stdlib::http_session;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
@@ -77,6 +84,10 @@ pub enum Value {
         closure: Rc<RefCell<Environment>>,
         contract: Option<FunctionContract>,
         type_params: Vec<TypeParam>,
+        /// Declared return type, checked against its refinement after each call
+        return_type: Option<TypeExpr>,
+        /// Calls run on a worker thread and return a task handle
+        is_async: bool,
     },
 
     /// Native/built-in function
@@ -130,6 +141,11 @@ impl Value {
         }
     }
 
+    /// Whether this is the `None` variant of `Option`
+    pub fn is_none(&self) -> bool {
+        matches!(self, Value::EnumValue { enum_name, variant, .. } if enum_name == "Option" && variant == "None")
+    }
+
     pub fn type_name(&self) -> &str {
         match self {
             Value::Unit => "Unit",
@@ -218,6 +234,8 @@ impl fmt::Display for Value {
 #[derive(Debug, Clone)]
 pub struct Environment {
     values: HashMap<String, Value>,
+    /// Declared types of annotated bindings, so reassignments can be checked
+    types: HashMap<String, TypeExpr>,
     parent: Option<Rc<RefCell<Environment>>>,
 }
 
@@ -225,6 +243,7 @@ impl Environment {
     pub fn new() -> Self {
         Environment {
             values: HashMap::new(),
+            types: HashMap::new(),
             parent: None,
         }
     }
@@ -232,14 +251,33 @@ impl Environment {
     pub fn with_parent(parent: Rc<RefCell<Environment>>) -> Self {
         Environment {
             values: HashMap::new(),
+            types: HashMap::new(),
             parent: Some(parent),
         }
     }
 
     pub fn define(&mut self, name: String, value: Value) {
+        self.types.remove(&name);
+        self.values.insert(name, value);
+    }
+
+    /// Define a binding declared with a type annotation (`let port: Port = 80`)
+    pub fn define_typed(&mut self, name: String, value: Value, type_expr: TypeExpr) {
+        self.types.insert(name.clone(), type_expr);
         self.values.insert(name, value);
     }
 
+    /// Declared type of the binding `name` resolves to, if it was annotated
+    pub fn declared_type(&self, name: &str) -> Option<TypeExpr> {
+        if self.values.contains_key(name) {
+            self.types.get(name).cloned()
+        } else if let Some(ref parent) = self.parent {
+            parent.borrow().declared_type(name)
+        } else {
+            None
+        }
+    }
+
     pub fn get(&self, name: &str) -> Option<Value> {
         if let Some(value) = self.values.get(name) {
             Some(value.clone())
@@ -261,6 +299,18 @@ impl Environment {
         }
     }
 
+    /// All visible bindings, with inner scopes shadowing outer ones
+    pub fn bindings(&self) -> HashMap<String, Value> {
+        let mut bindings = match self.parent {
+            Some(ref parent) => parent.borrow().bindings(),
+            None => HashMap::new(),
+        };
+        for (name, value) in &self.values {
+            bindings.insert(name.clone(), value.clone());
+        }
+        bindings
+    }
+
     pub fn keys(&self) -> Vec<String> {
         let mut keys: Vec<_> = self.values.keys().cloned().collect();
         if let Some(ref parent) = self.parent {
@@ -288,8 +338,15 @@ pub enum ExecutionMode {
     HotReload,
     /// Unit test mode - skip all server-related calls
     UnitTest,
+    /// Pool worker started by `listen(port, map { "workers": N })` - listen()
+    /// serves the worker's channel, schedules only run in the first interpreter
+    Worker,
 }
 
+/// Values of the calls made while evaluating a contract clause, keyed by the
+/// call expression, so a violation report can show them without calling again
+type ContractCalls = HashMap<*const Expression, Value>;
+
 /// The Intent interpreter
 pub struct Interpreter {
     environment: Rc<RefCell<Environment>>,
@@ -300,6 +357,8 @@ pub struct Interpreter {
     enums: HashMap<String, Vec<EnumVariant>>,
     /// Type aliases (alias -> target type expression)
     type_aliases: HashMap<String, TypeExpr>,
+    /// Refinement predicates for refined type aliases (alias -> predicate, declaring environment)
+    refinements: HashMap<String, (Expression, Rc<RefCell<Environment>>)>,
     /// Struct invariants
     struct_invariants: HashMap<String, Vec<Expression>>,
     /// Trait implementations: type_name -> list of trait names
@@ -312,6 +371,10 @@ pub struct Interpreter {
     current_old_values: Option<OldValues>,
     /// Current function's result value (used in postconditions)
     current_result: Option<Value>,
+    /// Call values recorded while a contract clause is being evaluated
+    contract_calls: Option<ContractCalls>,
+    /// Detailed report for the most recent contract violation (used for HTTP error bodies)
+    last_contract_violation: Option<ContractViolation>,
     /// Loaded modules cache
     loaded_modules: HashMap<String, HashMap<String, Value>>,
     /// Current file path (for relative imports)
@@ -332,6 +395,81 @@ pub struct Interpreter {
     execution_mode: ExecutionMode,
     /// Lib modules for file-based routing (stored for hot-reload)
     lib_modules: HashMap<String, HashMap<String, Value>>,
+    /// Top-level imports, replayed by worker interpreters so spawned code sees
+    /// properly resolved modules
+    task_imports: Vec<TaskImport>,
+    /// Nesting depth of file modules being evaluated (0 = main program)
+    module_depth: usize,
+    /// Owner ID for this interpreter's std/schedule jobs
+    scheduler_id: u64,
+    /// Callbacks of registered std/schedule jobs, by schedule ID
+    schedule_handlers: HashMap<u64, Value>,
+    /// std/jobs worker functions, by job name
+    job_workers: HashMap<String, Value>,
+    /// Schedule that polls the std/jobs queue once a worker is registered
+    jobs_poller: Option<u64>,
+    /// Channel served by listen() when running as a pool worker
+    pool_worker: Option<PoolWorker>,
+    /// Open WebSocket connections accepted by this interpreter, by connection ID
+    ws_connections: HashMap<u64, WsConnection>,
+    /// Sessions attached to the request being handled, saved once it has a response
+    active_sessions: Vec<u64>,
+    /// Prefix and middleware of each group() whose body is running, outermost first
+    route_groups: Vec<(String, Vec<Value>)>,
+}
+
+/// A WebSocket connection value and the callbacks registered for it
+struct WsConnection {
+    conn: Value,
+    on_message: Option<Value>,
+    on_close: Option<Value>,
+}
+
+/// Result of running the middleware chain on a request
+enum MiddlewareOutcome {
+    /// Continue with the (possibly modified) request
+    Continue(Value),
+    /// A middleware answered the request itself or failed
+    Respond(Value),
+}
+
+/// A streamed response body waiting to be produced
+struct PendingStream {
+    id: u64,
+    /// Function that writes the body, and the arguments it is called with
+    producer: Value,
+    args: Vec<Value>,
+}
+
+/// The bridge channel of an interpreter started as a pool worker
+struct PoolWorker {
+    index: usize,
+    rx: tokio::sync::mpsc::Receiver<crate::stdlib::http_bridge::BridgeMessage>,
+    routes: std::sync::Arc<crate::stdlib::http_server_async::AsyncServerState>,
+}
+
+/// An import statement recorded for replay in worker interpreters
+#[derive(Debug, Clone)]
+struct TaskImport {
+    file: Option<String>,
+    items: Vec<ImportItem>,
+    source: String,
+    alias: Option<String>,
+}
+
+/// Interpreter state sent to a worker thread to run an async task
+struct TaskSnapshot {
+    globals: Vec<(String, SerializedValue)>,
+    structs: HashMap<String, Vec<Field>>,
+    enums: HashMap<String, Vec<EnumVariant>>,
+    type_aliases: HashMap<String, TypeExpr>,
+    refinements: HashMap<String, Expression>,
+    struct_invariants: HashMap<String, Vec<Expression>>,
+    trait_implementations: HashMap<String, Vec<String>>,
+    trait_definitions: HashMap<String, TraitInfo>,
+    contract_config: ContractConfig,
+    current_file: Option<String>,
+    imports: Vec<TaskImport>,
 }
 
 /// Information about a trait definition
@@ -360,12 +498,15 @@ impl Interpreter {
             structs: HashMap::new(),
             enums: HashMap::new(),
             type_aliases: HashMap::new(),
+            refinements: HashMap::new(),
             struct_invariants: HashMap::new(),
             trait_implementations: HashMap::new(),
             trait_definitions: HashMap::new(),
             deferred_statements: Vec::new(),
             current_old_values: None,
             current_result: None,
+            contract_calls: None,
+            last_contract_violation: None,
             loaded_modules: HashMap::new(),
             current_file: None,
             server_state: crate::stdlib::http_server::ServerState::new(),
@@ -376,6 +517,16 @@ impl Interpreter {
             request_timeout_secs: 30,
             execution_mode: ExecutionMode::Normal,
             lib_modules: HashMap::new(),
+            task_imports: Vec::new(),
+            module_depth: 0,
+            scheduler_id: crate::stdlib::schedule::new_scheduler_id(),
+            schedule_handlers: HashMap::new(),
+            job_workers: HashMap::new(),
+            jobs_poller: None,
+            pool_worker: None,
+            ws_connections: HashMap::new(),
+            active_sessions: Vec::new(),
+            route_groups: Vec::new(),
         };
         interpreter.define_builtins();
         interpreter.define_builtin_types();
@@ -398,6 +549,16 @@ impl Interpreter {
         self.request_timeout_secs = seconds;
     }
 
+    /// Set how contract clauses are enforced (see `ContractConfig`)
+    pub fn set_contract_config(&mut self, config: ContractConfig) {
+        self.contracts.set_config(config);
+    }
+
+    /// Contract check and violation counts as (checks, violations)
+    pub fn contract_stats(&self) -> (usize, usize) {
+        self.contracts.stats()
+    }
+
     /// Set the execution mode for the interpreter
     pub fn set_execution_mode(&mut self, mode: ExecutionMode) {
         self.execution_mode = mode;
@@ -408,14 +569,23 @@ impl Interpreter {
         match self.execution_mode {
             ExecutionMode::Normal => false,
             ExecutionMode::HotReload => {
-                // In hot-reload, only skip listen() and on_shutdown()
-                matches!(name, "listen" | "on_shutdown")
+                // In hot-reload, only skip listen(), on_shutdown() and schedules,
+                // which are already registered
+                matches!(name, "listen" | "on_shutdown" | "every" | "after" | "cron")
             }
+            ExecutionMode::Worker => matches!(name, "every" | "after" | "cron"),
             ExecutionMode::UnitTest => {
                 // In unit test mode, skip all server-related functions
                 matches!(
                     name,
-                    "listen" | "serve_static" | "routes" | "use_middleware" | "on_shutdown"
+                    "listen"
+                        | "serve_static"
+                        | "routes"
+                        | "use_middleware"
+                        | "on_shutdown"
+                        | "every"
+                        | "after"
+                        | "cron"
                 )
             }
         }
@@ -496,6 +666,16 @@ impl Interpreter {
         self.main_source_mtime = std::fs::metadata(path).ok().and_then(|m| m.modified().ok());
     }
 
+    /// Modification time of the newest program file this interpreter loaded,
+    /// so pool workers reloading at once can tell whose routes are current
+    fn loaded_source_version(&self) -> Option<std::time::SystemTime> {
+        self.imported_files
+            .values()
+            .copied()
+            .chain(self.main_source_mtime)
+            .max()
+    }
+
     /// Check if any tracked source file needs reloading and reload if necessary
     /// Checks the main source file AND all imported files
     /// Returns true if reload happened, false otherwise
@@ -608,7 +788,7 @@ impl Interpreter {
         self.current_file = Some(file_path.clone());
 
         // Set hot-reload mode so listen() knows to skip re-binding
-        self.execution_mode = ExecutionMode::HotReload;
+        let previous_mode = std::mem::replace(&mut self.execution_mode, ExecutionMode::HotReload);
 
         // Re-evaluate the AST
         let result = match self.eval(&ast) {
@@ -633,8 +813,8 @@ impl Interpreter {
             }
         };
 
-        // Reset to normal mode
-        self.execution_mode = ExecutionMode::Normal;
+        // Restore the previous mode (Normal, or Worker for pool workers)
+        self.execution_mode = previous_mode;
         result
     }
 
@@ -670,6 +850,25 @@ impl Interpreter {
             },
         );
 
+        // format(template, args...) -> String, see crate::format
+        self.environment.borrow_mut().define(
+            "format".to_string(),
+            Value::NativeFunction {
+                name: "format".to_string(),
+                arity: 0,
+                func: |args| match args.split_first() {
+                    Some((Value::String(template), rest)) => {
+                        crate::format::format_values(template, rest)
+                            .map(Value::String)
+                            .map_err(|e| IntentError::RuntimeError(format!("format(): {}", e)))
+                    }
+                    _ => Err(IntentError::TypeError(
+                        "format() requires a template string as its first argument".to_string(),
+                    )),
+                },
+            },
+        );
+
         // Type function
         self.environment.borrow_mut().define(
             "type".to_string(),
@@ -1276,6 +1475,49 @@ impl Interpreter {
             );
         }
 
+        // ws(pattern, handler) - WebSocket route, registered like the HTTP routes
+        self.environment.borrow_mut().define(
+            "ws".to_string(),
+            Value::NativeFunction {
+                name: "ws".to_string(),
+                arity: 2,
+                func: |_args| {
+                    Err(IntentError::RuntimeError(
+                        "ws() must be called directly".to_string(),
+                    ))
+                },
+            },
+        );
+
+        // sse(pattern, handler) - Server-Sent Events route, answers GET requests
+        self.environment.borrow_mut().define(
+            "sse".to_string(),
+            Value::NativeFunction {
+                name: "sse".to_string(),
+                arity: 2,
+                func: |_args| {
+                    Err(IntentError::RuntimeError(
+                        "sse() must be called directly".to_string(),
+                    ))
+                },
+            },
+        );
+
+        // group(prefix, middleware, body) - routes registered by body get the
+        // prefix and middleware
+        self.environment.borrow_mut().define(
+            "group".to_string(),
+            Value::NativeFunction {
+                name: "group".to_string(),
+                arity: 3,
+                func: |_args| {
+                    Err(IntentError::RuntimeError(
+                        "group() must be called directly".to_string(),
+                    ))
+                },
+            },
+        );
+
         // new_server() - create a new server (resets routes)
         self.environment.borrow_mut().define(
             "new_server".to_string(),
@@ -1309,6 +1551,28 @@ impl Interpreter {
         source: &str,
         alias: Option<&str>,
     ) -> Result<Value> {
+        if self.module_depth == 0 {
+            let import = TaskImport {
+                file: self.current_file.clone(),
+                items: items.to_vec(),
+                source: source.to_string(),
+                alias: alias.map(str::to_string),
+            };
+            let already_recorded = self.task_imports.iter().any(|i| {
+                i.file == import.file
+                    && i.source == import.source
+                    && i.alias == import.alias
+                    && i.items.len() == import.items.len()
+                    && i.items
+                        .iter()
+                        .zip(&import.items)
+                        .all(|(a, b)| a.name == b.name && a.alias == b.alias)
+            });
+            if !already_recorded {
+                self.task_imports.push(import);
+            }
+        }
+
         // Check if it's a standard library module
         if source.starts_with("std/") {
             return self.import_std_module(items, source, alias);
@@ -1434,7 +1698,10 @@ impl Interpreter {
         self.define_builtin_types();
 
         // Evaluate the module
-        self.eval(&ast)?;
+        self.module_depth += 1;
+        let evaluated = self.eval(&ast);
+        self.module_depth -= 1;
+        evaluated?;
 
         // Collect exported items
         let mut module_exports: HashMap<String, Value> = HashMap::new();
@@ -1572,15 +1839,19 @@ impl Interpreter {
         // Scan routes directory recursively
         let routes = self.discover_routes(&base_dir, &base_dir, &lib_modules)?;
 
-        // Register all discovered routes with source info for hot-reload
+        // Register all discovered routes with source info for hot-reload,
+        // and the middleware of the `_middleware.tnt` files above them
+        let mut dir_middleware = HashMap::new();
         for (method, pattern, handler, file, imports) in &routes {
+            let middleware = self.subtree_middleware(&base_dir, file, &mut dir_middleware)?;
             self.server_state.add_route_with_source(
                 method,
                 pattern,
                 handler.clone(),
                 Some(file.clone()),
                 imports.clone(),
-            );
+                middleware,
+            )?;
             let import_count = imports.len();
             if import_count > 0 {
                 println!(
@@ -1595,6 +1866,53 @@ impl Interpreter {
         Ok(Value::Int(routes.len() as i64))
     }
 
+    /// Middleware of the `_middleware.tnt` files in the directories from the
+    /// routes directory down to a route file, outermost first
+    ///
+    /// `loaded` caches each directory's middleware (None if it has none).
+    fn subtree_middleware(
+        &mut self,
+        base_dir: &std::path::Path,
+        file: &str,
+        loaded: &mut HashMap<std::path::PathBuf, Option<Value>>,
+    ) -> Result<Vec<Value>> {
+        let mut dirs: Vec<&std::path::Path> = std::path::Path::new(file)
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(base_dir))
+            .collect();
+        dirs.reverse();
+
+        let mut middleware = Vec::new();
+        for dir in dirs {
+            if !loaded.contains_key(dir) {
+                let path = dir.join("_middleware.tnt");
+                let handler = if path.is_file() {
+                    let exports = self.load_module_exports(&path)?;
+                    let handler = exports
+                        .get("middleware")
+                        .or_else(|| exports.get("handler"))
+                        .cloned()
+                        .ok_or_else(|| {
+                            IntentError::RuntimeError(format!(
+                                "{} must define a 'middleware' function",
+                                path.display()
+                            ))
+                        })?;
+                    println!("  Loaded middleware: {}", path.display());
+                    Some(handler)
+                } else {
+                    None
+                };
+                loaded.insert(dir.to_path_buf(), handler);
+            }
+            if let Some(Some(handler)) = loaded.get(dir) {
+                middleware.push(handler.clone());
+            }
+        }
+        Ok(middleware)
+    }
+
     /// Load a module and return its exports
     fn load_module_exports(
         &mut self,
@@ -1686,6 +2004,12 @@ impl Interpreter {
                 // Recurse into subdirectory
                 let sub_routes = self.discover_routes(&path, base_dir, lib_modules)?;
                 routes.extend(sub_routes);
+            } else if path
+                .file_name()
+                .is_some_and(|name| name == "_middleware.tnt")
+            {
+                // Middleware for this subtree, applied when registering
+                continue;
             } else if path.extension().map(|e| e == "tnt").unwrap_or(false) {
                 // Process .tnt file
                 let file_routes = self.process_route_file(&path, base_dir, lib_modules)?;
@@ -1882,6 +2206,7 @@ impl Interpreter {
     /// - users/index.tnt → /users
     /// - users/[id].tnt → /users/{id}
     /// - api/products/[id]/reviews.tnt → /api/products/{id}/reviews
+    /// - docs/[...path].tnt → /docs/{*path}
     fn file_path_to_url_pattern(&self, path: &std::path::Path) -> String {
         let mut segments: Vec<String> = Vec::new();
 
@@ -1897,10 +2222,13 @@ impl Interpreter {
                     continue;
                 }
 
-                // Convert [param] to {param}
+                // Convert [param] to {param} and [...rest] to {*rest}
                 let segment = if segment.starts_with('[') && segment.ends_with(']') {
                     let param_name = &segment[1..segment.len() - 1];
-                    format!("{{{}}}", param_name)
+                    match param_name.strip_prefix("...") {
+                        Some(rest) => format!("{{*{}}}", rest),
+                        None => format!("{{{}}}", param_name),
+                    }
                 } else {
                     segment
                 };
@@ -1919,22 +2247,49 @@ impl Interpreter {
     /// Evaluate a program
     pub fn eval(&mut self, program: &Program) -> Result<Value> {
         let mut result = Value::Unit;
-        for stmt in &program.statements {
+        for (i, stmt) in program.statements.iter().enumerate() {
             result = self.eval_statement(stmt)?;
             // Unwrap return values at top level
             if let Value::Return(v) = result {
                 return Ok(*v);
             }
+            if i + 1 < program.statements.len() {
+                self.detach_discarded_task(stmt, &result);
+            }
         }
         Ok(result)
     }
 
+    /// Detach the task started by a bare `async fn` or `spawn` call whose handle is
+    /// thrown away (`notify(user)` as a statement), so the registry doesn't hold its
+    /// result for an await that never comes. Only called for values that are discarded,
+    /// never for the last statement of a body, which is its implicit return value.
+    fn detach_discarded_task(&self, stmt: &Statement, value: &Value) {
+        let Some(id) = crate::stdlib::concurrent::get_task_id(value) else {
+            return;
+        };
+        let Statement::Expression(Expression::Call { function, .. }) = stmt else {
+            return;
+        };
+        let Expression::Identifier(name) = function.as_ref() else {
+            return;
+        };
+        let starts_task = match self.environment.borrow().get(name) {
+            Some(Value::Function { is_async, .. }) => is_async,
+            Some(Value::NativeFunction { name, .. }) => name == "spawn",
+            _ => false,
+        };
+        if starts_task {
+            crate::stdlib::concurrent::detach_task(id);
+        }
+    }
+
     fn eval_statement(&mut self, stmt: &Statement) -> Result<Value> {
         match stmt {
             Statement::Let {
                 name,
                 mutable: _,
-                type_annotation: _,
+                type_annotation,
                 value,
                 pattern,
             } => {
@@ -1944,9 +2299,17 @@ impl Interpreter {
                     Value::Unit
                 };
 
+                if let (Some(ann), Some(_)) = (type_annotation, value) {
+                    self.check_refinement(ann, &val, &format!("variable '{}'", name))?;
+                }
+
                 // Handle pattern destructuring
                 if let Some(pat) = pattern {
                     self.bind_pattern(pat, &val)?;
+                } else if let Some(ann) = type_annotation {
+                    self.environment
+                        .borrow_mut()
+                        .define_typed(name.clone(), val, ann.clone());
                 } else {
                     self.environment.borrow_mut().define(name.clone(), val);
                 }
@@ -1957,20 +2320,29 @@ impl Interpreter {
                 name,
                 type_params: _,
                 target,
+                refinement,
             } => {
                 // Store type alias for later resolution
                 self.type_aliases.insert(name.clone(), target.clone());
+                // Refinement predicates are evaluated in the declaring scope (like closures)
+                if let Some(predicate) = refinement {
+                    self.refinements.insert(
+                        name.clone(),
+                        (predicate.clone(), Rc::clone(&self.environment)),
+                    );
+                }
                 Ok(Value::Unit)
             }
 
             Statement::Function {
                 name,
                 params,
-                return_type: _,
+                return_type,
                 contract,
                 body,
                 attributes: _,
                 type_params,
+                is_async,
             } => {
                 // Convert AST Contract to FunctionContract with expressions
                 let func_contract = contract.as_ref().map(|c| FunctionContract {
@@ -1985,6 +2357,8 @@ impl Interpreter {
                     closure: Rc::clone(&self.environment),
                     contract: func_contract,
                     type_params: type_params.clone(),
+                    return_type: return_type.clone(),
+                    is_async: *is_async,
                 };
                 self.environment.borrow_mut().define(name.clone(), func);
                 Ok(Value::Unit)
@@ -2008,7 +2382,11 @@ impl Interpreter {
                 } else {
                     Value::Unit
                 };
-                Ok(Value::Return(Box::new(value)))
+                match value {
+                    // The expression returned explicitly (e.g. a lambda's block body)
+                    Value::Return(_) => Ok(value),
+                    value => Ok(Value::Return(Box::new(value))),
+                }
             }
 
             Statement::If {
@@ -2037,7 +2415,11 @@ impl Interpreter {
                         Value::Break => break,
                         Value::Continue => continue,
                         Value::Return(_) => return Ok(result),
-                        _ => {}
+                        _ => {
+                            if let Some(last) = body.statements.last() {
+                                self.detach_discarded_task(last, &result);
+                            }
+                        }
                     }
                 }
                 Ok(Value::Unit)
@@ -2050,7 +2432,11 @@ impl Interpreter {
                         Value::Break => break,
                         Value::Continue => continue,
                         Value::Return(_) => return Ok(result),
-                        _ => {}
+                        _ => {
+                            if let Some(last) = body.statements.last() {
+                                self.detach_discarded_task(last, &result);
+                            }
+                        }
                     }
                 }
                 Ok(Value::Unit)
@@ -2061,7 +2447,8 @@ impl Interpreter {
 
             Statement::Module { name: _, body } => {
                 for stmt in body {
-                    self.eval_statement(stmt)?;
+                    let result = self.eval_statement(stmt)?;
+                    self.detach_discarded_task(stmt, &result);
                 }
                 Ok(Value::Unit)
             }
@@ -2226,7 +2613,8 @@ impl Interpreter {
                 };
 
                 let mut result = Value::Unit;
-                for item in items {
+                let count = items.len();
+                for (i, item) in items.into_iter().enumerate() {
                     // Create new scope for each iteration
                     let previous = Rc::clone(&self.environment);
                     self.environment =
@@ -2252,7 +2640,14 @@ impl Interpreter {
                             continue;
                         }
                         Value::Return(_) => break,
-                        _ => {}
+                        _ => {
+                            // Only the final iteration's value is the loop's result
+                            if i + 1 < count {
+                                if let Some(last) = body.statements.last() {
+                                    self.detach_discarded_task(last, &result);
+                                }
+                            }
+                        }
                     }
                 }
 
@@ -2276,11 +2671,14 @@ impl Interpreter {
         let deferred_count_before = self.deferred_statements.len();
 
         let mut result = Value::Unit;
-        for stmt in &block.statements {
+        for (i, stmt) in block.statements.iter().enumerate() {
             result = self.eval_statement(stmt)?;
             // Propagate control flow
             match result {
                 Value::Return(_) | Value::Break | Value::Continue => break,
+                _ if i + 1 < block.statements.len() => {
+                    self.detach_discarded_task(stmt, &result);
+                }
                 _ => {}
             }
         }
@@ -2302,6 +2700,31 @@ impl Interpreter {
     }
 
     fn eval_expression(&mut self, expr: &Expression) -> Result<Value> {
+        if self.contract_calls.is_some()
+            && matches!(
+                expr,
+                Expression::Call { .. } | Expression::MethodCall { .. }
+            )
+        {
+            let value = self.eval_expression_uncached(expr)?;
+            if let Some(calls) = self.contract_calls.as_mut() {
+                calls.insert(expr as *const Expression, value.clone());
+            }
+            return Ok(value);
+        }
+        self.eval_expression_uncached(expr)
+    }
+
+    /// Evaluate a contract clause, recording the value of every call it makes
+    fn eval_contract_clause(&mut self, expr: &Expression) -> Result<(Value, ContractCalls)> {
+        // Contracts checked by functions called from the clause record their own calls
+        let outer = self.contract_calls.replace(HashMap::new());
+        let result = self.eval_expression(expr);
+        let calls = std::mem::replace(&mut self.contract_calls, outer).unwrap_or_default();
+        Ok((result?, calls))
+    }
+
+    fn eval_expression_uncached(&mut self, expr: &Expression) -> Result<Value> {
         match expr {
             Expression::Integer(n) => Ok(Value::Int(*n)),
             Expression::Float(n) => Ok(Value::Float(*n)),
@@ -2400,19 +2823,62 @@ impl Interpreter {
                         return self.eval_expression(&arguments[0]);
                     }
 
+                    // std/concurrent spawn/parallel/parallel_map and the std/sync
+                    // callbacks run user functions, so they need the interpreter
+                    let is_concurrent =
+                        crate::stdlib::concurrent::INTERPRETER_FUNCTIONS.contains(&name.as_str());
+                    let is_sync =
+                        crate::stdlib::sync::INTERPRETER_FUNCTIONS.contains(&name.as_str());
+                    let is_schedule =
+                        crate::stdlib::schedule::INTERPRETER_FUNCTIONS.contains(&name.as_str());
+                    let is_jobs =
+                        crate::stdlib::jobs::INTERPRETER_FUNCTIONS.contains(&name.as_str());
+                    let is_ws =
+                        crate::stdlib::http_ws::INTERPRETER_FUNCTIONS.contains(&name.as_str());
+                    if is_concurrent || is_sync || is_schedule || is_jobs || is_ws {
+                        let is_std_native = matches!(
+                            self.environment.borrow().get(name),
+                            Some(Value::NativeFunction { name: ref native, .. }) if native == name
+                        );
+                        if is_std_native {
+                            let args = arguments
+                                .iter()
+                                .map(|arg| self.eval_expression(arg))
+                                .collect::<Result<Vec<_>>>()?;
+                            return if is_concurrent {
+                                self.call_concurrent_function(name, args)
+                            } else if is_sync {
+                                self.call_sync_function(name, args)
+                            } else if is_jobs {
+                                self.call_jobs_function(name, args)
+                            } else if is_ws {
+                                self.register_ws_callback(name, args)
+                            } else {
+                                self.register_schedule(name, args)
+                            };
+                        }
+                    }
+
                     // Special handling for listen() - starts HTTP server
-                    if name == "listen" && arguments.len() == 1 {
+                    if name == "listen" && (arguments.len() == 1 || arguments.len() == 2) {
                         // Skip in hot-reload (server already running) and unit-test mode
                         if self.should_skip_server_call("listen") {
                             return Ok(Value::Unit);
                         }
                         let port = self.eval_expression(&arguments[0])?;
+                        let options = match arguments.get(1) {
+                            Some(arg) => ListenOptions::from_value(&self.eval_expression(arg)?)?,
+                            None => ListenOptions::default(),
+                        };
                         if let Value::Int(port_num) = port {
+                            self.server_state.trusted_proxies = options.trusted_proxies.clone();
                             // Use sync server for test mode (intent check), async for production
-                            if self.test_mode.is_some() {
+                            if self.execution_mode == ExecutionMode::Worker {
+                                return self.serve_as_pool_worker();
+                            } else if self.test_mode.is_some() {
                                 return self.run_http_server(port_num as u16);
                             } else {
-                                return self.run_async_http_server(port_num as u16);
+                                return self.run_async_http_server(port_num as u16, options);
                             }
                         } else {
                             return Err(IntentError::TypeError(
@@ -2721,7 +3187,9 @@ impl Interpreter {
                     // Only intercept if first arg is a route pattern (starts with /)
                     // NOT if it's a URL (starts with http:// or https://) - those are HTTP client calls
                     let http_methods = ["get", "post", "put", "delete", "patch"];
-                    if http_methods.contains(&name.as_str()) && arguments.len() == 2 {
+                    if http_methods.contains(&name.as_str())
+                        && (arguments.len() == 2 || arguments.len() == 3)
+                    {
                         // Use eval_route_pattern to auto-detect route parameters:
                         // "/users/{id}" preserves {id} as a route param instead of interpolating
                         let pattern = self.eval_route_pattern(&arguments[0])?;
@@ -2735,13 +3203,80 @@ impl Interpreter {
                                     return Ok(Value::Unit);
                                 }
                                 let handler = self.eval_expression(&arguments[1])?;
+                                let options = match arguments.get(2) {
+                                    Some(arg) => {
+                                        RouteOptions::from_value(&self.eval_expression(arg)?)?
+                                    }
+                                    None => RouteOptions::default(),
+                                };
                                 let method = name.to_uppercase();
-                                self.server_state.add_route(&method, pattern_str, handler);
+                                let (pattern, middleware) = self.grouped_route(pattern_str);
+                                self.server_state.add_route_with_options(
+                                    &method, &pattern, handler, options, middleware,
+                                )?;
                                 return Ok(Value::Unit);
                             }
                             // Otherwise fall through to normal function call (HTTP client)
                         }
                     }
+
+                    // WebSocket and SSE routes are stored with the pseudo-methods
+                    // "WS" and "SSE"
+                    if (name == "ws" || name == "sse") && arguments.len() == 2 {
+                        let pattern = self.eval_route_pattern(&arguments[0])?;
+                        let Value::String(pattern_str) = &pattern else {
+                            return Err(IntentError::TypeError(format!(
+                                "{}() requires a route pattern string",
+                                name
+                            )));
+                        };
+                        if self.should_skip_route_registration() {
+                            return Ok(Value::Unit);
+                        }
+                        let handler = self.eval_expression(&arguments[1])?;
+                        let method = name.to_uppercase();
+                        let (pattern, middleware) = self.grouped_route(pattern_str);
+                        self.server_state.add_route_with_options(
+                            &method,
+                            &pattern,
+                            handler,
+                            RouteOptions::default(),
+                            middleware,
+                        )?;
+                        return Ok(Value::Unit);
+                    }
+
+                    // group(prefix, middleware, body): body registers routes
+                    // under the prefix, with the middleware
+                    if name == "group" && arguments.len() == 3 {
+                        let prefix = self.eval_route_pattern(&arguments[0])?;
+                        let Value::String(prefix) = prefix else {
+                            return Err(IntentError::TypeError(
+                                "group() requires a path prefix string".to_string(),
+                            ));
+                        };
+                        if !prefix.starts_with('/') {
+                            return Err(IntentError::RuntimeError(format!(
+                                "group() prefix must start with '/', got \"{}\"",
+                                prefix
+                            )));
+                        }
+                        let middleware = match self.eval_expression(&arguments[1])? {
+                            Value::Array(items) => items,
+                            _ => {
+                                return Err(IntentError::TypeError(
+                                    "group() requires an array of middleware".to_string(),
+                                ))
+                            }
+                        };
+                        let body = self.eval_expression(&arguments[2])?;
+                        self.route_groups
+                            .push((prefix.trim_end_matches('/').to_string(), middleware));
+                        let result = self.call_function(body, vec![]);
+                        self.route_groups.pop();
+                        result?;
+                        return Ok(Value::Unit);
+                    }
                 }
 
                 let callee = self.eval_expression(function)?;
@@ -2826,12 +3361,30 @@ impl Interpreter {
                 }
             }
 
+            Expression::OptionalChain { object, access } => {
+                let obj = self.eval_expression(object)?;
+                self.eval_optional_chain(obj, access)
+            }
+
             Expression::StructLiteral { name, fields } => {
                 let mut field_values = HashMap::new();
                 for (field_name, expr) in fields {
                     field_values.insert(field_name.clone(), self.eval_expression(expr)?);
                 }
 
+                // Check refined field types
+                if let Some(field_defs) = self.structs.get(name).cloned() {
+                    for field in &field_defs {
+                        if let Some(value) = field_values.get(&field.name) {
+                            self.check_refinement(
+                                &field.type_annotation,
+                                value,
+                                &format!("field '{}.{}'", name, field.name),
+                            )?;
+                        }
+                    }
+                }
+
                 let struct_val = Value::Struct {
                     name: name.clone(),
                     fields: field_values,
@@ -2866,6 +3419,10 @@ impl Interpreter {
                 let val = self.eval_expression(value)?;
                 match target.as_ref() {
                     Expression::Identifier(name) => {
+                        let declared = self.environment.borrow().declared_type(name);
+                        if let Some(ann) = declared {
+                            self.check_refinement(&ann, &val, &format!("variable '{}'", name))?;
+                        }
                         if self.environment.borrow_mut().set(name, val.clone()) {
                             // After assignment, check if this is a struct and verify invariants
                             if let Value::Struct {
@@ -2906,6 +3463,19 @@ impl Interpreter {
                             {
                                 // Update the field
                                 if fields.contains_key(field) {
+                                    let field_type =
+                                        self.structs.get(&struct_name).and_then(|defs| {
+                                            defs.iter()
+                                                .find(|f| &f.name == field)
+                                                .map(|f| f.type_annotation.clone())
+                                        });
+                                    if let Some(ann) = field_type {
+                                        self.check_refinement(
+                                            &ann,
+                                            &val,
+                                            &format!("field '{}.{}'", struct_name, field),
+                                        )?;
+                                    }
                                     fields.insert(field.clone(), val.clone());
 
                                     let new_struct = Value::Struct {
@@ -2925,17 +3495,34 @@ impl Interpreter {
                                         field, struct_name
                                     )))
                                 }
+                            } else if matches!(&current, Value::Map(map)
+                                if map.get(field).is_some_and(http_session::is_session))
+                            {
+                                Err(http_session::read_only_error())
                             } else {
                                 Err(IntentError::RuntimeError(
                                     "Cannot assign field on non-struct value".to_string(),
                                 ))
                             }
+                        } else if self
+                            .eval_expression(object)
+                            .is_ok_and(|v| http_session::is_session(&v))
+                        {
+                            Err(http_session::read_only_error())
                         } else {
                             Err(IntentError::RuntimeError(
                                 "Cannot assign to complex field access".to_string(),
                             ))
                         }
                     }
+                    // e.g. req.session["cart"] = items
+                    Expression::Index { object, .. }
+                        if self
+                            .eval_expression(object)
+                            .is_ok_and(|v| http_session::is_session(&v)) =>
+                    {
+                        Err(http_session::read_only_error())
+                    }
                     _ => Err(IntentError::RuntimeError(
                         "Invalid assignment target".to_string(),
                     )),
@@ -2966,6 +3553,8 @@ impl Interpreter {
                 closure: Rc::clone(&self.environment),
                 contract: None,
                 type_params: vec![],
+                return_type: None,
+                is_async: false,
             }),
 
             Expression::MethodCall {
@@ -3101,10 +3690,20 @@ impl Interpreter {
                 ))
             }
 
-            Expression::Await(_) | Expression::Try(_) => {
-                // TODO: Implement async/try
+            Expression::Await(inner) => {
+                // Awaiting a task handle blocks this interpreter until the task finishes;
+                // any other value is already available
+                let value = self.eval_expression(inner)?;
+                match crate::stdlib::concurrent::get_task_id(&value) {
+                    Some(id) => crate::stdlib::concurrent::await_task(id),
+                    None => Ok(value),
+                }
+            }
+
+            Expression::Try(_) => {
+                // TODO: Implement try
                 Err(IntentError::RuntimeError(
-                    "Async/Try not yet implemented".to_string(),
+                    "Try not yet implemented".to_string(),
                 ))
             }
 
@@ -3159,6 +3758,10 @@ impl Interpreter {
                             let value = self.eval_expression(expr)?;
                             result.push_str(&value.to_string());
                         }
+                        StringPart::Formatted { expr, spec } => {
+                            let text = self.eval_formatted(expr, spec)?;
+                            result.push_str(&text);
+                        }
                     }
                 }
                 Ok(Value::String(result))
@@ -3168,6 +3771,25 @@ impl Interpreter {
         }
     }
 
+    /// Apply the prefixes and middleware of the enclosing group() calls to a
+    /// route pattern
+    fn grouped_route(&self, pattern: &str) -> (String, Vec<Value>) {
+        let mut prefix = String::new();
+        let mut middleware = Vec::new();
+        for (group_prefix, group_middleware) in &self.route_groups {
+            prefix.push_str(group_prefix);
+            middleware.extend(group_middleware.iter().cloned());
+        }
+        let pattern = if prefix.is_empty() {
+            pattern.to_string()
+        } else if pattern == "/" {
+            prefix
+        } else {
+            format!("{}{}", prefix, pattern)
+        };
+        (pattern, middleware)
+    }
+
     /// Evaluate an expression as a route pattern.
     ///
     /// Route builtins (get, post, put, delete, patch) call this instead of
@@ -3175,6 +3797,7 @@ impl Interpreter {
     /// literal route parameter placeholders instead of interpolating them.
     ///
     /// - InterpolatedString with simple identifiers: `"/users/{id}"` → `/users/{id}`
+    /// - Typed parameters keep their type: `"/users/{id:int}"` → `/users/{id:int}`
     /// - InterpolatedString with complex expressions: evaluated normally
     /// - All other expressions (String, variable, concatenation): evaluated normally
     fn eval_route_pattern(&mut self, expr: &Expression) -> Result<Value> {
@@ -3197,6 +3820,15 @@ impl Interpreter {
                                 result.push_str(&value.to_string());
                             }
                         }
+                        StringPart::Formatted { expr: inner, spec } => {
+                            if let Expression::Identifier(name) = inner {
+                                // Typed route parameter: preserve {name:spec}
+                                result.push_str(&format!("{{{}:{}}}", name, spec));
+                            } else {
+                                let text = self.eval_formatted(inner, spec)?;
+                                result.push_str(&text);
+                            }
+                        }
                     }
                 }
                 Ok(Value::String(result))
@@ -3206,6 +3838,17 @@ impl Interpreter {
         }
     }
 
+    /// Evaluate an interpolation with a format spec: `"{price:.2}"`
+    fn eval_formatted(&mut self, expr: &Expression, spec: &str) -> Result<String> {
+        let parsed = crate::format::FormatSpec::parse(spec).map_err(|e| {
+            IntentError::RuntimeError(format!("Invalid format spec ':{}': {}", spec, e))
+        })?;
+        let value = self.eval_expression(expr)?;
+        parsed
+            .apply(&value)
+            .map_err(|e| IntentError::TypeError(format!("Cannot format with ':{}': {}", spec, e)))
+    }
+
     /// Render a template string with the given data
     fn render_template_with_data(
         &mut self,
@@ -3736,22 +4379,112 @@ impl Interpreter {
 
             Pattern::Array(patterns) => {
                 if let Value::Array(values) = value {
-                    if values.len() != patterns.len() {
+                    let rest_pos = patterns.iter().position(|p| matches!(p, Pattern::Rest(_)));
+                    let (head, tail) = match rest_pos {
+                        Some(pos) => (&patterns[..pos], &patterns[pos + 1..]),
+                        None => (&patterns[..], &patterns[..0]),
+                    };
+                    let fixed = head.len() + tail.len();
+                    if values.len() < fixed || (rest_pos.is_none() && values.len() != fixed) {
                         return Ok(None);
                     }
                     let mut bindings = vec![];
-                    for (pat, val) in patterns.iter().zip(values.iter()) {
+                    let tail_start = values.len() - tail.len();
+                    let pairs = head
+                        .iter()
+                        .zip(values.iter())
+                        .chain(tail.iter().zip(values[tail_start..].iter()));
+                    for (pat, val) in pairs {
                         if let Some(b) = self.match_pattern(pat, val)? {
                             bindings.extend(b);
                         } else {
                             return Ok(None);
                         }
                     }
+                    if let Some(Pattern::Rest(Some(name))) = rest_pos.map(|pos| &patterns[pos]) {
+                        let rest = values[head.len()..tail_start].to_vec();
+                        bindings.push((name.clone(), Value::Array(rest)));
+                    }
                     return Ok(Some(bindings));
                 }
                 Ok(None)
             }
 
+            // A rest element outside an array pattern matches anything
+            Pattern::Rest(name) => Ok(Some(
+                name.iter().map(|n| (n.clone(), value.clone())).collect(),
+            )),
+
+            Pattern::Or(alternatives) => {
+                for alt in alternatives {
+                    if let Some(b) = self.match_pattern(alt, value)? {
+                        return Ok(Some(b));
+                    }
+                }
+                Ok(None)
+            }
+
+            Pattern::Binding { name, pattern } => match self.match_pattern(pattern, value)? {
+                Some(mut bindings) => {
+                    bindings.insert(0, (name.clone(), value.clone()));
+                    Ok(Some(bindings))
+                }
+                None => Ok(None),
+            },
+
+            Pattern::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                let in_range = match (start, end, value) {
+                    (Expression::Integer(lo), Expression::Integer(hi), Value::Int(v)) => {
+                        v >= lo && (if *inclusive { v <= hi } else { v < hi })
+                    }
+                    (Expression::String(lo), Expression::String(hi), Value::String(v)) => {
+                        v >= lo && (if *inclusive { v <= hi } else { v < hi })
+                    }
+                    _ => {
+                        let as_f64 = |e: &Expression| match e {
+                            Expression::Integer(n) => Some(*n as f64),
+                            Expression::Float(n) => Some(*n),
+                            _ => None,
+                        };
+                        let v = match value {
+                            Value::Int(n) => Some(*n as f64),
+                            Value::Float(n) => Some(*n),
+                            _ => None,
+                        };
+                        match (as_f64(start), as_f64(end), v) {
+                            (Some(lo), Some(hi), Some(v)) => {
+                                v >= lo && (if *inclusive { v <= hi } else { v < hi })
+                            }
+                            _ => false,
+                        }
+                    }
+                };
+                Ok(if in_range { Some(vec![]) } else { None })
+            }
+
+            Pattern::Map(entries) => {
+                let lookup: &HashMap<String, Value> = match value {
+                    Value::Map(map) => map,
+                    Value::Struct { fields, .. } => fields,
+                    _ => return Ok(None),
+                };
+                let mut bindings = vec![];
+                for (key, pat) in entries {
+                    let Some(v) = lookup.get(key) else {
+                        return Ok(None);
+                    };
+                    match self.match_pattern(pat, v)? {
+                        Some(b) => bindings.extend(b),
+                        None => return Ok(None),
+                    }
+                }
+                Ok(Some(bindings))
+            }
+
             Pattern::Struct { name, fields } => {
                 if let Value::Struct {
                     name: struct_name,
@@ -3828,20 +4561,637 @@ impl Interpreter {
         }
     }
 
-    /// Bind variables from a pattern destructuring
-    fn bind_pattern(&mut self, pattern: &Pattern, value: &Value) -> Result<()> {
-        match self.match_pattern(pattern, value)? {
-            Some(bindings) => {
-                for (name, val) in bindings {
-                    self.environment.borrow_mut().define(name, val);
-                }
-                Ok(())
+    /// Evaluate one optional chaining step. `None`, `Unit`, a missing key or
+    /// an out-of-bounds index produce `None`; other results are wrapped in `Some`.
+    fn eval_optional_chain(&mut self, obj: Value, access: &ChainAccess) -> Result<Value> {
+        let none = Value::EnumValue {
+            enum_name: "Option".to_string(),
+            variant: "None".to_string(),
+            values: vec![],
+        };
+        let target = match obj {
+            Value::Unit => return Ok(none),
+            v if v.is_none() => return Ok(none),
+            Value::EnumValue {
+                enum_name,
+                variant,
+                mut values,
+            } if enum_name == "Option" && variant == "Some" && values.len() == 1 => {
+                values.remove(0)
             }
-            None => Err(IntentError::RuntimeError(
-                "Pattern destructuring failed: value does not match pattern".to_string(),
-            )),
-        }
-    }
+            v => v,
+        };
+
+        let result = match access {
+            ChainAccess::Field(field) => match &target {
+                Value::Struct { fields, .. } => fields.get(field).cloned(),
+                Value::Map(map) => map.get(field).cloned(),
+                _ => {
+                    return Err(IntentError::TypeError(format!(
+                        "Optional field access '?.{}' on {} value",
+                        field,
+                        target.type_name()
+                    )))
+                }
+            },
+            ChainAccess::Index(index) => {
+                let idx = self.eval_expression(index)?;
+                match (&target, &idx) {
+                    (Value::Array(arr), Value::Int(i)) => {
+                        let i = if *i < 0 { arr.len() as i64 + i } else { *i };
+                        usize::try_from(i).ok().and_then(|i| arr.get(i).cloned())
+                    }
+                    (Value::String(s), Value::Int(i)) => {
+                        let i = if *i < 0 { s.len() as i64 + i } else { *i };
+                        usize::try_from(i)
+                            .ok()
+                            .and_then(|i| s.chars().nth(i))
+                            .map(|c| Value::String(c.to_string()))
+                    }
+                    (Value::Map(map), Value::String(key)) => map.get(key).cloned(),
+                    (Value::Struct { fields, .. }, Value::String(key)) => fields.get(key).cloned(),
+                    _ => {
+                        return Err(IntentError::TypeError(
+                            "Invalid index operation".to_string(),
+                        ))
+                    }
+                }
+            }
+            ChainAccess::Method { method, arguments } => {
+                // Bind the receiver in a temporary scope so method dispatch is shared
+                // with regular method calls
+                let previous = Rc::clone(&self.environment);
+                self.environment =
+                    Rc::new(RefCell::new(Environment::with_parent(Rc::clone(&previous))));
+                self.environment
+                    .borrow_mut()
+                    .define("__chain_receiver".to_string(), target);
+                let call = Expression::MethodCall {
+                    object: Box::new(Expression::Identifier("__chain_receiver".to_string())),
+                    method: method.clone(),
+                    arguments: arguments.clone(),
+                };
+                let result = self.eval_expression(&call);
+                self.environment = previous;
+                Some(result?)
+            }
+        };
+
+        Ok(match result {
+            None | Some(Value::Unit) => none,
+            Some(v) if matches!(&v, Value::EnumValue { enum_name, .. } if enum_name == "Option") => {
+                v
+            }
+            Some(v) => Value::EnumValue {
+                enum_name: "Option".to_string(),
+                variant: "Some".to_string(),
+                values: vec![v],
+            },
+        })
+    }
+
+    /// Capture the state a worker interpreter needs to run a function defined
+    /// in `closure`: visible bindings, type definitions and contract settings
+    fn task_snapshot(&self, closure: &Rc<RefCell<Environment>>) -> TaskSnapshot {
+        let globals = closure
+            .borrow()
+            .bindings()
+            .into_iter()
+            .filter_map(|(name, value)| {
+                SerializedValue::capture(&value)
+                    .ok()
+                    .map(|captured| (name, captured))
+            })
+            .collect();
+        TaskSnapshot {
+            globals,
+            structs: self.structs.clone(),
+            enums: self.enums.clone(),
+            type_aliases: self.type_aliases.clone(),
+            refinements: self
+                .refinements
+                .iter()
+                .map(|(name, (predicate, _))| (name.clone(), predicate.clone()))
+                .collect(),
+            struct_invariants: self.struct_invariants.clone(),
+            trait_implementations: self.trait_implementations.clone(),
+            trait_definitions: self.trait_definitions.clone(),
+            contract_config: self.contracts.config().clone(),
+            current_file: self.current_file.clone(),
+            imports: self.task_imports.clone(),
+        }
+    }
+
+    /// Build a fresh interpreter from a snapshot (runs on the worker thread).
+    /// Captured bindings are defined first, then the program's imports are
+    /// re-resolved so imported functions keep their own module scope.
+    fn from_snapshot(snapshot: TaskSnapshot) -> Result<Self> {
+        let mut interpreter = Interpreter::new();
+        let env = Rc::clone(&interpreter.environment);
+        for (name, value) in snapshot.globals {
+            let value = value.to_value_in(&env);
+            env.borrow_mut().define(name, value);
+        }
+        interpreter.structs = snapshot.structs;
+        interpreter.enums = snapshot.enums;
+        interpreter.type_aliases = snapshot.type_aliases;
+        interpreter.refinements = snapshot
+            .refinements
+            .into_iter()
+            .map(|(name, predicate)| (name, (predicate, Rc::clone(&env))))
+            .collect();
+        interpreter.struct_invariants = snapshot.struct_invariants;
+        interpreter.trait_implementations = snapshot.trait_implementations;
+        interpreter.trait_definitions = snapshot.trait_definitions;
+        interpreter.contracts.set_config(snapshot.contract_config);
+        for import in &snapshot.imports {
+            interpreter.current_file = import.file.clone();
+            interpreter.handle_import(&import.items, &import.source, import.alias.as_deref())?;
+        }
+        interpreter.current_file = snapshot.current_file;
+        Ok(interpreter)
+    }
+
+    /// Resolve a function captured for a worker. Named functions are looked up
+    /// again after imports are replayed, and always run inline on the worker.
+    fn resolve_task_function(&self, func: &SerializedValue) -> Value {
+        let env = Rc::clone(&self.environment);
+        let resolved = match func {
+            SerializedValue::Function { name, .. } if name != "<lambda>" => {
+                match env.borrow().get(name) {
+                    Some(found @ Value::Function { .. }) => Some(found),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        match resolved.unwrap_or_else(|| func.to_value_in(&env)) {
+            Value::Function {
+                name,
+                params,
+                body,
+                closure,
+                contract,
+                type_params,
+                return_type,
+                ..
+            } => Value::Function {
+                name,
+                params,
+                body,
+                closure,
+                contract,
+                type_params,
+                return_type,
+                is_async: false,
+            },
+            other => other,
+        }
+    }
+
+    /// Capture a callable for a worker, along with the environment it was defined in
+    fn capture_callable(
+        &self,
+        func: &Value,
+        fn_name: &str,
+    ) -> Result<(SerializedValue, Rc<RefCell<Environment>>)> {
+        match func {
+            Value::Function { closure, .. } => {
+                Ok((SerializedValue::capture(func)?, Rc::clone(closure)))
+            }
+            Value::NativeFunction { .. } | Value::EnumConstructor { .. } => Ok((
+                SerializedValue::capture(func)?,
+                Rc::clone(&self.environment),
+            )),
+            _ => Err(IntentError::TypeError(format!(
+                "{}() requires a function",
+                fn_name
+            ))),
+        }
+    }
+
+    /// Run `func` once per argument list in a fresh interpreter on the task
+    /// pool and return a task handle. The task's result is the single call's
+    /// result, or an array of results when `batch` is set.
+    fn spawn_task(
+        &self,
+        name: &str,
+        closure: &Rc<RefCell<Environment>>,
+        func: SerializedValue,
+        calls: Vec<Vec<Value>>,
+        batch: bool,
+    ) -> Result<Value> {
+        let work = self.task_work(closure, func, calls, batch)?;
+        crate::stdlib::concurrent::spawn_task(name, work)
+    }
+
+    /// The work of a task: build an interpreter from a snapshot and call
+    /// `func` with each argument list. Arguments and results cross threads as
+    /// `SerializedValue`s.
+    fn task_work(
+        &self,
+        closure: &Rc<RefCell<Environment>>,
+        func: SerializedValue,
+        calls: Vec<Vec<Value>>,
+        batch: bool,
+    ) -> Result<impl FnOnce() -> crate::stdlib::concurrent::TaskOutcome + Send + 'static> {
+        let snapshot = self.task_snapshot(closure);
+        let calls = calls
+            .iter()
+            .map(|args| args.iter().map(SerializedValue::capture).collect())
+            .collect::<Result<Vec<Vec<_>>>>()?;
+        Ok(move || {
+            let run = || -> Result<Value> {
+                let mut interpreter = Interpreter::from_snapshot(snapshot)?;
+                let func = interpreter.resolve_task_function(&func);
+                let env = Rc::clone(&interpreter.environment);
+                let mut results = Vec::with_capacity(calls.len());
+                for args in &calls {
+                    let args = args.iter().map(|a| a.to_value_in(&env)).collect();
+                    results.push(interpreter.call_function(func.clone(), args)?);
+                }
+                Ok(if batch {
+                    Value::Array(results)
+                } else {
+                    results.pop().unwrap_or(Value::Unit)
+                })
+            };
+            run()
+                .and_then(|value| SerializedValue::capture(&value))
+                .map_err(|e| e.to_string())
+        })
+    }
+
+    /// Dispatch spawn/parallel/parallel_map calls from std/concurrent
+    fn call_concurrent_function(&self, name: &str, args: Vec<Value>) -> Result<Value> {
+        match (name, args.as_slice()) {
+            ("spawn", [func]) => self.concurrent_spawn(func, vec![]),
+            ("spawn", [func, Value::Array(call_args)]) => {
+                self.concurrent_spawn(func, call_args.clone())
+            }
+            ("spawn", _) => Err(IntentError::TypeError(
+                "spawn() requires (fn) or (fn, args_array)".to_string(),
+            )),
+            ("parallel", [funcs]) => self.concurrent_parallel(funcs),
+            ("parallel", _) => Err(IntentError::TypeError(
+                "parallel() requires an array of functions".to_string(),
+            )),
+            ("parallel_map", [items, func]) => {
+                let workers = std::thread::available_parallelism()
+                    .map(|n| n.get())
+                    .unwrap_or(1);
+                self.concurrent_parallel_map(items, func, workers)
+            }
+            ("parallel_map", [items, func, Value::Int(workers)]) if *workers > 0 => {
+                self.concurrent_parallel_map(items, func, *workers as usize)
+            }
+            ("parallel_map", _) => Err(IntentError::TypeError(
+                "parallel_map() requires (array, fn) or (array, fn, positive_int_workers)"
+                    .to_string(),
+            )),
+            _ => Err(IntentError::RuntimeError(format!(
+                "Unknown concurrent function: {}",
+                name
+            ))),
+        }
+    }
+
+    /// every(duration, fn), after(duration, fn) and cron(expr, fn, tz) from std/schedule
+    fn register_schedule(&mut self, name: &str, args: Vec<Value>) -> Result<Value> {
+        use crate::stdlib::schedule;
+        let (spec, handler, tz) = match (name, args.as_slice()) {
+            ("cron", [spec, handler, tz]) => (spec, handler, Some(tz)),
+            (_, [spec, handler]) => (spec, handler, None),
+            _ => {
+                let usage = if name == "cron" {
+                    "cron() requires (expression, fn) or (expression, fn, timezone)"
+                } else if name == "every" {
+                    "every() requires (duration, fn)"
+                } else {
+                    "after() requires (duration, fn)"
+                };
+                return Err(IntentError::TypeError(usage.to_string()));
+            }
+        };
+        if !matches!(
+            handler,
+            Value::Function { .. } | Value::NativeFunction { .. }
+        ) {
+            return Err(IntentError::TypeError(format!(
+                "{}() requires a function to run",
+                name
+            )));
+        }
+        let trigger = schedule::Trigger::from_args(name, spec, tz)?;
+        if self.should_skip_server_call(name) {
+            return Ok(schedule::inert_schedule());
+        }
+        let handle = schedule::register(self.scheduler_id, trigger)?;
+        if let Value::Map(map) = &handle {
+            if let Some(Value::Int(id)) = map.get("_schedule_id") {
+                self.schedule_handlers.insert(*id as u64, handler.clone());
+            }
+        }
+        Ok(handle)
+    }
+
+    /// Run a due std/schedule job. Errors are logged; the job keeps its schedule.
+    fn run_scheduled_job(&mut self, id: u64) {
+        if self.jobs_poller == Some(id) {
+            if let Err(e) = self.process_jobs(Some(crate::stdlib::jobs::JOBS_PER_POLL)) {
+                eprintln!("Job queue error: {}", e);
+            }
+        } else if let Some(handler) = self.schedule_handlers.get(&id).cloned() {
+            if let Err(e) = self.call_function(handler, vec![]) {
+                eprintln!("Scheduled job error: {}", e);
+            }
+        }
+        if !crate::stdlib::schedule::finish_run(id) {
+            self.schedule_handlers.remove(&id);
+        }
+    }
+
+    /// Keep running std/schedule jobs registered by a script that did not
+    /// start a server, until none are left (an `every` job runs until the
+    /// process is stopped)
+    pub fn run_schedules(&mut self) -> Result<()> {
+        use crate::stdlib::schedule;
+        if !schedule::has_jobs(self.scheduler_id) {
+            return Ok(());
+        }
+        let (tx, rx) = std::sync::mpsc::channel();
+        let timer = schedule::start_timer(self.scheduler_id, move |id| tx.send(id).is_ok())?;
+        while schedule::has_jobs(self.scheduler_id) {
+            match rx.recv_timeout(std::time::Duration::from_millis(200)) {
+                Ok(id) => self.run_scheduled_job(id),
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        timer.stop();
+        self.schedule_handlers.clear();
+        self.jobs_poller = None;
+        Ok(())
+    }
+
+    /// worker(name, fn, opts) and run_jobs(limit) from std/jobs
+    fn call_jobs_function(&mut self, name: &str, args: Vec<Value>) -> Result<Value> {
+        use crate::stdlib::schedule;
+        match (name, args.as_slice()) {
+            ("worker", [job, handler]) | ("worker", [job, handler, _]) => {
+                let Value::String(job) = job else {
+                    return Err(IntentError::TypeError(
+                        "worker() requires a job name string".to_string(),
+                    ));
+                };
+                if !matches!(
+                    handler,
+                    Value::Function { .. } | Value::NativeFunction { .. }
+                ) {
+                    return Err(IntentError::TypeError(
+                        "worker() requires a function to run".to_string(),
+                    ));
+                }
+                let poll = match args.get(2) {
+                    None => std::time::Duration::from_secs(1),
+                    Some(Value::Map(opts)) => match opts.get("poll") {
+                        Some(poll) => schedule::parse_duration(poll, "worker")?,
+                        None => std::time::Duration::from_secs(1),
+                    },
+                    Some(_) => {
+                        return Err(IntentError::TypeError(
+                            "worker() options must be a map".to_string(),
+                        ))
+                    }
+                };
+                self.job_workers.insert(job.clone(), handler.clone());
+                // Polling follows the same rules as every(): unit tests and
+                // hot reloads only (re)register the function
+                if self.jobs_poller.is_none() && !self.should_skip_server_call("every") {
+                    let handle = schedule::register(
+                        self.scheduler_id,
+                        schedule::Trigger::Every(poll.max(std::time::Duration::from_millis(10))),
+                    )?;
+                    if let Value::Map(map) = &handle {
+                        if let Some(Value::Int(id)) = map.get("_schedule_id") {
+                            self.jobs_poller = Some(*id as u64);
+                        }
+                    }
+                }
+                Ok(Value::Unit)
+            }
+            ("worker", _) => Err(IntentError::TypeError(
+                "worker() requires (name, fn) or (name, fn, options)".to_string(),
+            )),
+            ("run_jobs", []) => Ok(Value::Int(self.process_jobs(None)? as i64)),
+            ("run_jobs", [Value::Int(limit)]) if *limit >= 0 => {
+                Ok(Value::Int(self.process_jobs(Some(*limit as usize))? as i64))
+            }
+            ("run_jobs", _) => Err(IntentError::TypeError(
+                "run_jobs() takes an optional maximum number of jobs".to_string(),
+            )),
+            _ => Err(IntentError::RuntimeError(format!(
+                "Unknown jobs function: {}",
+                name
+            ))),
+        }
+    }
+
+    /// Run due std/jobs jobs that have a registered worker, up to `limit`.
+    /// Returns the number of jobs run, whether they succeeded or failed.
+    fn process_jobs(&mut self, limit: Option<usize>) -> Result<usize> {
+        use crate::stdlib::jobs::{self, FailOutcome};
+        let names: Vec<String> = self.job_workers.keys().cloned().collect();
+        let mut ran = 0;
+        while limit.is_none_or(|limit| ran < limit) {
+            let Some(job) = jobs::claim(&names)? else {
+                break;
+            };
+            let handler = self.job_workers[&job.name].clone();
+            let takes_job = matches!(&handler, Value::Function { params, .. } if params.len() >= 2);
+            let mut call_args = vec![job.args_value()];
+            if takes_job {
+                call_args.push(job.to_value());
+            }
+            let lease = jobs::LeaseKeeper::start(&job);
+            let result = self.call_function(handler, call_args);
+            drop(lease);
+            let error = match result {
+                Ok(Value::EnumValue {
+                    enum_name,
+                    variant,
+                    values,
+                }) if enum_name == "Result" && variant == "Err" => Some(
+                    values
+                        .first()
+                        .map(|v| v.to_string())
+                        .unwrap_or_else(|| "Err".to_string()),
+                ),
+                Ok(_) => None,
+                Err(e) => Some(e.to_string()),
+            };
+            match jobs::finish(&job, error.as_deref())? {
+                Some(FailOutcome::Retry(_)) => eprintln!(
+                    "Job {} #{} failed (attempt {}/{}), will retry: {}",
+                    job.name,
+                    job.id,
+                    job.attempts,
+                    job.max_attempts,
+                    error.as_deref().unwrap_or_default()
+                ),
+                Some(FailOutcome::Dead) => eprintln!(
+                    "Job {} #{} failed after {} attempts, moved to dead letters: {}",
+                    job.name,
+                    job.id,
+                    job.attempts,
+                    error.as_deref().unwrap_or_default()
+                ),
+                Some(FailOutcome::Stale) => eprintln!(
+                    "Job {} #{} was taken over after its lease expired; result of attempt {} ignored",
+                    job.name, job.id, job.attempts
+                ),
+                None => {}
+            }
+            ran += 1;
+        }
+        Ok(ran)
+    }
+
+    /// Dispatch std/sync calls that run a user callback on this interpreter
+    fn call_sync_function(&mut self, name: &str, args: Vec<Value>) -> Result<Value> {
+        use crate::stdlib::sync;
+        match (name, args.as_slice()) {
+            ("shared_update", [map, key, func]) => {
+                sync::shared_update(map, key, Value::Unit, |current| {
+                    self.call_function(func.clone(), vec![current])
+                })
+            }
+            ("shared_update", [map, key, func, default]) => {
+                sync::shared_update(map, key, default.clone(), |current| {
+                    self.call_function(func.clone(), vec![current])
+                })
+            }
+            ("shared_update", _) => Err(IntentError::TypeError(
+                "shared_update() requires (map, key, fn) or (map, key, fn, default)".to_string(),
+            )),
+            ("get_or_init", [cell, func]) => {
+                sync::once_get_or_init(cell, || self.call_function(func.clone(), vec![]))
+            }
+            ("get_or_init", _) => Err(IntentError::TypeError(
+                "get_or_init() requires (cell, fn)".to_string(),
+            )),
+            ("force", [cell]) => {
+                let init = sync::lazy_initializer(cell)?.ok_or_else(|| {
+                    IntentError::TypeError(
+                        "force() requires a cell created by lazy(); use get_or_init() for once_cell()"
+                            .to_string(),
+                    )
+                })?;
+                sync::once_get_or_init(cell, || {
+                    let func = self.resolve_task_function(&init);
+                    self.call_function(func, vec![])
+                })
+            }
+            ("force", _) => Err(IntentError::TypeError(
+                "force() requires a lazy cell".to_string(),
+            )),
+            _ => Err(IntentError::RuntimeError(format!(
+                "Unknown sync function: {}",
+                name
+            ))),
+        }
+    }
+
+    /// spawn(fn, args) -> Task
+    /// Runs on a thread of its own rather than the task pool, so spawned
+    /// functions can wait on each other (e.g. over channels) for as long as
+    /// they like
+    fn concurrent_spawn(&self, func: &Value, args: Vec<Value>) -> Result<Value> {
+        let (captured, closure) = self.capture_callable(func, "spawn")?;
+        let name = match func {
+            Value::Function { name, .. } | Value::NativeFunction { name, .. } => name.clone(),
+            _ => "spawn".to_string(),
+        };
+        let work = self.task_work(&closure, captured, vec![args], false)?;
+        crate::stdlib::concurrent::spawn_thread(&name, work)
+    }
+
+    /// parallel([fn, ...]) -> Array
+    /// Runs each zero-argument function on the task pool and waits for all results
+    fn concurrent_parallel(&self, funcs: &Value) -> Result<Value> {
+        let Value::Array(funcs) = funcs else {
+            return Err(IntentError::TypeError(
+                "parallel() requires an array of functions".to_string(),
+            ));
+        };
+        let tasks = funcs
+            .iter()
+            .map(|f| {
+                let (captured, closure) = self.capture_callable(f, "parallel")?;
+                self.spawn_task("parallel", &closure, captured, vec![vec![]], false)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        tasks
+            .iter()
+            .map(|task| {
+                let id = crate::stdlib::concurrent::get_task_id(task).unwrap_or_default();
+                crate::stdlib::concurrent::await_task(id)
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(Value::Array)
+    }
+
+    /// parallel_map(arr, fn, workers) -> Array
+    /// Splits `arr` into one contiguous chunk per worker thread and maps `fn`
+    /// over each chunk, preserving order
+    fn concurrent_parallel_map(
+        &self,
+        items: &Value,
+        func: &Value,
+        workers: usize,
+    ) -> Result<Value> {
+        let Value::Array(items) = items else {
+            return Err(IntentError::TypeError(
+                "parallel_map() requires an array as first argument".to_string(),
+            ));
+        };
+        if items.is_empty() {
+            return Ok(Value::Array(vec![]));
+        }
+        let (captured, closure) = self.capture_callable(func, "parallel_map")?;
+        let chunk_size = items.len().div_ceil(workers.max(1));
+        let tasks = items
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let calls = chunk.iter().map(|item| vec![item.clone()]).collect();
+                self.spawn_task("parallel_map", &closure, captured.clone(), calls, true)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut results = Vec::with_capacity(items.len());
+        for task in &tasks {
+            let id = crate::stdlib::concurrent::get_task_id(task).unwrap_or_default();
+            match crate::stdlib::concurrent::await_task(id)? {
+                Value::Array(chunk) => results.extend(chunk),
+                other => results.push(other),
+            }
+        }
+        Ok(Value::Array(results))
+    }
+
+    /// Bind variables from a pattern destructuring
+    fn bind_pattern(&mut self, pattern: &Pattern, value: &Value) -> Result<()> {
+        match self.match_pattern(pattern, value)? {
+            Some(bindings) => {
+                for (name, val) in bindings {
+                    self.environment.borrow_mut().define(name, val);
+                }
+                Ok(())
+            }
+            None => Err(IntentError::RuntimeError(
+                "Pattern destructuring failed: value does not match pattern".to_string(),
+            )),
+        }
+    }
 
     /// Check exhaustiveness of match arms against an enum type
     fn check_exhaustiveness(&self, enum_name: &str, arms: &[MatchArm]) -> Result<()> {
@@ -3855,21 +5205,35 @@ impl Interpreter {
         let mut covered = std::collections::HashSet::new();
         let mut has_wildcard = false;
 
-        for arm in arms {
-            match &arm.pattern {
+        fn collect<'a>(
+            pattern: &'a Pattern,
+            covered: &mut std::collections::HashSet<&'a str>,
+            has_wildcard: &mut bool,
+        ) {
+            match pattern {
                 Pattern::Wildcard => {
-                    has_wildcard = true;
+                    *has_wildcard = true;
                 }
                 Pattern::Variable(_) => {
-                    has_wildcard = true; // Variable captures all
+                    *has_wildcard = true; // Variable captures all
                 }
                 Pattern::Variant { variant, .. } => {
                     covered.insert(variant.as_str());
                 }
+                Pattern::Or(alternatives) => {
+                    for alt in alternatives {
+                        collect(alt, covered, has_wildcard);
+                    }
+                }
+                Pattern::Binding { pattern, .. } => collect(pattern, covered, has_wildcard),
                 _ => {}
             }
         }
 
+        for arm in arms {
+            collect(&arm.pattern, &mut covered, &mut has_wildcard);
+        }
+
         if has_wildcard {
             return Ok(()); // Wildcard covers everything
         }
@@ -3889,6 +5253,112 @@ impl Interpreter {
         Ok(())
     }
 
+    /// Check that arguments bound to generic type parameters implement the
+    /// parameters' trait bounds. Only user-defined struct and enum values are
+    /// checked, and only against traits that have been declared.
+    fn check_trait_bounds(
+        &self,
+        name: &str,
+        type_params: &[TypeParam],
+        params: &[Parameter],
+        args: &[Value],
+    ) -> Result<()> {
+        for (param, arg) in params.iter().zip(args.iter()) {
+            let Some(ann) = &param.type_annotation else {
+                continue;
+            };
+            let mut bound_values = Vec::new();
+            Self::collect_type_param_values(ann, arg, type_params, &mut bound_values);
+            for (type_param, value) in bound_values {
+                let type_name = match value {
+                    Value::Struct { name, .. } => name.as_str(),
+                    Value::EnumValue { enum_name, .. }
+                        if enum_name != "Option" && enum_name != "Result" =>
+                    {
+                        enum_name.as_str()
+                    }
+                    _ => continue,
+                };
+                for bound in &type_param.bounds {
+                    if self.trait_definitions.contains_key(bound)
+                        && !self.implements_trait(type_name, bound)
+                    {
+                        return Err(IntentError::TypeError(format!(
+                            "Type '{}' does not implement trait '{}' required by '{}<{}: {}>' (parameter '{}')",
+                            type_name,
+                            bound,
+                            name,
+                            type_param.name,
+                            type_param.bounds.join(" + "),
+                            param.name
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Pair each generic type parameter in `ann` with the value(s) it stands for
+    fn collect_type_param_values<'a>(
+        ann: &TypeExpr,
+        value: &'a Value,
+        type_params: &'a [TypeParam],
+        out: &mut Vec<(&'a TypeParam, &'a Value)>,
+    ) {
+        match (ann, value) {
+            (TypeExpr::Named(n), _) => {
+                if let Some(tp) = type_params.iter().find(|tp| &tp.name == n) {
+                    out.push((tp, value));
+                }
+            }
+            (TypeExpr::Optional(inner), _) => match value {
+                Value::EnumValue {
+                    enum_name,
+                    variant,
+                    values,
+                } if enum_name == "Option" && variant == "Some" => {
+                    if let Some(v) = values.first() {
+                        Self::collect_type_param_values(inner, v, type_params, out);
+                    }
+                }
+                _ => Self::collect_type_param_values(inner, value, type_params, out),
+            },
+            (TypeExpr::Array(inner), Value::Array(items)) => {
+                for item in items {
+                    Self::collect_type_param_values(inner, item, type_params, out);
+                }
+            }
+            (TypeExpr::Map { value_type, .. }, Value::Map(entries)) => {
+                for v in entries.values() {
+                    Self::collect_type_param_values(value_type, v, type_params, out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether `type_name` implements `trait_name`, directly or through a
+    /// trait whose supertraits include it
+    fn implements_trait(&self, type_name: &str, trait_name: &str) -> bool {
+        let Some(implemented) = self.trait_implementations.get(type_name) else {
+            return false;
+        };
+        let mut pending: Vec<&str> = implemented.iter().map(|t| t.as_str()).collect();
+        let mut seen = std::collections::HashSet::new();
+        while let Some(t) = pending.pop() {
+            if t == trait_name {
+                return true;
+            }
+            if seen.insert(t) {
+                if let Some(info) = self.trait_definitions.get(t) {
+                    pending.extend(info.supertraits.iter().map(|s| s.as_str()));
+                }
+            }
+        }
+        false
+    }
+
     fn call_function(&mut self, callee: Value, args: Vec<Value>) -> Result<Value> {
         match callee {
             Value::Function {
@@ -3897,7 +5367,9 @@ impl Interpreter {
                 body,
                 closure,
                 contract,
-                type_params: _, // Generic type params - for future type checking
+                type_params,
+                return_type,
+                is_async,
             } => {
                 if args.len() != params.len() {
                     return Err(IntentError::ArityMismatch {
@@ -3907,14 +5379,51 @@ impl Interpreter {
                     });
                 }
 
+                // async fn: run the body on a worker thread and hand back a task
+                if is_async {
+                    let func = SerializedValue::Function {
+                        name: name.clone(),
+                        params,
+                        body,
+                        contract,
+                        type_params,
+                        return_type,
+                        is_async: false,
+                    };
+                    return self.spawn_task(&name, &closure, func, vec![args], false);
+                }
+
+                // Check generic trait bounds (fn sort<T: Comparable>(arr: [T]))
+                if type_params.iter().any(|tp| !tp.bounds.is_empty()) {
+                    self.check_trait_bounds(&name, &type_params, &params, &args)?;
+                }
+
+                // Check refined parameter types (type Port = Int where ...)
+                for (param, arg) in params.iter().zip(args.iter()) {
+                    if let Some(ann) = &param.type_annotation {
+                        self.check_refinement(
+                            ann,
+                            arg,
+                            &format!("parameter '{}' of '{}'", param.name, name),
+                        )?;
+                    }
+                }
+
                 // Create new environment with closure as parent
                 let func_env = Rc::new(RefCell::new(Environment::with_parent(closure)));
 
                 // Bind parameters
                 for (param, arg) in params.iter().zip(args.iter()) {
-                    func_env
-                        .borrow_mut()
-                        .define(param.name.clone(), arg.clone());
+                    match &param.type_annotation {
+                        Some(ann) => func_env.borrow_mut().define_typed(
+                            param.name.clone(),
+                            arg.clone(),
+                            ann.clone(),
+                        ),
+                        None => func_env
+                            .borrow_mut()
+                            .define(param.name.clone(), arg.clone()),
+                    }
                 }
 
                 // Save current environment and switch to function's environment
@@ -3927,32 +5436,44 @@ impl Interpreter {
                 // Check preconditions BEFORE execution
                 if let Some(ref func_contract) = contract {
                     for req_expr in &func_contract.requires {
-                        let condition_str = Self::format_expression(req_expr);
-                        let result = self.eval_expression(req_expr)?;
+                        if !self.contracts.should_check(ContractType::Precondition) {
+                            continue;
+                        }
+                        let (result, calls) = self.eval_contract_clause(req_expr)?;
                         if !result.is_truthy() {
-                            self.environment = previous;
-                            return Err(IntentError::ContractViolation(format!(
-                                "Precondition failed in '{}': {}",
-                                name, condition_str
-                            )));
+                            let violation = self.explain_contract(
+                                ContractType::Precondition,
+                                &name,
+                                req_expr,
+                                &calls,
+                                &params,
+                                &args,
+                            );
+                            if self.contracts.report_violation(&violation) {
+                                self.environment = previous;
+                                return Err(self.contract_violation(violation));
+                            }
                         }
-                        self.contracts
-                            .check_precondition(&condition_str, true, None)?;
                     }
 
                     // Capture old values for postconditions containing old()
-                    self.current_old_values =
-                        Some(self.capture_old_values(&func_contract.ensures)?);
+                    if self.contracts.config().ensures != EnforcementMode::Off {
+                        self.current_old_values =
+                            Some(self.capture_old_values(&func_contract.ensures)?);
+                    }
                 }
 
                 // Execute function body
                 let mut result = Value::Unit;
-                for stmt in &body.statements {
+                for (i, stmt) in body.statements.iter().enumerate() {
                     result = self.eval_statement(stmt)?;
                     if let Value::Return(v) = result {
                         result = *v;
                         break;
                     }
+                    if i + 1 < body.statements.len() {
+                        self.detach_discarded_task(stmt, &result);
+                    }
                 }
 
                 // Execute deferred statements in reverse order (LIFO) before returning
@@ -3966,6 +5487,17 @@ impl Interpreter {
                     let _ = self.eval_expression(&deferred_expr);
                 }
 
+                // Check the refined return type (fn open() -> Port)
+                if let Some(ann) = &return_type {
+                    if let Err(e) =
+                        self.check_refinement(ann, &result, &format!("return value of '{}'", name))
+                    {
+                        self.current_old_values = None;
+                        self.environment = previous;
+                        return Err(e);
+                    }
+                }
+
                 // Store result for postcondition evaluation
                 self.current_result = Some(result.clone());
 
@@ -3977,20 +5509,28 @@ impl Interpreter {
                 // Check postconditions AFTER execution
                 if let Some(ref func_contract) = contract {
                     for ens_expr in &func_contract.ensures {
-                        let condition_str = Self::format_expression(ens_expr);
-                        let postcond_result = self.eval_expression(ens_expr)?;
+                        if !self.contracts.should_check(ContractType::Postcondition) {
+                            continue;
+                        }
+                        let (postcond_result, calls) = self.eval_contract_clause(ens_expr)?;
                         if !postcond_result.is_truthy() {
-                            // Clear state before returning error
-                            self.current_old_values = None;
-                            self.current_result = None;
-                            self.environment = previous;
-                            return Err(IntentError::ContractViolation(format!(
-                                "Postcondition failed in '{}': {}",
-                                name, condition_str
-                            )));
+                            // Explain the failure while result and old() values are still bound
+                            let violation = self.explain_contract(
+                                ContractType::Postcondition,
+                                &name,
+                                ens_expr,
+                                &calls,
+                                &params,
+                                &args,
+                            );
+                            if self.contracts.report_violation(&violation) {
+                                // Clear state before returning error
+                                self.current_old_values = None;
+                                self.current_result = None;
+                                self.environment = previous;
+                                return Err(self.contract_violation(violation));
+                            }
                         }
-                        self.contracts
-                            .check_postcondition(&condition_str, true, None)?;
                     }
                 }
 
@@ -4152,6 +5692,8 @@ impl Interpreter {
             if let Some((mut handler, route_params, route_index)) =
                 self.server_state.find_route(&method, &path)
             {
+                let is_sse = self.server_state.is_sse_route(route_index);
+
                 // Hot-reload check: if file or its imports changed, reload the handler
                 if self.server_state.needs_reload(route_index) {
                     if let Some(source) = self.server_state.get_route_source(route_index).cloned() {
@@ -4176,78 +5718,39 @@ impl Interpreter {
                 }
 
                 // Process request to get request Value
-                match http_server::process_request(request, route_params) {
-                    Ok((mut req_value, http_request)) => {
-                        // Run middleware chain and determine final response
-                        let middleware_handlers: Vec<Value> =
-                            self.server_state.get_middleware().to_vec();
-                        let mut early_response: Option<Value> = None;
-
-                        for mw in middleware_handlers {
-                            match self.call_function(mw.clone(), vec![req_value.clone()]) {
-                                Ok(result) => {
-                                    // Check if middleware returned a response (early exit) or modified request
-                                    match &result {
-                                        Value::Map(map) if map.contains_key("status") => {
-                                            // Middleware returned a response - use it and stop
-                                            early_response = Some(result);
-                                            break;
-                                        }
-                                        Value::Map(_) => {
-                                            // Middleware returned modified request - continue with it
-                                            req_value = result;
-                                        }
-                                        Value::Unit => {
-                                            // Middleware returned unit - continue with original request
-                                        }
-                                        _ => {
-                                            // Other return - continue with original request
-                                        }
-                                    }
-                                }
-                                Err(e) => {
-                                    eprintln!("Middleware error: {}", e);
-                                    early_response = Some(http_server::create_error_response(
-                                        500,
-                                        &e.to_string(),
-                                    ));
-                                    break;
-                                }
+                let max_body = self.server_state.body_limit(route_index);
+                match http_server::process_request(
+                    request,
+                    route_params,
+                    max_body,
+                    &self.server_state.trusted_proxies,
+                ) {
+                    Ok((req_value, http_request, temp_files)) => {
+                        // Run middleware, then the handler unless middleware responded
+                        let (final_response, pending_stream) = match self
+                            .run_middleware(req_value, route_index)
+                        {
+                            MiddlewareOutcome::Respond(early_response) => (early_response, None),
+                            MiddlewareOutcome::Continue(current_req) => {
+                                self.call_route_handler(handler, current_req, is_sse)
+                            }
+                        };
+                        let mut final_response = self.commit_sessions(final_response);
+
+                        // This server cannot stream, so streamed bodies are
+                        // collected and sent once the handler returns
+                        if let Some(pending) = pending_stream {
+                            let id = pending.id;
+                            self.run_stream(pending);
+                            if let Value::Map(map) = &mut final_response {
+                                map.insert(
+                                    "body".to_string(),
+                                    Value::String(crate::stdlib::http_stream::collect(id)),
+                                );
                             }
                         }
 
-                        // Determine final response
-                        let final_response = if let Some(resp) = early_response {
-                            resp
-                        } else {
-                            // Call the route handler
-                            match self.call_function(handler, vec![req_value]) {
-                                Ok(response) => response,
-                                Err(e) => {
-                                    eprintln!("Handler error: {}", e);
-                                    // Check for contract violations and return appropriate HTTP status
-                                    if let IntentError::ContractViolation(msg) = &e {
-                                        if msg.contains("Precondition failed") {
-                                            // Precondition = bad request from client
-                                            http_server::create_error_response(
-                                                400,
-                                                &format!("Bad Request: {}", msg),
-                                            )
-                                        } else if msg.contains("Postcondition failed") {
-                                            // Postcondition = server logic error
-                                            http_server::create_error_response(
-                                                500,
-                                                &format!("Internal Error: {}", msg),
-                                            )
-                                        } else {
-                                            http_server::create_error_response(500, &e.to_string())
-                                        }
-                                    } else {
-                                        http_server::create_error_response(500, &e.to_string())
-                                    }
-                                }
-                            }
-                        };
+                        crate::stdlib::http_multipart::remove_temp_files(&temp_files);
 
                         // Send the response (only once)
                         if let Err(e) = http_server::send_response(http_request, &final_response) {
@@ -4261,8 +5764,19 @@ impl Interpreter {
                 continue;
             }
 
-            // No matching route - check static files (only for GET requests)
-            if method == "GET" {
+            // The path has routes, but not for this method: 405, or the
+            // allowed methods for OPTIONS
+            let allow = self.server_state.allowed_methods(&path);
+            if !allow.is_empty() {
+                let response = http_server::allow_response(&method, &allow);
+                if let Err(e) = http_server::send_response(request, &response) {
+                    eprintln!("Error sending response: {}", e);
+                }
+                continue;
+            }
+
+            // No matching route - check static files (only for GET and HEAD requests)
+            if method == "GET" || method == "HEAD" {
                 if let Some((file_path, _relative)) = self.server_state.find_static_file(&path) {
                     // Serve static file
                     if let Err(e) = http_server::send_static_response(request, &file_path) {
@@ -4275,8 +5789,13 @@ impl Interpreter {
             // No matching route or static file - send 404
             let path_clone = path.clone();
             #[allow(clippy::single_match)]
-            match http_server::process_request(request, HashMap::new()) {
-                Ok((_, http_request)) => {
+            match http_server::process_request(
+                request,
+                HashMap::new(),
+                http_server::DEFAULT_MAX_BODY,
+                &self.server_state.trusted_proxies,
+            ) {
+                Ok((_, http_request, _)) => {
                     let not_found = http_server::create_error_response(
                         404,
                         &format!("Not Found: {} {}", method, path_clone),
@@ -4303,13 +5822,14 @@ impl Interpreter {
 
     /// Run the HTTP server using Axum + Tokio
     /// This provides high-concurrency handling for production workloads
-    fn run_async_http_server(&mut self, port: u16) -> Result<Value> {
+    fn run_async_http_server(&mut self, port: u16, options: ListenOptions) -> Result<Value> {
         use crate::stdlib::http_bridge::{
-            create_channel, BridgeConfig, BridgeResponse, HandlerRequest, InterpreterHandle,
+            create_channel, BridgeConfig, BridgeMessage, InterpreterHandle,
         };
         use crate::stdlib::http_server_async::{
-            start_server_with_bridge, AsyncServerConfig, AsyncServerState,
+            start_server_with_bridge, AsyncServerConfig, AsyncServerState, ContractMetricsEndpoint,
         };
+        use crate::stdlib::http_tls::TlsOptions;
         use std::sync::Arc;
         use std::thread;
 
@@ -4327,9 +5847,7 @@ impl Interpreter {
             .unwrap_or(port);
 
         // Enable hot-reload unless in production mode
-        let is_production = std::env::var("NTNT_ENV")
-            .map(|v| v == "production" || v == "prod")
-            .unwrap_or(false);
+        let is_production = is_production_env();
         self.server_state.hot_reload = !is_production;
 
         if is_production {
@@ -4343,26 +5861,6 @@ impl Interpreter {
         // Create async server state with registered routes
         let async_routes = Arc::new(AsyncServerState::new());
 
-        // Helper function to sync routes from interpreter to async state
-        fn sync_routes_to_async(
-            server_state: &crate::stdlib::http_server::ServerState,
-            async_routes: &AsyncServerState,
-            rt: &tokio::runtime::Runtime,
-        ) {
-            // Clear existing async routes
-            async_routes.clear_blocking(rt);
-
-            // Copy routes
-            for (route, _handler, _source) in &server_state.routes {
-                async_routes.register_route_blocking(rt, &route.method, &route.pattern, "handler");
-            }
-
-            // Copy static directories
-            for (url_prefix, fs_path) in &server_state.static_dirs {
-                async_routes.register_static_dir_blocking(rt, url_prefix, fs_path);
-            }
-        }
-
         // Create the async runtime for route registration and hot-reload sync
         let sync_rt = tokio::runtime::Builder::new_current_thread()
             .enable_all()
@@ -4370,10 +5868,33 @@ impl Interpreter {
             .map_err(|e| IntentError::RuntimeError(format!("Failed to create runtime: {}", e)))?;
 
         // Initial route sync from interpreter to async state
-        sync_routes_to_async(&self.server_state, &async_routes, &sync_rt);
+        sync_routes_to_async(
+            &self.server_state,
+            &async_routes,
+            &sync_rt,
+            self.loaded_source_version(),
+        );
+
+        // Additional workers each load the program into their own interpreter
+        let mut senders = vec![tx.clone()];
+        let mut worker_threads = Vec::new();
+        for index in 1..options.workers {
+            let (worker_tx, worker_rx) = create_channel(&config);
+            worker_threads.push(self.spawn_pool_worker(index, worker_rx, async_routes.clone())?);
+            senders.push(worker_tx);
+        }
+
+        // std/schedule jobs are queued through the same channel as requests
+        // (always to this interpreter, the first worker)
+        let schedule_tx = tx;
+        let schedule_timer = crate::stdlib::schedule::start_timer(self.scheduler_id, move |id| {
+            schedule_tx
+                .blocking_send(BridgeMessage::Scheduled(id))
+                .is_ok()
+        })?;
 
         // Create interpreter handle for async handlers
-        let interpreter_handle = Arc::new(InterpreterHandle::new(tx));
+        let interpreter_handle = Arc::new(InterpreterHandle::pool(senders, options.dispatch));
 
         // Create server config
         let server_config = AsyncServerConfig {
@@ -4382,11 +5903,31 @@ impl Interpreter {
             enable_compression: true,
             request_timeout_secs: self.request_timeout_secs,
             max_connections: 10_000,
+            max_body: options.max_body,
+            // Certificate paths are relative to the .tnt file, like serve_static()
+            tls: options.tls.map(|tls| TlsOptions {
+                cert: self
+                    .resolve_path_relative_to_script(&tls.cert.to_string_lossy())
+                    .into(),
+                key: self
+                    .resolve_path_relative_to_script(&tls.key.to_string_lossy())
+                    .into(),
+            }),
+            redirect_http: options.redirect_http,
+            trusted_proxies: options.trusted_proxies,
+            contract_metrics: self.contracts.config().metrics_path.clone().map(|path| {
+                ContractMetricsEndpoint {
+                    path,
+                    metrics: self.contracts.metrics(),
+                    config: self.contracts.config().clone(),
+                }
+            }),
         };
 
         // Spawn async server in a separate thread
         // Note: We move interpreter_handle into the thread (not clone) so it's dropped
-        // when the server shuts down, which closes the channel and signals the main loop to exit
+        // when the server shuts down, which closes the channels and signals the
+        // interpreter loops to exit
         let routes_clone = async_routes.clone();
         let server_handle = thread::spawn(move || {
             let rt = tokio::runtime::Builder::new_multi_thread()
@@ -4401,20 +5942,137 @@ impl Interpreter {
                     eprintln!("Server error: {}", e);
                 }
             });
+            // Stopping the timer drops its sender, so the channel closes
+            schedule_timer.stop();
         });
 
-        // Main thread: process requests from the channel
-        // This runs the interpreter in a single thread (required since it's not Send+Sync)
+        // Main thread: process requests from the channel
+        // This runs the interpreter in a single thread (required since it's not Send+Sync)
+        self.serve_bridge(&mut rx, &async_routes, &sync_rt);
+        println!("\n🛑 Server shutting down...");
+
+        // Wait for the server and the other workers (and their shutdown handlers)
+        let _ = server_handle.join();
+        for worker in worker_threads {
+            let _ = worker.join();
+        }
+
+        crate::stdlib::schedule::cancel_all(self.scheduler_id);
+        self.schedule_handlers.clear();
+        self.jobs_poller = None;
+
+        self.run_shutdown_handlers();
+
+        Ok(Value::Unit)
+    }
+
+    /// Start pool worker `index` on its own thread
+    ///
+    /// The worker is a fresh interpreter that runs the main source file in
+    /// `ExecutionMode::Worker`, so it registers the same routes, middleware
+    /// and shutdown handlers. Its `listen()` call serves `rx` instead of
+    /// binding a port.
+    fn spawn_pool_worker(
+        &self,
+        index: usize,
+        rx: tokio::sync::mpsc::Receiver<crate::stdlib::http_bridge::BridgeMessage>,
+        routes: std::sync::Arc<crate::stdlib::http_server_async::AsyncServerState>,
+    ) -> Result<std::thread::JoinHandle<()>> {
+        let file_path = self.main_source_file.clone().ok_or_else(|| {
+            IntentError::RuntimeError(
+                "listen() with more than one worker requires running a .tnt file".to_string(),
+            )
+        })?;
+        let request_timeout_secs = self.request_timeout_secs;
+        let contract_config = self.contracts.config().clone();
+        let contract_metrics = self.contracts.metrics();
+
+        std::thread::Builder::new()
+            .name(format!("ntnt-worker-{}", index))
+            .spawn(move || {
+                let mut worker = Interpreter::new();
+                worker.set_current_file(&file_path);
+                worker.set_main_source_file(&file_path);
+                worker.set_request_timeout(request_timeout_secs);
+                worker.set_contract_config(contract_config);
+                worker.contracts.share_metrics(contract_metrics);
+                worker.execution_mode = ExecutionMode::Worker;
+                worker.pool_worker = Some(PoolWorker { index, rx, routes });
+
+                let result = std::fs::read_to_string(&file_path)
+                    .map_err(|e| IntentError::RuntimeError(e.to_string()))
+                    .and_then(|source| {
+                        let tokens: Vec<_> = crate::lexer::Lexer::new(&source).collect();
+                        crate::parser::Parser::new(tokens).parse()
+                    })
+                    .and_then(|ast| worker.eval(&ast));
+                if let Err(e) = result {
+                    eprintln!("[worker {}] {}", index, e);
+                }
+            })
+            .map_err(|e| IntentError::RuntimeError(format!("Failed to start worker: {}", e)))
+    }
+
+    /// Serve requests as a pool worker (what `listen()` does in `ExecutionMode::Worker`)
+    fn serve_as_pool_worker(&mut self) -> Result<Value> {
+        let Some(PoolWorker {
+            index,
+            mut rx,
+            routes,
+        }) = self.pool_worker.take()
+        else {
+            // listen() was already called once in this worker
+            return Ok(Value::Unit);
+        };
+
+        self.server_state.hot_reload = !is_production_env();
+        let sync_rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| IntentError::RuntimeError(format!("Failed to create runtime: {}", e)))?;
+
+        self.serve_bridge(&mut rx, &routes, &sync_rt);
+        if !self.server_state.get_shutdown_handlers().is_empty() {
+            println!("[worker {}] stopping", index);
+        }
+        self.run_shutdown_handlers();
+        Ok(Value::Unit)
+    }
+
+    /// Handle messages from the HTTP bridge until the channel closes
+    fn serve_bridge(
+        &mut self,
+        rx: &mut tokio::sync::mpsc::Receiver<crate::stdlib::http_bridge::BridgeMessage>,
+        async_routes: &crate::stdlib::http_server_async::AsyncServerState,
+        sync_rt: &tokio::runtime::Runtime,
+    ) {
+        use crate::stdlib::http_bridge::{BridgeMessage, BridgeResponse, HandlerRequest};
+
         loop {
             // Block waiting for requests
             match rx.blocking_recv() {
-                Some(handler_request) => {
-                    let HandlerRequest { request, reply_tx } = handler_request;
+                Some(BridgeMessage::Scheduled(id)) => self.run_scheduled_job(id),
+                Some(BridgeMessage::Request(handler_request)) => {
+                    let HandlerRequest {
+                        request,
+                        websocket,
+                        reply_tx,
+                    } = *handler_request;
+
+                    if let Some(event) = websocket {
+                        let _ = reply_tx.send(self.handle_ws_event(request, event));
+                        continue;
+                    }
 
                     // Hot-reload check: if main source file changed, reload it
                     if self.check_and_reload_main_source() {
                         // Routes changed - sync to async state
-                        sync_routes_to_async(&self.server_state, &async_routes, &sync_rt);
+                        sync_routes_to_async(
+                            &self.server_state,
+                            async_routes,
+                            sync_rt,
+                            self.loaded_source_version(),
+                        );
                     }
 
                     // Find the matching route handler
@@ -4424,6 +6082,8 @@ impl Interpreter {
                     if let Some((mut handler, route_params, route_index)) =
                         self.server_state.find_route(method, path)
                     {
+                        let is_sse = self.server_state.is_sse_route(route_index);
+
                         // Hot-reload check: if route file or its imports changed, reload the handler
                         if self.server_state.needs_reload(route_index) {
                             if let Some(source) =
@@ -4442,8 +6102,9 @@ impl Interpreter {
                                             // Sync updated routes to async state
                                             sync_routes_to_async(
                                                 &self.server_state,
-                                                &async_routes,
-                                                &sync_rt,
+                                                async_routes,
+                                                sync_rt,
+                                                self.loaded_source_version(),
                                             );
                                         }
                                         Err(e) => {
@@ -4466,74 +6127,565 @@ impl Interpreter {
                         // Convert to NTNT Value
                         let req_value = full_request.to_value();
 
-                        // Run middleware
-                        let middleware_handlers: Vec<Value> =
-                            self.server_state.get_middleware().to_vec();
-                        let mut current_req = req_value;
-                        let mut early_response: Option<Value> = None;
-
-                        for mw in middleware_handlers {
-                            match self.call_function(mw.clone(), vec![current_req.clone()]) {
-                                Ok(result) => match &result {
-                                    Value::Map(map) if map.contains_key("status") => {
-                                        early_response = Some(result);
-                                        break;
-                                    }
-                                    Value::Map(_) => {
-                                        current_req = result;
-                                    }
-                                    _ => {}
-                                },
-                                Err(e) => {
-                                    eprintln!("Middleware error: {}", e);
-                                    early_response =
-                                        Some(crate::stdlib::http_server::create_error_response(
-                                            500,
-                                            &e.to_string(),
-                                        ));
-                                    break;
-                                }
-                            }
-                        }
-
-                        // Determine final response
-                        let final_response = if let Some(resp) = early_response {
-                            resp
-                        } else {
-                            match self.call_function(handler, vec![current_req]) {
-                                Ok(response) => response,
-                                Err(e) => {
-                                    eprintln!("Handler error: {}", e);
-                                    crate::stdlib::http_server::create_error_response(
-                                        500,
-                                        &e.to_string(),
-                                    )
-                                }
+                        // Run middleware, then the handler unless middleware responded
+                        let (final_response, pending_stream) = match self
+                            .run_middleware(req_value, route_index)
+                        {
+                            MiddlewareOutcome::Respond(early_response) => (early_response, None),
+                            MiddlewareOutcome::Continue(current_req) => {
+                                self.call_route_handler(handler, current_req, is_sse)
                             }
                         };
+                        let final_response = self.commit_sessions(final_response);
 
                         // Convert to BridgeResponse and send back
-                        let bridge_response = BridgeResponse::from_value(&final_response);
-                        let _ = reply_tx.send(bridge_response);
+                        let mut bridge_response = BridgeResponse::from_value(&final_response);
+                        bridge_response.stream = pending_stream.as_ref().map(|p| p.id);
+                        let delivered = reply_tx.send(bridge_response).is_ok();
+
+                        // Streamed bodies are produced after the headers went out
+                        if let Some(pending) = pending_stream {
+                            if !delivered {
+                                // Nobody will read it, so make writes fail
+                                drop(crate::stdlib::http_stream::take(pending.id));
+                            }
+                            self.spawn_stream(pending);
+                        }
                     } else {
                         // No route found
                         let _ = reply_tx.send(BridgeResponse::not_found());
                     }
                 }
-                None => {
-                    // Channel closed, server shutting down
-                    println!("\n🛑 Server shutting down...");
-                    break;
+                // Channel closed, server shutting down
+                None => break,
+            }
+        }
+    }
+
+    /// Run the global middleware, then the route's own, on a request value
+    ///
+    /// Returns the (possibly modified) request, or the response when a
+    /// middleware answered the request itself or failed.
+    fn run_middleware(&mut self, request: Value, route_index: usize) -> MiddlewareOutcome {
+        let middleware_handlers = self.server_state.middleware_for(route_index);
+        let mut current_req = request;
+
+        for mw in middleware_handlers {
+            // sessions() middleware is run natively
+            if let Some(manager_id) = http_session::middleware_id(&mw) {
+                match http_session::attach(manager_id, current_req) {
+                    Ok((req, handle)) => {
+                        self.active_sessions.push(handle);
+                        current_req = req;
+                        continue;
+                    }
+                    Err(e) => {
+                        eprintln!("Session error: {}", e);
+                        return MiddlewareOutcome::Respond(
+                            crate::stdlib::http_server::create_error_response(500, &e.to_string()),
+                        );
+                    }
+                }
+            }
+
+            match self.call_function(mw, vec![current_req.clone()]) {
+                Ok(result) => match &result {
+                    Value::Map(map) if map.contains_key("status") => {
+                        return MiddlewareOutcome::Respond(result)
+                    }
+                    Value::Map(_) => {
+                        if let Err(e) = http_session::check_unchanged(&current_req, &result) {
+                            eprintln!("Middleware error: {}", e);
+                            return MiddlewareOutcome::Respond(
+                                crate::stdlib::http_server::create_error_response(
+                                    500,
+                                    &e.to_string(),
+                                ),
+                            );
+                        }
+                        current_req = result
+                    }
+                    _ => {}
+                },
+                Err(e) => {
+                    eprintln!("Middleware error: {}", e);
+                    return MiddlewareOutcome::Respond(
+                        crate::stdlib::http_server::create_error_response(500, &e.to_string()),
+                    );
                 }
             }
         }
+        MiddlewareOutcome::Continue(current_req)
+    }
 
-        // Wait for server thread to finish
-        let _ = server_handle.join();
+    /// Save the sessions attached by run_middleware and add their cookies
+    /// to the response
+    fn commit_sessions(&mut self, response: Value) -> Value {
+        let mut response = response;
+        for handle in std::mem::take(&mut self.active_sessions) {
+            match http_session::commit(handle, &response) {
+                Ok(with_cookie) => response = with_cookie,
+                Err(e) => {
+                    eprintln!("Session error: {}", e);
+                    response = crate::stdlib::http_server::create_error_response(
+                        500,
+                        "Failed to save session",
+                    );
+                }
+            }
+        }
+        response
+    }
+
+    /// Call a route handler
+    ///
+    /// sse() handlers get the request and an event stream, and are run later
+    /// by `run_stream` or `spawn_stream` once the event-stream headers are sent. A handler that
+    /// returns a stream() response likewise gets its producer run afterwards.
+    fn call_route_handler(
+        &mut self,
+        handler: Value,
+        request: Value,
+        is_sse: bool,
+    ) -> (Value, Option<PendingStream>) {
+        use crate::stdlib::http_stream::{self, StreamKind};
+
+        if is_sse {
+            let id = http_stream::open(StreamKind::Events);
+            let pending = PendingStream {
+                id,
+                producer: handler,
+                args: vec![request, http_stream::stream_value(id)],
+            };
+            return (http_stream::sse_response(), Some(pending));
+        }
+
+        let response = match self.call_function(handler, vec![request]) {
+            Ok(response) => response,
+            Err(e) => {
+                eprintln!("Handler error: {}", e);
+                return (self.handler_error_response(&e), None);
+            }
+        };
+        let producer = match &response {
+            Value::Map(map) => map.get("_stream").cloned(),
+            _ => None,
+        };
+        match producer {
+            Some(producer) => {
+                let id = http_stream::open(StreamKind::Raw);
+                let pending = PendingStream {
+                    id,
+                    producer,
+                    args: vec![http_stream::stream_value(id)],
+                };
+                (response, Some(pending))
+            }
+            None => (response, None),
+        }
+    }
+
+    /// Run the function that writes a streamed body, then end the stream
+    fn run_stream(&mut self, pending: PendingStream) {
+        if let Err(e) = self.call_function(pending.producer, pending.args) {
+            eprintln!("Stream error: {}", e);
+        }
+        crate::stdlib::http_stream::finish(pending.id);
+    }
+
+    /// Run the function that writes a streamed body on a thread of its own,
+    /// so a long-lived stream doesn't keep this interpreter from serving
+    /// requests. Like spawn(), the producer runs in a fresh interpreter built
+    /// from a snapshot of its environment.
+    fn spawn_stream(&self, pending: PendingStream) {
+        let id = pending.id;
+        let started = self
+            .capture_callable(&pending.producer, "stream")
+            .and_then(|(func, closure)| self.task_work(&closure, func, vec![pending.args], false))
+            .and_then(|work| {
+                std::thread::Builder::new()
+                    .name(format!("ntnt-stream-{}", id))
+                    .spawn(move || {
+                        if let Err(e) = work() {
+                            eprintln!("Stream error: {}", e);
+                        }
+                        crate::stdlib::http_stream::finish(id);
+                    })
+                    .map_err(|e| {
+                        IntentError::RuntimeError(format!("Failed to start stream: {}", e))
+                    })
+            });
+        if let Err(e) = started {
+            eprintln!("Stream error: {}", e);
+            crate::stdlib::http_stream::finish(id);
+        }
+    }
+
+    /// Handle activity on a ws() route
+    ///
+    /// Opening runs middleware and the route handler; the connection is
+    /// accepted (status 101) unless either returns a response. Messages and
+    /// closes go to the callbacks registered with on_message()/on_close().
+    fn handle_ws_event(
+        &mut self,
+        request: crate::stdlib::http_bridge::BridgeRequest,
+        event: crate::stdlib::http_bridge::WsEvent,
+    ) -> crate::stdlib::http_bridge::BridgeResponse {
+        use crate::stdlib::http_bridge::{BridgeResponse, WsEvent};
+        use crate::stdlib::http_ws;
+
+        let accepted = || BridgeResponse {
+            status: 101,
+            headers: Vec::new(),
+            body: String::new(),
+            stream: None,
+        };
+
+        match event {
+            WsEvent::Open(id) => {
+                let Some((handler, route_params, route_index)) =
+                    self.server_state.find_route("WS", &request.path)
+                else {
+                    return BridgeResponse::not_found();
+                };
+                let mut full_request = request;
+                full_request.params.extend(route_params);
+
+                let outcome = self.run_middleware(full_request.to_value(), route_index);
+                // Sessions are read-only in WebSocket handlers
+                for handle in std::mem::take(&mut self.active_sessions) {
+                    http_session::discard(handle);
+                }
+                let conn = match outcome {
+                    MiddlewareOutcome::Respond(response) => {
+                        return BridgeResponse::from_value(&response)
+                    }
+                    MiddlewareOutcome::Continue(Value::Map(mut req)) => {
+                        req.insert("_ws_id".to_string(), Value::Int(id as i64));
+                        Value::Map(req)
+                    }
+                    MiddlewareOutcome::Continue(_) => http_ws::connection_value(id, &full_request),
+                };
+
+                // Registered before the handler runs so it can call on_message()
+                self.ws_connections.insert(
+                    id,
+                    WsConnection {
+                        conn: conn.clone(),
+                        on_message: None,
+                        on_close: None,
+                    },
+                );
+                match self.call_function(handler, vec![conn]) {
+                    // A handler that returns a response rejects the connection
+                    Ok(Value::Map(response)) if response.contains_key("status") => {
+                        self.ws_connections.remove(&id);
+                        BridgeResponse::from_value(&Value::Map(response))
+                    }
+                    Ok(_) => accepted(),
+                    Err(e) => {
+                        eprintln!("Handler error: {}", e);
+                        self.ws_connections.remove(&id);
+                        BridgeResponse::from_value(&self.handler_error_response(&e))
+                    }
+                }
+            }
+            WsEvent::Message(id, text) => {
+                let callback = self
+                    .ws_connections
+                    .get(&id)
+                    .and_then(|c| c.on_message.clone().map(|f| (c.conn.clone(), f)));
+                if let Some((conn, on_message)) = callback {
+                    if let Err(e) = self.call_function(on_message, vec![conn, Value::String(text)])
+                    {
+                        eprintln!("WebSocket handler error: {}", e);
+                    }
+                }
+                accepted()
+            }
+            WsEvent::Close(id) => {
+                if let Some(WsConnection {
+                    conn,
+                    on_close: Some(on_close),
+                    ..
+                }) = self.ws_connections.remove(&id)
+                {
+                    if let Err(e) = self.call_function(on_close, vec![conn]) {
+                        eprintln!("WebSocket handler error: {}", e);
+                    }
+                }
+                accepted()
+            }
+        }
+    }
 
+    /// on_message(conn, fn) / on_close(conn, fn) from std/http/ws
+    fn register_ws_callback(&mut self, name: &str, args: Vec<Value>) -> Result<Value> {
+        let [conn, callback] = args.as_slice() else {
+            return Err(IntentError::ArityMismatch {
+                name: name.to_string(),
+                expected: 2,
+                got: args.len(),
+            });
+        };
+        if !matches!(
+            callback,
+            Value::Function { .. } | Value::NativeFunction { .. }
+        ) {
+            return Err(IntentError::TypeError(format!(
+                "{}() requires a function",
+                name
+            )));
+        }
+        let id = crate::stdlib::http_ws::connection_id(conn, name)?;
+        let connection = self.ws_connections.get_mut(&id).ok_or_else(|| {
+            IntentError::RuntimeError(format!("{}() requires an open WebSocket connection", name))
+        })?;
+        if name == "on_message" {
+            connection.on_message = Some(callback.clone());
+        } else {
+            connection.on_close = Some(callback.clone());
+        }
         Ok(Value::Unit)
     }
 
+    /// Call the handlers registered with on_shutdown()
+    fn run_shutdown_handlers(&mut self) {
+        let shutdown_handlers: Vec<Value> = self.server_state.get_shutdown_handlers().to_vec();
+        if !shutdown_handlers.is_empty() {
+            println!("Running shutdown handlers...");
+            for handler in shutdown_handlers {
+                if let Err(e) = self.call_function(handler, vec![]) {
+                    eprintln!("Shutdown handler error: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Convert a route handler error into an HTTP error response
+    ///
+    /// Contract violations map to 400 (precondition: bad input from the client)
+    /// or 500 (postcondition/invariant: server logic error) with a JSON body
+    /// describing the failing clause. Everything else is a plain 500.
+    fn handler_error_response(&mut self, e: &IntentError) -> Value {
+        use crate::stdlib::http_server;
+
+        if let IntentError::ContractViolation(msg) = e {
+            if let Some(violation) = self.last_contract_violation.take() {
+                if msg.starts_with(&violation.headline()) {
+                    return http_server::create_contract_error_response(&violation);
+                }
+            }
+            if msg.contains("Precondition failed") {
+                return http_server::create_error_response(400, &format!("Bad Request: {}", msg));
+            }
+            if msg.contains("Postcondition failed") {
+                return http_server::create_error_response(
+                    500,
+                    &format!("Internal Error: {}", msg),
+                );
+            }
+        }
+        http_server::create_error_response(500, &e.to_string())
+    }
+
+    /// Record a detailed contract violation and turn it into an error
+    fn contract_violation(&mut self, violation: ContractViolation) -> IntentError {
+        let error = IntentError::ContractViolation(violation.to_string());
+        self.last_contract_violation = Some(violation);
+        error
+    }
+
+    /// Build a power-assert style report for a contract clause that evaluated to false
+    ///
+    /// Must be called while the clause's evaluation environment (parameters,
+    /// `result`, old values, struct fields) is still active. `calls` are the
+    /// call values recorded by `eval_contract_clause`; calls are never re-run.
+    fn explain_contract(
+        &mut self,
+        clause_type: ContractType,
+        owner: &str,
+        expr: &Expression,
+        calls: &ContractCalls,
+        params: &[Parameter],
+        args: &[Value],
+    ) -> ContractViolation {
+        let mut violation =
+            ContractViolation::new(clause_type, owner, &Self::format_expression(expr));
+
+        self.collect_contract_values(expr, calls, &mut violation.values);
+        // The clause as a whole is known to be false; only its parts are interesting
+        violation
+            .values
+            .retain(|(label, _)| label != &violation.condition);
+
+        violation.arguments = params
+            .iter()
+            .zip(args.iter())
+            .map(|(param, arg)| (param.name.clone(), Self::format_contract_value(arg)))
+            .collect();
+
+        let mut old_calls = Vec::new();
+        Self::find_old_calls(expr, &mut old_calls);
+        if let Some(ref old_values) = self.current_old_values {
+            for inner in old_calls {
+                if let Some(stored) = old_values.get(&format!("{:?}", inner)) {
+                    let label = format!("old({})", Self::format_expression(inner));
+                    if !violation.old_values.iter().any(|(l, _)| l == &label) {
+                        violation.old_values.push((label, stored.to_string()));
+                    }
+                }
+            }
+        }
+
+        violation
+    }
+
+    /// Evaluate each non-literal sub-expression of a contract clause, outermost first
+    ///
+    /// Calls report the value they returned while the clause was checked, and
+    /// expressions containing a call are skipped rather than evaluated again.
+    fn collect_contract_values(
+        &mut self,
+        expr: &Expression,
+        calls: &ContractCalls,
+        values: &mut Vec<(String, String)>,
+    ) {
+        let children: Vec<&Expression> = match expr {
+            Expression::Integer(_)
+            | Expression::Float(_)
+            | Expression::String(_)
+            | Expression::Bool(_)
+            | Expression::Unit => return,
+            Expression::Identifier(_) => vec![],
+            Expression::Binary { left, right, .. } => vec![left, right],
+            Expression::Unary { operand, .. } => vec![operand],
+            Expression::Call {
+                function,
+                arguments,
+            } => {
+                // old(x) is reported from the snapshot, not the current value of x
+                if matches!(function.as_ref(), Expression::Identifier(name) if name == "old") {
+                    vec![]
+                } else {
+                    arguments.iter().collect()
+                }
+            }
+            Expression::MethodCall {
+                object, arguments, ..
+            } => std::iter::once(object.as_ref())
+                .chain(arguments.iter())
+                .collect(),
+            Expression::FieldAccess { object, .. } => vec![object],
+            Expression::Index { object, index } => vec![object, index],
+            _ => return,
+        };
+
+        let label = Self::format_expression(expr);
+        if !values.iter().any(|(l, _)| l == &label) {
+            let value = if Self::is_contract_call(expr) {
+                // Not recorded if the clause short-circuited before reaching it
+                calls.get(&(expr as *const Expression)).cloned()
+            } else if Self::contains_contract_call(expr) {
+                None
+            } else {
+                self.eval_expression(expr).ok()
+            };
+            if let Some(value) = value {
+                values.push((label, Self::format_contract_value(&value)));
+            }
+        }
+
+        for child in children {
+            self.collect_contract_values(child, calls, values);
+        }
+    }
+
+    /// Whether an expression is a call other than old(), which only reads a snapshot
+    fn is_contract_call(expr: &Expression) -> bool {
+        match expr {
+            Expression::Call { function, .. } => {
+                !matches!(function.as_ref(), Expression::Identifier(name) if name == "old")
+            }
+            Expression::MethodCall { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Whether evaluating an expression would make a call
+    fn contains_contract_call(expr: &Expression) -> bool {
+        if Self::is_contract_call(expr) {
+            return true;
+        }
+        match expr {
+            Expression::Binary { left, right, .. } => {
+                Self::contains_contract_call(left) || Self::contains_contract_call(right)
+            }
+            Expression::Unary { operand, .. } => Self::contains_contract_call(operand),
+            Expression::FieldAccess { object, .. } => Self::contains_contract_call(object),
+            Expression::Index { object, index } => {
+                Self::contains_contract_call(object) || Self::contains_contract_call(index)
+            }
+            // old(x) is looked up, not evaluated
+            Expression::Call { .. } => false,
+            Expression::Identifier(_)
+            | Expression::Integer(_)
+            | Expression::Float(_)
+            | Expression::String(_)
+            | Expression::Bool(_)
+            | Expression::Unit => false,
+            // Anything else isn't reported, but err on the side of not evaluating it
+            _ => true,
+        }
+    }
+
+    /// Find the inner expressions of all old() calls in a contract clause
+    fn find_old_calls<'a>(expr: &'a Expression, found: &mut Vec<&'a Expression>) {
+        match expr {
+            Expression::Call {
+                function,
+                arguments,
+            } => {
+                if matches!(function.as_ref(), Expression::Identifier(name) if name == "old")
+                    && arguments.len() == 1
+                {
+                    found.push(&arguments[0]);
+                    return;
+                }
+                for arg in arguments {
+                    Self::find_old_calls(arg, found);
+                }
+            }
+            Expression::Binary { left, right, .. } => {
+                Self::find_old_calls(left, found);
+                Self::find_old_calls(right, found);
+            }
+            Expression::Unary { operand, .. } => Self::find_old_calls(operand, found),
+            Expression::MethodCall {
+                object, arguments, ..
+            } => {
+                Self::find_old_calls(object, found);
+                for arg in arguments {
+                    Self::find_old_calls(arg, found);
+                }
+            }
+            Expression::FieldAccess { object, .. } => Self::find_old_calls(object, found),
+            Expression::Index { object, index } => {
+                Self::find_old_calls(object, found);
+                Self::find_old_calls(index, found);
+            }
+            _ => {}
+        }
+    }
+
+    /// Format a runtime value for contract reports (strings are quoted)
+    fn format_contract_value(value: &Value) -> String {
+        match value {
+            Value::String(s) => format!("\"{}\"", s),
+            other => other.to_string(),
+        }
+    }
+
     /// Capture old values from expressions in postconditions
     fn capture_old_values(&mut self, ensures: &[Expression]) -> Result<OldValues> {
         let mut old_values = OldValues::new();
@@ -4665,9 +6817,42 @@ impl Interpreter {
                 let args_str: Vec<String> = arguments.iter().map(Self::format_expression).collect();
                 format!("{}({})", func_str, args_str.join(", "))
             }
+            Expression::MethodCall {
+                object,
+                method,
+                arguments,
+            } => {
+                let args_str: Vec<String> = arguments.iter().map(Self::format_expression).collect();
+                format!(
+                    "{}.{}({})",
+                    Self::format_expression(object),
+                    method,
+                    args_str.join(", ")
+                )
+            }
             Expression::FieldAccess { object, field } => {
                 format!("{}.{}", Self::format_expression(object), field)
             }
+            Expression::OptionalChain { object, access } => match access {
+                ChainAccess::Field(field) => {
+                    format!("{}?.{}", Self::format_expression(object), field)
+                }
+                ChainAccess::Index(index) => format!(
+                    "{}?.[{}]",
+                    Self::format_expression(object),
+                    Self::format_expression(index)
+                ),
+                ChainAccess::Method { method, arguments } => {
+                    let args_str: Vec<String> =
+                        arguments.iter().map(Self::format_expression).collect();
+                    format!(
+                        "{}?.{}({})",
+                        Self::format_expression(object),
+                        method,
+                        args_str.join(", ")
+                    )
+                }
+            },
             Expression::Index { object, index } => {
                 format!(
                     "{}[{}]",
@@ -4683,6 +6868,59 @@ impl Interpreter {
         }
     }
 
+    /// Check a value against the refinement predicate of its declared type
+    ///
+    /// Refinements of refined aliases are checked base-first, so
+    /// `type HighPort = Port where self > 1024` also enforces `Port`'s predicate.
+    fn check_refinement(
+        &mut self,
+        type_expr: &TypeExpr,
+        value: &Value,
+        subject: &str,
+    ) -> Result<()> {
+        let name = match type_expr {
+            TypeExpr::Named(name) => name,
+            TypeExpr::Optional(inner) => {
+                if matches!(value, Value::EnumValue { enum_name, variant, .. } if enum_name == "Option" && variant == "None")
+                {
+                    return Ok(());
+                }
+                return self.check_refinement(inner, value, subject);
+            }
+            _ => return Ok(()),
+        };
+
+        if let Some(target) = self.type_aliases.get(name).cloned() {
+            if !matches!(&target, TypeExpr::Named(t) if t == name) {
+                self.check_refinement(&target, value, subject)?;
+            }
+        }
+
+        let Some((predicate, declaring_env)) = self.refinements.get(name).cloned() else {
+            return Ok(());
+        };
+
+        let previous = Rc::clone(&self.environment);
+        let refinement_env = Rc::new(RefCell::new(Environment::with_parent(declaring_env)));
+        refinement_env
+            .borrow_mut()
+            .define("self".to_string(), value.clone());
+        self.environment = refinement_env;
+        let result = self.eval_expression(&predicate);
+        self.environment = previous;
+
+        if !result?.is_truthy() {
+            return Err(IntentError::ContractViolation(format!(
+                "Refinement failed for {}: {} is not a valid {} (requires {})",
+                subject,
+                Self::format_contract_value(value),
+                name,
+                Self::format_expression(&predicate)
+            )));
+        }
+        Ok(())
+    }
+
     /// Check struct invariants after construction or mutation
     fn check_struct_invariants(&mut self, struct_name: &str, struct_val: &Value) -> Result<()> {
         // Look up invariants for this struct type
@@ -4719,17 +6957,25 @@ impl Interpreter {
 
         // Check each invariant
         for inv_expr in &invariants {
-            let condition_str = Self::format_expression(inv_expr);
-            let result = self.eval_expression(inv_expr)?;
+            if !self.contracts.should_check(ContractType::Invariant) {
+                continue;
+            }
+            let (result, calls) = self.eval_contract_clause(inv_expr)?;
 
             if !result.is_truthy() {
-                self.environment = previous;
-                return Err(IntentError::ContractViolation(format!(
-                    "Invariant violated for '{}': {}",
-                    struct_name, condition_str
-                )));
+                let violation = self.explain_contract(
+                    ContractType::Invariant,
+                    struct_name,
+                    inv_expr,
+                    &calls,
+                    &[],
+                    &[],
+                );
+                if self.contracts.report_violation(&violation) {
+                    self.environment = previous;
+                    return Err(self.contract_violation(violation));
+                }
             }
-            self.contracts.check_invariant(&condition_str, true, None)?;
         }
 
         self.environment = previous;
@@ -4818,6 +7064,12 @@ impl Interpreter {
             (BinaryOp::Ge, Value::Int(a), Value::Float(b)) => Ok(Value::Bool((a as f64) >= b)),
             (BinaryOp::Ge, Value::Float(a), Value::Int(b)) => Ok(Value::Bool(a >= (b as f64))),
 
+            // None checks: only None equals None (`x != None` is true for Some and plain values)
+            (op @ (BinaryOp::Eq | BinaryOp::Ne), lhs, rhs) if lhs.is_none() || rhs.is_none() => {
+                let equal = lhs.is_none() && rhs.is_none();
+                Ok(Value::Bool(if op == BinaryOp::Eq { equal } else { !equal }))
+            }
+
             (op, lhs, rhs) => Err(IntentError::InvalidOperation(format!(
                 "Cannot apply {:?} to {} and {}",
                 op,
@@ -4853,6 +7105,42 @@ impl Default for Interpreter {
     }
 }
 
+/// Whether NTNT_ENV selects production mode (hot-reload disabled)
+fn is_production_env() -> bool {
+    std::env::var("NTNT_ENV")
+        .map(|v| v == "production" || v == "prod")
+        .unwrap_or(false)
+}
+
+/// Copy the interpreter's routes and static directories into the async server
+/// state. The new table is built first and swapped in whole, so requests
+/// arriving meanwhile (on any pool worker) still match the previous routes.
+fn sync_routes_to_async(
+    server_state: &crate::stdlib::http_server::ServerState,
+    async_routes: &crate::stdlib::http_server_async::AsyncServerState,
+    rt: &tokio::runtime::Runtime,
+    version: Option<std::time::SystemTime>,
+) {
+    let mut table = crate::stdlib::http_server_async::RouteTable::new();
+    table.version = version;
+
+    // Copy routes (the interpreter already rejected invalid or conflicting ones)
+    for (route, _handler, _source) in &server_state.routes {
+        if let Err(e) =
+            table.register_route(&route.method, &route.pattern, "handler", route.max_body)
+        {
+            eprintln!("Failed to register route {}: {}", route.pattern, e);
+        }
+    }
+
+    // Copy static directories
+    for (url_prefix, fs_path) in &server_state.static_dirs {
+        table.register_static_dir(url_prefix, fs_path);
+    }
+
+    async_routes.replace_blocking(rt, table);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -4932,6 +7220,161 @@ mod tests {
         assert!(err.to_string().contains("Precondition failed"));
     }
 
+    #[test]
+    fn test_contract_violation_shows_values() {
+        let result = eval(
+            r#"
+            fn divide(a, b) ensures result * b == a { return a / b; }
+            divide(7, 2)
+        "#,
+        );
+        let msg = result.unwrap_err().to_string();
+        assert!(msg.contains("Postcondition failed in 'divide': result * b == a"));
+        assert!(msg.contains("result * b = 6"));
+        assert!(msg.contains("result = 3"));
+        assert!(msg.contains("arguments: a = 7, b = 2"));
+    }
+
+    #[test]
+    fn test_contract_violation_does_not_repeat_calls() {
+        let result = eval(
+            r#"
+            let mut issued = 0
+            fn next_id() {
+                issued = issued + 1
+                return issued
+            }
+            fn reserve(n) requires next_id() > n && n.abs() >= 0 { return n; }
+            reserve(5)
+        "#,
+        );
+        let msg = result.unwrap_err().to_string();
+        // The value from the check itself, not from calling next_id() again
+        assert!(msg.contains("next_id() = 1"), "{}", msg);
+        assert!(!msg.contains("next_id() > n ="), "{}", msg);
+        // Never reached because && short-circuited
+        assert!(!msg.contains("n.abs() ="), "{}", msg);
+    }
+
+    #[test]
+    fn test_contract_violation_shows_old_values() {
+        let result = eval(
+            r#"
+            fn bump(x) ensures result == old(x) + 2 { return x + 1; }
+            bump(4)
+        "#,
+        );
+        let msg = result.unwrap_err().to_string();
+        assert!(msg.contains("result = 5"));
+        assert!(msg.contains("old: old(x) = 4"));
+    }
+
+    #[test]
+    fn test_contract_log_mode_continues() {
+        let source = r#"
+            fn divide(a, b) requires b != 0 ensures result >= 0 { return a - b; }
+            divide(1, 0) + divide(1, 5)
+        "#;
+        let tokens: Vec<_> = Lexer::new(source).collect();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.set_contract_config(ContractConfig {
+            requires: EnforcementMode::Log,
+            ensures: EnforcementMode::Off,
+            ..ContractConfig::default()
+        });
+
+        let result = interpreter.eval(&ast).unwrap();
+        assert!(matches!(result, Value::Int(-3)));
+        // Two requires checks (one violation); ensures is off and never evaluated
+        assert_eq!(interpreter.contract_stats(), (2, 1));
+    }
+
+    #[test]
+    fn test_none_comparison() {
+        assert!(matches!(eval("None == None"), Ok(Value::Bool(true))));
+        assert!(matches!(eval("Some(1) != None"), Ok(Value::Bool(true))));
+        assert!(matches!(eval("None != \"a\""), Ok(Value::Bool(true))));
+        assert!(matches!(eval("5 == None"), Ok(Value::Bool(false))));
+    }
+
+    #[test]
+    fn test_refinement_checked_on_let_params_and_fields() {
+        let prelude = r#"
+            type Port = Int where self > 0 && self < 65536
+            type Privileged = Port where self < 1024
+            struct Server { port: Privileged }
+            fn connect(p: Port) -> Int { return p }
+        "#;
+        let ok = eval(&format!(
+            "{} let p: Port = 8080\n connect(p) + Server {{ port: 80 }}.port",
+            prelude
+        ));
+        assert!(matches!(ok, Ok(Value::Int(8160))));
+
+        let err = eval(&format!("{} let p: Port = 0", prelude)).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Refinement failed for variable 'p': 0 is not a valid Port"));
+
+        let err = eval(&format!("{} connect(70000)", prelude)).unwrap_err();
+        assert!(err.to_string().contains("parameter 'p' of 'connect'"));
+
+        // Predicates of the base refinement apply too
+        let err = eval(&format!("{} Server {{ port: 8080 }}", prelude)).unwrap_err();
+        assert!(err.to_string().contains(
+            "field 'Server.port': 8080 is not a valid Privileged (requires self < 1024)"
+        ));
+        let err = eval(&format!("{} Server {{ port: -1 }}", prelude)).unwrap_err();
+        assert!(err.to_string().contains("not a valid Port"));
+    }
+
+    #[test]
+    fn test_refinement_checked_on_return_and_mutation() {
+        let prelude = r#"
+            type Port = Int where self > 0 && self < 65536
+            struct Server { port: Port }
+            fn pick(n) -> Port { return n }
+            fn bump(p: Port) { p = p * 100; return p }
+        "#;
+        let ok = eval(&format!(
+            "{} let mut p: Port = pick(80)\n p = 8080\n let mut s = Server {{ port: p }}\n s.port = 443\n s.port",
+            prelude
+        ));
+        assert!(matches!(ok, Ok(Value::Int(443))), "{:?}", ok);
+
+        let err = eval(&format!("{} pick(0)", prelude)).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Refinement failed for return value of 'pick': 0 is not a valid Port"));
+
+        let err = eval(&format!("{} let mut p: Port = 80\n p = 70000", prelude)).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Refinement failed for variable 'p': 70000 is not a valid Port"));
+
+        let err = eval(&format!("{} bump(1000)", prelude)).unwrap_err();
+        assert!(err.to_string().contains("variable 'p': 100000"), "{}", err);
+
+        let err = eval(&format!(
+            "{} let mut s = Server {{ port: 80 }}\n s.port = -1",
+            prelude
+        ))
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("field 'Server.port': -1"),
+            "{}",
+            err
+        );
+
+        // An unannotated binding shadowing a refined one isn't checked
+        let ok = eval(&format!(
+            "{} let p: Port = 80\n let mut p = p\n p = 0\n p",
+            prelude
+        ));
+        assert!(matches!(ok, Ok(Value::Int(0))), "{:?}", ok);
+    }
+
     #[test]
     fn test_contract_postcondition_passes() {
         // Postcondition passes when result >= 0
@@ -5631,6 +8074,449 @@ mod tests {
         assert!(matches!(result, Value::Int(1)));
     }
 
+    #[test]
+    fn test_match_or_and_range_patterns() {
+        let result = eval(
+            r#"
+            fn classify(code) {
+                return match code {
+                    301 | 302 | 307 => "redirect",
+                    200..=299 => "ok",
+                    400..500 => "client",
+                    _ => "other"
+                }
+            }
+            [classify(204), classify(302), classify(499), classify(500)]
+        "#,
+        )
+        .unwrap();
+        match result {
+            Value::Array(values) => {
+                let strs: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                assert_eq!(strs, vec!["ok", "redirect", "client", "other"]);
+            }
+            other => panic!("expected array, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_match_binding_and_map_patterns() {
+        let result = eval(
+            r#"
+            let event = map { "type": "card", "amount": 42, "currency": "EUR" }
+            let amount = match event {
+                { "type": "cash" } => 0,
+                { "type": "card", "amount": a } => a,
+                _ => -1
+            }
+            let resp = map { "status": 500 }
+            let status = match resp {
+                r @ { status: 500 } => r["status"],
+                _ => 0
+            }
+            amount + status
+        "#,
+        )
+        .unwrap();
+        assert!(matches!(result, Value::Int(542)));
+    }
+
+    #[test]
+    fn test_match_array_rest_patterns() {
+        let result = eval(
+            r#"
+            let xs = [1, 2, 3, 4]
+            let head = match xs { [first, ..] => first, [] => 0 }
+            let tail_len = match xs { [_, ..rest] => len(rest), _ => 0 }
+            let last = match xs { [.., z] => z, _ => 0 }
+            let single = match [7] { [a, .., b] => 100, [a] => a, _ => 0 }
+            head * 1000 + tail_len * 100 + last * 10 + single
+        "#,
+        )
+        .unwrap();
+        assert!(matches!(result, Value::Int(1347)));
+    }
+
+    #[test]
+    fn test_optional_chaining_short_circuits() {
+        let result = eval(
+            r#"
+            let data = map { "user": map { "name": "Ada", "tags": ["a", "b"], "phone": None } }
+            let missing = None
+            let results = [
+                data?.user?.name ?? "anon",
+                data?.user?.address?.city ?? "unknown",
+                data?.user?.phone?.number ?? "no phone",
+                data?.user?.tags?.[1] ?? "none",
+                data?.user?.tags?.[9] ?? "none",
+                missing?.anything ?? "was none",
+                Some(map { "x": "wrapped" })?.x ?? "none"
+            ]
+            results
+        "#,
+        )
+        .unwrap();
+        match result {
+            Value::Array(values) => {
+                let strs: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                assert_eq!(
+                    strs,
+                    vec!["Ada", "unknown", "no phone", "b", "none", "was none", "wrapped"]
+                );
+            }
+            other => panic!("expected array, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_optional_chaining_method_call() {
+        let result = eval(
+            r#"
+            let data = map { "tags": ["a", "b", "c"] }
+            let missing = None
+            let n = data?.tags?.len() ?? 0
+            let m = missing?.len() ?? 0
+            n * 10 + m
+        "#,
+        )
+        .unwrap();
+        assert!(matches!(result, Value::Int(30)));
+    }
+
+    #[test]
+    fn test_async_fn_runs_on_worker() {
+        let result = eval(
+            r#"
+            import { await_all, await_any, sleep_ms } from "std/concurrent"
+            let factor = 10
+            fn scale(n) { return n * factor }
+            async fn slow(n, ms) {
+                sleep_ms(ms)
+                return scale(n)
+            }
+            let first = await slow(1, 5)
+            let all = await_all([slow(1, 30), slow(2, 20), slow(3, 10)])
+            let fastest = await_any([slow(7, 500), slow(8, 1)])
+            first + all[0] + all[1] + all[2] + fastest + await 5
+        "#,
+        )
+        .unwrap();
+        assert!(matches!(result, Value::Int(155)));
+    }
+
+    #[test]
+    fn test_async_fn_returns_task_handle() {
+        let result = eval(
+            r#"
+            async fn make(x) { return x }
+            make(1)
+        "#,
+        )
+        .unwrap();
+        assert!(crate::stdlib::concurrent::get_task_id(&result).is_some());
+    }
+
+    #[test]
+    fn test_discarded_async_call_is_detached() {
+        use crate::stdlib::concurrent::{await_task, get_task_id, task_registered};
+
+        let mut interpreter = Interpreter::new();
+        let parse = |source: &str| Parser::new(Lexer::new(source).collect()).parse().unwrap();
+        interpreter
+            .eval(&parse(
+                r#"
+                async fn work() { return 1 }
+                fn start() { work() }
+            "#,
+            ))
+            .unwrap();
+
+        // A bare `async fn` call's handle is thrown away, so the task is detached
+        let bare = parse("work()");
+        let value = interpreter.eval_statement(&bare.statements[0]).unwrap();
+        let id = get_task_id(&value).unwrap();
+        interpreter.detach_discarded_task(&bare.statements[0], &value);
+        let mut removed = false;
+        for _ in 0..200 {
+            if !task_registered(id) {
+                removed = true;
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(removed, "detached task {} was never removed", id);
+
+        // A handle returned from a plain function call may be a stored one; keep it
+        let wrapped = parse("start()");
+        let value = interpreter.eval_statement(&wrapped.statements[0]).unwrap();
+        let id = get_task_id(&value).unwrap();
+        interpreter.detach_discarded_task(&wrapped.statements[0], &value);
+        assert!(matches!(await_task(id).unwrap(), Value::Int(1)));
+    }
+
+    #[test]
+    fn test_await_failed_task() {
+        let result = eval(
+            r#"
+            async fn boom() {
+                let m = map {}
+                return m["missing"]
+            }
+            await boom()
+        "#,
+        );
+        match result {
+            Err(e) => assert!(e.to_string().contains("Task failed"), "{}", e),
+            Ok(v) => panic!("expected failure, got {:?}", v),
+        }
+    }
+
+    #[test]
+    fn test_spawn_parallel_and_parallel_map() {
+        let result = eval(
+            r#"
+            import { spawn, join, parallel, parallel_map } from "std/concurrent"
+            let offset = 100
+            fn add(a, b) { return a + b + offset }
+            fn one() { return 1 }
+            fn two() { return 2 }
+            fn square(n) { return n * n }
+            let fns = [one, two]
+            let spawned = join(spawn(add, [1, 2])) + await spawn(one)
+            let both = parallel(fns)
+            let squares = parallel_map([1, 2, 3, 4, 5, 6, 7], square, 3)
+            let defaults = parallel_map([3], square)
+            let out = [spawned, both[0] + both[1], squares, defaults]
+            out
+        "#,
+        )
+        .unwrap();
+        assert_eq!(
+            format!("{}", result),
+            "[104, 3, [1, 4, 9, 16, 25, 36, 49], [9]]"
+        );
+    }
+
+    #[test]
+    fn test_spawned_functions_wait_on_each_other() {
+        let result = eval(
+            r#"
+            import { spawn, join, channel, send, recv } from "std/concurrent"
+            let jobs = channel()
+            fn consumer(jobs) { return recv(jobs) }
+            let mut consumers = []
+            for i in 0..20 {
+                consumers = push(consumers, spawn(consumer, [jobs]))
+            }
+            let producer = spawn(fn() {
+                for i in 0..20 {
+                    send(jobs, i)
+                }
+                return 20
+            }, [])
+            let mut total = join(producer)
+            for c in consumers {
+                total = total + join(c)
+            }
+            total
+        "#,
+        )
+        .unwrap();
+        assert!(matches!(result, Value::Int(210)));
+    }
+
+    #[test]
+    fn test_tasks_accept_lambdas_with_explicit_return() {
+        let result = eval(
+            r#"
+            import { spawn, join, parallel_map } from "std/concurrent"
+            let spawned = join(spawn(fn() { return 4 }, []))
+            let doubled = parallel_map([1, 2, 3], fn(n) { return n * 2 }, 2)
+            let add_one = fn(n) { return n + 1 }
+            let out = [spawned, doubled, add_one(1)]
+            out
+        "#,
+        )
+        .unwrap();
+        assert_eq!(format!("{}", result), "[4, [2, 4, 6], 2]");
+    }
+
+    #[test]
+    fn test_spawn_must_be_called_directly() {
+        let result = eval(
+            r#"
+            import { spawn } from "std/concurrent"
+            fn one() { return 1 }
+            let s = spawn
+            s(one)
+        "#,
+        );
+        match result {
+            Err(e) => assert!(e.to_string().contains("must be called directly"), "{}", e),
+            Ok(v) => panic!("expected error, got {:?}", v),
+        }
+    }
+
+    #[test]
+    fn test_spawn_reresolves_module_imports() {
+        let dir = std::env::temp_dir().join(format!("ntnt_spawn_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("work.tnt"),
+            "fn helper(x) { return x * 10 }\nexport fn work(x) { return helper(x) + 1 }\n",
+        )
+        .unwrap();
+        let source = r#"
+            import { spawn, join, parallel_map } from "std/concurrent"
+            import { work } from "./work"
+            join(spawn(work, [4])) + parallel_map([1, 2], work, 2)[1]
+        "#;
+        let tokens: Vec<_> = Lexer::new(source).collect();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.current_file = Some(dir.join("main.tnt").to_string_lossy().to_string());
+        let result = interpreter.eval(&ast);
+        std::fs::remove_dir_all(&dir).ok();
+        assert!(matches!(result.unwrap(), Value::Int(62)));
+    }
+
+    #[test]
+    fn test_sync_state_shared_with_workers() {
+        let result = eval(
+            r#"
+            import { parallel_map } from "std/concurrent"
+            import { atomic_int, atomic_add, atomic_get, shared_map, shared_update, shared_get, lazy, force } from "std/sync"
+            let hits = atomic_int(0)
+            let counts = shared_map()
+            fn bump(n) { return n + 1 }
+            fn work(x) {
+                atomic_add(hits, 1)
+                shared_update(counts, "even{x % 2 == 0}", bump, 0)
+                return x
+            }
+            let items = [1, 2, 3, 4, 5, 6, 7, 8]
+            parallel_map(items, work, 4)
+            let loads = atomic_int(0)
+            fn load() {
+                atomic_add(loads, 1)
+                return 42
+            }
+            let config = lazy(load)
+            let total = force(config) + force(config)
+            let evens = shared_get(counts, "eventrue") ?? 0
+            let out = [atomic_get(hits), evens, total, atomic_get(loads)]
+            out
+        "#,
+        )
+        .unwrap();
+        assert_eq!(format!("{}", result), "[8, 4, 84, 1]");
+    }
+
+    #[test]
+    fn test_schedules_run_until_finished() {
+        let source = r#"
+            import { every, after, cancel } from "std/schedule"
+            import { atomic_int, atomic_add, atomic_get } from "std/sync"
+            let ticks = atomic_int(0)
+            let ticker = 0
+            fn tick() { atomic_add(ticks, 1) }
+            fn stop() {
+                if atomic_get(ticks) >= 3 {
+                    cancel(ticker)
+                } else {
+                    after(20, stop)
+                }
+            }
+            ticker = every("10ms", tick)
+            after(25, stop)
+        "#;
+        let tokens: Vec<_> = Lexer::new(source).collect();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.eval(&ast).unwrap();
+        interpreter.run_schedules().unwrap();
+        let ticks = interpreter.eval_expression(&Expression::Call {
+            function: Box::new(Expression::Identifier("atomic_get".to_string())),
+            arguments: vec![Expression::Identifier("ticks".to_string())],
+        });
+        assert!(matches!(ticks, Ok(Value::Int(n)) if n >= 3));
+        assert!(!crate::stdlib::schedule::has_jobs(interpreter.scheduler_id));
+    }
+
+    #[test]
+    fn test_schedules_skipped_in_unit_test_mode() {
+        let source = r#"
+            import { every } from "std/schedule"
+            fn tick() { return 1 }
+            every("1s", tick)
+        "#;
+        let tokens: Vec<_> = Lexer::new(source).collect();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.set_execution_mode(ExecutionMode::UnitTest);
+        interpreter.eval(&ast).unwrap();
+        assert!(interpreter.schedule_handlers.is_empty());
+        assert!(!crate::stdlib::schedule::has_jobs(interpreter.scheduler_id));
+    }
+
+    #[test]
+    fn test_assigning_to_req_session_is_an_error() {
+        for edit in [
+            "req.session[\"cart\"] = 3",
+            "req.session = map {}",
+            "req.session.cart = 3",
+            "let s = req.session\n s[\"cart\"] = 3",
+        ] {
+            let source = format!(
+                "let mut req = map {{ \"session\": map {{ \"_session_id\": 1 }} }}\n{}",
+                edit
+            );
+            let err = eval(&source).unwrap_err();
+            assert!(err.to_string().contains("session_set"), "{}: {}", edit, err);
+        }
+        let err = eval("let mut m = map { \"a\": map {} }\nm[\"a\"] = 1").unwrap_err();
+        assert!(
+            err.to_string().contains("Invalid assignment target"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_job_workers_run_retry_and_dead_letter() {
+        let source = r#"
+            import { configure, enqueue, worker, run_jobs, list_jobs } from "std/jobs"
+            fn ok(args) { return args["n"] * 2 }
+            fn broken(args, job) { return Err("attempt {job["attempts"]}") }
+            configure(map { "db": ":memory:" })
+            worker("ok", ok)
+            worker("broken", broken)
+            enqueue("ok", map { "n": 1 })
+            enqueue("ok", map { "n": 2 })
+            enqueue("broken", map {}, map { "max_attempts": 2, "backoff": 0 })
+            let ran = run_jobs()
+            let dead = list_jobs("dead")
+            let out = [ran, len(list_jobs("done")), len(dead), dead[0]["last_error"]]
+            out
+        "#;
+        let tokens: Vec<_> = Lexer::new(source).collect();
+        let ast = Parser::new(tokens).parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.set_execution_mode(ExecutionMode::UnitTest);
+        let result = interpreter.eval(&ast).unwrap();
+        match result {
+            Value::Array(values) => match values.as_slice() {
+                [Value::Int(4), Value::Int(2), Value::Int(1), Value::String(error)] => {
+                    assert_eq!(error, "attempt 2")
+                }
+                other => panic!("Unexpected job results: {:?}", other),
+            },
+            other => panic!("Expected array, got {:?}", other),
+        }
+        // Unit tests register workers without polling the queue
+        assert!(interpreter.jobs_poller.is_none());
+    }
+
     #[test]
     fn test_enum_unit_variants() {
         // Enum with only unit variants
@@ -6539,6 +9425,40 @@ c")
         }
     }
 
+    #[test]
+    fn test_interpolated_string_with_format_spec() {
+        let result = eval(
+            r#"
+            let item = "Widget"
+            let price = 1234.5
+            let qty = 12000
+            "{item:<8}|{price:>10,.2}|{qty:,}|{qty:#x}"
+        "#,
+        )
+        .unwrap();
+        if let Value::String(s) = result {
+            assert_eq!(s, "Widget  |  1,234.50|12,000|0x2ee0");
+        } else {
+            panic!("Expected string, got {:?}", result);
+        }
+
+        let err = eval(r#"let name = "x"; "{name:,}""#).unwrap_err();
+        assert!(err.to_string().contains("requires a number"), "{}", err);
+    }
+
+    #[test]
+    fn test_format_builtin() {
+        let result = eval(r#"format("{:>6}|{:.1%}|{1:+}", "ab", 0.256)"#).unwrap();
+        if let Value::String(s) = result {
+            assert_eq!(s, "    ab|25.6%|+0.256");
+        } else {
+            panic!("Expected string, got {:?}", result);
+        }
+
+        let err = eval(r#"format("{} {}", 1)"#).unwrap_err();
+        assert!(err.to_string().contains("uses 2 arguments"), "{}", err);
+    }
+
     #[test]
     fn test_defer_basic() {
         // Defer should execute when scope exits
@@ -6685,6 +9605,36 @@ c")
         }
     }
 
+    #[test]
+    fn test_trait_bounds_checked_at_runtime() {
+        let prelude = r#"
+            trait Comparable { fn compare(self, other) -> Int }
+            trait Ordered: Comparable { fn rank(self) -> Int }
+            struct User { id: Int }
+            struct Tag { name: String }
+            struct Score { v: Int }
+            impl Comparable for User { fn compare(self, other) -> Int { return self.id - other.id } }
+            impl Ordered for Score { fn rank(self) -> Int { return self.v } }
+            fn sort<T: Comparable>(arr: [T]) -> [T] { return arr }
+        "#;
+
+        // Direct impl, impl through a subtrait, and primitives all pass
+        let ok = eval(&format!(
+            "{} len(sort([User {{ id: 1 }}])) + len(sort([Score {{ v: 1 }}])) + len(sort([1, 2]))",
+            prelude
+        ));
+        assert!(matches!(ok, Ok(Value::Int(4))));
+
+        let err = eval(&format!(
+            "{} sort([User {{ id: 1 }}, Tag {{ name: \"x\" }}])",
+            prelude
+        ))
+        .unwrap_err();
+        assert!(err.to_string().contains(
+            "Type 'Tag' does not implement trait 'Comparable' required by 'sort<T: Comparable>'"
+        ));
+    }
+
     #[test]
     fn test_struct_with_bounded_type_param() {
         let result = eval(
@@ -12,6 +12,62 @@ pub enum StringPart {
     Literal(String),
     /// Expression to be interpolated (stored as string, parsed later)
     Interpolation(String),
+    /// Expression with a format spec: `{price:.2}`
+    Formatted { expr: String, spec: String },
+}
+
+/// Split `{expr:spec}` at the first `:` outside brackets and quotes.
+/// `::` paths don't count.
+fn split_format_spec(content: &str) -> Option<(&str, &str)> {
+    let bytes = content.as_bytes();
+    let mut depth = 0i32;
+    let mut quote: Option<u8> = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None => match b {
+                b'"' | b'\'' => quote = Some(b),
+                b'(' | b'[' | b'{' => depth += 1,
+                b')' | b']' | b'}' => depth -= 1,
+                b':' if bytes.get(i + 1) == Some(&b':') => i += 1,
+                b':' if depth == 0 => return Some((&content[..i], &content[i + 1..])),
+                _ => {}
+            },
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Whether `content` is a route catch-all like `*path`
+fn is_catch_all(content: &str) -> bool {
+    content.strip_prefix('*').is_some_and(|name| {
+        name.chars()
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+    })
+}
+
+/// Whether `spec` after `head:` is a format spec rather than text that only
+/// looks like one (`"{ x: 1 }"`). Unknown specs on a bare name are kept so
+/// they can be reported (`{price:.2q}`).
+fn is_format_spec(head: &str, spec: &str) -> bool {
+    if head.is_empty() || head.chars().all(|c| c.is_ascii_digit()) {
+        return true;
+    }
+    if crate::format::FormatSpec::parse(spec).is_ok() {
+        return true;
+    }
+    let is_name = head
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && head.chars().all(|c| c.is_alphanumeric() || c == '_');
+    is_name && !spec.is_empty() && !spec.chars().any(char::is_whitespace)
 }
 
 /// Part of a template string (triple-quoted)
@@ -158,6 +214,7 @@ pub enum TokenKind {
     FatArrow,         // =>
     Question,         // ?
     QuestionQuestion, // ??
+    QuestionDot,      // ?.
     At,               // @
     Hash,             // #
     Ampersand,        // &
@@ -323,12 +380,6 @@ impl<'a> Lexer<'a> {
                     None => break,
                 }
             } else if ch == '{' {
-                // Start of interpolation
-                has_interpolation = true;
-                if !value.is_empty() {
-                    parts.push(StringPart::Literal(value.clone()));
-                    value.clear();
-                }
                 self.advance(); // consume '{'
 
                 // Read until matching '}'
@@ -350,7 +401,36 @@ impl<'a> Lexer<'a> {
                     self.advance();
                 }
                 self.advance(); // consume '}'
-                parts.push(StringPart::Interpolation(expr_str));
+
+                // format() placeholders ({}, {0}, {:>8}, {1:.2}) and route
+                // catch-alls ({*path}) stay in the text
+                let (head, spec) = match split_format_spec(&expr_str) {
+                    Some((head, spec)) if is_format_spec(head.trim(), spec) => {
+                        (head.trim(), Some(spec))
+                    }
+                    _ => (expr_str.trim(), None),
+                };
+                if head.is_empty() || head.chars().all(|c| c.is_ascii_digit()) || is_catch_all(head)
+                {
+                    value.push('{');
+                    value.push_str(&expr_str);
+                    value.push('}');
+                    continue;
+                }
+
+                // Start of interpolation
+                has_interpolation = true;
+                if !value.is_empty() {
+                    parts.push(StringPart::Literal(value.clone()));
+                    value.clear();
+                }
+                parts.push(match spec {
+                    Some(spec) => StringPart::Formatted {
+                        expr: head.to_string(),
+                        spec: spec.to_string(),
+                    },
+                    None => StringPart::Interpolation(expr_str),
+                });
             } else {
                 value.push(ch);
                 self.advance();
@@ -1336,6 +1416,14 @@ impl<'a> Lexer<'a> {
                         start_column,
                         "??".into(),
                     )
+                } else if self.peek() == Some(&'.') {
+                    self.advance();
+                    Token::new(
+                        TokenKind::QuestionDot,
+                        start_line,
+                        start_column,
+                        "?.".into(),
+                    )
                 } else {
                     Token::new(TokenKind::Question, start_line, start_column, "?".into())
                 }
@@ -1389,6 +1477,39 @@ mod tests {
         assert!(matches!(&tokens[0].kind, TokenKind::String(s) if s == "hello world"));
     }
 
+    #[test]
+    fn test_format_placeholders_and_specs() {
+        let tokens: Vec<_> = Lexer::new(r#""{} {0} {:>8}""#).collect();
+        assert!(matches!(&tokens[0].kind, TokenKind::String(s) if s == "{} {0} {:>8}"));
+
+        let tokens: Vec<_> = Lexer::new(r#""{price:,.2} {items[0]} { x: 1 }""#).collect();
+        let TokenKind::InterpolatedString(parts) = &tokens[0].kind else {
+            panic!("expected interpolated string, got {:?}", tokens[0].kind);
+        };
+        assert_eq!(
+            parts[0],
+            StringPart::Formatted {
+                expr: "price".to_string(),
+                spec: ",.2".to_string()
+            }
+        );
+        assert_eq!(parts[2], StringPart::Interpolation("items[0]".to_string()));
+        assert_eq!(parts[4], StringPart::Interpolation(" x: 1 ".to_string()));
+    }
+
+    #[test]
+    fn test_route_catch_all_stays_literal() {
+        let tokens: Vec<_> = Lexer::new(r#""/files/{*path}""#).collect();
+        assert!(matches!(&tokens[0].kind, TokenKind::String(s) if s == "/files/{*path}"));
+
+        let tokens: Vec<_> = Lexer::new(r#""/users/{id}/{*rest}""#).collect();
+        let TokenKind::InterpolatedString(parts) = &tokens[0].kind else {
+            panic!("expected interpolated string, got {:?}", tokens[0].kind);
+        };
+        assert_eq!(parts[1], StringPart::Interpolation("id".to_string()));
+        assert_eq!(parts[2], StringPart::Literal("/{*rest}".to_string()));
+    }
+
     #[test]
     fn test_function() {
         let source = "fn add(x, y) { return x + y; }";
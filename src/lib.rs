@@ -6,6 +6,7 @@
 pub mod ast;
 pub mod contracts;
 pub mod error;
+pub mod format;
 pub mod ial;
 pub mod intent;
 pub mod intent_studio_server;
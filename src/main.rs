@@ -2,12 +2,12 @@
 //!
 //! Command-line interface for the NTNT (Intent) programming language.
 
-use clap::{CommandFactory, Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
 use clap_complete::{generate, Shell};
 use colored::*;
 use ntnt::{
-    error::IntentError, intent, intent_studio_server, interpreter::Interpreter, lexer::Lexer,
-    parser::Parser as IntentParser,
+    contracts::ContractConfig, error::IntentError, intent, intent_studio_server,
+    interpreter::Interpreter, lexer::Lexer, parser::Parser as IntentParser,
 };
 use rustyline::error::ReadlineError;
 use rustyline::DefaultEditor;
@@ -23,7 +23,7 @@ use std::path::PathBuf;
 #[command(
     long_about = "NTNT (Intent) - A programming language for AI-driven development\n\n\
 Environment variables:\n  \
-NTNT_ENV=production    Disable hot-reload for better performance\n  \
+NTNT_ENV=production    Disable hot-reload, log (not enforce) ensures/invariant failures\n  \
 NTNT_TIMEOUT=60        Request timeout in seconds (default: 30)\n\n\
 Quick start:\n  \
 ntnt run server.tnt    Run a file (hot-reload enabled by default)\n  \
@@ -210,6 +210,19 @@ enum Commands {
     #[command(subcommand)]
     Intent(IntentCommands),
 
+    /// Manage the std/jobs background job queue
+    ///
+    /// Works on the SQLite queue database used by the app: $NTNT_JOBS_DB,
+    /// or ntnt_jobs.db in the current directory unless --db is given.
+    ///
+    /// Examples:
+    ///   ntnt jobs list
+    ///   ntnt jobs list --status dead --format json
+    ///   ntnt jobs retry 42
+    ///   ntnt jobs cancel 43 --db data/jobs.db
+    #[command(subcommand)]
+    Jobs(JobsCommands),
+
     /// Browse and validate stdlib documentation
     ///
     /// Documentation is auto-generated from docs/stdlib.toml.
@@ -363,6 +376,60 @@ enum IntentCommands {
     },
 }
 
+/// Background job queue subcommands
+#[derive(Subcommand)]
+enum JobsCommands {
+    /// List queued, finished and dead jobs
+    List {
+        /// Only show jobs with this status (pending, running, done, cancelled, dead)
+        #[arg(long)]
+        status: Option<String>,
+
+        /// Queue database (default: $NTNT_JOBS_DB or ntnt_jobs.db)
+        #[arg(long)]
+        db: Option<PathBuf>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+    },
+    /// Put a dead, cancelled, finished or abandoned job back on the queue
+    Retry {
+        /// Job ID
+        #[arg(value_name = "ID")]
+        id: i64,
+
+        /// Queue database (default: $NTNT_JOBS_DB or ntnt_jobs.db)
+        #[arg(long)]
+        db: Option<PathBuf>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+    },
+    /// Cancel a pending or abandoned job, or discard a dead one
+    Cancel {
+        /// Job ID
+        #[arg(value_name = "ID")]
+        id: i64,
+
+        /// Queue database (default: $NTNT_JOBS_DB or ntnt_jobs.db)
+        #[arg(long)]
+        db: Option<PathBuf>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+    },
+}
+
+/// Output format for commands with machine-readable output
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
 /// Format and display an error with rich context (error codes, source snippets, suggestions).
 fn format_error(error: &anyhow::Error, file_path: Option<&PathBuf>) {
     // Try to downcast to IntentError for rich formatting
@@ -495,6 +562,7 @@ fn main() {
             strict,
         }) => lint_project(&path, quiet, fix, strict),
         Some(Commands::Intent(intent_cmd)) => run_intent_command(intent_cmd),
+        Some(Commands::Jobs(jobs_cmd)) => run_jobs_command(jobs_cmd),
         Some(Commands::Docs {
             query,
             validate,
@@ -624,9 +692,13 @@ fn print_repl_help() {
         "std/math".cyan()
     );
     println!(
-        "  {} json, html, text, redirect, parse_form, parse_json",
+        "  {} json, html, text, redirect, stream, parse_form, parse_json",
         "std/http/server".cyan()
     );
+    println!(
+        "  {}     on_message, send, join, broadcast, close",
+        "std/http/ws".cyan()
+    );
     println!("  {}        fetch, download", "std/http".cyan());
     println!(
         "  {} push, pop, keys, values, first, last, has_key...",
@@ -664,6 +736,15 @@ fn print_repl_help() {
         "  {}  channel, send, recv, sleep_ms",
         "std/concurrent".cyan()
     );
+    println!(
+        "  {}        atomic_int, atomic_add, shared_map, lazy...",
+        "std/sync".cyan()
+    );
+    println!("  {}    every, after, cron, cancel", "std/schedule".cyan());
+    println!(
+        "  {}        enqueue, enqueue_at, worker, run_jobs...",
+        "std/jobs".cyan()
+    );
     println!("  Run {} for full documentation", "ntnt docs".green());
 
     println!("\n{}", "Quick Reference:".yellow().bold());
@@ -683,7 +764,9 @@ fn print_repl_help() {
         "\n{}",
         "HTTP Server (globals - no import needed):".yellow().bold()
     );
-    println!("  get, post, put, delete, listen, serve_static, routes, template, use_middleware");
+    println!(
+        "  get, post, put, delete, ws, sse, listen, serve_static, routes, template, use_middleware"
+    );
 
     println!("\n{}", "Imports:".yellow().bold());
     println!("  {}", r#"import { split, join } from "std/string""#.cyan());
@@ -715,6 +798,9 @@ fn run_file(path: &PathBuf, timeout: u64) -> anyhow::Result<()> {
     // Set request timeout for HTTP server
     interpreter.set_request_timeout(timeout);
 
+    // Contract enforcement modes from ntnt.toml / NTNT_ENV
+    interpreter.set_contract_config(read_project_contract_config(path)?);
+
     let lexer = Lexer::new(&source);
     let tokens: Vec<_> = lexer.collect();
 
@@ -749,6 +835,8 @@ fn run_file(path: &PathBuf, timeout: u64) -> anyhow::Result<()> {
     }
 
     interpreter.eval(&ast)?;
+    // Scripts that register std/schedule jobs keep running until they finish
+    interpreter.run_schedules()?;
     Ok(())
 }
 
@@ -1050,9 +1138,11 @@ fn inspect_project(path: &PathBuf, pretty: bool) -> anyhow::Result<()> {
     let mut routes: Vec<JsonValue> = Vec::new();
     let mut structs: Vec<JsonValue> = Vec::new();
     let mut enums: Vec<JsonValue> = Vec::new();
+    let mut types: Vec<JsonValue> = Vec::new();
     let mut imports: Vec<JsonValue> = Vec::new();
     let mut middleware: Vec<JsonValue> = Vec::new();
     let mut static_dirs: Vec<JsonValue> = Vec::new();
+    let mut schedules: Vec<JsonValue> = Vec::new();
 
     for file_path in &files {
         let source = fs::read_to_string(file_path)?;
@@ -1140,6 +1230,23 @@ fn inspect_project(path: &PathBuf, pretty: bool) -> anyhow::Result<()> {
                     });
                     enums.push(enum_json);
                 }
+                Statement::TypeAlias {
+                    name,
+                    target,
+                    type_params,
+                    refinement,
+                } => {
+                    let line = line_map.get(&format!("type {}", name)).copied();
+                    let type_json = json!({
+                        "name": name,
+                        "file": relative_path,
+                        "line": line,
+                        "target": type_to_string(target),
+                        "refinement": refinement.as_ref().map(expr_to_string),
+                        "type_params": type_params.iter().map(|tp| tp.name.clone()).collect::<Vec<_>>(),
+                    });
+                    types.push(type_json);
+                }
                 Statement::Import {
                     items,
                     source,
@@ -1155,15 +1262,24 @@ fn inspect_project(path: &PathBuf, pretty: bool) -> anyhow::Result<()> {
                 }
                 // Detect HTTP route, middleware, and static registrations
                 Statement::Expression(expr) => {
-                    if let Some(route) = extract_route_with_line(expr, &relative_path, &source) {
-                        routes.push(route);
-                    }
+                    extract_group_routes(expr, &ast, &relative_path, &source, "", &[], &mut routes);
                     if let Some(mw) = extract_middleware(expr, &relative_path, &source) {
                         middleware.push(mw);
                     }
                     if let Some(sd) = extract_static_dir(expr, &relative_path, &source) {
                         static_dirs.push(sd);
                     }
+                    if let Some(schedule) = extract_schedule(expr, &relative_path, &source) {
+                        schedules.push(schedule);
+                    }
+                }
+                // Schedules are often kept for cancel(): `let job = every(...)`
+                Statement::Let {
+                    value: Some(expr), ..
+                } => {
+                    if let Some(schedule) = extract_schedule(expr, &relative_path, &source) {
+                        schedules.push(schedule);
+                    }
                 }
                 _ => {}
             }
@@ -1185,6 +1301,7 @@ fn inspect_project(path: &PathBuf, pretty: bool) -> anyhow::Result<()> {
                             "file": relative_path.clone(),
                             "line": line,
                             "routing": "file-based",
+                            "middleware": subtree_middleware_files(file_path, path.parent().unwrap_or(path)),
                         });
                         routes.push(route);
                     }
@@ -1193,6 +1310,17 @@ fn inspect_project(path: &PathBuf, pretty: bool) -> anyhow::Result<()> {
         }
     }
 
+    // Effective middleware stack per route: global use_middleware() first,
+    // then group / _middleware.tnt middleware (outermost first)
+    let global: Vec<JsonValue> = middleware.iter().map(|mw| mw["handler"].clone()).collect();
+    for route in &mut routes {
+        let mut stack = global.clone();
+        if let Some(JsonValue::Array(local)) = route.get("middleware") {
+            stack.extend(local.iter().cloned());
+        }
+        route["middleware"] = JsonValue::Array(stack);
+    }
+
     let output = json!({
         "files": files.iter().map(|f| f.strip_prefix(path.parent().unwrap_or(path))
             .unwrap_or(f).to_string_lossy().to_string()).collect::<Vec<_>>(),
@@ -1200,8 +1328,10 @@ fn inspect_project(path: &PathBuf, pretty: bool) -> anyhow::Result<()> {
         "routes": routes,
         "middleware": middleware,
         "static": static_dirs,
+        "schedules": schedules,
         "structs": structs,
         "enums": enums,
+        "types": types,
         "imports": imports,
         "syntax_reference": {
             "critical_rules": {
@@ -1264,6 +1394,14 @@ fn build_line_number_map(source: &str) -> std::collections::HashMap<String, usiz
             }
         }
 
+        // Match type alias declarations
+        if let Some(rest) = trimmed.strip_prefix("type ") {
+            if let Some(name_end) = rest.find(|c: char| c == '=' || c == '<' || c.is_whitespace()) {
+                let name = &rest[..name_end];
+                map.insert(format!("type {}", name), line_num + 1);
+            }
+        }
+
         // Match enum declarations
         if trimmed.starts_with("enum ") {
             if let Some(name_end) =
@@ -1275,7 +1413,7 @@ fn build_line_number_map(source: &str) -> std::collections::HashMap<String, usiz
         }
 
         // Match route registrations: get("/path", ...) etc
-        for method in &["get", "post", "put", "delete", "patch", "head"] {
+        for method in &["get", "post", "put", "delete", "patch", "head", "ws", "sse"] {
             let prefix = format!("{}(", method);
             if trimmed.starts_with(&prefix) || trimmed.contains(&format!(" {}(", method)) {
                 // Extract the path string
@@ -1320,34 +1458,9 @@ fn extract_route_with_line(
     } = expr
     {
         if let Expression::Identifier(method) = function.as_ref() {
-            let http_methods = ["get", "post", "put", "delete", "patch", "head"];
+            let http_methods = ["get", "post", "put", "delete", "patch", "head", "ws", "sse"];
             if http_methods.contains(&method.as_str()) && arguments.len() >= 2 {
-                let path = match &arguments[0] {
-                    Expression::String(s) => s.clone(),
-                    // Route auto-detection: reconstruct {param} patterns from
-                    // InterpolatedString (same logic as interpreter's eval_route_pattern)
-                    Expression::InterpolatedString(parts) => {
-                        use ntnt::ast::StringPart;
-                        let mut result = String::new();
-                        for part in parts {
-                            match part {
-                                StringPart::Literal(s) => result.push_str(s),
-                                StringPart::Expr(inner) => {
-                                    if let Expression::Identifier(name) = inner {
-                                        result.push('{');
-                                        result.push_str(name);
-                                        result.push('}');
-                                    } else {
-                                        // Complex expression — can't resolve statically
-                                        return None;
-                                    }
-                                }
-                            }
-                        }
-                        result
-                    }
-                    _ => return None,
-                };
+                let path = route_pattern_from_expr(&arguments[0])?;
                 let handler = match &arguments[1] {
                     Expression::Identifier(name) => name.clone(),
                     Expression::Lambda { .. } => "<lambda>".to_string(),
@@ -1373,6 +1486,161 @@ fn extract_route_with_line(
     None
 }
 
+/// Reconstruct a route pattern from a string literal, turning interpolated
+/// `{param}` / `{param:spec}` parts back into placeholders (same logic as the
+/// interpreter's eval_route_pattern). Returns None for complex expressions.
+fn route_pattern_from_expr(expr: &ntnt::ast::Expression) -> Option<String> {
+    use ntnt::ast::{Expression, StringPart};
+
+    match expr {
+        Expression::String(s) => Some(s.clone()),
+        Expression::InterpolatedString(parts) => {
+            let mut result = String::new();
+            for part in parts {
+                match part {
+                    StringPart::Literal(s) => result.push_str(s),
+                    StringPart::Expr(Expression::Identifier(name)) => {
+                        result.push('{');
+                        result.push_str(name);
+                        result.push('}');
+                    }
+                    // Typed route parameter: {id:int}
+                    StringPart::Formatted {
+                        expr: Expression::Identifier(name),
+                        spec,
+                    } => result.push_str(&format!("{{{}:{}}}", name, spec)),
+                    // Complex expression — can't resolve statically
+                    _ => return None,
+                }
+            }
+            Some(result)
+        }
+        _ => None,
+    }
+}
+
+/// Display name for a middleware expression
+fn middleware_name(expr: &ntnt::ast::Expression) -> String {
+    use ntnt::ast::Expression;
+
+    match expr {
+        Expression::Identifier(name) => name.clone(),
+        Expression::Lambda { .. } => "<lambda>".to_string(),
+        // Middleware built by a call, e.g. sessions(map { ... })
+        Expression::Call { function, .. } => match function.as_ref() {
+            Expression::Identifier(name) => format!("{}()", name),
+            _ => "<handler>".to_string(),
+        },
+        _ => "<handler>".to_string(),
+    }
+}
+
+/// Extract routes registered inside `group(prefix, [middleware], body)`,
+/// applying the group's prefix and recording its middleware on each route.
+/// The body may be an anonymous function or the name of a top-level function.
+fn extract_group_routes(
+    expr: &ntnt::ast::Expression,
+    program: &ntnt::ast::Program,
+    file: &str,
+    source: &str,
+    prefix: &str,
+    group_middleware: &[String],
+    routes: &mut Vec<serde_json::Value>,
+) {
+    use ntnt::ast::{Expression, Statement};
+    use serde_json::json;
+
+    if let Some(mut route) = extract_route_with_line(expr, file, source) {
+        if !prefix.is_empty() {
+            let path = route["path"].as_str().unwrap_or("").to_string();
+            let full = if path == "/" {
+                prefix.to_string()
+            } else {
+                format!("{}{}", prefix, path)
+            };
+            route["path"] = json!(full);
+        }
+        route["middleware"] = json!(group_middleware);
+        routes.push(route);
+        return;
+    }
+
+    let Expression::Call {
+        function,
+        arguments,
+    } = expr
+    else {
+        return;
+    };
+    if !matches!(function.as_ref(), Expression::Identifier(name) if name == "group")
+        || arguments.len() != 3
+    {
+        return;
+    }
+    let Some(group_prefix) = route_pattern_from_expr(&arguments[0]) else {
+        return;
+    };
+    let prefix = format!("{}{}", prefix, group_prefix.trim_end_matches('/'));
+    let mut group_middleware = group_middleware.to_vec();
+    if let Expression::Array(items) = &arguments[1] {
+        group_middleware.extend(items.iter().map(middleware_name));
+    }
+
+    let body = match &arguments[2] {
+        Expression::Lambda { body, .. } => match body.as_ref() {
+            Expression::Block(block) => Some(&block.statements),
+            _ => None,
+        },
+        Expression::Identifier(name) => program.statements.iter().find_map(|stmt| match stmt {
+            Statement::Function {
+                name: fn_name,
+                body,
+                ..
+            } if fn_name == name => Some(&body.statements),
+            _ => None,
+        }),
+        _ => None,
+    };
+    for stmt in body.into_iter().flatten() {
+        if let Statement::Expression(inner) = stmt {
+            extract_group_routes(
+                inner,
+                program,
+                file,
+                source,
+                &prefix,
+                &group_middleware,
+                routes,
+            );
+        }
+    }
+}
+
+/// `_middleware.tnt` files that apply to a file-based route, outermost first
+fn subtree_middleware_files(
+    file_path: &std::path::Path,
+    project_root: &std::path::Path,
+) -> Vec<String> {
+    let mut found = Vec::new();
+    for dir in file_path.ancestors().skip(1) {
+        let candidate = dir.join("_middleware.tnt");
+        if candidate.is_file() && candidate != file_path {
+            found.push(
+                candidate
+                    .strip_prefix(project_root)
+                    .unwrap_or(&candidate)
+                    .to_string_lossy()
+                    .to_string(),
+            );
+        }
+        if dir.file_name().and_then(|n| n.to_str()) == Some("routes") {
+            break;
+        }
+    }
+    found.reverse();
+    found
+}
+
 /// Extract middleware registration
 fn extract_middleware(
     expr: &ntnt::ast::Expression,
@@ -1390,11 +1658,7 @@ fn extract_middleware(
         if let Expression::Identifier(name) = function.as_ref() {
             // Check for both "middleware" and "use_middleware"
             if (name == "middleware" || name == "use_middleware") && !arguments.is_empty() {
-                let handler = match &arguments[0] {
-                    Expression::Identifier(name) => name.clone(),
-                    Expression::Lambda { .. } => "<lambda>".to_string(),
-                    _ => "<handler>".to_string(),
-                };
+                let handler = middleware_name(&arguments[0]);
 
                 // Find approximate line by searching source
                 let line = find_call_line(source, "middleware");
@@ -1450,6 +1714,57 @@ fn extract_static_dir(
     None
 }
 
+/// Extract a std/schedule job registration: every(), after() or cron()
+fn extract_schedule(
+    expr: &ntnt::ast::Expression,
+    file: &str,
+    source: &str,
+) -> Option<serde_json::Value> {
+    use ntnt::ast::Expression;
+    use serde_json::json;
+
+    let expr = match expr {
+        Expression::Assign { value, .. } => value.as_ref(),
+        other => other,
+    };
+    if let Expression::Call {
+        function,
+        arguments,
+    } = expr
+    {
+        if let Expression::Identifier(kind) = function.as_ref() {
+            if matches!(kind.as_str(), "every" | "after" | "cron") && arguments.len() >= 2 {
+                let schedule = match &arguments[0] {
+                    Expression::String(s) => json!(s),
+                    Expression::Integer(ms) => json!(format!("{}ms", ms)),
+                    other => json!(expr_to_string(other)),
+                };
+                let handler = match &arguments[1] {
+                    Expression::Identifier(name) => name.clone(),
+                    Expression::Lambda { .. } => "<lambda>".to_string(),
+                    _ => "<handler>".to_string(),
+                };
+                let timezone = match arguments.get(2) {
+                    Some(Expression::String(tz)) => Some(tz.clone()),
+                    Some(other) => Some(expr_to_string(other)),
+                    None if kind == "cron" => Some("UTC".to_string()),
+                    None => None,
+                };
+
+                return Some(json!({
+                    "kind": kind,
+                    "schedule": schedule,
+                    "handler": handler,
+                    "timezone": timezone,
+                    "file": file,
+                    "line": find_call_line(source, kind),
+                }));
+            }
+        }
+    }
+    None
+}
+
 /// Convert a file path in routes/ directory to a URL pattern
 ///
 /// Examples:
@@ -1953,6 +2268,20 @@ fn lint_ast(ast: &ntnt::ast::Program, source: &str, _filename: &str) -> Vec<serd
                 check_expr_for_issues(object, source_lines, issues, http_route_functions);
                 check_expr_for_issues(index, source_lines, issues, http_route_functions);
             }
+            Expression::OptionalChain { object, access } => {
+                check_expr_for_issues(object, source_lines, issues, http_route_functions);
+                match access {
+                    ntnt::ast::ChainAccess::Field(_) => {}
+                    ntnt::ast::ChainAccess::Index(index) => {
+                        check_expr_for_issues(index, source_lines, issues, http_route_functions);
+                    }
+                    ntnt::ast::ChainAccess::Method { arguments, .. } => {
+                        for arg in arguments {
+                            check_expr_for_issues(arg, source_lines, issues, http_route_functions);
+                        }
+                    }
+                }
+            }
             Expression::Range { start, end, .. } => {
                 check_expr_for_issues(start, source_lines, issues, http_route_functions);
                 check_expr_for_issues(end, source_lines, issues, http_route_functions);
@@ -2183,6 +2512,101 @@ fn run_intent_command(cmd: IntentCommands) -> anyhow::Result<()> {
     }
 }
 
+fn run_jobs_command(cmd: JobsCommands) -> anyhow::Result<()> {
+    use ntnt::stdlib::jobs;
+
+    let open = |db: Option<PathBuf>| {
+        let path = db
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(jobs::default_db_path);
+        jobs::open_store(&path)
+    };
+
+    match cmd {
+        JobsCommands::List { status, db, format } => {
+            let conn = open(db)?;
+            let list = jobs::list_jobs(&conn, status.as_deref())?;
+            if format == OutputFormat::Json {
+                let json: Vec<_> = list.iter().map(|job| job.to_json()).collect();
+                println!("{}", serde_json::to_string_pretty(&json)?);
+                return Ok(());
+            }
+            if list.is_empty() {
+                println!("No jobs");
+                return Ok(());
+            }
+            println!(
+                "ID     NAME                     STATUS     ATTEMPTS PRIORITY  RUN AT               LAST ERROR"
+            );
+            for job in &list {
+                let run_at = chrono::DateTime::from_timestamp_millis(job.run_at)
+                    .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_default();
+                // Pad before colouring so escape codes don't break the columns
+                let padded = format!("{:<10}", job.status);
+                let status = match job.status.as_str() {
+                    "dead" => padded.red().to_string(),
+                    "done" => padded.green().to_string(),
+                    "running" => padded.cyan().to_string(),
+                    _ => padded,
+                };
+                println!(
+                    "{:<6} {:<24} {} {:>8} {:>8}  {:<20} {}",
+                    job.id,
+                    job.name,
+                    status,
+                    format!("{}/{}", job.attempts, job.max_attempts),
+                    job.priority,
+                    run_at,
+                    job.last_error.as_deref().unwrap_or("")
+                );
+            }
+            Ok(())
+        }
+        JobsCommands::Retry { id, db, format } => {
+            let conn = open(db)?;
+            let retried = jobs::retry_job(&conn, id)?;
+            report_job_change(&conn, id, "retried", retried, format)
+        }
+        JobsCommands::Cancel { id, db, format } => {
+            let conn = open(db)?;
+            let cancelled = jobs::cancel_job(&conn, id)?;
+            report_job_change(&conn, id, "cancelled", cancelled, format)
+        }
+    }
+}
+
+/// Print the result of `ntnt jobs retry|cancel`. Fails (exit code 1) when the
+/// job was not changed.
+fn report_job_change(
+    conn: &rusqlite::Connection,
+    id: i64,
+    action: &str,
+    changed: bool,
+    format: OutputFormat,
+) -> anyhow::Result<()> {
+    let status = ntnt::stdlib::jobs::find_job(conn, id)?.map(|job| job.status);
+    if format == OutputFormat::Json {
+        println!(
+            "{}",
+            serde_json::json!({ "id": id, action: changed, "status": status })
+        );
+        if !changed {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    match (changed, status) {
+        (true, Some(status)) => println!("{} Job {} {} (now {})", "✓".green(), id, action, status),
+        (true, None) => println!("{} Job {} {}", "✓".green(), id, action),
+        (false, Some(status)) => {
+            anyhow::bail!("Job {} cannot be {} (status: {})", id, action, status)
+        }
+        (false, None) => anyhow::bail!("No job with ID {}", id),
+    }
+    Ok(())
+}
+
 /// Run the intent check command
 ///
 /// Verbosity levels:
@@ -2883,7 +3307,7 @@ fn run_intent_studio_command(
 }
 /// Collect all .tnt files from a path (file or directory)
 /// Read project config from ntnt.toml (searches path's directory and ancestors)
-fn read_project_config_strict(path: &PathBuf) -> bool {
+fn read_project_config(path: &PathBuf) -> Option<toml::Value> {
     // Start from the path (or its parent if it's a file) and walk up
     let start_dir = if path.is_file() {
         path.parent().unwrap_or(path).to_path_buf()
@@ -2895,20 +3319,34 @@ fn read_project_config_strict(path: &PathBuf) -> bool {
     while let Some(d) = dir {
         let config_path = d.join("ntnt.toml");
         if config_path.exists() {
-            if let Ok(content) = fs::read_to_string(&config_path) {
-                if let Ok(config) = content.parse::<toml::Value>() {
-                    return config
-                        .get("lint")
-                        .and_then(|l| l.get("strict"))
-                        .and_then(|v| v.as_bool())
-                        .unwrap_or(false);
-                }
-            }
-            return false;
+            return fs::read_to_string(&config_path)
+                .ok()
+                .and_then(|content| content.parse::<toml::Value>().ok());
         }
         dir = d.parent();
     }
-    false
+    None
+}
+
+/// Read `[lint] strict` from the project config
+fn read_project_config_strict(path: &PathBuf) -> bool {
+    read_project_config(path)
+        .and_then(|config| {
+            config
+                .get("lint")
+                .and_then(|l| l.get("strict"))
+                .and_then(|v| v.as_bool())
+        })
+        .unwrap_or(false)
+}
+
+/// Read contract enforcement modes from the project config, on top of `NTNT_ENV` defaults
+fn read_project_contract_config(path: &PathBuf) -> anyhow::Result<ContractConfig> {
+    let mut contracts = ContractConfig::from_env();
+    if let Some(config) = read_project_config(path) {
+        contracts.apply_toml(&config)?;
+    }
+    Ok(contracts)
 }
 
 fn collect_tnt_files(path: &PathBuf) -> anyhow::Result<Vec<PathBuf>> {
@@ -3035,16 +3473,54 @@ fn expr_to_string(expr: &ntnt::ast::Expression) -> String {
             operator,
             right,
         } => {
-            format!(
-                "{} {:?} {}",
-                expr_to_string(left),
-                operator,
-                expr_to_string(right)
-            )
+            use ntnt::ast::BinaryOp;
+            let op = match operator {
+                BinaryOp::Add => "+",
+                BinaryOp::Sub => "-",
+                BinaryOp::Mul => "*",
+                BinaryOp::Div => "/",
+                BinaryOp::Mod => "%",
+                BinaryOp::Pow => "**",
+                BinaryOp::Eq => "==",
+                BinaryOp::Ne => "!=",
+                BinaryOp::Lt => "<",
+                BinaryOp::Le => "<=",
+                BinaryOp::Gt => ">",
+                BinaryOp::Ge => ">=",
+                BinaryOp::And => "&&",
+                BinaryOp::Or => "||",
+                BinaryOp::NullCoalesce => "??",
+            };
+            format!("{} {} {}", expr_to_string(left), op, expr_to_string(right))
+        }
+        Expression::Unary { operator, operand } => {
+            let op = match operator {
+                ntnt::ast::UnaryOp::Neg => "-",
+                ntnt::ast::UnaryOp::Not => "!",
+            };
+            format!("{}{}", op, expr_to_string(operand))
         }
         Expression::FieldAccess { object, field } => {
             format!("{}.{}", expr_to_string(object), field)
         }
+        Expression::OptionalChain { object, access } => match access {
+            ntnt::ast::ChainAccess::Field(field) => {
+                format!("{}?.{}", expr_to_string(object), field)
+            }
+            ntnt::ast::ChainAccess::Index(index) => {
+                format!("{}?.[{}]", expr_to_string(object), expr_to_string(index))
+            }
+            ntnt::ast::ChainAccess::Method { method, arguments } => format!(
+                "{}?.{}({})",
+                expr_to_string(object),
+                method,
+                arguments
+                    .iter()
+                    .map(expr_to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        },
         Expression::MethodCall {
             object,
             method,
@@ -3119,7 +3595,7 @@ fn analyze_ast_warnings(ast: &ntnt::ast::Program, _source: &str) -> Vec<serde_js
 
 /// Collect used identifiers from a statement (comprehensive AST traversal)
 fn collect_used_names(stmt: &ntnt::ast::Statement, names: &mut std::collections::HashSet<String>) {
-    use ntnt::ast::{Expression, Statement, StringPart};
+    use ntnt::ast::{Expression, Statement, StringPart, TypeExpr};
 
     fn collect_from_expr(expr: &Expression, names: &mut std::collections::HashSet<String>) {
         match expr {
@@ -3171,6 +3647,20 @@ fn collect_used_names(stmt: &ntnt::ast::Statement, names: &mut std::collections:
                 collect_from_expr(index, names);
             }
 
+            // Optional chaining - object plus any index or method arguments
+            Expression::OptionalChain { object, access } => {
+                collect_from_expr(object, names);
+                match access {
+                    ntnt::ast::ChainAccess::Field(_) => {}
+                    ntnt::ast::ChainAccess::Index(index) => collect_from_expr(index, names),
+                    ntnt::ast::ChainAccess::Method { arguments, .. } => {
+                        for arg in arguments {
+                            collect_from_expr(arg, names);
+                        }
+                    }
+                }
+            }
+
             // Array literals
             Expression::Array(items) => {
                 for item in items {
@@ -3195,7 +3685,7 @@ fn collect_used_names(stmt: &ntnt::ast::Statement, names: &mut std::collections:
             // Interpolated strings - expressions inside {}
             Expression::InterpolatedString(parts) => {
                 for part in parts {
-                    if let StringPart::Expr(expr) = part {
+                    if let StringPart::Expr(expr) | StringPart::Formatted { expr, .. } = part {
                         collect_from_expr(expr, names);
                     }
                 }
@@ -3354,29 +3844,96 @@ fn collect_used_names(stmt: &ntnt::ast::Statement, names: &mut std::collections:
                     }
                 }
             }
-            Pattern::Tuple(patterns) | Pattern::Array(patterns) => {
+            Pattern::Tuple(patterns) | Pattern::Array(patterns) | Pattern::Or(patterns) => {
                 for p in patterns {
                     collect_from_pattern(p, names);
                 }
             }
+            Pattern::Map(entries) => {
+                for (_, p) in entries {
+                    collect_from_pattern(p, names);
+                }
+            }
+            Pattern::Binding { pattern, .. } => collect_from_pattern(pattern, names),
             Pattern::Literal(expr) => {
                 collect_from_expr(expr, names);
             }
-            Pattern::Variable(_) | Pattern::Wildcard => {}
+            Pattern::Range { start, end, .. } => {
+                collect_from_expr(start, names);
+                collect_from_expr(end, names);
+            }
+            Pattern::Variable(_) | Pattern::Wildcard | Pattern::Rest(_) => {}
+        }
+    }
+
+    // Imported structs, enums, aliases and traits are used through type annotations
+    fn collect_from_type(typ: &TypeExpr, names: &mut std::collections::HashSet<String>) {
+        match typ {
+            TypeExpr::Named(name) => {
+                names.insert(name.clone());
+            }
+            TypeExpr::Array(inner) | TypeExpr::Optional(inner) => collect_from_type(inner, names),
+            TypeExpr::Map {
+                key_type,
+                value_type,
+            } => {
+                collect_from_type(key_type, names);
+                collect_from_type(value_type, names);
+            }
+            TypeExpr::Tuple(types) | TypeExpr::Union(types) => {
+                for t in types {
+                    collect_from_type(t, names);
+                }
+            }
+            TypeExpr::Function {
+                params,
+                return_type,
+            } => {
+                for t in params {
+                    collect_from_type(t, names);
+                }
+                collect_from_type(return_type, names);
+            }
+            TypeExpr::Generic { name, args } => {
+                names.insert(name.clone());
+                for t in args {
+                    collect_from_type(t, names);
+                }
+            }
         }
     }
 
     match stmt {
         Statement::Expression(expr) => collect_from_expr(expr, names),
-        Statement::Let { value, pattern, .. } => {
+        Statement::Let {
+            value,
+            pattern,
+            type_annotation,
+            ..
+        } => {
             if let Some(expr) = value {
                 collect_from_expr(expr, names);
             }
             if let Some(pat) = pattern {
                 collect_from_pattern(pat, names);
             }
+            if let Some(t) = type_annotation {
+                collect_from_type(t, names);
+            }
         }
-        Statement::Function { body, contract, .. } => {
+        Statement::Function {
+            body,
+            contract,
+            params,
+            return_type,
+            ..
+        } => {
+            for t in params.iter().filter_map(|p| p.type_annotation.as_ref()) {
+                collect_from_type(t, names);
+            }
+            if let Some(t) = return_type {
+                collect_from_type(t, names);
+            }
             // Collect from function body
             for s in &body.statements {
                 collect_used_names(s, names);
@@ -3426,10 +3983,15 @@ fn collect_used_names(stmt: &ntnt::ast::Statement, names: &mut std::collections:
         Statement::Return(Some(expr)) => collect_from_expr(expr, names),
         Statement::Defer(expr) => collect_from_expr(expr, names),
         Statement::Impl {
+            type_name,
+            trait_name,
             methods,
             invariants,
-            ..
         } => {
+            names.insert(type_name.clone());
+            if let Some(t) = trait_name {
+                names.insert(t.clone());
+            }
             for method in methods {
                 collect_used_names(method, names);
             }
@@ -3450,14 +4012,24 @@ fn collect_used_names(stmt: &ntnt::ast::Statement, names: &mut std::collections:
         Statement::Intent { target, .. } => {
             collect_used_names(target, names);
         }
+        Statement::Struct { fields, .. } => {
+            for f in fields {
+                collect_from_type(&f.type_annotation, names);
+            }
+        }
+        Statement::Enum { variants, .. } => {
+            for t in variants.iter().filter_map(|v| v.fields.as_ref()).flatten() {
+                collect_from_type(t, names);
+            }
+        }
+        Statement::TypeAlias { target, .. } => collect_from_type(target, names),
+        Statement::Trait { supertraits, .. } => {
+            names.extend(supertraits.iter().cloned());
+        }
         // These don't contain expressions to analyze
         Statement::Return(None)
         | Statement::Break
         | Statement::Continue
-        | Statement::Struct { .. }
-        | Statement::Enum { .. }
-        | Statement::Trait { .. }
-        | Statement::TypeAlias { .. }
         | Statement::Use { .. }
         | Statement::Import { .. }
         | Statement::Protocol { .. } => {}
@@ -4099,6 +4671,7 @@ fn generate_syntax_markdown(toml_path: &PathBuf) -> anyhow::Result<()> {
     md.push_str("- [String Interpolation](#string-interpolation)\n");
     md.push_str("- [Template Strings](#template-strings)\n");
     md.push_str("- [Truthy/Falsy Values](#truthyfalsy-values)\n");
+    md.push_str("- [Functions](#functions)\n");
     md.push_str("- [Contracts](#contracts)\n");
     md.push_str("- [Types](#types)\n");
     md.push_str("- [Imports](#imports)\n");
@@ -4153,6 +4726,7 @@ fn generate_syntax_markdown(toml_path: &PathBuf) -> anyhow::Result<()> {
 
         let op_categories = [
             "assignment",
+            "null_coalesce",
             "logical_or",
             "logical_and",
             "comparison",
@@ -4160,6 +4734,7 @@ fn generate_syntax_markdown(toml_path: &PathBuf) -> anyhow::Result<()> {
             "unary",
             "range",
             "member",
+            "optional_chain",
             "pipe",
         ];
 
@@ -4258,6 +4833,19 @@ fn generate_syntax_markdown(toml_path: &PathBuf) -> anyhow::Result<()> {
             }
         }
 
+        if let Some(formatted) = interp.get("formatted") {
+            md.push_str("### Format Specs\n\n");
+            if let Some(syntax_str) = formatted.get("syntax").and_then(|v| v.as_str()) {
+                md.push_str(&format!("Syntax: `{}`\n\n", syntax_str));
+            }
+            if let Some(desc) = formatted.get("description").and_then(|v| v.as_str()) {
+                md.push_str(&format!("{}\n\n", desc));
+            }
+            if let Some(note) = formatted.get("note").and_then(|v| v.as_str()) {
+                md.push_str(&format!("{}\n\n", note));
+            }
+        }
+
         if let Some(template) = interp.get("template") {
             md.push_str("### Template Strings\n\n");
             if let Some(syntax_str) = template.get("syntax").and_then(|v| v.as_str()) {
@@ -4365,6 +4953,37 @@ fn generate_syntax_markdown(toml_path: &PathBuf) -> anyhow::Result<()> {
         md.push_str("---\n\n");
     }
 
+    // Functions
+    if let Some(functions) = syntax.get("functions") {
+        md.push_str("## Functions\n\n");
+        if let Some(desc) = functions.get("description").and_then(|v| v.as_str()) {
+            md.push_str(&format!("{}\n\n", desc));
+        }
+
+        md.push_str("| Form | Syntax | Description |\n");
+        md.push_str("|------|--------|-------------|\n");
+
+        let forms = ["named", "anonymous", "async"];
+        for form in &forms {
+            if let Some(f) = functions.get(*form) {
+                let syntax_str = f.get("syntax").and_then(|v| v.as_str()).unwrap_or("");
+                let desc = f.get("description").and_then(|v| v.as_str()).unwrap_or("");
+                md.push_str(&format!("| {} | `{}` | {} |\n", form, syntax_str, desc));
+            }
+        }
+
+        for form in &forms {
+            if let Some(example) = functions
+                .get(*form)
+                .and_then(|f| f.get("example"))
+                .and_then(|v| v.as_str())
+            {
+                md.push_str(&format!("\n```ntnt\n{}\n```\n", example));
+            }
+        }
+        md.push_str("\n---\n\n");
+    }
+
     // Contracts
     if let Some(contracts) = syntax.get("contracts") {
         md.push_str("## Contracts\n\n");
@@ -4468,7 +5087,17 @@ fn generate_syntax_markdown(toml_path: &PathBuf) -> anyhow::Result<()> {
         md.push_str("| Feature | Syntax | Description |\n");
         md.push_str("|---------|--------|-------------|\n");
 
-        let features = ["basic", "guards", "wildcard", "binding"];
+        let features = [
+            "basic",
+            "guards",
+            "wildcard",
+            "binding",
+            "or",
+            "range",
+            "at_binding",
+            "map",
+            "rest",
+        ];
         for feat in &features {
             if let Some(f) = match_expr.get(*feat) {
                 let syntax_str = f.get("syntax").and_then(|v| v.as_str()).unwrap_or("");
@@ -5101,6 +5730,9 @@ fn generate_runtime_markdown(toml_path: &PathBuf) -> anyhow::Result<()> {
             if let Some(func) = mw.get("function").and_then(|v| v.as_str()) {
                 md.push_str(&format!("- **Function:** {}\n", func));
             }
+            if let Some(subtree) = mw.get("subtree").and_then(|v| v.as_str()) {
+                md.push_str(&format!("- **Subtree:** {}\n", subtree));
+            }
             md.push_str("\n");
         }
         md.push_str("---\n\n");
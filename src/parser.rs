@@ -69,6 +69,24 @@ impl Parser {
         }
     }
 
+    /// `type(x)` is the builtin call, not the start of a type alias
+    fn check_type_builtin_call(&self) -> bool {
+        self.check(&TokenKind::Type)
+            && matches!(
+                self.tokens.get(self.current + 1).map(|t| &t.kind),
+                Some(TokenKind::LeftParen)
+            )
+    }
+
+    /// `async fn` starts an async function declaration
+    fn check_async_fn(&self) -> bool {
+        self.check(&TokenKind::Async)
+            && matches!(
+                self.tokens.get(self.current + 1).map(|t| &t.kind),
+                Some(TokenKind::Fn)
+            )
+    }
+
     fn check_identifier(&self) -> bool {
         if let Some(token) = self.peek() {
             matches!(token.kind, TokenKind::Identifier(_))
@@ -119,7 +137,9 @@ impl Parser {
             self.let_declaration()
         } else if self.match_token(&[TokenKind::Fn]) {
             self.function_declaration(attributes)
-        } else if self.match_token(&[TokenKind::Type]) {
+        } else if self.check_async_fn() {
+            self.async_function_declaration(attributes)
+        } else if !self.check_type_builtin_call() && self.match_token(&[TokenKind::Type]) {
             self.type_alias_declaration()
         } else if self.match_token(&[TokenKind::Struct]) {
             self.struct_declaration(attributes)
@@ -222,12 +242,20 @@ impl Parser {
 
         self.consume(&TokenKind::Assign, "Expected '=' after type name")?;
         let target = self.parse_type()?;
+
+        // Optional refinement predicate: type Port = Int where self > 0
+        let refinement = if self.match_token(&[TokenKind::Where]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
         self.match_token(&[TokenKind::Semicolon]);
 
         Ok(Statement::TypeAlias {
             name,
             type_params,
             target,
+            refinement,
         })
     }
 
@@ -261,9 +289,21 @@ impl Parser {
             body,
             attributes,
             type_params,
+            is_async: false,
         })
     }
 
+    /// Parse `async fn name(...) { ... }`
+    fn async_function_declaration(&mut self, attributes: Vec<Attribute>) -> Result<Statement> {
+        self.consume(&TokenKind::Async, "Expected 'async'")?;
+        self.consume(&TokenKind::Fn, "Expected 'fn' after 'async'")?;
+        let mut stmt = self.function_declaration(attributes)?;
+        if let Statement::Function { is_async, .. } = &mut stmt {
+            *is_async = true;
+        }
+        Ok(stmt)
+    }
+
     fn parse_parameters(&mut self) -> Result<Vec<Parameter>> {
         let mut params = Vec::new();
 
@@ -724,6 +764,8 @@ impl Parser {
         // Export a declaration: export fn foo() or export struct Bar
         let stmt = if self.match_token(&[TokenKind::Fn]) {
             self.function_declaration(attributes)?
+        } else if self.check_async_fn() {
+            self.async_function_declaration(attributes)?
         } else if self.match_token(&[TokenKind::Struct]) {
             self.struct_declaration(attributes)?
         } else if self.match_token(&[TokenKind::Enum]) {
@@ -1111,6 +1153,11 @@ impl Parser {
             });
         }
 
+        if self.match_token(&[TokenKind::Await]) {
+            let operand = self.unary()?;
+            return Ok(Expression::Await(Box::new(operand)));
+        }
+
         self.call()
     }
 
@@ -1134,7 +1181,7 @@ impl Parser {
                     break;
                 }
             } else if self.match_token(&[TokenKind::Dot]) {
-                let name = self.consume_identifier("Expected property name after '.'")?;
+                let name = self.consume_property_name("Expected property name after '.'")?;
                 if self.match_token(&[TokenKind::LeftParen]) {
                     let arguments = self.arguments()?;
                     self.consume(&TokenKind::RightParen, "Expected ')' after arguments")?;
@@ -1156,6 +1203,29 @@ impl Parser {
                     object: Box::new(expr),
                     index: Box::new(index),
                 };
+            } else if self.match_token(&[TokenKind::QuestionDot]) {
+                // Optional chaining: obj?.field, obj?.[index], obj?.method(args)
+                let access = if self.match_token(&[TokenKind::LeftBracket]) {
+                    let index = self.expression()?;
+                    self.consume(&TokenKind::RightBracket, "Expected ']' after index")?;
+                    ChainAccess::Index(Box::new(index))
+                } else {
+                    let name = self.consume_property_name("Expected property name after '?.'")?;
+                    if self.match_token(&[TokenKind::LeftParen]) {
+                        let arguments = self.arguments()?;
+                        self.consume(&TokenKind::RightParen, "Expected ')' after arguments")?;
+                        ChainAccess::Method {
+                            method: name,
+                            arguments,
+                        }
+                    } else {
+                        ChainAccess::Field(name)
+                    }
+                };
+                expr = Expression::OptionalChain {
+                    object: Box::new(expr),
+                    access,
+                };
             } else {
                 break;
             }
@@ -1397,6 +1467,12 @@ impl Parser {
             }
         }
 
+        // The `type(x)` builtin shares its name with the `type` keyword
+        if self.check_type_builtin_call() {
+            self.advance();
+            return Ok(Expression::Identifier("type".to_string()));
+        }
+
         // Identifier (or EnumName::Variant)
         if let Some(token) = self.peek() {
             if let TokenKind::Identifier(ref name) = token.kind {
@@ -1490,6 +1566,25 @@ impl Parser {
             return self.parse_map_contents();
         }
 
+        // Anonymous function: fn(req) { ... }
+        if self.check(&TokenKind::Fn)
+            && matches!(
+                self.tokens.get(self.current + 1).map(|t| &t.kind),
+                Some(TokenKind::LeftParen)
+            )
+        {
+            self.advance(); // fn
+            self.advance(); // (
+            let params = self.parse_parameters()?;
+            self.consume(&TokenKind::RightParen, "Expected ')' after parameters")?;
+            self.consume(&TokenKind::LeftBrace, "Expected '{' before function body")?;
+            let body = self.block()?;
+            return Ok(Expression::Lambda {
+                params,
+                body: Box::new(Expression::Block(body)),
+            });
+        }
+
         // Block expression
         if self.match_token(&[TokenKind::LeftBrace]) {
             let block = self.block()?;
@@ -1532,34 +1627,15 @@ impl Parser {
                     ast_parts.push(StringPart::Literal(s.clone()));
                 }
                 LexerStringPart::Interpolation(expr_str) => {
-                    // Parse the expression string
-                    let lexer = crate::lexer::Lexer::new(expr_str);
-                    let tokens: Vec<_> = lexer.collect();
-                    let mut parser = Parser::new(tokens);
-                    match parser.expression() {
-                        Ok(expr) => ast_parts.push(StringPart::Expr(expr)),
-                        Err(e) => {
-                            // Extract the original error message
-                            let original_msg = match &e {
-                                IntentError::ParserError { message, .. } => message.clone(),
-                                _ => e.to_string(),
-                            };
-                            let preview = if expr_str.len() > 30 {
-                                format!("{}...", &expr_str[..30])
-                            } else {
-                                expr_str.clone()
-                            };
-                            return Err(IntentError::ParserError {
-                                line,
-                                column: 0,
-                                message: format!(
-                                    "Error in string interpolation '{{{}}}': {}. \
-                                    (Hint: if you meant literal braces, escape them with \\{{ and \\}})",
-                                    preview, original_msg
-                                ),
-                            });
-                        }
-                    }
+                    let expr = Self::parse_interpolation(expr_str, line)?;
+                    ast_parts.push(StringPart::Expr(expr));
+                }
+                LexerStringPart::Formatted { expr, spec } => {
+                    let expr = Self::parse_interpolation(expr, line)?;
+                    ast_parts.push(StringPart::Formatted {
+                        expr,
+                        spec: spec.clone(),
+                    });
                 }
             }
         }
@@ -1567,6 +1643,34 @@ impl Parser {
         Ok(Expression::InterpolatedString(ast_parts))
     }
 
+    /// Parse the expression inside a `{...}` interpolation
+    fn parse_interpolation(expr_str: &str, line: usize) -> Result<Expression> {
+        let lexer = crate::lexer::Lexer::new(expr_str);
+        let tokens: Vec<_> = lexer.collect();
+        let mut parser = Parser::new(tokens);
+        parser.expression().map_err(|e| {
+            // Extract the original error message
+            let original_msg = match &e {
+                IntentError::ParserError { message, .. } => message.clone(),
+                _ => e.to_string(),
+            };
+            let preview = if expr_str.len() > 30 {
+                format!("{}...", &expr_str[..30])
+            } else {
+                expr_str.to_string()
+            };
+            IntentError::ParserError {
+                line,
+                column: 0,
+                message: format!(
+                    "Error in string interpolation '{{{}}}': {}. \
+                    (Hint: if you meant literal braces, escape them with \\{{ and \\}})",
+                    preview, original_msg
+                ),
+            }
+        })
+    }
+
     /// Parse a template string (triple-quoted) into TemplateParts
     fn parse_template_string(
         &mut self,
@@ -1829,8 +1933,42 @@ impl Parser {
         })
     }
 
-    /// Parse a pattern for match expressions
+    /// Parse a pattern for match expressions, including or-patterns `a | b`
     fn parse_pattern(&mut self) -> Result<Pattern> {
+        let first = self.parse_single_pattern()?;
+        if !self.check(&TokenKind::Pipe) {
+            return Ok(first);
+        }
+        let mut alternatives = vec![first];
+        while self.match_token(&[TokenKind::Pipe]) {
+            alternatives.push(self.parse_single_pattern()?);
+        }
+        Ok(Pattern::Or(alternatives))
+    }
+
+    /// Parse a literal usable in literal and range patterns, including negative numbers
+    fn parse_literal_pattern_value(&mut self) -> Option<Expression> {
+        let negative = self.check(&TokenKind::Minus)
+            && matches!(
+                self.tokens.get(self.current + 1).map(|t| &t.kind),
+                Some(TokenKind::Integer(_)) | Some(TokenKind::Float(_))
+            );
+        if negative {
+            self.advance();
+        }
+        let expr = match &self.peek()?.kind {
+            TokenKind::Integer(n) => Expression::Integer(if negative { -*n } else { *n }),
+            TokenKind::Float(n) => Expression::Float(if negative { -*n } else { *n }),
+            TokenKind::String(s) | TokenKind::RawString(s) => Expression::String(s.clone()),
+            TokenKind::Bool(b) => Expression::Bool(*b),
+            _ => return None,
+        };
+        self.advance();
+        Some(expr)
+    }
+
+    /// Parse a single (non-or) pattern
+    fn parse_single_pattern(&mut self) -> Result<Pattern> {
         // Wildcard pattern: _
         if let Some(token) = self.peek() {
             if let TokenKind::Identifier(ref name) = token.kind {
@@ -1841,36 +1979,25 @@ impl Parser {
             }
         }
 
-        // Literal patterns
-        if let Some(token) = self.peek() {
-            match &token.kind {
-                TokenKind::Integer(n) => {
-                    let n = *n;
-                    self.advance();
-                    return Ok(Pattern::Literal(Expression::Integer(n)));
-                }
-                TokenKind::Float(n) => {
-                    let n = *n;
-                    self.advance();
-                    return Ok(Pattern::Literal(Expression::Float(n)));
-                }
-                TokenKind::String(s) => {
-                    let s = s.clone();
-                    self.advance();
-                    return Ok(Pattern::Literal(Expression::String(s)));
-                }
-                TokenKind::RawString(s) => {
-                    let s = s.clone();
-                    self.advance();
-                    return Ok(Pattern::Literal(Expression::String(s)));
-                }
-                TokenKind::Bool(b) => {
-                    let b = *b;
-                    self.advance();
-                    return Ok(Pattern::Literal(Expression::Bool(b)));
-                }
-                _ => {}
+        // Literal and range patterns: 42, -1, "text", 200..=299
+        if let Some(start) = self.parse_literal_pattern_value() {
+            let inclusive = self.check(&TokenKind::DotDotEqual);
+            if inclusive || self.check(&TokenKind::DotDot) {
+                self.advance();
+                let end =
+                    self.parse_literal_pattern_value()
+                        .ok_or_else(|| IntentError::ParserError {
+                            line: self.peek().map(|t| t.line).unwrap_or(0),
+                            column: self.peek().map(|t| t.column).unwrap_or(0),
+                            message: "Expected literal after '..' in range pattern".to_string(),
+                        })?;
+                return Ok(Pattern::Range {
+                    start,
+                    end,
+                    inclusive,
+                });
             }
+            return Ok(Pattern::Literal(start));
         }
 
         // Array pattern: [pat1, pat2, ...]
@@ -1878,7 +2005,20 @@ impl Parser {
             let mut patterns = Vec::new();
             if !self.check(&TokenKind::RightBracket) {
                 loop {
-                    patterns.push(self.parse_pattern()?);
+                    // Rest element: `..` or `..name`
+                    if self.match_token(&[TokenKind::DotDot]) {
+                        let name = match self.peek().map(|t| &t.kind) {
+                            Some(TokenKind::Identifier(n)) => {
+                                let n = n.clone();
+                                self.advance();
+                                Some(n)
+                            }
+                            _ => None,
+                        };
+                        patterns.push(Pattern::Rest(name));
+                    } else {
+                        patterns.push(self.parse_pattern()?);
+                    }
                     if !self.match_token(&[TokenKind::Comma]) {
                         break;
                     }
@@ -1888,6 +2028,35 @@ impl Parser {
             return Ok(Pattern::Array(patterns));
         }
 
+        // Map pattern: { "key": pat, name: pat, shorthand }
+        if self.match_token(&[TokenKind::LeftBrace]) {
+            let mut entries = Vec::new();
+            while !self.check(&TokenKind::RightBrace) && !self.is_at_end() {
+                let key = match self.peek().map(|t| &t.kind) {
+                    Some(TokenKind::String(k)) | Some(TokenKind::Identifier(k)) => k.clone(),
+                    _ => {
+                        return Err(IntentError::ParserError {
+                            line: self.peek().map(|t| t.line).unwrap_or(0),
+                            column: self.peek().map(|t| t.column).unwrap_or(0),
+                            message: "Expected key in map pattern".to_string(),
+                        })
+                    }
+                };
+                self.advance();
+                let value = if self.match_token(&[TokenKind::Colon]) {
+                    self.parse_pattern()?
+                } else {
+                    Pattern::Variable(key.clone())
+                };
+                entries.push((key, value));
+                if !self.match_token(&[TokenKind::Comma]) {
+                    break;
+                }
+            }
+            self.consume(&TokenKind::RightBrace, "Expected '}' after map pattern")?;
+            return Ok(Pattern::Map(entries));
+        }
+
         // Tuple pattern: (pat1, pat2, ...)
         if self.match_token(&[TokenKind::LeftParen]) {
             let mut patterns = Vec::new();
@@ -1995,6 +2164,21 @@ impl Parser {
                     });
                 }
 
+                // Binding pattern: name @ pattern
+                let at = match self.peek().map(|t| &t.kind) {
+                    Some(TokenKind::At) => true,
+                    Some(TokenKind::Identifier(n)) => n == "@",
+                    _ => false,
+                };
+                if at {
+                    self.advance(); // consume @
+                    let pattern = self.parse_single_pattern()?;
+                    return Ok(Pattern::Binding {
+                        name,
+                        pattern: Box::new(pattern),
+                    });
+                }
+
                 // Variable binding pattern
                 return Ok(Pattern::Variable(name));
             }
@@ -2023,6 +2207,20 @@ impl Parser {
     }
 
     fn parse_single_type(&mut self) -> Result<TypeExpr> {
+        // Array type: [T]
+        if self.match_token(&[TokenKind::LeftBracket]) {
+            let inner = self.parse_type()?;
+            self.consume(
+                &TokenKind::RightBracket,
+                "Expected ']' after array element type",
+            )?;
+            let array = TypeExpr::Array(Box::new(inner));
+            if self.match_token(&[TokenKind::Question]) {
+                return Ok(TypeExpr::Optional(Box::new(array)));
+            }
+            return Ok(array);
+        }
+
         let name = self.consume_identifier("Expected type name")?;
 
         // Check for generic parameters
@@ -2075,6 +2273,28 @@ impl Parser {
         })
     }
 
+    /// Property name after `.` or `?.`: an identifier or a keyword used as a
+    /// field name (`req.protocol`, `job.with`)
+    fn consume_property_name(&mut self, message: &str) -> Result<String> {
+        if let Some(token) = self.peek() {
+            let is_word = token
+                .lexeme
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_alphabetic() || c == '_')
+                && token
+                    .lexeme
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_');
+            if is_word && !matches!(token.kind, TokenKind::Identifier(_)) {
+                let name = token.lexeme.clone();
+                self.advance();
+                return Ok(name);
+            }
+        }
+        self.consume_identifier(message)
+    }
+
     /// Parse type parameters with optional bounds: `<T, U: Trait, V: A + B>`
     fn parse_type_params(&mut self) -> Result<Vec<TypeParam>> {
         if !self.match_token(&[TokenKind::Less]) {
@@ -2140,4 +2360,149 @@ mod tests {
         let program = parse("1 + 2 * 3").unwrap();
         assert_eq!(program.statements.len(), 1);
     }
+
+    #[test]
+    fn test_type_builtin_call() {
+        let program = parse(r#"type(x) == "String""#).unwrap();
+        assert!(matches!(
+            &program.statements[0],
+            Statement::Expression(Expression::Binary { .. })
+        ));
+    }
+
+    #[test]
+    fn test_array_type_syntax() {
+        let program = parse("fn f(xs: [Int], ys: [[String]]?) { }").unwrap();
+        match &program.statements[0] {
+            Statement::Function { params, .. } => {
+                assert!(matches!(
+                    params[0].type_annotation,
+                    Some(TypeExpr::Array(_))
+                ));
+                assert!(matches!(
+                    params[1].type_annotation,
+                    Some(TypeExpr::Optional(_))
+                ));
+            }
+            other => panic!("expected function, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_refined_type_alias() {
+        let program = parse("type Port = Int where self > 0 && self < 65536").unwrap();
+        match &program.statements[0] {
+            Statement::TypeAlias {
+                name, refinement, ..
+            } => {
+                assert_eq!(name, "Port");
+                assert!(matches!(refinement, Some(Expression::Binary { .. })));
+            }
+            other => panic!("expected type alias, got {:?}", other),
+        }
+    }
+    #[test]
+    fn test_rich_match_patterns() {
+        let program = parse(
+            r#"match x {
+                1 | 2 | 3 => "small",
+                200..=299 => "ok",
+                -10..0 => "negative",
+                resp @ { "status": 500 } => "error",
+                [first, ..rest] => "array",
+                _ => "other"
+            }"#,
+        )
+        .unwrap();
+        let arms = match &program.statements[0] {
+            Statement::Expression(Expression::Match { arms, .. }) => arms,
+            other => panic!("expected match, got {:?}", other),
+        };
+        assert!(matches!(&arms[0].pattern, Pattern::Or(alts) if alts.len() == 3));
+        assert!(matches!(
+            &arms[1].pattern,
+            Pattern::Range {
+                start: Expression::Integer(200),
+                end: Expression::Integer(299),
+                inclusive: true
+            }
+        ));
+        assert!(matches!(
+            &arms[2].pattern,
+            Pattern::Range {
+                start: Expression::Integer(-10),
+                inclusive: false,
+                ..
+            }
+        ));
+        match &arms[3].pattern {
+            Pattern::Binding { name, pattern } => {
+                assert_eq!(name, "resp");
+                assert!(
+                    matches!(pattern.as_ref(), Pattern::Map(entries) if entries[0].0 == "status")
+                );
+            }
+            other => panic!("expected binding, got {:?}", other),
+        }
+        assert!(matches!(
+            &arms[4].pattern,
+            Pattern::Array(ps) if matches!(&ps[1], Pattern::Rest(Some(n)) if n == "rest")
+        ));
+    }
+    #[test]
+    fn test_optional_chaining() {
+        let program = parse("data?.user?.[0]?.name()").unwrap();
+        match &program.statements[0] {
+            Statement::Expression(Expression::OptionalChain { object, access }) => {
+                assert!(matches!(access, ChainAccess::Method { method, .. } if method == "name"));
+                assert!(matches!(
+                    object.as_ref(),
+                    Expression::OptionalChain {
+                        access: ChainAccess::Index(_),
+                        ..
+                    }
+                ));
+            }
+            other => panic!("expected optional chain, got {:?}", other),
+        }
+    }
+    #[test]
+    fn test_async_fn_and_await() {
+        let program = parse("async fn load(id) { return id }\nlet x = await load(1)").unwrap();
+        assert!(matches!(
+            &program.statements[0],
+            Statement::Function { is_async: true, .. }
+        ));
+        match &program.statements[1] {
+            Statement::Let {
+                value: Some(Expression::Await(inner)),
+                ..
+            } => assert!(matches!(inner.as_ref(), Expression::Call { .. })),
+            other => panic!("expected let with await, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_anonymous_function() {
+        let program = parse(r#"group("/admin", [auth], fn() { get("/users", list) })"#).unwrap();
+        let Statement::Expression(Expression::Call { arguments, .. }) = &program.statements[0]
+        else {
+            panic!("expected call");
+        };
+        assert!(matches!(&arguments[2], Expression::Lambda { params, .. } if params.is_empty()));
+
+        // Named functions are still declarations
+        let program = parse("fn add(a, b) { return a + b }").unwrap();
+        assert!(matches!(&program.statements[0], Statement::Function { .. }));
+    }
+
+    #[test]
+    fn test_keyword_property_names() {
+        let program = parse("req.protocol").unwrap();
+        assert!(matches!(
+            &program.statements[0],
+            Statement::Expression(Expression::FieldAccess { field, .. }) if field == "protocol"
+        ));
+        assert!(parse("req.\"protocol\"").is_err());
+    }
 }
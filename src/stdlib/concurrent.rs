@@ -28,20 +28,22 @@
 //! close(ch)
 //! ```
 //!
-//! For CPU-bound parallel work, use `parallel()`:
+//! For CPU-bound parallel work, use `spawn()`, `parallel()` and `parallel_map()`:
 //! ```ntnt
-//! // Run expensive operations in parallel (thread pool)
-//! let results = parallel([
-//!     || compute_a(),
-//!     || compute_b(),
-//!     || compute_c()
-//! ])
+//! // Each call runs in a fresh interpreter on a worker thread
+//! let task = spawn(compute_a, [input])
+//! let a = join(task)
+//! let results = parallel([compute_b, compute_c])
+//! let squares = parallel_map([1, 2, 3, 4], square, 2)
 //! ```
 
+use crate::ast::{Block, Parameter, TypeExpr, TypeParam};
 use crate::error::IntentError;
-use crate::interpreter::Value;
-use std::collections::HashMap;
-use std::sync::{mpsc, Arc, Mutex};
+use crate::interpreter::{Environment, FunctionContract, Value};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+use std::sync::{mpsc, Arc, Condvar, Mutex};
 use std::thread;
 use std::time::Duration;
 
@@ -51,11 +53,16 @@ type Result<T> = std::result::Result<T, IntentError>;
 static CHANNEL_REGISTRY: std::sync::LazyLock<Mutex<HashMap<u64, ChannelPair>>> =
     std::sync::LazyLock::new(|| Mutex::new(HashMap::new()));
 static CHANNEL_ID_COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+// Bumped on every send/close so `select` can sleep until some channel changes
+static CHANNEL_ACTIVITY: Mutex<u64> = Mutex::new(0);
+static CHANNEL_ACTIVITY_CHANGED: Condvar = Condvar::new();
+// Rotates the first channel `select` polls so one busy channel cannot starve the others
+static SELECT_ROUND: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
 
 /// Serialized value for thread-safe transmission
 /// Only primitive and composite types that can be cloned
 #[derive(Debug, Clone)]
-enum SerializedValue {
+pub(crate) enum SerializedValue {
     Unit,
     Int(i64),
     Float(f64),
@@ -63,11 +70,126 @@ enum SerializedValue {
     String(String),
     Array(Vec<SerializedValue>),
     Map(HashMap<String, SerializedValue>),
+    Range {
+        start: i64,
+        end: i64,
+        inclusive: bool,
+    },
+    EnumConstructor {
+        enum_name: String,
+        variant: String,
+        arity: usize,
+    },
+    /// A function without its closure; it is rebound to the receiving
+    /// interpreter's globals. Only produced by `capture`, never by channels.
+    Function {
+        name: String,
+        params: Vec<Parameter>,
+        body: Block,
+        contract: Option<FunctionContract>,
+        type_params: Vec<TypeParam>,
+        return_type: Option<TypeExpr>,
+        is_async: bool,
+    },
+    NativeFunction {
+        name: String,
+        arity: usize,
+        func: fn(&[Value]) -> Result<Value>,
+    },
 }
 
 impl SerializedValue {
+    /// Convert from Value for transfer to another interpreter, including
+    /// functions. Control-flow values cannot be captured.
+    pub(crate) fn capture(value: &Value) -> Result<Self> {
+        match value {
+            Value::Array(arr) => Ok(SerializedValue::Array(
+                arr.iter().map(Self::capture).collect::<Result<_>>()?,
+            )),
+            Value::Map(map) => {
+                let mut captured = HashMap::new();
+                for (k, v) in map {
+                    captured.insert(k.clone(), Self::capture(v)?);
+                }
+                Ok(SerializedValue::Map(captured))
+            }
+            Value::Struct { name, fields } => {
+                let mut captured = HashMap::new();
+                captured.insert("__type".to_string(), SerializedValue::String(name.clone()));
+                for (k, v) in fields {
+                    captured.insert(k.clone(), Self::capture(v)?);
+                }
+                Ok(SerializedValue::Map(captured))
+            }
+            Value::Range {
+                start,
+                end,
+                inclusive,
+            } => Ok(SerializedValue::Range {
+                start: *start,
+                end: *end,
+                inclusive: *inclusive,
+            }),
+            Value::EnumConstructor {
+                enum_name,
+                variant,
+                arity,
+            } => Ok(SerializedValue::EnumConstructor {
+                enum_name: enum_name.clone(),
+                variant: variant.clone(),
+                arity: *arity,
+            }),
+            Value::Function {
+                name,
+                params,
+                body,
+                contract,
+                type_params,
+                return_type,
+                is_async,
+                ..
+            } => Ok(SerializedValue::Function {
+                name: name.clone(),
+                params: params.clone(),
+                body: body.clone(),
+                contract: contract.clone(),
+                type_params: type_params.clone(),
+                return_type: return_type.clone(),
+                is_async: *is_async,
+            }),
+            Value::NativeFunction { name, arity, func } => Ok(SerializedValue::NativeFunction {
+                name: name.clone(),
+                arity: *arity,
+                func: *func,
+            }),
+            Value::EnumValue {
+                enum_name,
+                variant,
+                values,
+            } => {
+                // Enum payloads may hold functions too
+                let mut captured = HashMap::new();
+                captured.insert(
+                    "__enum".to_string(),
+                    SerializedValue::String(enum_name.clone()),
+                );
+                captured.insert(
+                    "__variant".to_string(),
+                    SerializedValue::String(variant.clone()),
+                );
+                let vals: Result<Vec<_>> = values.iter().map(Self::capture).collect();
+                captured.insert("__values".to_string(), SerializedValue::Array(vals?));
+                Ok(SerializedValue::Map(captured))
+            }
+            Value::Return(_) | Value::Break | Value::Continue => Err(IntentError::TypeError(
+                "Control flow values cannot be sent to another task".to_string(),
+            )),
+            other => Self::from_value(other),
+        }
+    }
+
     /// Convert from Value to SerializedValue (only safe types)
-    fn from_value(value: &Value) -> Result<Self> {
+    pub(crate) fn from_value(value: &Value) -> Result<Self> {
         match value {
             Value::Unit => Ok(SerializedValue::Unit),
             Value::Int(i) => Ok(SerializedValue::Int(*i)),
@@ -111,15 +233,63 @@ impl SerializedValue {
         }
     }
 
-    /// Convert back to Value
-    fn to_value(&self) -> Value {
+    /// Convert back to Value. Functions are bound to a fresh, empty environment.
+    pub(crate) fn to_value(&self) -> Value {
+        self.to_value_in(&Rc::new(RefCell::new(Environment::new())))
+    }
+
+    /// Convert back to Value, binding functions to `closure`
+    pub(crate) fn to_value_in(&self, closure: &Rc<RefCell<Environment>>) -> Value {
         match self {
+            SerializedValue::Range {
+                start,
+                end,
+                inclusive,
+            } => Value::Range {
+                start: *start,
+                end: *end,
+                inclusive: *inclusive,
+            },
+            SerializedValue::EnumConstructor {
+                enum_name,
+                variant,
+                arity,
+            } => Value::EnumConstructor {
+                enum_name: enum_name.clone(),
+                variant: variant.clone(),
+                arity: *arity,
+            },
+            SerializedValue::Function {
+                name,
+                params,
+                body,
+                contract,
+                type_params,
+                return_type,
+                is_async,
+            } => Value::Function {
+                name: name.clone(),
+                params: params.clone(),
+                body: body.clone(),
+                closure: Rc::clone(closure),
+                contract: contract.clone(),
+                type_params: type_params.clone(),
+                return_type: return_type.clone(),
+                is_async: *is_async,
+            },
+            SerializedValue::NativeFunction { name, arity, func } => Value::NativeFunction {
+                name: name.clone(),
+                arity: *arity,
+                func: *func,
+            },
             SerializedValue::Unit => Value::Unit,
             SerializedValue::Int(i) => Value::Int(*i),
             SerializedValue::Float(f) => Value::Float(*f),
             SerializedValue::Bool(b) => Value::Bool(*b),
             SerializedValue::String(s) => Value::String(s.clone()),
-            SerializedValue::Array(arr) => Value::Array(arr.iter().map(|v| v.to_value()).collect()),
+            SerializedValue::Array(arr) => {
+                Value::Array(arr.iter().map(|v| v.to_value_in(closure)).collect())
+            }
             SerializedValue::Map(map) => {
                 // Check for special __enum marker
                 if let Some(SerializedValue::String(enum_name)) = map.get("__enum") {
@@ -131,7 +301,7 @@ impl SerializedValue {
                         return Value::EnumValue {
                             enum_name: enum_name.clone(),
                             variant: variant.clone(),
-                            values: values.iter().map(|v| v.to_value()).collect(),
+                            values: values.iter().map(|v| v.to_value_in(closure)).collect(),
                         };
                     }
                 }
@@ -140,7 +310,7 @@ impl SerializedValue {
                     let mut fields = HashMap::new();
                     for (k, v) in map {
                         if k != "__type" {
-                            fields.insert(k.clone(), v.to_value());
+                            fields.insert(k.clone(), v.to_value_in(closure));
                         }
                     }
                     return Value::Struct {
@@ -151,7 +321,7 @@ impl SerializedValue {
                 // Regular map
                 let mut result = HashMap::new();
                 for (k, v) in map {
-                    result.insert(k.clone(), v.to_value());
+                    result.insert(k.clone(), v.to_value_in(closure));
                 }
                 Value::Map(result)
             }
@@ -224,7 +394,10 @@ fn concurrent_send(ch: &Value, value: &Value) -> Result<Value> {
         }
 
         match pair.sender.send(serialized) {
-            Ok(_) => Ok(Value::Bool(true)),
+            Ok(_) => {
+                notify_channel_activity();
+                Ok(Value::Bool(true))
+            }
             Err(_) => Ok(Value::Bool(false)), // Receiver dropped
         }
     } else {
@@ -250,6 +423,8 @@ fn concurrent_recv(ch: &Value) -> Result<Value> {
         }
     };
 
+    // Waiting for the receiver lock blocks too, while another recv() holds it
+    let _blocked = block_worker();
     let rx = receiver
         .lock()
         .map_err(|e| IntentError::RuntimeError(format!("Failed to lock receiver: {}", e)))?;
@@ -277,6 +452,7 @@ fn concurrent_recv_timeout(ch: &Value, timeout_ms: i64) -> Result<Value> {
         }
     };
 
+    let _blocked = block_worker();
     let rx = receiver
         .lock()
         .map_err(|e| IntentError::RuntimeError(format!("Failed to lock receiver: {}", e)))?;
@@ -352,12 +528,529 @@ fn concurrent_close(ch: &Value) -> Result<Value> {
     if let Some(pair) = registry.get(&id) {
         let mut closed = pair.closed.lock().unwrap();
         *closed = true;
+        notify_channel_activity();
         Ok(Value::Bool(true))
     } else {
         Ok(Value::Bool(false))
     }
 }
 
+/// Wake any `select` calls waiting for channel activity
+fn notify_channel_activity() {
+    if let Ok(mut generation) = CHANNEL_ACTIVITY.lock() {
+        *generation = generation.wrapping_add(1);
+    }
+    CHANNEL_ACTIVITY_CHANGED.notify_all();
+}
+
+/// What a single non-blocking poll of a channel found
+enum ChannelPoll {
+    Value(SerializedValue),
+    Closed,
+    Empty,
+}
+
+/// Poll one channel without blocking. A receiver held by a blocked `recv`
+/// elsewhere counts as empty; that receiver will take the next value.
+fn poll_channel(id: u64) -> Result<ChannelPoll> {
+    let (receiver, closed) = {
+        let registry = CHANNEL_REGISTRY
+            .lock()
+            .map_err(|e| IntentError::RuntimeError(format!("Failed to lock registry: {}", e)))?;
+        match registry.get(&id) {
+            Some(pair) => (Arc::clone(&pair.receiver), Arc::clone(&pair.closed)),
+            None => return Err(IntentError::RuntimeError("Invalid channel".to_string())),
+        }
+    };
+    let Ok(rx) = receiver.try_lock() else {
+        return Ok(ChannelPoll::Empty);
+    };
+    match rx.try_recv() {
+        Ok(serialized) => Ok(ChannelPoll::Value(serialized)),
+        Err(mpsc::TryRecvError::Disconnected) => Ok(ChannelPoll::Closed),
+        Err(mpsc::TryRecvError::Empty) if *closed.lock().unwrap() => Ok(ChannelPoll::Closed),
+        Err(mpsc::TryRecvError::Empty) => Ok(ChannelPoll::Empty),
+    }
+}
+
+/// select(channels, timeout_ms?) -> Option<Map>
+/// Blocks until any channel has a value or is closed. Returns
+/// Some(map { "index", "channel", "value", "closed" }) for the channel that fired,
+/// or None if the timeout expires first. Closed channels report `closed: true`
+/// once their buffered values are drained.
+fn concurrent_select(channels: &Value, timeout_ms: Option<i64>) -> Result<Value> {
+    let handles = match channels {
+        Value::Array(items) if !items.is_empty() => items,
+        _ => {
+            return Err(IntentError::TypeError(
+                "select requires a non-empty array of channels".to_string(),
+            ))
+        }
+    };
+    let ids = handles
+        .iter()
+        .map(get_channel_id)
+        .collect::<Result<Vec<_>>>()?;
+    let deadline = match timeout_ms {
+        Some(ms) if ms < 0 => {
+            return Err(IntentError::TypeError(
+                "select timeout must not be negative".to_string(),
+            ))
+        }
+        Some(ms) => Some(std::time::Instant::now() + Duration::from_millis(ms as u64)),
+        None => None,
+    };
+    let start = SELECT_ROUND.fetch_add(1, std::sync::atomic::Ordering::Relaxed) % ids.len();
+    let mut blocked = None;
+
+    loop {
+        // Read the activity generation before polling so a send that lands
+        // between the poll and the wait is not missed
+        let seen = *CHANNEL_ACTIVITY
+            .lock()
+            .map_err(|e| IntentError::RuntimeError(format!("Failed to lock channels: {}", e)))?;
+
+        for offset in 0..ids.len() {
+            let index = (start + offset) % ids.len();
+            let (value, closed) = match poll_channel(ids[index])? {
+                ChannelPoll::Value(serialized) => (serialized.to_value(), false),
+                ChannelPoll::Closed => (Value::Unit, true),
+                ChannelPoll::Empty => continue,
+            };
+            let mut fired = HashMap::new();
+            fired.insert("index".to_string(), Value::Int(index as i64));
+            fired.insert("channel".to_string(), handles[index].clone());
+            fired.insert("value".to_string(), value);
+            fired.insert("closed".to_string(), Value::Bool(closed));
+            return Ok(Value::EnumValue {
+                enum_name: "Option".to_string(),
+                variant: "Some".to_string(),
+                values: vec![Value::Map(fired)],
+            });
+        }
+
+        blocked.get_or_insert_with(block_worker);
+        let mut generation = CHANNEL_ACTIVITY
+            .lock()
+            .map_err(|e| IntentError::RuntimeError(format!("Failed to lock channels: {}", e)))?;
+        while *generation == seen {
+            match deadline {
+                Some(deadline) => {
+                    let now = std::time::Instant::now();
+                    if now >= deadline {
+                        return Ok(Value::EnumValue {
+                            enum_name: "Option".to_string(),
+                            variant: "None".to_string(),
+                            values: vec![],
+                        });
+                    }
+                    generation = CHANNEL_ACTIVITY_CHANGED
+                        .wait_timeout(generation, deadline - now)
+                        .map_err(|e| {
+                            IntentError::RuntimeError(format!("Failed to wait for channels: {}", e))
+                        })?
+                        .0;
+                }
+                None => {
+                    generation = CHANNEL_ACTIVITY_CHANGED.wait(generation).map_err(|e| {
+                        IntentError::RuntimeError(format!("Failed to wait for channels: {}", e))
+                    })?;
+                }
+            }
+        }
+    }
+}
+
+// Global registry for async tasks. Queued tasks wait for one of a bounded pool
+// of worker threads; workers store the result and notify TASK_COMPLETED so
+// awaiting threads can wake up.
+static TASK_REGISTRY: std::sync::LazyLock<Mutex<HashMap<u64, TaskEntry>>> =
+    std::sync::LazyLock::new(|| Mutex::new(HashMap::new()));
+static TASK_COMPLETED: Condvar = Condvar::new();
+static TASK_ID_COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+static TASK_QUEUE: Mutex<TaskQueue> = Mutex::new(TaskQueue {
+    ids: VecDeque::new(),
+    workers: 0,
+    idle: 0,
+    blocked: 0,
+});
+static TASK_QUEUED: Condvar = Condvar::new();
+
+/// The pool grows to this many running workers, or one per CPU thread if that
+/// is more. Workers blocked waiting on channels or other tasks don't count.
+const MIN_TASK_WORKERS: usize = 8;
+/// Idle workers exit after this long without a task
+const TASK_WORKER_IDLE: Duration = Duration::from_secs(30);
+
+thread_local! {
+    /// Set on pool worker threads, which run queued tasks they await themselves
+    static IS_TASK_WORKER: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// Outcome of a task run on a worker thread (errors are carried as messages)
+pub(crate) type TaskOutcome = std::result::Result<SerializedValue, String>;
+
+type TaskJob = Box<dyn FnOnce() -> TaskOutcome + Send>;
+
+struct TaskEntry {
+    state: TaskState,
+    /// Nobody will await the task, so its result is dropped when it finishes
+    detached: bool,
+}
+
+enum TaskState {
+    Queued(TaskJob),
+    Running,
+    Done(TaskOutcome),
+}
+
+/// Task IDs waiting for a worker, and the size of the pool
+struct TaskQueue {
+    ids: VecDeque<u64>,
+    workers: usize,
+    idle: usize,
+    /// Workers waiting in recv(), select() or await
+    blocked: usize,
+}
+
+/// Start a worker if tasks are queued, no worker is idle and fewer than the
+/// pool size are running (not blocked)
+fn grow_pool(queue: &mut TaskQueue) -> std::io::Result<()> {
+    let max_workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .max(MIN_TASK_WORKERS);
+    if queue.ids.is_empty() || queue.idle > 0 || queue.workers - queue.blocked >= max_workers {
+        return Ok(());
+    }
+    thread::Builder::new()
+        .name("ntnt-task".to_string())
+        .spawn(task_worker)?;
+    queue.workers += 1;
+    Ok(())
+}
+
+/// Held by a pool worker while it waits on a channel or another task. If
+/// every worker ends up waiting, a new one starts for the queued tasks they
+/// may be waiting for.
+struct BlockedWorker(bool);
+
+fn block_worker() -> BlockedWorker {
+    if !IS_TASK_WORKER.with(|flag| flag.get()) {
+        return BlockedWorker(false);
+    }
+    match TASK_QUEUE.lock() {
+        Ok(mut queue) => {
+            queue.blocked += 1;
+            // Existing workers get to the queue once they stop waiting
+            let _ = grow_pool(&mut queue);
+            BlockedWorker(true)
+        }
+        Err(_) => BlockedWorker(false),
+    }
+}
+
+impl Drop for BlockedWorker {
+    fn drop(&mut self) {
+        if self.0 {
+            if let Ok(mut queue) = TASK_QUEUE.lock() {
+                queue.blocked -= 1;
+            }
+        }
+    }
+}
+
+fn lock_tasks() -> Result<std::sync::MutexGuard<'static, HashMap<u64, TaskEntry>>> {
+    TASK_REGISTRY
+        .lock()
+        .map_err(|e| IntentError::RuntimeError(format!("Failed to lock task registry: {}", e)))
+}
+
+/// Create a task handle value
+fn create_task_value(id: u64) -> Value {
+    let mut task = HashMap::new();
+    task.insert("_task_id".to_string(), Value::Int(id as i64));
+    task.insert("type".to_string(), Value::String("Task".to_string()));
+    Value::Map(task)
+}
+
+/// Get task ID from a task value, or None if the value is not a task handle
+pub(crate) fn get_task_id(task: &Value) -> Option<u64> {
+    match task {
+        Value::Map(map) => match map.get("_task_id") {
+            Some(Value::Int(id)) => Some(*id as u64),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Queue `work` for the task worker pool and return a task handle for its result
+pub(crate) fn spawn_task<F>(name: &str, work: F) -> Result<Value>
+where
+    F: FnOnce() -> TaskOutcome + Send + 'static,
+{
+    let id = TASK_ID_COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    lock_tasks()?.insert(
+        id,
+        TaskEntry {
+            state: TaskState::Queued(Box::new(work)),
+            detached: false,
+        },
+    );
+
+    let mut queue = TASK_QUEUE
+        .lock()
+        .map_err(|e| IntentError::RuntimeError(format!("Failed to lock task queue: {}", e)))?;
+    queue.ids.push_back(id);
+    match grow_pool(&mut queue) {
+        Ok(()) => {}
+        // Existing workers will get to it
+        Err(_) if queue.workers > 0 => {}
+        Err(e) => {
+            queue.ids.pop_back();
+            drop(queue);
+            lock_tasks()?.remove(&id);
+            return Err(IntentError::RuntimeError(format!(
+                "Failed to start task '{}': {}",
+                name, e
+            )));
+        }
+    }
+    TASK_QUEUED.notify_one();
+    Ok(create_task_value(id))
+}
+
+/// Run `work` on a new thread of its own, outside the pool, and return a task
+/// handle for its result
+pub(crate) fn spawn_thread<F>(name: &str, work: F) -> Result<Value>
+where
+    F: FnOnce() -> TaskOutcome + Send + 'static,
+{
+    let id = TASK_ID_COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    lock_tasks()?.insert(
+        id,
+        TaskEntry {
+            state: TaskState::Running,
+            detached: false,
+        },
+    );
+    let spawned = thread::Builder::new()
+        .name(format!("ntnt-spawn-{}", name))
+        .spawn(move || finish_task(id, work()));
+    if let Err(e) = spawned {
+        lock_tasks()?.remove(&id);
+        return Err(IntentError::RuntimeError(format!(
+            "Failed to start thread for '{}': {}",
+            name, e
+        )));
+    }
+    Ok(create_task_value(id))
+}
+
+/// Pool worker: run queued tasks until idle for `TASK_WORKER_IDLE`
+fn task_worker() {
+    IS_TASK_WORKER.with(|flag| flag.set(true));
+    loop {
+        let id = {
+            let Ok(mut queue) = TASK_QUEUE.lock() else {
+                return;
+            };
+            loop {
+                if let Some(id) = queue.ids.pop_front() {
+                    break id;
+                }
+                queue.idle += 1;
+                let Ok((next, timeout)) = TASK_QUEUED.wait_timeout(queue, TASK_WORKER_IDLE) else {
+                    return;
+                };
+                queue = next;
+                queue.idle -= 1;
+                if timeout.timed_out() && queue.ids.is_empty() {
+                    queue.workers -= 1;
+                    return;
+                }
+            }
+        };
+        // An awaiting worker may already have run it
+        if let Some(job) = start_queued(id) {
+            finish_task(id, job());
+        }
+    }
+}
+
+/// Take a queued task's job, marking it running
+fn start_queued(id: u64) -> Option<TaskJob> {
+    let mut registry = TASK_REGISTRY.lock().ok()?;
+    let entry = registry.get_mut(&id)?;
+    if !matches!(entry.state, TaskState::Queued(_)) {
+        return None;
+    }
+    match std::mem::replace(&mut entry.state, TaskState::Running) {
+        TaskState::Queued(job) => Some(job),
+        _ => None,
+    }
+}
+
+/// Store a task's outcome, or drop it if the task was detached
+fn finish_task(id: u64, outcome: TaskOutcome) {
+    if let Ok(mut registry) = TASK_REGISTRY.lock() {
+        let detached = registry.get(&id).is_some_and(|entry| entry.detached);
+        if detached {
+            registry.remove(&id);
+        } else if let Some(entry) = registry.get_mut(&id) {
+            entry.state = TaskState::Done(outcome);
+        }
+    }
+    TASK_COMPLETED.notify_all();
+}
+
+/// Give up on a task's result: it keeps running (or runs, if queued), and its
+/// entry is removed once it finishes
+pub(crate) fn detach_task(id: u64) {
+    if let Ok(mut registry) = TASK_REGISTRY.lock() {
+        match registry.get_mut(&id) {
+            Some(TaskEntry {
+                state: TaskState::Done(_),
+                ..
+            }) => {
+                registry.remove(&id);
+            }
+            Some(entry) => entry.detached = true,
+            None => {}
+        }
+    }
+}
+
+/// Whether the registry still holds an entry for task `id`
+#[cfg(test)]
+pub(crate) fn task_registered(id: u64) -> bool {
+    lock_tasks().is_ok_and(|registry| registry.contains_key(&id))
+}
+
+/// Take a finished task's result out of the registry
+fn take_outcome(registry: &mut HashMap<u64, TaskEntry>, id: u64) -> Result<Value> {
+    match registry.remove(&id).map(|entry| entry.state) {
+        Some(TaskState::Done(Ok(value))) => Ok(value.to_value()),
+        Some(TaskState::Done(Err(message))) => Err(IntentError::RuntimeError(format!(
+            "Task failed: {}",
+            message
+        ))),
+        _ => Err(IntentError::RuntimeError(format!(
+            "Task {} was already awaited",
+            id
+        ))),
+    }
+}
+
+/// Collect task IDs from an array of task handles
+fn task_ids(tasks: &Value, fn_name: &str) -> Result<Vec<u64>> {
+    match tasks {
+        Value::Array(items) => items
+            .iter()
+            .map(|t| {
+                get_task_id(t).ok_or_else(|| {
+                    IntentError::TypeError(format!("{} requires an array of tasks", fn_name))
+                })
+            })
+            .collect(),
+        _ => Err(IntentError::TypeError(format!(
+            "{} requires an array of tasks",
+            fn_name
+        ))),
+    }
+}
+
+/// Wait for the first of `ids` to finish and take its result. On a pool
+/// worker, a queued task is run on the spot instead of waiting for another
+/// worker, so tasks that await tasks cannot exhaust the pool.
+fn await_first(ids: &[u64]) -> Result<(u64, Value)> {
+    let on_worker = IS_TASK_WORKER.with(|flag| flag.get());
+    let mut blocked = None;
+    let mut registry = lock_tasks()?;
+    loop {
+        let mut queued = None;
+        for id in ids {
+            match registry.get_mut(id) {
+                Some(entry) => {
+                    // Awaiting a detached task takes it back
+                    entry.detached = false;
+                    match entry.state {
+                        TaskState::Done(_) => {
+                            let value = take_outcome(&mut registry, *id);
+                            return value.map(|value| (*id, value));
+                        }
+                        TaskState::Queued(_) if queued.is_none() => queued = Some(*id),
+                        _ => {}
+                    }
+                }
+                None => {
+                    return Err(IntentError::RuntimeError(format!(
+                        "Task {} was already awaited",
+                        id
+                    )))
+                }
+            }
+        }
+        if let (true, Some(id)) = (on_worker, queued) {
+            drop(registry);
+            if let Some(job) = start_queued(id) {
+                finish_task(id, job());
+            }
+            registry = lock_tasks()?;
+            continue;
+        }
+        blocked.get_or_insert_with(block_worker);
+        registry = TASK_COMPLETED
+            .wait(registry)
+            .map_err(|e| IntentError::RuntimeError(format!("Failed to wait for task: {}", e)))?;
+    }
+}
+
+/// await task -> Value
+/// Blocks until the task finishes and returns its result
+pub(crate) fn await_task(id: u64) -> Result<Value> {
+    await_first(&[id]).map(|(_, value)| value)
+}
+
+/// await_all(tasks) -> Array
+/// Waits for every task and returns their results in order
+fn concurrent_await_all(tasks: &Value) -> Result<Value> {
+    let ids = task_ids(tasks, "await_all")?;
+    let results: Result<Vec<Value>> = ids.into_iter().map(await_task).collect();
+    Ok(Value::Array(results?))
+}
+
+/// await_any(tasks) -> Value
+/// Waits for the first task to finish and returns its result. The other tasks
+/// keep running but are detached: their results are discarded.
+fn concurrent_await_any(tasks: &Value) -> Result<Value> {
+    let ids = task_ids(tasks, "await_any")?;
+    if ids.is_empty() {
+        return Err(IntentError::RuntimeError(
+            "await_any requires at least one task".to_string(),
+        ));
+    }
+    let result = await_first(&ids);
+    let winner = result.as_ref().ok().map(|(id, _)| *id);
+    for id in ids.into_iter().filter(|id| Some(*id) != winner) {
+        detach_task(id);
+    }
+    result.map(|(_, value)| value)
+}
+
+/// join(task) -> Value
+/// Blocks until a spawned task finishes and returns its result
+fn concurrent_join(task: &Value) -> Result<Value> {
+    let id = get_task_id(task)
+        .ok_or_else(|| IntentError::TypeError("join requires a task handle".to_string()))?;
+    await_task(id)
+}
+
+/// Names of the functions that need the interpreter to run user code on worker
+/// threads; the interpreter intercepts calls to them
+pub(crate) const INTERPRETER_FUNCTIONS: [&str; 3] = ["spawn", "parallel", "parallel_map"];
+
 /// sleep_ms(millis) -> Unit
 /// Sleep for the specified number of milliseconds (re-exported from std/time for convenience)
 fn concurrent_sleep_ms(ms: i64) -> Result<Value> {
@@ -446,6 +1139,22 @@ pub fn init() -> HashMap<String, Value> {
         },
     );
 
+    // select(channels, timeout_ms?) -> Option<Map>
+    module.insert(
+        "select".to_string(),
+        Value::NativeFunction {
+            name: "select".to_string(),
+            arity: 0,
+            func: |args| match args {
+                [channels] => concurrent_select(channels, None),
+                [channels, Value::Int(ms)] => concurrent_select(channels, Some(*ms)),
+                _ => Err(IntentError::TypeError(
+                    "select requires (channels) or (channels, int_millis)".to_string(),
+                )),
+            },
+        },
+    );
+
     // sleep_ms(millis) -> Unit
     module.insert(
         "sleep_ms".to_string(),
@@ -461,6 +1170,75 @@ pub fn init() -> HashMap<String, Value> {
         },
     );
 
+    // await_all(tasks) -> Array
+    module.insert(
+        "await_all".to_string(),
+        Value::NativeFunction {
+            name: "await_all".to_string(),
+            arity: 1,
+            func: |args| concurrent_await_all(&args[0]),
+        },
+    );
+
+    // await_any(tasks) -> Value
+    module.insert(
+        "await_any".to_string(),
+        Value::NativeFunction {
+            name: "await_any".to_string(),
+            arity: 1,
+            func: |args| concurrent_await_any(&args[0]),
+        },
+    );
+
+    // join(task) -> Value
+    module.insert(
+        "join".to_string(),
+        Value::NativeFunction {
+            name: "join".to_string(),
+            arity: 1,
+            func: |args| concurrent_join(&args[0]),
+        },
+    );
+
+    // spawn(fn, args) -> Task, parallel(fns) -> Array, parallel_map(arr, fn, workers) -> Array
+    // These run user functions, so the interpreter handles the call itself
+    module.insert(
+        "spawn".to_string(),
+        Value::NativeFunction {
+            name: "spawn".to_string(),
+            arity: 0,
+            func: |_args| {
+                Err(IntentError::RuntimeError(
+                    "spawn() must be called directly, not stored in a variable".to_string(),
+                ))
+            },
+        },
+    );
+    module.insert(
+        "parallel".to_string(),
+        Value::NativeFunction {
+            name: "parallel".to_string(),
+            arity: 0,
+            func: |_args| {
+                Err(IntentError::RuntimeError(
+                    "parallel() must be called directly, not stored in a variable".to_string(),
+                ))
+            },
+        },
+    );
+    module.insert(
+        "parallel_map".to_string(),
+        Value::NativeFunction {
+            name: "parallel_map".to_string(),
+            arity: 0,
+            func: |_args| {
+                Err(IntentError::RuntimeError(
+                    "parallel_map() must be called directly, not stored in a variable".to_string(),
+                ))
+            },
+        },
+    );
+
     // thread_count() -> Int
     module.insert(
         "thread_count".to_string(),
@@ -522,6 +1300,57 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_select_returns_fired_channel() {
+        let jobs = concurrent_channel().unwrap();
+        let shutdown = concurrent_channel().unwrap();
+        let channels = Value::Array(vec![jobs.clone(), shutdown.clone()]);
+
+        // Nothing ready: times out with None
+        match concurrent_select(&channels, Some(20)).unwrap() {
+            Value::EnumValue { variant, .. } => assert_eq!(variant, "None"),
+            other => panic!("Expected Option::None, got {:?}", other),
+        }
+
+        // A send from another thread wakes the blocked select
+        let shutdown_id = get_channel_id(&shutdown).unwrap();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(30));
+            let sender = create_channel_value(shutdown_id);
+            concurrent_send(&sender, &Value::String("stop".to_string())).unwrap();
+        });
+        let fired = match concurrent_select(&channels, None).unwrap() {
+            Value::EnumValue { mut values, .. } => values.remove(0),
+            other => panic!("Expected Option::Some, got {:?}", other),
+        };
+        let Value::Map(fired) = fired else {
+            panic!("Expected a map")
+        };
+        assert!(matches!(fired.get("index"), Some(Value::Int(1))));
+        assert!(matches!(fired.get("value"), Some(Value::String(s)) if s == "stop"));
+        assert!(matches!(fired.get("closed"), Some(Value::Bool(false))));
+    }
+
+    #[test]
+    fn test_select_drains_then_reports_closed() {
+        let ch = concurrent_channel().unwrap();
+        concurrent_send(&ch, &Value::Int(7)).unwrap();
+        concurrent_close(&ch).unwrap();
+        let channels = Value::Array(vec![ch]);
+
+        let field = |result: Value, key: &str| match result {
+            Value::EnumValue { values, .. } => match &values[0] {
+                Value::Map(m) => m.get(key).cloned().unwrap(),
+                other => panic!("Expected a map, got {:?}", other),
+            },
+            other => panic!("Expected Option::Some, got {:?}", other),
+        };
+        let first = concurrent_select(&channels, Some(0)).unwrap();
+        assert!(matches!(field(first, "value"), Value::Int(7)));
+        let second = concurrent_select(&channels, Some(0)).unwrap();
+        assert!(matches!(field(second, "closed"), Value::Bool(true)));
+    }
+
     #[test]
     fn test_serialization_round_trip() {
         // Test primitive types
@@ -548,6 +1377,158 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_capture_function_rebinds_closure() {
+        let func = Value::Function {
+            name: "double".to_string(),
+            params: vec![],
+            body: Block { statements: vec![] },
+            closure: Rc::new(RefCell::new(Environment::new())),
+            contract: None,
+            type_params: vec![],
+            return_type: None,
+            is_async: true,
+        };
+        // Channels only carry data
+        assert!(SerializedValue::from_value(&func).is_err());
+
+        let captured = SerializedValue::capture(&func).unwrap();
+        let env = Rc::new(RefCell::new(Environment::new()));
+        match captured.to_value_in(&env) {
+            Value::Function {
+                name,
+                closure,
+                is_async,
+                ..
+            } => {
+                assert_eq!(name, "double");
+                assert!(is_async);
+                assert!(Rc::ptr_eq(&closure, &env));
+            }
+            other => panic!("Expected function, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_spawn_and_await_task() {
+        let task = spawn_task("test", || Ok(SerializedValue::Int(7))).unwrap();
+        let id = get_task_id(&task).unwrap();
+        assert!(matches!(await_task(id).unwrap(), Value::Int(7)));
+        // A task's result can only be taken once
+        assert!(await_task(id).is_err());
+    }
+
+    #[test]
+    fn test_await_any_returns_first_finished() {
+        let slow = spawn_task("slow", || {
+            thread::sleep(Duration::from_millis(300));
+            Ok(SerializedValue::Int(1))
+        })
+        .unwrap();
+        let fast = spawn_task("fast", || Ok(SerializedValue::Int(2))).unwrap();
+        let slow_id = get_task_id(&slow).unwrap();
+        let result = concurrent_await_any(&Value::Array(vec![slow, fast])).unwrap();
+        assert!(matches!(result, Value::Int(2)));
+
+        // The loser is detached, so its entry goes away once it finishes
+        assert!(wait_until_unregistered(slow_id));
+        assert!(await_task(slow_id).is_err());
+    }
+
+    fn wait_until_unregistered(id: u64) -> bool {
+        for _ in 0..200 {
+            if !task_registered(id) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        false
+    }
+
+    #[test]
+    fn test_detached_task_still_runs() {
+        let (tx, rx) = mpsc::channel();
+        let task = spawn_task("detached", move || {
+            tx.send(()).ok();
+            Ok(SerializedValue::Unit)
+        })
+        .unwrap();
+        let id = get_task_id(&task).unwrap();
+        detach_task(id);
+        assert!(rx.recv_timeout(Duration::from_secs(5)).is_ok());
+        assert!(wait_until_unregistered(id));
+    }
+
+    #[test]
+    fn test_awaiting_task_inside_task_does_not_block_pool() {
+        // More nested awaits than the pool can hold workers
+        let tasks: Vec<Value> = (0..MIN_TASK_WORKERS as i64 * 4)
+            .map(|n| {
+                spawn_task("outer", move || {
+                    let inner = spawn_task("inner", move || Ok(SerializedValue::Int(n)))
+                        .map_err(|e| e.to_string())?;
+                    let id = get_task_id(&inner).unwrap_or_default();
+                    await_task(id)
+                        .and_then(|value| SerializedValue::capture(&value))
+                        .map_err(|e| e.to_string())
+                })
+                .unwrap()
+            })
+            .collect();
+        let results = concurrent_await_all(&Value::Array(tasks)).unwrap();
+        match results {
+            Value::Array(values) => assert_eq!(values.len(), MIN_TASK_WORKERS * 4),
+            other => panic!("Expected Array, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_pool_grows_when_every_worker_is_blocked() {
+        // More consumers blocked in recv() than the pool holds, and a producer
+        // queued behind them
+        let consumers = MIN_TASK_WORKERS * 2;
+        let jobs = get_channel_id(&concurrent_channel().unwrap()).unwrap();
+        let mut tasks: Vec<Value> = (0..consumers)
+            .map(|_| {
+                spawn_task("consumer", move || {
+                    concurrent_recv(&create_channel_value(jobs))
+                        .and_then(|value| SerializedValue::capture(&value))
+                        .map_err(|e| e.to_string())
+                })
+                .unwrap()
+            })
+            .collect();
+        tasks.push(
+            spawn_task("producer", move || {
+                for n in 0..consumers {
+                    concurrent_send(&create_channel_value(jobs), &Value::Int(n as i64))
+                        .map_err(|e| e.to_string())?;
+                }
+                Ok(SerializedValue::Unit)
+            })
+            .unwrap(),
+        );
+
+        let ids: Vec<u64> = tasks.iter().filter_map(get_task_id).collect();
+        let (done_tx, done_rx) = mpsc::channel();
+        thread::spawn(move || {
+            let finished = ids.iter().all(|id| await_task(*id).is_ok());
+            done_tx.send(finished).ok();
+        });
+        assert_eq!(done_rx.recv_timeout(Duration::from_secs(10)), Ok(true));
+    }
+
+    #[test]
+    fn test_spawn_thread_runs_outside_the_pool() {
+        let task = spawn_thread("own", || {
+            let on_worker = IS_TASK_WORKER.with(|flag| flag.get());
+            Ok(SerializedValue::Bool(on_worker))
+        })
+        .unwrap();
+        let id = get_task_id(&task).unwrap();
+        assert!(matches!(await_task(id).unwrap(), Value::Bool(false)));
+    }
+
     #[test]
     fn test_thread_count() {
         let count = concurrent_thread_count().unwrap();
@@ -8,6 +8,27 @@ use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use uuid::Uuid;
 
+type HmacSha256 = Hmac<Sha256>;
+
+/// HMAC-SHA256 of `data` under `key`
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    // HMAC accepts keys of any length, so this cannot fail
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Check an HMAC-SHA256 tag in constant time
+pub fn verify_hmac_sha256(key: &[u8], data: &[u8], tag: &[u8]) -> bool {
+    match HmacSha256::new_from_slice(key) {
+        Ok(mut mac) => {
+            mac.update(data);
+            mac.verify_slice(tag).is_ok()
+        }
+        Err(_) => false,
+    }
+}
+
 /// Initialize the std/crypto module
 pub fn init() -> HashMap<String, Value> {
     let mut module: HashMap<String, Value> = HashMap::new();
@@ -79,14 +100,9 @@ pub fn init() -> HashMap<String, Value> {
             name: "hmac_sha256".to_string(),
             arity: 2,
             func: |args| match (&args[0], &args[1]) {
-                (Value::String(key), Value::String(data)) => {
-                    type HmacSha256 = Hmac<Sha256>;
-                    let mut mac = HmacSha256::new_from_slice(key.as_bytes())
-                        .map_err(|e| IntentError::RuntimeError(format!("HMAC error: {}", e)))?;
-                    mac.update(data.as_bytes());
-                    let result = mac.finalize();
-                    Ok(Value::String(hex::encode(result.into_bytes())))
-                }
+                (Value::String(key), Value::String(data)) => Ok(Value::String(hex::encode(
+                    hmac_sha256(key.as_bytes(), data.as_bytes()),
+                ))),
                 _ => Err(IntentError::TypeError(
                     "hmac_sha256() requires two strings (key, data)".to_string(),
                 )),
@@ -33,10 +33,24 @@
 //! │  └──────────────────────────────────────────────────────────┘   │
 //! └─────────────────────────────────────────────────────────────────┘
 //! ```
+//!
+//! The std/schedule timer thread sends `BridgeMessage::Scheduled` through the
+//! same channel, so scheduled jobs run on the interpreter between requests.
+//!
+//! ## Worker Pools
+//!
+//! `listen(port, map { "workers": N })` starts N interpreter threads, each
+//! with its own channel and its own copy of the program. `InterpreterHandle`
+//! holds one sender per worker and picks a worker for every request according
+//! to its `Dispatch` strategy. Scheduled jobs only go to the first worker.
 
 use crate::error::{IntentError, Result};
 use crate::interpreter::Value;
+use crate::stdlib::http_cookies;
+use crate::stdlib::http_multipart::MultipartForm;
+use crate::stdlib::http_request;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::sync::{mpsc, oneshot};
 
@@ -51,14 +65,20 @@ pub struct BridgeRequest {
     pub url: String,
     /// Query string (after ?)
     pub query: String,
-    /// Parsed query parameters
+    /// Decoded query parameters (the last value of repeated keys)
     pub query_params: HashMap<String, String>,
+    /// Every value of each query parameter, in order
+    pub query_all: HashMap<String, Vec<String>>,
     /// Route parameters extracted from path (e.g., {id} -> "123")
     pub params: HashMap<String, String>,
-    /// HTTP headers (lowercase keys)
+    /// HTTP headers (lowercase keys; repeated headers are joined)
     pub headers: HashMap<String, String>,
     /// Request body as string
     pub body: String,
+    /// Raw body, kept when it is not valid UTF-8 (`body` then holds a lossy copy)
+    pub body_bytes: Option<Vec<u8>>,
+    /// Parsed multipart/form-data body (`body` is then empty)
+    pub multipart: Option<MultipartForm>,
     /// Unique request ID
     pub id: String,
     /// Client IP address
@@ -88,6 +108,10 @@ impl BridgeRequest {
             .map(|(k, v)| (k.clone(), Value::String(v.clone())))
             .collect();
         map.insert("query_params".to_string(), Value::Map(query_params));
+        map.insert(
+            "query_all".to_string(),
+            http_request::query_all_value(&self.query_all),
+        );
 
         // Route params
         let params: HashMap<String, Value> = self
@@ -104,6 +128,21 @@ impl BridgeRequest {
             .map(|(k, v)| (k.clone(), Value::String(v.clone())))
             .collect();
         map.insert("headers".to_string(), Value::Map(headers));
+        map.insert(
+            "cookies".to_string(),
+            http_cookies::cookies_value(self.headers.get("cookie").map(String::as_str)),
+        );
+
+        if let Some(bytes) = &self.body_bytes {
+            map.insert(
+                "body_bytes".to_string(),
+                Value::Array(bytes.iter().map(|b| Value::Int(*b as i64)).collect()),
+            );
+        }
+        // Read by parse_multipart()
+        if let Some(form) = &self.multipart {
+            map.insert("_multipart".to_string(), form.to_value());
+        }
 
         Value::Map(map)
     }
@@ -114,10 +153,14 @@ impl BridgeRequest {
 pub struct BridgeResponse {
     /// HTTP status code
     pub status: u16,
-    /// Response headers
-    pub headers: HashMap<String, String>,
+    /// Response headers in order; a name appears once per value
+    /// (e.g. one `set-cookie` entry per cookie)
+    pub headers: Vec<(String, String)>,
     /// Response body
     pub body: String,
+    /// Stream that supplies the body while the handler keeps running
+    /// (see `http_stream`); `body` is ignored when set
+    pub stream: Option<u64>,
 }
 
 impl BridgeResponse {
@@ -135,11 +178,11 @@ impl BridgeResponse {
                     _ => String::new(),
                 };
 
-                let mut headers = HashMap::new();
+                let mut headers = Vec::new();
                 if let Some(Value::Map(h)) = map.get("headers") {
                     for (k, v) in h {
-                        if let Value::String(val) = v {
-                            headers.insert(k.clone(), val.clone());
+                        for val in http_cookies::header_values(v) {
+                            headers.push((k.clone(), val));
                         }
                     }
                 }
@@ -148,30 +191,39 @@ impl BridgeResponse {
                     status,
                     headers,
                     body,
+                    stream: None,
                 }
             }
             _ => BridgeResponse {
                 status: 500,
-                headers: HashMap::new(),
+                headers: Vec::new(),
                 body: "Handler did not return a valid response".to_string(),
+                stream: None,
             },
         }
     }
 
     /// Create an error response
     pub fn error(status: u16, message: &str) -> Self {
-        let mut headers = HashMap::new();
-        headers.insert(
-            "content-type".to_string(),
-            "text/plain; charset=utf-8".to_string(),
-        );
         BridgeResponse {
             status,
-            headers,
+            headers: vec![(
+                "content-type".to_string(),
+                "text/plain; charset=utf-8".to_string(),
+            )],
             body: message.to_string(),
+            stream: None,
         }
     }
 
+    /// First value of a header (names compare case-insensitively)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
     /// Create a not found response
     pub fn not_found() -> Self {
         Self::error(404, "Not Found")
@@ -182,30 +234,179 @@ impl BridgeResponse {
 pub struct HandlerRequest {
     /// The HTTP request data
     pub request: BridgeRequest,
+    /// WebSocket activity on a ws() route (None for plain HTTP requests)
+    pub websocket: Option<WsEvent>,
     /// Channel to send the response back
     pub reply_tx: oneshot::Sender<BridgeResponse>,
 }
 
-/// Handle to send requests to the interpreter
-#[derive(Clone)]
+/// WebSocket activity, sent with the request that opened the connection
+#[derive(Debug, Clone, PartialEq)]
+pub enum WsEvent {
+    /// A client asked to connect; replying with status 101 accepts it
+    Open(u64),
+    /// A message arrived on the connection
+    Message(u64, String),
+    /// The connection closed
+    Close(u64),
+}
+
+/// Message delivered to the interpreter thread
+pub enum BridgeMessage {
+    /// An HTTP request to route to a handler
+    Request(Box<HandlerRequest>),
+    /// A std/schedule job that is due to run (by schedule ID)
+    Scheduled(u64),
+}
+
+/// How requests are spread across a worker pool
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Dispatch {
+    /// Each request goes to the next worker in turn
+    RoundRobin,
+    /// Each request goes to the worker with the fewest requests in flight
+    #[default]
+    LeastBusy,
+}
+
+impl Dispatch {
+    /// Parse a dispatch name as used in `listen()` options
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "round_robin" => Some(Dispatch::RoundRobin),
+            "least_busy" => Some(Dispatch::LeastBusy),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dispatch::RoundRobin => "round_robin",
+            Dispatch::LeastBusy => "least_busy",
+        }
+    }
+}
+
+/// One interpreter thread in the pool
+struct Worker {
+    tx: mpsc::Sender<BridgeMessage>,
+    /// Requests sent to this worker that have not been answered yet
+    in_flight: AtomicUsize,
+}
+
+/// Decrements a worker's in-flight count when the request finishes or is dropped
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Handle to send requests to the interpreter (or a pool of interpreters)
 pub struct InterpreterHandle {
-    tx: mpsc::Sender<HandlerRequest>,
+    workers: Vec<Worker>,
+    dispatch: Dispatch,
+    next: AtomicUsize,
 }
 
 impl InterpreterHandle {
     /// Create a new handle with the given sender
-    pub fn new(tx: mpsc::Sender<HandlerRequest>) -> Self {
-        InterpreterHandle { tx }
+    pub fn new(tx: mpsc::Sender<BridgeMessage>) -> Self {
+        Self::pool(vec![tx], Dispatch::default())
+    }
+
+    /// Create a handle that spreads requests over several interpreters
+    pub fn pool(senders: Vec<mpsc::Sender<BridgeMessage>>, dispatch: Dispatch) -> Self {
+        InterpreterHandle {
+            workers: senders
+                .into_iter()
+                .map(|tx| Worker {
+                    tx,
+                    in_flight: AtomicUsize::new(0),
+                })
+                .collect(),
+            dispatch,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Number of interpreters behind this handle
+    pub fn worker_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Strategy used to pick a worker for each request
+    pub fn dispatch(&self) -> Dispatch {
+        self.dispatch
+    }
+
+    /// Choose the worker for the next request, skipping workers whose
+    /// interpreter has stopped
+    fn pick(&self) -> Option<usize> {
+        let count = self.workers.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut live = (0..count)
+            .map(|offset| (start + offset) % count)
+            .filter(|index| !self.workers[*index].tx.is_closed());
+        match self.dispatch {
+            Dispatch::RoundRobin => live.next(),
+            Dispatch::LeastBusy => {
+                live.min_by_key(|index| self.workers[*index].in_flight.load(Ordering::SeqCst))
+            }
+        }
     }
 
     /// Send a request to the interpreter and wait for response
     pub async fn call(&self, request: BridgeRequest) -> Result<BridgeResponse> {
+        let worker = self
+            .pick()
+            .ok_or_else(|| IntentError::RuntimeError("Interpreter channel closed".to_string()))?;
+        self.send_to(worker, request, None).await
+    }
+
+    /// Send a WebSocket event and wait for the interpreter to handle it
+    ///
+    /// Opening a connection picks a worker like any request; later events of
+    /// the connection pass that worker's index so they reach the interpreter
+    /// holding its callbacks. Returns the worker used.
+    pub async fn call_websocket(
+        &self,
+        worker: Option<usize>,
+        request: BridgeRequest,
+        event: WsEvent,
+    ) -> Result<(usize, BridgeResponse)> {
+        let worker = worker
+            .or_else(|| self.pick())
+            .ok_or_else(|| IntentError::RuntimeError("Interpreter channel closed".to_string()))?;
+        let response = self.send_to(worker, request, Some(event)).await?;
+        Ok((worker, response))
+    }
+
+    async fn send_to(
+        &self,
+        index: usize,
+        request: BridgeRequest,
+        websocket: Option<WsEvent>,
+    ) -> Result<BridgeResponse> {
+        let worker = self
+            .workers
+            .get(index)
+            .ok_or_else(|| IntentError::RuntimeError("Interpreter channel closed".to_string()))?;
+        worker.in_flight.fetch_add(1, Ordering::SeqCst);
+        let _in_flight = InFlight(&worker.in_flight);
+
         let (reply_tx, reply_rx) = oneshot::channel();
 
-        let handler_request = HandlerRequest { request, reply_tx };
+        let handler_request = HandlerRequest {
+            request,
+            websocket,
+            reply_tx,
+        };
 
-        self.tx
-            .send(handler_request)
+        worker
+            .tx
+            .send(BridgeMessage::Request(Box::new(handler_request)))
             .await
             .map_err(|_| IntentError::RuntimeError("Interpreter channel closed".to_string()))?;
 
@@ -232,7 +433,7 @@ impl Default for BridgeConfig {
 /// Create a channel pair for interpreter communication
 pub fn create_channel(
     config: &BridgeConfig,
-) -> (mpsc::Sender<HandlerRequest>, mpsc::Receiver<HandlerRequest>) {
+) -> (mpsc::Sender<BridgeMessage>, mpsc::Receiver<BridgeMessage>) {
     mpsc::channel(config.channel_buffer)
 }
 
@@ -250,6 +451,9 @@ mod tests {
             path: "/users/42".to_string(),
             url: "/users/42?foo=bar".to_string(),
             query: "foo=bar".to_string(),
+            query_all: [("foo".to_string(), vec!["bar".to_string()])]
+                .into_iter()
+                .collect(),
             query_params: [("foo".to_string(), "bar".to_string())]
                 .into_iter()
                 .collect(),
@@ -258,6 +462,8 @@ mod tests {
                 .into_iter()
                 .collect(),
             body: "".to_string(),
+            body_bytes: None,
+            multipart: None,
             id: "req-123".to_string(),
             ip: "127.0.0.1".to_string(),
             protocol: "http".to_string(),
@@ -303,10 +509,7 @@ mod tests {
 
         assert_eq!(response.status, 201);
         assert_eq!(response.body, "{\"id\":1}");
-        assert_eq!(
-            response.headers.get("content-type"),
-            Some(&"application/json".to_string())
-        );
+        assert_eq!(response.header("content-type"), Some("application/json"));
     }
 
     #[test]
@@ -323,33 +526,163 @@ mod tests {
 
         // Spawn a mock "interpreter" that echoes back
         tokio::spawn(async move {
-            if let Some(req) = rx.recv().await {
+            if let Some(BridgeMessage::Request(req)) = rx.recv().await {
                 let response = BridgeResponse {
                     status: 200,
-                    headers: HashMap::new(),
+                    headers: Vec::new(),
                     body: format!("Echo: {}", req.request.path),
+                    stream: None,
                 };
                 let _ = req.reply_tx.send(response);
             }
         });
 
         let handle = InterpreterHandle::new(tx);
-        let request = BridgeRequest {
+        let response = handle.call(test_request("/test")).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, "Echo: /test");
+    }
+
+    fn test_request(path: &str) -> BridgeRequest {
+        BridgeRequest {
             method: "GET".to_string(),
-            path: "/test".to_string(),
-            url: "/test".to_string(),
+            path: path.to_string(),
+            url: path.to_string(),
             query: "".to_string(),
             query_params: HashMap::new(),
+            query_all: HashMap::new(),
             params: HashMap::new(),
             headers: HashMap::new(),
             body: "".to_string(),
+            body_bytes: None,
+            multipart: None,
             id: "1".to_string(),
             ip: "127.0.0.1".to_string(),
             protocol: "http".to_string(),
-        };
+        }
+    }
 
-        let response = handle.call(request).await.unwrap();
-        assert_eq!(response.status, 200);
-        assert_eq!(response.body, "Echo: /test");
+    /// Mock pool worker that answers every request with its index. Requests to
+    /// "/hold" are parked until `release` fires.
+    fn spawn_mock_worker(
+        index: usize,
+        mut rx: mpsc::Receiver<BridgeMessage>,
+        held: Option<oneshot::Sender<()>>,
+        release: Option<oneshot::Receiver<()>>,
+    ) {
+        tokio::spawn(async move {
+            let mut held = held;
+            let mut release = release;
+            while let Some(BridgeMessage::Request(req)) = rx.recv().await {
+                if req.request.path == "/hold" {
+                    if let Some(held) = held.take() {
+                        let _ = held.send(());
+                    }
+                    if let Some(release) = release.take() {
+                        let _ = release.await;
+                    }
+                }
+                let _ = req.reply_tx.send(BridgeResponse {
+                    status: 200,
+                    headers: Vec::new(),
+                    body: index.to_string(),
+                    stream: None,
+                });
+            }
+        });
+    }
+
+    #[tokio::test]
+    async fn test_pool_round_robin() {
+        let config = BridgeConfig::default();
+        let mut senders = Vec::new();
+        for index in 0..2 {
+            let (tx, rx) = create_channel(&config);
+            spawn_mock_worker(index, rx, None, None);
+            senders.push(tx);
+        }
+        let handle = InterpreterHandle::pool(senders, Dispatch::RoundRobin);
+        assert_eq!(handle.worker_count(), 2);
+
+        let mut bodies = Vec::new();
+        for _ in 0..4 {
+            bodies.push(handle.call(test_request("/")).await.unwrap().body);
+        }
+        assert_eq!(bodies, vec!["0", "1", "0", "1"]);
+    }
+
+    #[tokio::test]
+    async fn test_pool_least_busy_avoids_blocked_worker() {
+        let config = BridgeConfig::default();
+        let (held_tx, held_rx) = oneshot::channel();
+        let (release_tx, release_rx) = oneshot::channel();
+        let (tx0, rx0) = create_channel(&config);
+        let (tx1, rx1) = create_channel(&config);
+        spawn_mock_worker(0, rx0, Some(held_tx), Some(release_rx));
+        spawn_mock_worker(1, rx1, None, None);
+        let handle = Arc::new(InterpreterHandle::pool(vec![tx0, tx1], Dispatch::LeastBusy));
+
+        // The first request goes to worker 0 and stays in flight
+        let slow = tokio::spawn({
+            let handle = handle.clone();
+            async move { handle.call(test_request("/hold")).await.unwrap().body }
+        });
+        held_rx.await.unwrap();
+
+        for _ in 0..3 {
+            assert_eq!(handle.call(test_request("/")).await.unwrap().body, "1");
+        }
+
+        release_tx.send(()).unwrap();
+        assert_eq!(slow.await.unwrap(), "0");
+    }
+
+    #[tokio::test]
+    async fn test_pool_skips_stopped_worker() {
+        let config = BridgeConfig::default();
+        let (tx0, rx0) = create_channel(&config);
+        let (tx1, rx1) = create_channel(&config);
+        drop(rx0);
+        spawn_mock_worker(1, rx1, None, None);
+        let handle = InterpreterHandle::pool(vec![tx0, tx1], Dispatch::RoundRobin);
+
+        for _ in 0..2 {
+            assert_eq!(handle.call(test_request("/")).await.unwrap().body, "1");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_websocket_events_stay_on_their_worker() {
+        let config = BridgeConfig::default();
+        let mut senders = Vec::new();
+        for index in 0..3 {
+            let (tx, rx) = create_channel(&config);
+            spawn_mock_worker(index, rx, None, None);
+            senders.push(tx);
+        }
+        let handle = InterpreterHandle::pool(senders, Dispatch::RoundRobin);
+        handle.call(test_request("/")).await.unwrap();
+
+        let (worker, response) = handle
+            .call_websocket(None, test_request("/ws"), WsEvent::Open(1))
+            .await
+            .unwrap();
+        assert_eq!(worker, 1);
+        assert_eq!(response.body, "1");
+        for event in [WsEvent::Message(1, "hi".to_string()), WsEvent::Close(1)] {
+            let (pinned, response) = handle
+                .call_websocket(Some(worker), test_request("/ws"), event)
+                .await
+                .unwrap();
+            assert_eq!((pinned, response.body.as_str()), (1, "1"));
+        }
+    }
+
+    #[test]
+    fn test_dispatch_parse() {
+        assert_eq!(Dispatch::parse("round_robin"), Some(Dispatch::RoundRobin));
+        assert_eq!(Dispatch::parse("least_busy"), Some(Dispatch::LeastBusy));
+        assert_eq!(Dispatch::parse("random"), None);
+        assert_eq!(Dispatch::default().name(), "least_busy");
     }
 }
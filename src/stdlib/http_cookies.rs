@@ -0,0 +1,565 @@
+//! Cookies for `std/http/server`
+//!
+//! Request cookies are parsed into `req.cookies`. Responses get cookies with
+//! `set_cookie()`, which adds a `Set-Cookie` header; a response can carry any
+//! number of them because header values may be arrays:
+//!
+//! ```ntnt
+//! import { json, status, set_cookie, set_signed_cookie, get_signed_cookie } from "std/http/server"
+//!
+//! fn login(req) {
+//!     let resp = json(map { "ok": true })
+//!     let resp = set_cookie(resp, "theme", "dark", map { "max_age": 31536000 })
+//!     return set_signed_cookie(resp, "user_id", "42", map { "http_only": true })
+//! }
+//!
+//! fn me(req) {
+//!     match get_signed_cookie(req, "user_id") {
+//!         Some(id) => { return json(map { "id": id }) },
+//!         None => { return status(401, "Not signed in") }
+//!     }
+//! }
+//! ```
+//!
+//! Signed cookies carry an HMAC-SHA256 tag and encrypted cookies are sealed
+//! with AES-256-GCM, both keyed by `NTNT_COOKIE_SECRET`. A cookie that was
+//! tampered with, or sealed under another secret, reads as `None`.
+
+use crate::error::IntentError;
+use crate::interpreter::Value;
+use crate::stdlib::crypto::{hmac_sha256, verify_hmac_sha256};
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::RngCore;
+use std::collections::HashMap;
+
+type Result<T> = std::result::Result<T, IntentError>;
+
+/// Environment variable holding the key for signed and encrypted cookies
+pub const SECRET_ENV: &str = "NTNT_COOKIE_SECRET";
+
+/// Shortest secret accepted for signed and encrypted cookies
+const MIN_SECRET_LEN: usize = 32;
+
+/// AES-GCM nonce length
+const NONCE_LEN: usize = 12;
+
+/// Parse a `Cookie` request header into name/value pairs.
+/// Values are percent-decoded; the first of duplicate names wins.
+pub fn parse_cookie_header(header: &str) -> HashMap<String, String> {
+    let mut cookies = HashMap::new();
+    for pair in header.split(';') {
+        let Some((name, value)) = pair.split_once('=') else {
+            continue;
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            continue;
+        }
+        let value = value.trim().trim_matches('"');
+        let value = urlencoding::decode(value)
+            .map(|v| v.to_string())
+            .unwrap_or_else(|_| value.to_string());
+        cookies.entry(name.to_string()).or_insert(value);
+    }
+    cookies
+}
+
+/// Build the `req.cookies` map from the request headers
+pub fn cookies_value(cookie_header: Option<&str>) -> Value {
+    let cookies = cookie_header.map(parse_cookie_header).unwrap_or_default();
+    Value::Map(
+        cookies
+            .into_iter()
+            .map(|(name, value)| (name, Value::String(value)))
+            .collect(),
+    )
+}
+
+/// Attributes for a `Set-Cookie` header
+#[derive(Debug, Clone, PartialEq)]
+pub struct CookieOptions {
+    pub path: Option<String>,
+    pub domain: Option<String>,
+    pub max_age: Option<i64>,
+    pub http_only: bool,
+    pub secure: bool,
+    pub same_site: Option<&'static str>,
+}
+
+impl Default for CookieOptions {
+    fn default() -> Self {
+        CookieOptions {
+            path: Some("/".to_string()),
+            domain: None,
+            max_age: None,
+            http_only: false,
+            secure: false,
+            same_site: None,
+        }
+    }
+}
+
+impl CookieOptions {
+    /// Read options from the map passed to `set_cookie()` and friends
+    pub fn from_value(value: Option<&Value>, fn_name: &str) -> Result<Self> {
+        let mut options = CookieOptions::default();
+        let map = match value {
+            None => return Ok(options),
+            Some(Value::Map(map)) => map,
+            Some(_) => {
+                return Err(IntentError::TypeError(format!(
+                    "{}() options must be a map",
+                    fn_name
+                )))
+            }
+        };
+
+        let wrong_type = |key: &str| {
+            IntentError::TypeError(format!("{}() option '{}' has the wrong type", fn_name, key))
+        };
+        for (key, value) in map {
+            match (key.as_str(), value) {
+                ("path", Value::String(path)) => options.path = Some(path.clone()),
+                ("domain", Value::String(domain)) => options.domain = Some(domain.clone()),
+                ("max_age", Value::Int(seconds)) => options.max_age = Some(*seconds),
+                ("http_only", Value::Bool(b)) => options.http_only = *b,
+                ("secure", Value::Bool(b)) => options.secure = *b,
+                ("same_site", Value::String(s)) => {
+                    options.same_site = Some(match s.to_ascii_lowercase().as_str() {
+                        "strict" => "Strict",
+                        "lax" => "Lax",
+                        "none" => "None",
+                        _ => {
+                            return Err(IntentError::RuntimeError(format!(
+                                "{}() same_site must be \"Strict\", \"Lax\" or \"None\"",
+                                fn_name
+                            )))
+                        }
+                    })
+                }
+                ("path" | "domain" | "max_age" | "http_only" | "secure" | "same_site", _) => {
+                    return Err(wrong_type(key))
+                }
+                _ => {
+                    return Err(IntentError::RuntimeError(format!(
+                        "Unknown {}() option '{}'",
+                        fn_name, key
+                    )))
+                }
+            }
+        }
+
+        // Browsers drop SameSite=None cookies that are not also Secure
+        if options.same_site == Some("None") && !options.secure {
+            return Err(IntentError::RuntimeError(format!(
+                "{}() same_site \"None\" requires secure: true",
+                fn_name
+            )));
+        }
+        for value in [&options.path, &options.domain].into_iter().flatten() {
+            if value.contains([';', '\r', '\n']) {
+                return Err(IntentError::RuntimeError(format!(
+                    "{}() path and domain cannot contain ';' or line breaks",
+                    fn_name
+                )));
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Cookie names are HTTP tokens
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Format a `Set-Cookie` header value (the value is percent-encoded)
+pub fn format_set_cookie(name: &str, value: &str, options: &CookieOptions) -> String {
+    let mut cookie = format!("{}={}", name, urlencoding::encode(value));
+    if let Some(path) = &options.path {
+        cookie.push_str(&format!("; Path={}", path));
+    }
+    if let Some(domain) = &options.domain {
+        cookie.push_str(&format!("; Domain={}", domain));
+    }
+    if let Some(max_age) = options.max_age {
+        cookie.push_str(&format!("; Max-Age={}", max_age));
+    }
+    if options.http_only {
+        cookie.push_str("; HttpOnly");
+    }
+    if options.secure {
+        cookie.push_str("; Secure");
+    }
+    if let Some(same_site) = options.same_site {
+        cookie.push_str(&format!("; SameSite={}", same_site));
+    }
+    cookie
+}
+
+/// Append a value to a response header, turning it into an array of values
+/// when the header is already set
+pub fn append_header(response: &Value, name: &str, value: String, fn_name: &str) -> Result<Value> {
+    let Value::Map(response) = response else {
+        return Err(IntentError::TypeError(format!(
+            "{}() requires a response map",
+            fn_name
+        )));
+    };
+    let mut response = response.clone();
+    let mut headers = match response.get("headers") {
+        Some(Value::Map(headers)) => headers.clone(),
+        _ => HashMap::new(),
+    };
+    let key = headers
+        .keys()
+        .find(|key| key.eq_ignore_ascii_case(name))
+        .cloned()
+        .unwrap_or_else(|| name.to_string());
+    let merged = match headers.remove(&key) {
+        None => Value::String(value),
+        Some(Value::Array(mut values)) => {
+            values.push(Value::String(value));
+            Value::Array(values)
+        }
+        Some(existing) => Value::Array(vec![existing, Value::String(value)]),
+    };
+    headers.insert(key, merged);
+    response.insert("headers".to_string(), Value::Map(headers));
+    Ok(Value::Map(response))
+}
+
+/// The values of a response header: a string, or an array of strings for
+/// headers sent more than once (such as `set-cookie`)
+pub fn header_values(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(s) => vec![s.clone()],
+        Value::Array(items) => items
+            .iter()
+            .filter_map(|item| match item {
+                Value::String(s) => Some(s.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn string_arg<'a>(value: &'a Value, what: &str, fn_name: &str) -> Result<&'a str> {
+    match value {
+        Value::String(s) => Ok(s),
+        _ => Err(IntentError::TypeError(format!(
+            "{}() requires a string {}",
+            fn_name, what
+        ))),
+    }
+}
+
+fn checked_name<'a>(name: &'a Value, fn_name: &str) -> Result<&'a str> {
+    let name = string_arg(name, "cookie name", fn_name)?;
+    if !is_valid_name(name) {
+        return Err(IntentError::RuntimeError(format!(
+            "{}(): invalid cookie name '{}'",
+            fn_name, name
+        )));
+    }
+    Ok(name)
+}
+
+/// set_cookie(resp, name, value, options?) -> Response
+pub fn set_cookie(
+    response: &Value,
+    name: &Value,
+    value: &Value,
+    options: Option<&Value>,
+) -> Result<Value> {
+    let name = checked_name(name, "set_cookie")?;
+    let value = string_arg(value, "cookie value", "set_cookie")?;
+    let options = CookieOptions::from_value(options, "set_cookie")?;
+    let cookie = format_set_cookie(name, value, &options);
+    append_header(response, "set-cookie", cookie, "set_cookie")
+}
+
+/// clear_cookie(resp, name, options?) -> Response
+/// Path and domain must match the ones the cookie was set with.
+pub fn clear_cookie(response: &Value, name: &Value, options: Option<&Value>) -> Result<Value> {
+    let name = checked_name(name, "clear_cookie")?;
+    let mut options = CookieOptions::from_value(options, "clear_cookie")?;
+    options.max_age = Some(0);
+    let cookie = format_set_cookie(name, "", &options);
+    append_header(response, "set-cookie", cookie, "clear_cookie")
+}
+
+/// The cookie secret from the environment
+fn secret(fn_name: &str) -> Result<Vec<u8>> {
+    match std::env::var(SECRET_ENV) {
+        Ok(secret) if secret.len() >= MIN_SECRET_LEN => Ok(secret.into_bytes()),
+        Ok(_) => Err(IntentError::RuntimeError(format!(
+            "{}() needs {} to be at least {} characters",
+            fn_name, SECRET_ENV, MIN_SECRET_LEN
+        ))),
+        Err(_) => Err(IntentError::RuntimeError(format!(
+            "{}() needs {} to be set (for example to the output of `openssl rand -hex 32`)",
+            fn_name, SECRET_ENV
+        ))),
+    }
+}
+
+/// Sign a cookie value: `value.tag`, where the tag covers name and value
+pub fn sign(secret: &[u8], name: &str, value: &str) -> String {
+    let tag = hmac_sha256(secret, format!("{}={}", name, value).as_bytes());
+    format!("{}.{}", value, URL_SAFE_NO_PAD.encode(tag))
+}
+
+/// Check a signed cookie value and return the original value
+pub fn unsign(secret: &[u8], name: &str, signed: &str) -> Option<String> {
+    let (value, tag) = signed.rsplit_once('.')?;
+    let tag = URL_SAFE_NO_PAD.decode(tag).ok()?;
+    verify_hmac_sha256(secret, format!("{}={}", name, value).as_bytes(), &tag)
+        .then(|| value.to_string())
+}
+
+/// AES-256 key for encrypted cookies, derived from the secret
+fn cipher(secret: &[u8]) -> Aes256Gcm {
+    let key = hmac_sha256(secret, b"ntnt cookie encryption");
+    Aes256Gcm::new_from_slice(&key).expect("HMAC-SHA256 output is a valid AES-256 key")
+}
+
+/// Encrypt a cookie value; the cookie name is authenticated too, so a value
+/// cannot be moved to another cookie
+pub fn encrypt(secret: &[u8], name: &str, value: &str) -> String {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let payload = Payload {
+        msg: value.as_bytes(),
+        aad: name.as_bytes(),
+    };
+    // Encryption only fails for messages far larger than a cookie
+    let ciphertext = cipher(secret)
+        .encrypt(Nonce::from_slice(&nonce), payload)
+        .unwrap_or_default();
+    let mut sealed = nonce.to_vec();
+    sealed.extend(ciphertext);
+    URL_SAFE_NO_PAD.encode(sealed)
+}
+
+/// Decrypt a cookie value sealed by `encrypt()`
+pub fn decrypt(secret: &[u8], name: &str, sealed: &str) -> Option<String> {
+    let sealed = URL_SAFE_NO_PAD.decode(sealed).ok()?;
+    if sealed.len() < NONCE_LEN {
+        return None;
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let payload = Payload {
+        msg: ciphertext,
+        aad: name.as_bytes(),
+    };
+    let plaintext = cipher(secret)
+        .decrypt(Nonce::from_slice(nonce), payload)
+        .ok()?;
+    String::from_utf8(plaintext).ok()
+}
+
+/// Raw value of a request cookie
+fn request_cookie(request: &Value, name: &str, fn_name: &str) -> Result<Option<String>> {
+    match request {
+        Value::Map(map) => Ok(match map.get("cookies") {
+            Some(Value::Map(cookies)) => match cookies.get(name) {
+                Some(Value::String(value)) => Some(value.clone()),
+                _ => None,
+            },
+            _ => None,
+        }),
+        _ => Err(IntentError::TypeError(format!(
+            "{}() requires a request",
+            fn_name
+        ))),
+    }
+}
+
+fn option(value: Option<String>) -> Value {
+    match value {
+        Some(value) => Value::EnumValue {
+            enum_name: "Option".to_string(),
+            variant: "Some".to_string(),
+            values: vec![Value::String(value)],
+        },
+        None => Value::EnumValue {
+            enum_name: "Option".to_string(),
+            variant: "None".to_string(),
+            values: vec![],
+        },
+    }
+}
+
+/// set_signed_cookie(resp, name, value, options?) -> Response
+pub fn set_signed_cookie(
+    response: &Value,
+    name: &Value,
+    value: &Value,
+    options: Option<&Value>,
+) -> Result<Value> {
+    let fn_name = "set_signed_cookie";
+    let name = checked_name(name, fn_name)?;
+    let value = string_arg(value, "cookie value", fn_name)?;
+    let options = CookieOptions::from_value(options, fn_name)?;
+    let signed = sign(&secret(fn_name)?, name, value);
+    append_header(
+        response,
+        "set-cookie",
+        format_set_cookie(name, &signed, &options),
+        fn_name,
+    )
+}
+
+/// get_signed_cookie(req, name) -> Option<String>
+pub fn get_signed_cookie(request: &Value, name: &Value) -> Result<Value> {
+    let fn_name = "get_signed_cookie";
+    let name = string_arg(name, "cookie name", fn_name)?;
+    let secret = secret(fn_name)?;
+    let cookie = request_cookie(request, name, fn_name)?;
+    Ok(option(
+        cookie.and_then(|signed| unsign(&secret, name, &signed)),
+    ))
+}
+
+/// set_encrypted_cookie(resp, name, value, options?) -> Response
+pub fn set_encrypted_cookie(
+    response: &Value,
+    name: &Value,
+    value: &Value,
+    options: Option<&Value>,
+) -> Result<Value> {
+    let fn_name = "set_encrypted_cookie";
+    let name = checked_name(name, fn_name)?;
+    let value = string_arg(value, "cookie value", fn_name)?;
+    let options = CookieOptions::from_value(options, fn_name)?;
+    let sealed = encrypt(&secret(fn_name)?, name, value);
+    append_header(
+        response,
+        "set-cookie",
+        format_set_cookie(name, &sealed, &options),
+        fn_name,
+    )
+}
+
+/// get_encrypted_cookie(req, name) -> Option<String>
+pub fn get_encrypted_cookie(request: &Value, name: &Value) -> Result<Value> {
+    let fn_name = "get_encrypted_cookie";
+    let name = string_arg(name, "cookie name", fn_name)?;
+    let secret = secret(fn_name)?;
+    let cookie = request_cookie(request, name, fn_name)?;
+    Ok(option(
+        cookie.and_then(|sealed| decrypt(&secret, name, &sealed)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+    fn text(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn test_parse_cookie_header() {
+        let cookies = parse_cookie_header("a=1; b=hello%20world;c=\"quoted\"; bad; a=2");
+        assert_eq!(cookies.len(), 3);
+        assert_eq!(cookies["a"], "1");
+        assert_eq!(cookies["b"], "hello world");
+        assert_eq!(cookies["c"], "quoted");
+    }
+
+    #[test]
+    fn test_set_cookie_attributes_and_multiple_headers() {
+        let mut options = HashMap::new();
+        options.insert("http_only".to_string(), Value::Bool(true));
+        options.insert("secure".to_string(), Value::Bool(true));
+        options.insert("same_site".to_string(), text("lax"));
+        options.insert("max_age".to_string(), Value::Int(60));
+        options.insert("domain".to_string(), text("example.com"));
+        let options = Value::Map(options);
+
+        let response = Value::Map(HashMap::new());
+        let response = set_cookie(&response, &text("a"), &text("x y"), Some(&options)).unwrap();
+        let response = set_cookie(&response, &text("b"), &text("2"), None).unwrap();
+        let response = clear_cookie(&response, &text("c"), None).unwrap();
+
+        let Value::Map(map) = &response else {
+            panic!("expected a map")
+        };
+        let Some(Value::Map(headers)) = map.get("headers") else {
+            panic!("expected headers")
+        };
+        assert_eq!(
+            header_values(&headers["set-cookie"]),
+            [
+                "a=x%20y; Path=/; Domain=example.com; Max-Age=60; HttpOnly; Secure; SameSite=Lax",
+                "b=2; Path=/",
+                "c=; Path=/; Max-Age=0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cookie_option_errors() {
+        let response = Value::Map(HashMap::new());
+        let with = |key: &str, value: Value| {
+            let mut options = HashMap::new();
+            options.insert(key.to_string(), value);
+            set_cookie(
+                &response,
+                &text("a"),
+                &text("1"),
+                Some(&Value::Map(options)),
+            )
+        };
+        assert!(with("max_age", text("soon")).is_err());
+        assert!(with("same_site", text("sometimes")).is_err());
+        assert!(with("same_site", text("None")).is_err());
+        assert!(with("path", text("/; evil")).is_err());
+        assert!(with("httponly", Value::Bool(true)).is_err());
+        assert!(set_cookie(&response, &text("a b"), &text("1"), None).is_err());
+    }
+
+    #[test]
+    fn test_signed_values() {
+        let signed = sign(SECRET, "user", "42");
+        assert_eq!(unsign(SECRET, "user", &signed).as_deref(), Some("42"));
+        assert_eq!(unsign(SECRET, "other", &signed), None);
+        assert_eq!(
+            unsign(b"another secret, also 32 bytes...", "user", &signed),
+            None
+        );
+        let tampered = signed.replacen("42", "43", 1);
+        assert_eq!(unsign(SECRET, "user", &tampered), None);
+        assert_eq!(unsign(SECRET, "user", "42"), None);
+    }
+
+    #[test]
+    fn test_encrypted_values() {
+        let sealed = encrypt(SECRET, "cart", "apples=3");
+        assert!(!sealed.contains("apples"));
+        assert_ne!(sealed, encrypt(SECRET, "cart", "apples=3"));
+        assert_eq!(
+            decrypt(SECRET, "cart", &sealed).as_deref(),
+            Some("apples=3")
+        );
+        assert_eq!(decrypt(SECRET, "other", &sealed), None);
+        assert_eq!(
+            decrypt(b"another secret, also 32 bytes...", "cart", &sealed),
+            None
+        );
+        assert_eq!(decrypt(SECRET, "cart", "not sealed"), None);
+        assert_eq!(decrypt(SECRET, "cart", ""), None);
+    }
+}
@@ -0,0 +1,433 @@
+//! multipart/form-data request bodies
+//!
+//! The server parses multipart bodies as it reads them, before the request
+//! reaches a handler, and `parse_multipart(req)` returns the result:
+//!
+//! ```ntnt
+//! import { parse_multipart, save_upload, json, status } from "std/http/server"
+//!
+//! fn upload(req) {
+//!     match parse_multipart(req) {
+//!         Ok(form) => {
+//!             for file in form.files {
+//!                 save_upload(file, "uploads/" + file.filename)
+//!             }
+//!             return json(map { "title": form.fields["title"], "files": len(form.files) })
+//!         },
+//!         Err(e) => { return status(400, e) }
+//!     }
+//! }
+//!
+//! post("/upload", upload, map { "max_body": "50mb" })
+//! ```
+//!
+//! Text fields become strings. File parts up to `MEMORY_LIMIT` are kept in
+//! memory and given to handlers as `bytes` (an array of byte values); larger
+//! ones are streamed to a temp file and given as `path`, so big uploads are
+//! never held in memory. Temp files are removed once the handler has
+//! returned, unless it moved them away with `save_upload()`.
+
+use crate::error::IntentError;
+use crate::interpreter::Value;
+use axum::body::Bytes;
+use futures_util::Stream;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+type Result<T> = std::result::Result<T, IntentError>;
+
+/// File parts larger than this are streamed to a temp file
+pub const MEMORY_LIMIT: usize = 64 * 1024;
+
+/// Why a request body could not be read
+#[derive(Debug)]
+pub enum BodyError {
+    /// The body is larger than the route allows
+    TooLarge,
+    /// The body is malformed or could not be read
+    Invalid(String),
+}
+
+impl std::fmt::Display for BodyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BodyError::TooLarge => write!(f, "Request body too large"),
+            BodyError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<multer::Error> for BodyError {
+    fn from(e: multer::Error) -> Self {
+        match e {
+            multer::Error::StreamSizeExceeded { .. } => BodyError::TooLarge,
+            other => BodyError::Invalid(other.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for BodyError {
+    fn from(e: std::io::Error) -> Self {
+        BodyError::Invalid(format!("Failed to store upload: {}", e))
+    }
+}
+
+/// Contents of an uploaded file
+#[derive(Debug, Clone)]
+pub enum UploadData {
+    Memory(Vec<u8>),
+    TempFile(PathBuf),
+}
+
+/// A file part of a multipart body
+#[derive(Debug, Clone)]
+pub struct UploadedFile {
+    /// Form field name
+    pub field: String,
+    /// File name sent by the client
+    pub filename: String,
+    pub content_type: String,
+    /// Size in bytes
+    pub size: u64,
+    pub data: UploadData,
+}
+
+/// A parsed multipart/form-data body
+#[derive(Debug, Clone, Default)]
+pub struct MultipartForm {
+    /// Text fields in the order they were sent
+    pub fields: Vec<(String, String)>,
+    pub files: Vec<UploadedFile>,
+}
+
+impl MultipartForm {
+    /// Temp files holding large uploads
+    pub fn temp_files(&self) -> Vec<PathBuf> {
+        self.files
+            .iter()
+            .filter_map(|file| match &file.data {
+                UploadData::TempFile(path) => Some(path.clone()),
+                UploadData::Memory(_) => None,
+            })
+            .collect()
+    }
+
+    /// Convert to the map returned by parse_multipart()
+    pub fn to_value(&self) -> Value {
+        let fields: HashMap<String, Value> = self
+            .fields
+            .iter()
+            .map(|(name, value)| (name.clone(), Value::String(value.clone())))
+            .collect();
+
+        let files = self
+            .files
+            .iter()
+            .map(|file| {
+                let mut map = HashMap::new();
+                map.insert("field".to_string(), Value::String(file.field.clone()));
+                map.insert("filename".to_string(), Value::String(file.filename.clone()));
+                map.insert(
+                    "content_type".to_string(),
+                    Value::String(file.content_type.clone()),
+                );
+                map.insert("size".to_string(), Value::Int(file.size as i64));
+                match &file.data {
+                    UploadData::Memory(bytes) => map.insert(
+                        "bytes".to_string(),
+                        Value::Array(bytes.iter().map(|b| Value::Int(*b as i64)).collect()),
+                    ),
+                    UploadData::TempFile(path) => map.insert(
+                        "path".to_string(),
+                        Value::String(path.to_string_lossy().to_string()),
+                    ),
+                };
+                Value::Map(map)
+            })
+            .collect();
+
+        let mut map = HashMap::new();
+        map.insert("fields".to_string(), Value::Map(fields));
+        map.insert("files".to_string(), Value::Array(files));
+        Value::Map(map)
+    }
+}
+
+/// Remove temp files left over after a request (missing files are ignored)
+pub fn remove_temp_files(paths: &[PathBuf]) {
+    for path in paths {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// The boundary of a multipart/form-data content type
+pub fn boundary(content_type: &str) -> Option<String> {
+    if !content_type
+        .trim_start()
+        .to_ascii_lowercase()
+        .starts_with("multipart/form-data")
+    {
+        return None;
+    }
+    multer::parse_boundary(content_type).ok()
+}
+
+fn temp_path() -> PathBuf {
+    std::env::temp_dir().join(format!("ntnt-upload-{}", uuid::Uuid::new_v4()))
+}
+
+/// Read a multipart body of at most `limit` bytes from `stream`
+pub async fn read_form<S, E>(
+    stream: S,
+    boundary: String,
+    limit: usize,
+) -> std::result::Result<MultipartForm, BodyError>
+where
+    S: Stream<Item = std::result::Result<Bytes, E>> + Send + 'static,
+    E: Into<Box<dyn std::error::Error + Send + Sync>> + 'static,
+{
+    let constraints =
+        multer::Constraints::new().size_limit(multer::SizeLimit::new().whole_stream(limit as u64));
+    let mut multipart = multer::Multipart::with_constraints(stream, boundary, constraints);
+    let mut form = MultipartForm::default();
+    match read_parts(&mut multipart, &mut form).await {
+        Ok(()) => Ok(form),
+        Err(e) => {
+            remove_temp_files(&form.temp_files());
+            Err(e)
+        }
+    }
+}
+
+async fn read_parts(
+    multipart: &mut multer::Multipart<'static>,
+    form: &mut MultipartForm,
+) -> std::result::Result<(), BodyError> {
+    while let Some(mut field) = multipart.next_field().await? {
+        let name = field.name().unwrap_or_default().to_string();
+        let Some(filename) = field.file_name().map(str::to_string) else {
+            let bytes = field.bytes().await?;
+            form.fields
+                .push((name, String::from_utf8_lossy(&bytes).to_string()));
+            continue;
+        };
+        let content_type = field
+            .content_type()
+            .map(|mime| mime.to_string())
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+
+        let mut size = 0u64;
+        let mut memory = Vec::new();
+        let mut temp: Option<(PathBuf, tokio::fs::File)> = None;
+        let result = async {
+            while let Some(chunk) = field.chunk().await? {
+                size += chunk.len() as u64;
+                if temp.is_none() && memory.len() + chunk.len() > MEMORY_LIMIT {
+                    // Too big to keep in memory: continue on disk
+                    let path = temp_path();
+                    let file = tokio::fs::File::create(&path).await?;
+                    let (_, file) = temp.insert((path, file));
+                    file.write_all(&std::mem::take(&mut memory)).await?;
+                }
+                match &mut temp {
+                    Some((_, file)) => file.write_all(&chunk).await?,
+                    None => memory.extend_from_slice(&chunk),
+                }
+            }
+            if let Some((_, file)) = &mut temp {
+                file.flush().await?;
+            }
+            Ok::<(), BodyError>(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            if let Some((path, _)) = temp {
+                remove_temp_files(&[path]);
+            }
+            return Err(e);
+        }
+        form.files.push(UploadedFile {
+            field: name,
+            filename,
+            content_type,
+            size,
+            data: match temp {
+                Some((path, _)) => UploadData::TempFile(path),
+                None => UploadData::Memory(memory),
+            },
+        });
+    }
+    Ok(())
+}
+
+/// Parse a multipart body that has already been read into memory
+/// (used by the synchronous server)
+pub fn read_form_blocking(
+    body: Vec<u8>,
+    boundary: String,
+    limit: usize,
+) -> std::result::Result<MultipartForm, BodyError> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let stream = futures_util::stream::once(async move { Ok::<_, Infallible>(Bytes::from(body)) });
+    runtime.block_on(read_form(stream, boundary, limit))
+}
+
+fn ok(value: Value) -> Value {
+    Value::EnumValue {
+        enum_name: "Result".to_string(),
+        variant: "Ok".to_string(),
+        values: vec![value],
+    }
+}
+
+fn err(message: String) -> Value {
+    Value::EnumValue {
+        enum_name: "Result".to_string(),
+        variant: "Err".to_string(),
+        values: vec![Value::String(message)],
+    }
+}
+
+/// parse_multipart(req) -> Result<Map, String>
+pub fn parse_multipart(req: &Value) -> Result<Value> {
+    match req {
+        Value::Map(map) => Ok(match map.get("_multipart") {
+            Some(form) => ok(form.clone()),
+            None => err("Request body is not multipart/form-data".to_string()),
+        }),
+        _ => Err(IntentError::TypeError(
+            "parse_multipart() requires a request".to_string(),
+        )),
+    }
+}
+
+/// save_upload(file, dest) -> Result<String, String>
+/// Moves a temp file into place, or writes an in-memory upload
+pub fn save_upload(file: &Value, dest: &str) -> Result<Value> {
+    let Value::Map(file) = file else {
+        return Err(IntentError::TypeError(
+            "save_upload() requires a file from parse_multipart()".to_string(),
+        ));
+    };
+    let dest_path = Path::new(dest);
+    let result = match (file.get("path"), file.get("bytes")) {
+        (Some(Value::String(path)), _) => std::fs::rename(path, dest_path).or_else(|_| {
+            // rename fails across file systems
+            std::fs::copy(path, dest_path)?;
+            std::fs::remove_file(path)
+        }),
+        (_, Some(Value::Array(bytes))) => {
+            let bytes: Vec<u8> = bytes
+                .iter()
+                .map(|b| match b {
+                    Value::Int(n) => *n as u8,
+                    _ => 0,
+                })
+                .collect();
+            std::fs::write(dest_path, bytes)
+        }
+        _ => {
+            return Err(IntentError::TypeError(
+                "save_upload() requires a file from parse_multipart()".to_string(),
+            ))
+        }
+    };
+    Ok(match result {
+        Ok(()) => ok(Value::String(dest.to_string())),
+        Err(e) => err(format!("Failed to save upload to {}: {}", dest, e)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn multipart_body(parts: &[(&str, Option<&str>, &[u8])]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (name, filename, data) in parts {
+            body.extend_from_slice(b"--XYZ\r\n");
+            let disposition = match filename {
+                Some(f) => format!(
+                    "Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n",
+                    name, f
+                ),
+                None => format!("Content-Disposition: form-data; name=\"{}\"\r\n", name),
+            };
+            body.extend_from_slice(disposition.as_bytes());
+            body.extend_from_slice(b"\r\n");
+            body.extend_from_slice(data);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(b"--XYZ--\r\n");
+        body
+    }
+
+    #[test]
+    fn test_boundary() {
+        assert_eq!(
+            boundary("multipart/form-data; boundary=XYZ"),
+            Some("XYZ".to_string())
+        );
+        assert_eq!(boundary("application/x-www-form-urlencoded"), None);
+    }
+
+    #[test]
+    fn test_small_and_large_files() {
+        let binary: Vec<u8> = vec![0, 159, 146, 150, 255];
+        let large = vec![7u8; MEMORY_LIMIT + 10];
+        let body = multipart_body(&[
+            ("title", None, b"Holiday"),
+            ("small", Some("a.bin"), &binary),
+            ("large", Some("b.bin"), &large),
+        ]);
+        let form = read_form_blocking(body, "XYZ".to_string(), 1024 * 1024).unwrap();
+
+        assert_eq!(
+            form.fields,
+            vec![("title".to_string(), "Holiday".to_string())]
+        );
+        assert_eq!(form.files.len(), 2);
+        assert!(matches!(&form.files[0].data, UploadData::Memory(b) if *b == binary));
+        assert_eq!(form.files[1].size, large.len() as u64);
+        let UploadData::TempFile(path) = &form.files[1].data else {
+            panic!("large file was kept in memory");
+        };
+        assert_eq!(std::fs::read(path).unwrap(), large);
+
+        // Saving moves the temp file; cleanup then has nothing left to do
+        let Value::Array(files) = (match form.to_value() {
+            Value::Map(map) => map["files"].clone(),
+            _ => unreachable!(),
+        }) else {
+            unreachable!()
+        };
+        let dest = std::env::temp_dir().join(format!("ntnt-saved-{}", std::process::id()));
+        let saved = save_upload(&files[1], &dest.to_string_lossy()).unwrap();
+        assert!(matches!(saved, Value::EnumValue { ref variant, .. } if variant == "Ok"));
+        assert!(!path.exists());
+        assert_eq!(std::fs::read(&dest).unwrap(), large);
+        save_upload(&files[0], &dest.to_string_lossy()).unwrap();
+        assert_eq!(std::fs::read(&dest).unwrap(), binary);
+        std::fs::remove_file(&dest).unwrap();
+        remove_temp_files(&form.temp_files());
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let body = multipart_body(&[("file", Some("a.bin"), &[1u8; 2048])]);
+        assert!(matches!(
+            read_form_blocking(body, "XYZ".to_string(), 1024),
+            Err(BodyError::TooLarge)
+        ));
+        let truncated = b"--XYZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nabc".to_vec();
+        assert!(matches!(
+            read_form_blocking(truncated, "XYZ".to_string(), 1024),
+            Err(BodyError::Invalid(_))
+        ));
+    }
+}
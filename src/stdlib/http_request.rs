@@ -0,0 +1,346 @@
+//! Query strings and client addresses of incoming requests
+//!
+//! Both servers (the async one and the tiny_http one used by
+//! `ntnt intent check`) fill in `req.query_params`, `req.query_all`,
+//! `req.ip` and `req.protocol` with these helpers:
+//!
+//! - Query keys and values are percent-decoded, with `+` as a space. A key
+//!   without `=` (`?debug`) is a flag with the value `""`. `query_params`
+//!   keeps the last value of a repeated key, `query_all` keeps all of them.
+//! - `req.ip` is the address of the connection's peer. Forwarding headers
+//!   (`Forwarded`, `X-Forwarded-For`, `X-Real-IP`, `X-Forwarded-Proto`) are
+//!   believed only when the peer is a trusted proxy: loopback addresses by
+//!   default, or the list given as `listen(port, map { "trusted_proxies":
+//!   ["10.0.0.0/8", "192.168.1.4"] })` (`[]` trusts no one). The client is
+//!   then the nearest address in the chain that isn't a trusted proxy.
+
+use crate::error::IntentError;
+use crate::interpreter::Value;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+type Result<T> = std::result::Result<T, IntentError>;
+
+/// Decoded query parameters: the last value of each key, and all of them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    pub params: HashMap<String, String>,
+    pub all: HashMap<String, Vec<String>>,
+}
+
+impl Query {
+    /// Parse a raw query string (without the leading `?`)
+    pub fn parse(query: &str) -> Self {
+        let mut parsed = Query::default();
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let key = decode_component(key);
+            let value = decode_component(value);
+            parsed
+                .all
+                .entry(key.clone())
+                .or_default()
+                .push(value.clone());
+            parsed.params.insert(key, value);
+        }
+        parsed
+    }
+}
+
+/// `req.query_all`: a map from each key to an array of its values
+pub fn query_all_value(all: &HashMap<String, Vec<String>>) -> Value {
+    Value::Map(
+        all.iter()
+            .map(|(key, values)| {
+                let values = values.iter().cloned().map(Value::String).collect();
+                (key.clone(), Value::Array(values))
+            })
+            .collect(),
+    )
+}
+
+/// Percent-decode a query component, treating `+` as a space
+fn decode_component(component: &str) -> String {
+    let component = component.replace('+', " ");
+    String::from_utf8_lossy(&urlencoding::decode_binary(component.as_bytes())).into_owned()
+}
+
+/// Peers whose forwarding headers are believed
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrustedProxies(Vec<(IpAddr, u8)>);
+
+impl Default for TrustedProxies {
+    /// Loopback, for a proxy or tunnel on the same machine
+    fn default() -> Self {
+        TrustedProxies(vec![
+            (IpAddr::V4(Ipv4Addr::new(127, 0, 0, 0)), 8),
+            (IpAddr::V6(Ipv6Addr::LOCALHOST), 128),
+        ])
+    }
+}
+
+impl TrustedProxies {
+    /// Read the `trusted_proxies` listen() option: an array of addresses and
+    /// CIDR ranges
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let invalid = || {
+            IntentError::TypeError(
+                "listen() option 'trusted_proxies' must be an array of IP addresses or CIDR ranges"
+                    .to_string(),
+            )
+        };
+        let Value::Array(entries) = value else {
+            return Err(invalid());
+        };
+        let mut ranges = Vec::new();
+        for entry in entries {
+            let Value::String(entry) = entry else {
+                return Err(invalid());
+            };
+            ranges.push(parse_range(entry).ok_or_else(|| {
+                IntentError::RuntimeError(format!(
+                    "Invalid trusted proxy '{}' (expected an IP address or CIDR range)",
+                    entry
+                ))
+            })?);
+        }
+        Ok(TrustedProxies(ranges))
+    }
+
+    /// Whether `ip` is in one of the trusted ranges
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.0.iter().any(|(network, prefix)| match (network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - *prefix as u32).unwrap_or(0);
+                u32::from(*network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - *prefix as u32).unwrap_or(0);
+                u128::from(*network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        })
+    }
+}
+
+/// `10.0.0.0/8`, `::1` or `192.168.1.4`
+fn parse_range(entry: &str) -> Option<(IpAddr, u8)> {
+    let (address, prefix) = match entry.trim().split_once('/') {
+        Some((address, prefix)) => (address, Some(prefix.parse::<u8>().ok()?)),
+        None => (entry.trim(), None),
+    };
+    let address: IpAddr = address.parse().ok()?;
+    let max = if address.is_ipv4() { 32 } else { 128 };
+    let prefix = prefix.unwrap_or(max);
+    (prefix <= max).then_some((address, prefix))
+}
+
+/// The client's address: the peer itself, or, when the peer is a trusted
+/// proxy, the nearest untrusted address in the forwarding headers
+pub fn client_ip(
+    peer: Option<IpAddr>,
+    headers: &HashMap<String, String>,
+    trusted: &TrustedProxies,
+) -> String {
+    let Some(peer) = peer.map(|ip| ip.to_canonical()) else {
+        return "unknown".to_string();
+    };
+    if !trusted.contains(peer) {
+        return peer.to_string();
+    }
+
+    let chain = forwarded_for(headers);
+    for hop in chain.iter().rev() {
+        match parse_hop(hop) {
+            Some(ip) if trusted.contains(ip) => continue,
+            Some(ip) => return ip.to_string(),
+            // "unknown" or an obfuscated identifier
+            None => return hop.clone(),
+        }
+    }
+    // Every hop is a trusted proxy: the first one is as close as we get
+    match chain.first() {
+        Some(hop) => parse_hop(hop).map_or_else(|| hop.clone(), |ip| ip.to_string()),
+        None => peer.to_string(),
+    }
+}
+
+/// The scheme the client used, as reported by a trusted proxy
+pub fn forwarded_proto(
+    peer: Option<IpAddr>,
+    headers: &HashMap<String, String>,
+    trusted: &TrustedProxies,
+) -> Option<String> {
+    if !peer.is_some_and(|ip| trusted.contains(ip)) {
+        return None;
+    }
+    // The first element was added by the proxy the client connected to
+    let proto = match headers.get("forwarded") {
+        Some(forwarded) => forwarded_param(forwarded, "proto").into_iter().next(),
+        None => headers
+            .get("x-forwarded-proto")
+            .and_then(|proto| proto.split(',').next())
+            .map(|proto| proto.trim().to_string()),
+    }?
+    .to_lowercase();
+    (proto == "http" || proto == "https").then_some(proto)
+}
+
+/// Forwarding chain, client first: `Forwarded` `for=` values, else
+/// `X-Forwarded-For`, else `X-Real-IP`
+fn forwarded_for(headers: &HashMap<String, String>) -> Vec<String> {
+    if let Some(forwarded) = headers.get("forwarded") {
+        return forwarded_param(forwarded, "for");
+    }
+    if let Some(forwarded_for) = headers.get("x-forwarded-for") {
+        return forwarded_for
+            .split(',')
+            .map(|hop| hop.trim().to_string())
+            .filter(|hop| !hop.is_empty())
+            .collect();
+    }
+    headers
+        .get("x-real-ip")
+        .map(|ip| vec![ip.trim().to_string()])
+        .unwrap_or_default()
+}
+
+/// Values of `name` in each element of an RFC 7239 `Forwarded` header
+fn forwarded_param(header: &str, name: &str) -> Vec<String> {
+    header
+        .split(',')
+        .filter_map(|element| {
+            element.split(';').find_map(|pair| {
+                let (key, value) = pair.split_once('=')?;
+                key.trim()
+                    .eq_ignore_ascii_case(name)
+                    .then(|| value.trim().trim_matches('"').to_string())
+            })
+        })
+        .collect()
+}
+
+/// An address from a forwarding header, possibly with a port:
+/// `203.0.113.7`, `203.0.113.7:4711`, `[2001:db8::1]:4711`
+fn parse_hop(hop: &str) -> Option<IpAddr> {
+    let hop = hop.trim();
+    hop.parse::<IpAddr>()
+        .or_else(|_| hop.parse::<SocketAddr>().map(|addr| addr.ip()))
+        .or_else(|_| hop.trim_start_matches('[').trim_end_matches(']').parse())
+        .ok()
+        .map(|ip| ip.to_canonical())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn ip(s: &str) -> Option<IpAddr> {
+        Some(s.parse().unwrap())
+    }
+
+    #[test]
+    fn test_query_decoding_repeated_keys_and_flags() {
+        let query = Query::parse("q=hello+world%21&tag=a&tag=b&debug&name=J%C3%BCrgen&&=x");
+        assert_eq!(query.params["q"], "hello world!");
+        assert_eq!(query.params["tag"], "b");
+        assert_eq!(query.all["tag"], vec!["a", "b"]);
+        assert_eq!(query.params["debug"], "");
+        assert_eq!(query.params["name"], "Jürgen");
+        assert_eq!(query.params[""], "x");
+        assert_eq!(Query::parse(""), Query::default());
+    }
+
+    #[test]
+    fn test_trusted_proxies() {
+        let default = TrustedProxies::default();
+        assert!(default.contains("127.0.0.1".parse().unwrap()));
+        assert!(default.contains("::ffff:127.0.0.1".parse().unwrap()));
+        assert!(default.contains("::1".parse().unwrap()));
+        assert!(!default.contains("10.0.0.1".parse().unwrap()));
+
+        let value = Value::Array(vec![
+            Value::String("10.0.0.0/8".to_string()),
+            Value::String("2001:db8::/32".to_string()),
+            Value::String("192.168.1.4".to_string()),
+        ]);
+        let trusted = TrustedProxies::from_value(&value).unwrap();
+        assert!(trusted.contains("10.200.3.4".parse().unwrap()));
+        assert!(trusted.contains("2001:db8::7".parse().unwrap()));
+        assert!(trusted.contains("192.168.1.4".parse().unwrap()));
+        assert!(!trusted.contains("192.168.1.5".parse().unwrap()));
+        assert!(!trusted.contains("127.0.0.1".parse().unwrap()));
+
+        for bad in ["10.0.0.0/33", "not-an-ip", "10.0.0.0/x"] {
+            let value = Value::Array(vec![Value::String(bad.to_string())]);
+            assert!(TrustedProxies::from_value(&value).is_err(), "{}", bad);
+        }
+        assert!(TrustedProxies::from_value(&Value::String("*".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let trusted = TrustedProxies::default();
+        let xff = headers(&[("x-forwarded-for", "203.0.113.7, 127.0.0.1")]);
+
+        // Headers from an untrusted peer are ignored
+        assert_eq!(
+            client_ip(ip("198.51.100.2"), &xff, &trusted),
+            "198.51.100.2"
+        );
+        // From a trusted proxy, the nearest untrusted hop is the client
+        assert_eq!(client_ip(ip("127.0.0.1"), &xff, &trusted), "203.0.113.7");
+        let spoofed = headers(&[("x-forwarded-for", "1.1.1.1, 203.0.113.7")]);
+        assert_eq!(
+            client_ip(ip("127.0.0.1"), &spoofed, &trusted),
+            "203.0.113.7"
+        );
+        // No forwarding headers: the proxy itself
+        assert_eq!(client_ip(ip("::1"), &headers(&[]), &trusted), "::1");
+        assert_eq!(client_ip(None, &xff, &trusted), "unknown");
+
+        // Forwarded wins over X-Forwarded-For
+        let forwarded = headers(&[
+            (
+                "forwarded",
+                "for=\"[2001:db8::1]:4711\";proto=https, for=127.0.0.1",
+            ),
+            ("x-forwarded-for", "198.51.100.9"),
+        ]);
+        assert_eq!(
+            client_ip(ip("127.0.0.1"), &forwarded, &trusted),
+            "2001:db8::1"
+        );
+        let real_ip = headers(&[("x-real-ip", "203.0.113.8")]);
+        assert_eq!(
+            client_ip(ip("127.0.0.1"), &real_ip, &trusted),
+            "203.0.113.8"
+        );
+    }
+
+    #[test]
+    fn test_forwarded_proto() {
+        let trusted = TrustedProxies::default();
+        let xfp = headers(&[("x-forwarded-proto", "HTTPS")]);
+        assert_eq!(
+            forwarded_proto(ip("127.0.0.1"), &xfp, &trusted).as_deref(),
+            Some("https")
+        );
+        assert_eq!(forwarded_proto(ip("203.0.113.7"), &xfp, &trusted), None);
+        let forwarded = headers(&[("forwarded", "for=203.0.113.7;proto=https")]);
+        assert_eq!(
+            forwarded_proto(ip("127.0.0.1"), &forwarded, &trusted).as_deref(),
+            Some("https")
+        );
+        let bogus = headers(&[("x-forwarded-proto", "gopher")]);
+        assert_eq!(forwarded_proto(ip("127.0.0.1"), &bogus, &trusted), None);
+    }
+}
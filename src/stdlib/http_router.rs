@@ -0,0 +1,452 @@
+//! Route matching for the HTTP servers
+//!
+//! Routes are stored in a tree with one level per path segment, shared by
+//! routes with a common prefix. Patterns can contain:
+//!
+//! - static segments: `/users/new`
+//! - parameters: `/users/{id}`
+//! - typed parameters: `/users/{id:int}` (digits only) or a regex such as
+//!   `/posts/{slug:[a-z0-9-]+}` that must match the whole segment
+//! - a catch-all as the last segment: `/files/{*path}` matches one or more
+//!   remaining segments and captures them joined by `/`
+//!
+//! When several routes match a path, static segments win over parameters,
+//! typed parameters over plain ones, and parameters over catch-alls, decided
+//! segment by segment from the left. The method is matched last, so
+//! `GET /users/new` can fall back to `GET /users/{id}` when `/users/new` only
+//! has a POST route.
+//!
+//! HEAD requests are answered by GET routes, and GET requests by sse()
+//! routes (stored as "SSE"). ws() routes ("WS") only match WebSocket
+//! upgrades. Registering two routes with the same method and the same shape
+//! (ignoring parameter names) is an error.
+
+use crate::error::{IntentError, Result};
+use regex::Regex;
+use std::collections::HashMap;
+
+/// A parsed route pattern segment
+#[derive(Debug, Clone)]
+pub enum RouteSegment {
+    Static(String),
+    Param(String),
+    /// Parameter with a type or regex: `{id:int}`, `{slug:[a-z-]+}`
+    Typed {
+        name: String,
+        spec: String,
+    },
+    /// Catch-all for the rest of the path: `{*path}`
+    Wildcard(String),
+}
+
+/// Parse a route pattern into segments
+/// e.g., "/users/{id}/posts/{post_id}" -> [Static("users"), Param("id"), Static("posts"), Param("post_id")]
+pub fn parse_route_pattern(pattern: &str) -> Vec<RouteSegment> {
+    pattern
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(|segment| {
+            if segment.starts_with('{') && segment.ends_with('}') {
+                let inner = &segment[1..segment.len() - 1];
+                if let Some(name) = inner.strip_prefix('*') {
+                    RouteSegment::Wildcard(name.to_string())
+                } else if let Some((name, spec)) = inner.split_once(':') {
+                    RouteSegment::Typed {
+                        name: name.to_string(),
+                        spec: spec.to_string(),
+                    }
+                } else {
+                    RouteSegment::Param(inner.to_string())
+                }
+            } else {
+                RouteSegment::Static(segment.to_string())
+            }
+        })
+        .collect()
+}
+
+/// The method a route answers for the purpose of matching and conflicts
+fn canonical_method(method: &str) -> &str {
+    if method == "SSE" {
+        "GET"
+    } else {
+        method
+    }
+}
+
+/// A route registered at a node
+#[derive(Debug, Clone)]
+struct Endpoint {
+    method: String,
+    pattern: String,
+    /// Index of the route in the server's route list
+    index: usize,
+    /// Parameter names, in the order their values are captured
+    names: Vec<String>,
+}
+
+/// A parameter child: `None` constraint for `{name}`
+#[derive(Debug, Clone)]
+struct ParamChild {
+    spec: Option<String>,
+    regex: Option<Regex>,
+    node: Node,
+}
+
+impl ParamChild {
+    fn accepts(&self, segment: &str) -> bool {
+        match &self.regex {
+            Some(regex) => regex.is_match(segment),
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    statics: HashMap<String, Node>,
+    /// Typed parameters first (in registration order), then the plain one
+    params: Vec<ParamChild>,
+    /// Routes ending here
+    endpoints: Vec<Endpoint>,
+    /// Routes ending in a catch-all here
+    catch_all: Vec<Endpoint>,
+}
+
+/// Compile a parameter spec: `int` or an anchored regex
+fn compile_spec(spec: &str, pattern: &str) -> Result<Regex> {
+    let source = if spec == "int" {
+        "[0-9]+".to_string()
+    } else if !spec.is_empty() && spec.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return Err(IntentError::RuntimeError(format!(
+            "Unknown parameter type '{}' in route {} (use int or a regex like [a-z-]+)",
+            spec, pattern
+        )));
+    } else {
+        spec.to_string()
+    };
+    Regex::new(&format!("^(?:{})$", source)).map_err(|e| {
+        IntentError::RuntimeError(format!(
+            "Invalid parameter pattern '{}' in route {}: {}",
+            spec, pattern, e
+        ))
+    })
+}
+
+/// Route tree for one server
+#[derive(Debug, Clone, Default)]
+pub struct Router {
+    root: Node,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Router::default()
+    }
+
+    /// Add a route. Fails if the pattern is invalid or another route with
+    /// the same method already has the same shape.
+    pub fn insert(
+        &mut self,
+        method: &str,
+        pattern: &str,
+        segments: &[RouteSegment],
+        index: usize,
+    ) -> Result<()> {
+        let mut node = &mut self.root;
+        let mut names = Vec::new();
+        let mut catch_all = false;
+
+        for (position, segment) in segments.iter().enumerate() {
+            match segment {
+                RouteSegment::Static(text) => {
+                    node = node.statics.entry(text.clone()).or_default();
+                }
+                RouteSegment::Param(name) => {
+                    names.push(name.clone());
+                    node = param_node(node, None, pattern)?;
+                }
+                RouteSegment::Typed { name, spec } => {
+                    names.push(name.clone());
+                    node = param_node(node, Some(spec), pattern)?;
+                }
+                RouteSegment::Wildcard(name) => {
+                    if position + 1 != segments.len() {
+                        return Err(IntentError::RuntimeError(format!(
+                            "Catch-all {{*{}}} must be the last segment of route {}",
+                            name, pattern
+                        )));
+                    }
+                    names.push(name.clone());
+                    catch_all = true;
+                }
+            }
+        }
+
+        let endpoints = if catch_all {
+            &mut node.catch_all
+        } else {
+            &mut node.endpoints
+        };
+        if let Some(existing) = endpoints
+            .iter()
+            .find(|e| canonical_method(&e.method) == canonical_method(method))
+        {
+            return Err(IntentError::RuntimeError(format!(
+                "Route {} {} conflicts with {} {}",
+                method, pattern, existing.method, existing.pattern
+            )));
+        }
+        endpoints.push(Endpoint {
+            method: method.to_string(),
+            pattern: pattern.to_string(),
+            index,
+            names,
+        });
+        Ok(())
+    }
+
+    /// Find the route for a request, returning its index and parameters
+    pub fn lookup(&self, method: &str, path: &str) -> Option<(usize, HashMap<String, String>)> {
+        let candidates = self.candidates(path);
+        let find = |method: &str| {
+            candidates.iter().find(|(endpoint, _)| {
+                endpoint.method == method || (method == "GET" && endpoint.method == "SSE")
+            })
+        };
+        // HEAD falls back to GET when there is no explicit HEAD route
+        let (endpoint, values) = find(method).or_else(|| match method {
+            "HEAD" => find("GET").filter(|(endpoint, _)| endpoint.method == "GET"),
+            _ => None,
+        })?;
+        let params = endpoint.names.iter().cloned().zip(values.clone()).collect();
+        Some((endpoint.index, params))
+    }
+
+    /// Methods accepted on `path` for the Allow header, or an empty list
+    /// when no HTTP route matches it
+    pub fn allowed_methods(&self, path: &str) -> Vec<String> {
+        let mut methods: Vec<String> = self
+            .candidates(path)
+            .iter()
+            .map(|(endpoint, _)| canonical_method(&endpoint.method).to_string())
+            .filter(|method| method != "WS")
+            .collect();
+        if methods.is_empty() {
+            return methods;
+        }
+        if methods.iter().any(|m| m == "GET") {
+            methods.push("HEAD".to_string());
+        }
+        methods.push("OPTIONS".to_string());
+        methods.sort();
+        methods.dedup();
+        methods
+    }
+
+    /// Every route matching `path`, best first, with its captured values
+    fn candidates(&self, path: &str) -> Vec<(&Endpoint, Vec<String>)> {
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        let mut found = Vec::new();
+        collect(&self.root, &segments, &mut Vec::new(), &mut found);
+        found
+    }
+}
+
+/// Child node for a parameter, created if needed
+fn param_node<'a>(
+    node: &'a mut Node,
+    spec: Option<&String>,
+    pattern: &str,
+) -> Result<&'a mut Node> {
+    let position = node
+        .params
+        .iter()
+        .position(|child| child.spec.as_ref() == spec);
+    let position = match position {
+        Some(position) => position,
+        None => {
+            let child = ParamChild {
+                spec: spec.cloned(),
+                regex: spec.map(|s| compile_spec(s, pattern)).transpose()?,
+                node: Node::default(),
+            };
+            // Typed parameters are tried before the plain one
+            let at = if spec.is_some() {
+                node.params
+                    .iter()
+                    .position(|child| child.spec.is_none())
+                    .unwrap_or(node.params.len())
+            } else {
+                node.params.len()
+            };
+            node.params.insert(at, child);
+            at
+        }
+    };
+    Ok(&mut node.params[position].node)
+}
+
+fn collect<'a>(
+    node: &'a Node,
+    segments: &[&str],
+    values: &mut Vec<String>,
+    found: &mut Vec<(&'a Endpoint, Vec<String>)>,
+) {
+    let Some((first, rest)) = segments.split_first() else {
+        for endpoint in &node.endpoints {
+            found.push((endpoint, values.clone()));
+        }
+        return;
+    };
+
+    if let Some(child) = node.statics.get(*first) {
+        collect(child, rest, values, found);
+    }
+    for child in &node.params {
+        if child.accepts(first) {
+            values.push(first.to_string());
+            collect(&child.node, rest, values, found);
+            values.pop();
+        }
+    }
+    for endpoint in &node.catch_all {
+        let mut captured = values.clone();
+        captured.push(segments.join("/"));
+        found.push((endpoint, captured));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router(routes: &[(&str, &str)]) -> Router {
+        let mut router = Router::new();
+        for (index, (method, pattern)) in routes.iter().enumerate() {
+            router
+                .insert(method, pattern, &parse_route_pattern(pattern), index)
+                .unwrap();
+        }
+        router
+    }
+
+    fn find(router: &Router, method: &str, path: &str) -> Option<(usize, Vec<(String, String)>)> {
+        router.lookup(method, path).map(|(index, params)| {
+            let mut params: Vec<_> = params.into_iter().collect();
+            params.sort();
+            (index, params)
+        })
+    }
+
+    fn param(name: &str, value: &str) -> (String, String) {
+        (name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn test_priority_static_param_wildcard() {
+        let r = router(&[
+            ("GET", "/files/{*path}"),
+            ("GET", "/files/{name}"),
+            ("GET", "/files/readme"),
+            ("GET", "/files/{id:int}"),
+        ]);
+        assert_eq!(find(&r, "GET", "/files/readme"), Some((2, vec![])));
+        assert_eq!(
+            find(&r, "GET", "/files/42"),
+            Some((3, vec![param("id", "42")]))
+        );
+        assert_eq!(
+            find(&r, "GET", "/files/notes"),
+            Some((1, vec![param("name", "notes")]))
+        );
+        assert_eq!(
+            find(&r, "GET", "/files/a/b/c.txt"),
+            Some((0, vec![param("path", "a/b/c.txt")]))
+        );
+        // A catch-all needs at least one segment
+        assert_eq!(find(&r, "GET", "/files"), None);
+    }
+
+    #[test]
+    fn test_regex_params_and_backtracking() {
+        let r = router(&[
+            ("GET", "/posts/{slug:[a-z-]+}"),
+            ("GET", "/users/new/edit"),
+            ("GET", "/users/{id}"),
+            ("GET", "/users/{id}/edit"),
+        ]);
+        assert_eq!(
+            find(&r, "GET", "/posts/hello-world"),
+            Some((0, vec![param("slug", "hello-world")]))
+        );
+        assert_eq!(find(&r, "GET", "/posts/Hello"), None);
+        // "/users/new" has no route of its own, so the parameter route answers
+        assert_eq!(
+            find(&r, "GET", "/users/new"),
+            Some((2, vec![param("id", "new")]))
+        );
+        assert_eq!(find(&r, "GET", "/users/new/edit"), Some((1, vec![])));
+        assert_eq!(
+            find(&r, "GET", "/users/7/edit"),
+            Some((3, vec![param("id", "7")]))
+        );
+    }
+
+    #[test]
+    fn test_methods_head_and_allow() {
+        let r = router(&[
+            ("GET", "/items"),
+            ("POST", "/items"),
+            ("SSE", "/events"),
+            ("WS", "/chat"),
+            ("POST", "/items/{id}"),
+            ("GET", "/{*page}"),
+        ]);
+        assert_eq!(find(&r, "HEAD", "/items"), Some((0, vec![])));
+        assert_eq!(find(&r, "HEAD", "/events"), None);
+        assert_eq!(find(&r, "GET", "/events"), Some((2, vec![])));
+        assert_eq!(find(&r, "DELETE", "/items"), None);
+        assert_eq!(
+            r.allowed_methods("/items"),
+            vec!["GET", "HEAD", "OPTIONS", "POST"]
+        );
+        // GET /items/5 falls back to the catch-all
+        assert_eq!(
+            find(&r, "GET", "/items/5"),
+            Some((5, vec![param("page", "items/5")]))
+        );
+        assert_eq!(
+            r.allowed_methods("/items/5"),
+            vec!["GET", "HEAD", "OPTIONS", "POST"]
+        );
+        // WebSocket routes only match upgrades
+        assert_eq!(find(&r, "WS", "/chat"), Some((3, vec![])));
+        assert_eq!(find(&r, "GET", "/chat").unwrap().0, 5);
+        assert!(router(&[("WS", "/chat")])
+            .allowed_methods("/chat")
+            .is_empty());
+        assert!(r.allowed_methods("/").is_empty());
+    }
+
+    #[test]
+    fn test_conflicts_and_invalid_patterns() {
+        let mut r = router(&[("GET", "/users/{id}"), ("SSE", "/events")]);
+        let mut insert = |method: &str, pattern: &str| {
+            r.insert(method, pattern, &parse_route_pattern(pattern), 99)
+        };
+        let err = insert("GET", "/users/{user_id}").unwrap_err().to_string();
+        assert!(
+            err.contains("GET /users/{user_id} conflicts with GET /users/{id}"),
+            "{}",
+            err
+        );
+        assert!(insert("GET", "/events").is_err());
+        assert!(insert("POST", "/users/{id}").is_ok());
+        assert!(insert("GET", "/users/{id:int}").is_ok());
+        assert!(insert("GET", "/users/{n:int}").is_err());
+        assert!(insert("GET", "/files/{*path}/raw").is_err());
+        assert!(insert("GET", "/x/{id:integer}").is_err());
+        assert!(insert("GET", "/x/{id:[a-z}").is_err());
+    }
+}
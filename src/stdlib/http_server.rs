@@ -20,18 +20,20 @@
 //! listen(8080)
 //! ```
 
+use crate::contracts::{ContractType, ContractViolation};
 use crate::error::{IntentError, Result};
 use crate::interpreter::Value;
+use crate::stdlib::http_cookies;
+use crate::stdlib::http_request::{self, TrustedProxies};
+pub use crate::stdlib::http_router::RouteSegment;
+use crate::stdlib::http_router::{parse_route_pattern, Router};
+use crate::stdlib::http_session;
 use crate::stdlib::json::json_to_intent_value;
 use std::collections::HashMap;
 use std::time::SystemTime;
 
-/// Represents a route segment - either static text or a parameter
-#[derive(Debug, Clone)]
-pub enum RouteSegment {
-    Static(String),
-    Param(String),
-}
+/// Request body limit for routes that do not set `max_body`
+pub const DEFAULT_MAX_BODY: usize = 10 * 1024 * 1024;
 
 /// A compiled route with its pattern parsed into segments
 #[derive(Debug, Clone)]
@@ -39,6 +41,73 @@ pub struct Route {
     pub method: String,
     pub pattern: String,
     pub segments: Vec<RouteSegment>,
+    /// Body size limit set for this route (`max_body` route option)
+    pub max_body: Option<usize>,
+    /// Middleware from group() calls and `_middleware.tnt` files, run after
+    /// the global middleware (outermost first)
+    pub middleware: Vec<Value>,
+}
+
+/// Options passed as the third argument of a route function,
+/// e.g. `post("/upload", upload, map { "max_body": "50mb" })`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RouteOptions {
+    pub max_body: Option<usize>,
+}
+
+impl RouteOptions {
+    /// Read options from the map passed to a route function
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let map = match value {
+            Value::Map(map) => map,
+            _ => {
+                return Err(IntentError::TypeError(
+                    "Route options must be a map".to_string(),
+                ))
+            }
+        };
+
+        let mut options = RouteOptions::default();
+        for (key, value) in map {
+            match key.as_str() {
+                "max_body" => options.max_body = Some(parse_size(value, "Route option")?),
+                _ => {
+                    return Err(IntentError::RuntimeError(format!(
+                        "Unknown route option '{}'",
+                        key
+                    )))
+                }
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Parse a size given as a byte count or a string like "512kb" or "10mb"
+pub fn parse_size(value: &Value, context: &str) -> Result<usize> {
+    let invalid = || {
+        IntentError::TypeError(format!(
+            "{} 'max_body' must be a byte count or a size like \"10mb\"",
+            context
+        ))
+    };
+    match value {
+        Value::Int(n) if *n >= 0 => Ok(*n as usize),
+        Value::String(s) => {
+            let s = s.trim().to_ascii_lowercase();
+            let digits_end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+            let number: usize = s[..digits_end].parse().map_err(|_| invalid())?;
+            let unit = match s[digits_end..].trim() {
+                "" | "b" => 1,
+                "kb" => 1024,
+                "mb" => 1024 * 1024,
+                "gb" => 1024 * 1024 * 1024,
+                _ => return Err(invalid()),
+            };
+            number.checked_mul(unit).ok_or_else(invalid)
+        }
+        _ => Err(invalid()),
+    }
 }
 
 /// Information about a route's source file for hot-reload
@@ -53,25 +122,30 @@ pub struct RouteSource {
 #[derive(Debug, Clone)]
 pub struct ServerState {
     pub routes: Vec<(Route, Value, RouteSource)>, // Routes with handlers and source info
+    pub router: Router,                           // Route tree indexing `routes`
     pub static_dirs: Vec<(String, String)>,       // (url_prefix, filesystem_path)
     pub middleware: Vec<Value>,                   // Middleware functions to run before handlers
     pub hot_reload: bool,                         // Whether hot-reload is enabled
     pub shutdown_handlers: Vec<Value>,            // Functions to call on server shutdown
+    pub trusted_proxies: TrustedProxies,          // Peers whose forwarding headers are believed
 }
 
 impl ServerState {
     pub fn new() -> Self {
         ServerState {
             routes: Vec::new(),
+            router: Router::new(),
             static_dirs: Vec::new(),
             middleware: Vec::new(),
             hot_reload: true, // Enable hot-reload by default in dev
             shutdown_handlers: Vec::new(),
+            trusted_proxies: TrustedProxies::default(),
         }
     }
 
     pub fn clear(&mut self) {
         self.routes.clear();
+        self.router = Router::new();
         self.static_dirs.clear();
         self.middleware.clear();
         self.shutdown_handlers.clear();
@@ -86,11 +160,29 @@ impl ServerState {
     }
 
     /// Add a route without source file info (inline routes)
-    pub fn add_route(&mut self, method: &str, pattern: &str, handler: Value) {
-        self.add_route_with_source(method, pattern, handler, None, HashMap::new());
+    pub fn add_route(&mut self, method: &str, pattern: &str, handler: Value) -> Result<()> {
+        self.add_route_with_source(method, pattern, handler, None, HashMap::new(), Vec::new())
+    }
+
+    /// Add an inline route with options and its own middleware
+    pub fn add_route_with_options(
+        &mut self,
+        method: &str,
+        pattern: &str,
+        handler: Value,
+        options: RouteOptions,
+        middleware: Vec<Value>,
+    ) -> Result<()> {
+        self.add_route_with_source(method, pattern, handler, None, HashMap::new(), middleware)?;
+        if let Some((route, _, _)) = self.routes.last_mut() {
+            route.max_body = options.max_body;
+        }
+        Ok(())
     }
 
     /// Add a route with source file info for hot-reload
+    ///
+    /// Fails if the pattern is invalid or conflicts with a registered route.
     pub fn add_route_with_source(
         &mut self,
         method: &str,
@@ -98,12 +190,17 @@ impl ServerState {
         handler: Value,
         file_path: Option<String>,
         imported_files: HashMap<String, SystemTime>,
-    ) {
+        middleware: Vec<Value>,
+    ) -> Result<()> {
         let route = Route {
             method: method.to_string(),
             pattern: pattern.to_string(),
             segments: parse_route_pattern(pattern),
+            max_body: None,
+            middleware,
         };
+        self.router
+            .insert(method, pattern, &route.segments, self.routes.len())?;
 
         // Get file mtime if path provided
         let mtime = file_path
@@ -116,6 +213,7 @@ impl ServerState {
             imported_files,
         };
         self.routes.push((route, handler, source));
+        Ok(())
     }
 
     pub fn route_count(&self) -> usize {
@@ -123,19 +221,45 @@ impl ServerState {
     }
 
     /// Find a route and return its index for potential hot-reload
+    /// (sse() routes, stored as "SSE", answer GET requests, and GET routes
+    /// answer HEAD)
     pub fn find_route(
         &self,
         method: &str,
         path: &str,
     ) -> Option<(Value, HashMap<String, String>, usize)> {
-        for (index, (route, handler, _source)) in self.routes.iter().enumerate() {
-            if route.method == method {
-                if let Some(params) = match_route(path, route) {
-                    return Some((handler.clone(), params, index));
-                }
-            }
+        let (index, params) = self.router.lookup(method, path)?;
+        let (_, handler, _) = self.routes.get(index)?;
+        Some((handler.clone(), params, index))
+    }
+
+    /// Methods the routes matching `path` accept (empty if none match)
+    pub fn allowed_methods(&self, path: &str) -> Vec<String> {
+        self.router.allowed_methods(path)
+    }
+
+    /// Largest request body the route at `route_index` accepts
+    pub fn body_limit(&self, route_index: usize) -> usize {
+        self.routes
+            .get(route_index)
+            .and_then(|(route, _, _)| route.max_body)
+            .unwrap_or(DEFAULT_MAX_BODY)
+    }
+
+    /// Global middleware followed by the route's own middleware
+    pub fn middleware_for(&self, route_index: usize) -> Vec<Value> {
+        let mut chain = self.middleware.clone();
+        if let Some((route, _, _)) = self.routes.get(route_index) {
+            chain.extend(route.middleware.iter().cloned());
         }
-        None
+        chain
+    }
+
+    /// Whether the route at `route_index` was registered with sse()
+    pub fn is_sse_route(&self, route_index: usize) -> bool {
+        self.routes
+            .get(route_index)
+            .is_some_and(|(route, _, _)| route.method == "SSE")
     }
 
     /// Check if a route needs reloading based on file mtime or imported files
@@ -256,53 +380,12 @@ impl Default for ServerState {
     }
 }
 
-/// Parse a route pattern into segments
-/// e.g., "/users/{id}/posts/{post_id}" -> [Static("users"), Param("id"), Static("posts"), Param("post_id")]
-fn parse_route_pattern(pattern: &str) -> Vec<RouteSegment> {
-    pattern
-        .split('/')
-        .filter(|s| !s.is_empty())
-        .map(|segment| {
-            if segment.starts_with('{') && segment.ends_with('}') {
-                RouteSegment::Param(segment[1..segment.len() - 1].to_string())
-            } else {
-                RouteSegment::Static(segment.to_string())
-            }
-        })
-        .collect()
-}
-
-/// Match a URL path against a route, returning extracted parameters if matched
-fn match_route(path: &str, route: &Route) -> Option<HashMap<String, String>> {
-    let path_segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
-
-    if path_segments.len() != route.segments.len() {
-        return None;
-    }
-
-    let mut params = HashMap::new();
-
-    for (path_seg, route_seg) in path_segments.iter().zip(route.segments.iter()) {
-        match route_seg {
-            RouteSegment::Static(expected) => {
-                if path_seg != expected {
-                    return None;
-                }
-            }
-            RouteSegment::Param(name) => {
-                params.insert(name.clone(), path_seg.to_string());
-            }
-        }
-    }
-
-    Some(params)
-}
-
 /// Convert a tiny_http Request to an Intent Value
 pub fn request_to_value(
     request: &tiny_http::Request,
     params: HashMap<String, String>,
     body: String,
+    trusted_proxies: &TrustedProxies,
 ) -> Value {
     let mut req_map: HashMap<String, Value> = HashMap::new();
 
@@ -326,16 +409,18 @@ pub fn request_to_value(
     };
     req_map.insert("query".to_string(), Value::String(query.clone()));
 
-    // Parse query params into a map
-    let mut query_params: HashMap<String, Value> = HashMap::new();
-    if !query.is_empty() {
-        for pair in query.split('&') {
-            if let Some((key, value)) = pair.split_once('=') {
-                query_params.insert(key.to_string(), Value::String(value.to_string()));
-            }
-        }
-    }
+    // Decoded query params (last value wins) and every value of each key
+    let query = http_request::Query::parse(&query);
+    let query_params: HashMap<String, Value> = query
+        .params
+        .into_iter()
+        .map(|(k, v)| (k, Value::String(v)))
+        .collect();
     req_map.insert("query_params".to_string(), Value::Map(query_params));
+    req_map.insert(
+        "query_all".to_string(),
+        http_request::query_all_value(&query.all),
+    );
 
     // Route params (from path like /users/{id})
     let param_map: HashMap<String, Value> = params
@@ -344,63 +429,52 @@ pub fn request_to_value(
         .collect();
     req_map.insert("params".to_string(), Value::Map(param_map));
 
-    // Headers
-    let mut headers: HashMap<String, Value> = HashMap::new();
-    let mut client_ip: Option<String> = None;
-    let mut request_id: Option<String> = None;
-
+    // Headers (repeated ones are joined, cookies with "; ")
+    let mut headers: HashMap<String, String> = HashMap::new();
     for header in request.headers() {
         let field_lower = header.field.to_string().to_lowercase();
         let value = header.value.to_string();
+        let separator = if field_lower == "cookie" { "; " } else { ", " };
+        headers
+            .entry(field_lower)
+            .and_modify(|existing| {
+                existing.push_str(separator);
+                existing.push_str(&value);
+            })
+            .or_insert(value);
+    }
+
+    // Client IP and protocol (forwarding headers only count from trusted proxies)
+    let peer = request.remote_addr().map(|addr| addr.ip());
+    let ip = http_request::client_ip(peer, &headers, trusted_proxies);
+    let protocol = http_request::forwarded_proto(peer, &headers, trusted_proxies)
+        .unwrap_or_else(|| "http".to_string());
+    req_map.insert("ip".to_string(), Value::String(ip));
+    req_map.insert("protocol".to_string(), Value::String(protocol));
 
-        // Extract proxy headers
-        if field_lower == "x-forwarded-for" {
-            // X-Forwarded-For can be comma-separated, take the first (original client)
-            client_ip = Some(value.split(',').next().unwrap_or(&value).trim().to_string());
-        } else if field_lower == "x-real-ip" && client_ip.is_none() {
-            client_ip = Some(value.clone());
-        } else if field_lower == "x-request-id" {
-            request_id = Some(value.clone());
-        }
+    // Request ID (from header or generate one)
+    let id = headers
+        .get("x-request-id")
+        .cloned()
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    req_map.insert("id".to_string(), Value::String(id));
 
-        headers.insert(field_lower, Value::String(value));
-    }
+    req_map.insert(
+        "cookies".to_string(),
+        http_cookies::cookies_value(headers.get("cookie").map(String::as_str)),
+    );
+    let headers: HashMap<String, Value> = headers
+        .into_iter()
+        .map(|(k, v)| (k, Value::String(v)))
+        .collect();
     req_map.insert("headers".to_string(), Value::Map(headers));
 
     // Body
     req_map.insert("body".to_string(), Value::String(body));
 
-    // Client IP (from proxy headers or remote address)
-    let ip = client_ip.unwrap_or_else(|| {
-        request
-            .remote_addr()
-            .map(|addr| addr.ip().to_string())
-            .unwrap_or_else(|| "unknown".to_string())
-    });
-    req_map.insert("ip".to_string(), Value::String(ip));
-
-    // Request ID (from header or generate one)
-    let id = request_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
-    req_map.insert("id".to_string(), Value::String(id));
-
-    // Protocol (assume HTTP unless X-Forwarded-Proto says HTTPS)
-    let protocol =
-        headers_get_string(&req_map, "x-forwarded-proto").unwrap_or_else(|| "http".to_string());
-    req_map.insert("protocol".to_string(), Value::String(protocol));
-
     Value::Map(req_map)
 }
 
-/// Helper to get a string from the headers map
-fn headers_get_string(req_map: &HashMap<String, Value>, key: &str) -> Option<String> {
-    if let Some(Value::Map(headers)) = req_map.get("headers") {
-        if let Some(Value::String(value)) = headers.get(key) {
-            return Some(value.clone());
-        }
-    }
-    None
-}
-
 /// Convert Intent Value to JSON for response serialization
 fn intent_value_to_json(value: &Value) -> serde_json::Value {
     match value {
@@ -734,6 +808,88 @@ pub fn init() -> HashMap<String, Value> {
         },
     );
 
+    // stream(producer, content_type?) -> Response
+    // Streamed response: after the headers are sent, producer(out) is called
+    // and everything it write()s goes to the client as it is produced
+    module.insert(
+        "stream".to_string(),
+        Value::NativeFunction {
+            name: "stream".to_string(),
+            arity: 0,
+            func: |args| {
+                if args.is_empty() || args.len() > 2 {
+                    return Err(IntentError::ArityMismatch {
+                        name: "stream".to_string(),
+                        expected: 1,
+                        got: args.len(),
+                    });
+                }
+                let producer = match &args[0] {
+                    f @ (Value::Function { .. } | Value::NativeFunction { .. }) => f.clone(),
+                    _ => {
+                        return Err(IntentError::TypeError(
+                            "stream() requires a producer function".to_string(),
+                        ))
+                    }
+                };
+                let content_type = match args.get(1) {
+                    None => "text/plain; charset=utf-8".to_string(),
+                    Some(Value::String(ct)) => ct.clone(),
+                    Some(_) => {
+                        return Err(IntentError::TypeError(
+                            "stream() content type must be a string".to_string(),
+                        ))
+                    }
+                };
+
+                let mut headers = HashMap::new();
+                headers.insert("content-type".to_string(), Value::String(content_type));
+                headers.insert(
+                    "cache-control".to_string(),
+                    Value::String("no-cache".to_string()),
+                );
+                let mut response = HashMap::new();
+                response.insert("status".to_string(), Value::Int(200));
+                response.insert("headers".to_string(), Value::Map(headers));
+                response.insert("body".to_string(), Value::String(String::new()));
+                response.insert("_stream".to_string(), producer);
+                Ok(Value::Map(response))
+            },
+        },
+    );
+
+    // write(out, chunk) -> Bool - Send a chunk on a response stream
+    // Returns false once the client has disconnected
+    module.insert(
+        "write".to_string(),
+        Value::NativeFunction {
+            name: "write".to_string(),
+            arity: 2,
+            func: |args| crate::stdlib::http_stream::write(&args[0], &args[1]),
+        },
+    );
+
+    // send_event(out, data, options?) -> Bool - Send a Server-Sent Event
+    // Options: "event", "id", "retry". Returns false once the client has disconnected
+    module.insert(
+        "send_event".to_string(),
+        Value::NativeFunction {
+            name: "send_event".to_string(),
+            arity: 0,
+            func: |args| match args {
+                [out, data] => crate::stdlib::http_stream::send_event(out, data, None),
+                [out, data, options] => {
+                    crate::stdlib::http_stream::send_event(out, data, Some(options))
+                }
+                _ => Err(IntentError::ArityMismatch {
+                    name: "send_event".to_string(),
+                    expected: 2,
+                    got: args.len(),
+                }),
+            },
+        },
+    );
+
     // parse_json(req) -> Result<Value, Error> - Parse request body as JSON
     module.insert(
         "parse_json".to_string(),
@@ -777,13 +933,232 @@ pub fn init() -> HashMap<String, Value> {
         },
     );
 
+    // parse_multipart(req) -> Result<Map, String> - Read a multipart/form-data body
+    // Returns map { "fields": Map, "files": [file] }; each file has field,
+    // filename, content_type, size, and bytes (small uploads) or path (large ones)
+    module.insert(
+        "parse_multipart".to_string(),
+        Value::NativeFunction {
+            name: "parse_multipart".to_string(),
+            arity: 1,
+            func: |args| crate::stdlib::http_multipart::parse_multipart(&args[0]),
+        },
+    );
+
+    // save_upload(file, dest) -> Result<String, String> - Write an uploaded file to disk
+    module.insert(
+        "save_upload".to_string(),
+        Value::NativeFunction {
+            name: "save_upload".to_string(),
+            arity: 2,
+            func: |args| match &args[1] {
+                Value::String(dest) => crate::stdlib::http_multipart::save_upload(&args[0], dest),
+                _ => Err(IntentError::TypeError(
+                    "save_upload() requires a destination path".to_string(),
+                )),
+            },
+        },
+    );
+
+    // set_cookie(resp, name, value, options?) -> Response - Add a Set-Cookie header
+    // Options: path (default "/"), domain, max_age, http_only, secure, same_site
+    module.insert(
+        "set_cookie".to_string(),
+        Value::NativeFunction {
+            name: "set_cookie".to_string(),
+            arity: 0,
+            func: |args| match args {
+                [resp, name, value] => http_cookies::set_cookie(resp, name, value, None),
+                [resp, name, value, options] => {
+                    http_cookies::set_cookie(resp, name, value, Some(options))
+                }
+                _ => Err(IntentError::ArityMismatch {
+                    name: "set_cookie".to_string(),
+                    expected: 3,
+                    got: args.len(),
+                }),
+            },
+        },
+    );
+
+    // clear_cookie(resp, name, options?) -> Response - Expire a cookie
+    module.insert(
+        "clear_cookie".to_string(),
+        Value::NativeFunction {
+            name: "clear_cookie".to_string(),
+            arity: 0,
+            func: |args| match args {
+                [resp, name] => http_cookies::clear_cookie(resp, name, None),
+                [resp, name, options] => http_cookies::clear_cookie(resp, name, Some(options)),
+                _ => Err(IntentError::ArityMismatch {
+                    name: "clear_cookie".to_string(),
+                    expected: 2,
+                    got: args.len(),
+                }),
+            },
+        },
+    );
+
+    // set_signed_cookie(resp, name, value, options?) -> Response
+    // The value is readable by the client but signed with NTNT_COOKIE_SECRET
+    module.insert(
+        "set_signed_cookie".to_string(),
+        Value::NativeFunction {
+            name: "set_signed_cookie".to_string(),
+            arity: 0,
+            func: |args| match args {
+                [resp, name, value] => http_cookies::set_signed_cookie(resp, name, value, None),
+                [resp, name, value, options] => {
+                    http_cookies::set_signed_cookie(resp, name, value, Some(options))
+                }
+                _ => Err(IntentError::ArityMismatch {
+                    name: "set_signed_cookie".to_string(),
+                    expected: 3,
+                    got: args.len(),
+                }),
+            },
+        },
+    );
+
+    // get_signed_cookie(req, name) -> Option<String> - None if missing or tampered with
+    module.insert(
+        "get_signed_cookie".to_string(),
+        Value::NativeFunction {
+            name: "get_signed_cookie".to_string(),
+            arity: 2,
+            func: |args| http_cookies::get_signed_cookie(&args[0], &args[1]),
+        },
+    );
+
+    // set_encrypted_cookie(resp, name, value, options?) -> Response
+    // The value is encrypted with a key derived from NTNT_COOKIE_SECRET
+    module.insert(
+        "set_encrypted_cookie".to_string(),
+        Value::NativeFunction {
+            name: "set_encrypted_cookie".to_string(),
+            arity: 0,
+            func: |args| match args {
+                [resp, name, value] => http_cookies::set_encrypted_cookie(resp, name, value, None),
+                [resp, name, value, options] => {
+                    http_cookies::set_encrypted_cookie(resp, name, value, Some(options))
+                }
+                _ => Err(IntentError::ArityMismatch {
+                    name: "set_encrypted_cookie".to_string(),
+                    expected: 3,
+                    got: args.len(),
+                }),
+            },
+        },
+    );
+
+    // get_encrypted_cookie(req, name) -> Option<String> - None if missing or tampered with
+    module.insert(
+        "get_encrypted_cookie".to_string(),
+        Value::NativeFunction {
+            name: "get_encrypted_cookie".to_string(),
+            arity: 2,
+            func: |args| http_cookies::get_encrypted_cookie(&args[0], &args[1]),
+        },
+    );
+
+    // sessions(options?) -> Middleware - Session middleware for use_middleware()
+    // Options: store ("memory" or "sqlite"), path, ttl, cookie, secure, same_site, domain
+    module.insert(
+        "sessions".to_string(),
+        Value::NativeFunction {
+            name: "sessions".to_string(),
+            arity: 0,
+            func: |args| match args {
+                [] => http_session::sessions(None),
+                [options] => http_session::sessions(Some(options)),
+                _ => Err(IntentError::ArityMismatch {
+                    name: "sessions".to_string(),
+                    expected: 1,
+                    got: args.len(),
+                }),
+            },
+        },
+    );
+
+    // session_get(req, key) -> Option<Value> - Read a value from the live session
+    module.insert(
+        "session_get".to_string(),
+        Value::NativeFunction {
+            name: "session_get".to_string(),
+            arity: 2,
+            func: |args| http_session::session_get(&args[0], session_key(&args[1], "session_get")?),
+        },
+    );
+
+    // session_set(req, key, value) -> Unit - Store a value; saved after the handler returns
+    module.insert(
+        "session_set".to_string(),
+        Value::NativeFunction {
+            name: "session_set".to_string(),
+            arity: 3,
+            func: |args| {
+                http_session::session_set(&args[0], session_key(&args[1], "session_set")?, &args[2])
+            },
+        },
+    );
+
+    // session_delete(req, key) -> Bool - Remove a value from the session
+    module.insert(
+        "session_delete".to_string(),
+        Value::NativeFunction {
+            name: "session_delete".to_string(),
+            arity: 2,
+            func: |args| {
+                http_session::session_delete(&args[0], session_key(&args[1], "session_delete")?)
+            },
+        },
+    );
+
+    // session_all(req) -> Map - Every value in the live session
+    module.insert(
+        "session_all".to_string(),
+        Value::NativeFunction {
+            name: "session_all".to_string(),
+            arity: 1,
+            func: |args| http_session::session_all(&args[0]),
+        },
+    );
+
+    // rotate_session(req) -> Unit - Move the session to a new ID (call on sign-in)
+    module.insert(
+        "rotate_session".to_string(),
+        Value::NativeFunction {
+            name: "rotate_session".to_string(),
+            arity: 1,
+            func: |args| http_session::rotate_session(&args[0]),
+        },
+    );
+
+    // destroy_session(req) -> Unit - Delete the session and expire its cookie
+    module.insert(
+        "destroy_session".to_string(),
+        Value::NativeFunction {
+            name: "destroy_session".to_string(),
+            arity: 1,
+            func: |args| http_session::destroy_session(&args[0]),
+        },
+    );
+
     // parse_form(req) -> Map - Parse request body as URL-encoded form data
+    // (for multipart/form-data bodies, returns the text fields)
     module.insert(
         "parse_form".to_string(),
         Value::NativeFunction {
             name: "parse_form".to_string(),
             arity: 1,
             func: |args| {
+                if let Value::Map(map) = &args[0] {
+                    if let Some(Value::Map(form)) = map.get("_multipart") {
+                        if let Some(fields) = form.get("fields") {
+                            return Ok(fields.clone());
+                        }
+                    }
+                }
                 let body = match &args[0] {
                     Value::Map(map) => match map.get("body") {
                         Some(Value::String(b)) => b.clone(),
@@ -834,6 +1209,17 @@ pub fn init() -> HashMap<String, Value> {
     module
 }
 
+/// Session keys are strings
+fn session_key<'a>(key: &'a Value, fn_name: &str) -> Result<&'a str> {
+    match key {
+        Value::String(key) => Ok(key),
+        _ => Err(IntentError::TypeError(format!(
+            "{}() requires a string key",
+            fn_name
+        ))),
+    }
+}
+
 /// Start the HTTP server - this is called from the interpreter
 pub fn start_server(port: u16) -> Result<tiny_http::Server> {
     let addr = format!("0.0.0.0:{}", port);
@@ -853,23 +1239,85 @@ pub fn start_server_with_timeout(
 }
 
 /// Read request body and create request Value
+///
+/// Bodies larger than `max_body` are answered with 413 and reported as an
+/// error. Also returns the temp files holding large uploads, which the caller
+/// removes once the handler has run.
 pub fn process_request(
     mut request: tiny_http::Request,
     params: HashMap<String, String>,
-) -> Result<(Value, tiny_http::Request)> {
-    // Read the request body
-    let mut body = String::new();
-    if let Err(e) = request.as_reader().read_to_string(&mut body) {
+    max_body: usize,
+    trusted_proxies: &TrustedProxies,
+) -> Result<(Value, tiny_http::Request, Vec<std::path::PathBuf>)> {
+    use crate::stdlib::http_multipart::{self, BodyError};
+    use std::io::Read;
+
+    let too_large = |request: tiny_http::Request| {
+        let _ = send_response(request, &create_error_response(413, "Payload Too Large"));
+        IntentError::RuntimeError(format!("Request body exceeds {} bytes", max_body))
+    };
+
+    if request.body_length().is_some_and(|len| len > max_body) {
+        return Err(too_large(request));
+    }
+
+    // Read the request body (one byte past the limit to detect oversized bodies)
+    let mut bytes = Vec::new();
+    if let Err(e) = Read::take(request.as_reader(), max_body as u64 + 1).read_to_end(&mut bytes) {
         return Err(IntentError::RuntimeError(format!(
             "Failed to read request body: {}",
             e
         )));
     }
+    if bytes.len() > max_body {
+        return Err(too_large(request));
+    }
+
+    let boundary = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Content-Type"))
+        .and_then(|h| http_multipart::boundary(h.value.as_str()));
+
+    let (body, body_bytes, form) = match boundary {
+        Some(boundary) => match http_multipart::read_form_blocking(bytes, boundary, max_body) {
+            Ok(form) => (String::new(), None, Some(form)),
+            Err(BodyError::TooLarge) => return Err(too_large(request)),
+            Err(e) => {
+                let _ = send_response(request, &create_error_response(400, &e.to_string()));
+                return Err(IntentError::RuntimeError(e.to_string()));
+            }
+        },
+        None => match String::from_utf8(bytes) {
+            Ok(body) => (body, None, None),
+            Err(e) => {
+                let bytes = e.into_bytes();
+                (
+                    String::from_utf8_lossy(&bytes).to_string(),
+                    Some(bytes),
+                    None,
+                )
+            }
+        },
+    };
 
     // Create request value
-    let req_value = request_to_value(&request, params, body);
+    let mut req_value = request_to_value(&request, params, body, trusted_proxies);
+    let temp_files = form.as_ref().map(|f| f.temp_files()).unwrap_or_default();
+    if let Value::Map(map) = &mut req_value {
+        if let Some(bytes) = body_bytes {
+            map.insert(
+                "body_bytes".to_string(),
+                Value::Array(bytes.iter().map(|b| Value::Int(*b as i64)).collect()),
+            );
+        }
+        // Read by parse_multipart()
+        if let Some(form) = form {
+            map.insert("_multipart".to_string(), form.to_value());
+        }
+    }
 
-    Ok((req_value, request))
+    Ok((req_value, request, temp_files))
 }
 
 /// Send a response back to the client
@@ -899,9 +1347,9 @@ pub fn send_response(request: tiny_http::Request, response: &Value) -> Result<()
     // Build tiny_http response
     let mut response_builder = tiny_http::Response::from_string(body).with_status_code(status);
 
-    // Add headers
+    // Add headers (an array value sends the header once per item)
     for (key, value) in headers {
-        if let Value::String(v) = value {
+        for v in http_cookies::header_values(&value) {
             if let Ok(header) = tiny_http::Header::from_bytes(key.as_bytes(), v.as_bytes()) {
                 response_builder = response_builder.with_header(header);
             }
@@ -933,6 +1381,45 @@ pub fn create_error_response(status: i64, message: &str) -> Value {
     create_response_value(status, headers, message.to_string())
 }
 
+/// Response for a path whose routes do not accept the request method:
+/// 204 with the allowed methods for OPTIONS, otherwise 405
+pub fn allow_response(method: &str, allow: &[String]) -> Value {
+    let mut headers = HashMap::new();
+    headers.insert("allow".to_string(), Value::String(allow.join(", ")));
+    if method == "OPTIONS" {
+        return create_response_value(204, headers, String::new());
+    }
+    headers.insert(
+        "content-type".to_string(),
+        Value::String("text/plain; charset=utf-8".to_string()),
+    );
+    create_response_value(405, headers, "Method Not Allowed".to_string())
+}
+
+/// Create a JSON error response for a contract violation
+///
+/// Preconditions are the caller's fault (400); postconditions and invariants
+/// are server logic errors (500). The body carries the failing sub-expression
+/// values so clients and `ntnt intent check` can show why the contract failed.
+pub fn create_contract_error_response(violation: &ContractViolation) -> Value {
+    let (status, error) = match violation.clause_type {
+        ContractType::Precondition => (400, "Bad Request"),
+        _ => (500, "Internal Error"),
+    };
+    let body = serde_json::json!({
+        "error": error,
+        "message": violation.headline(),
+        "contract": violation.to_json(),
+    });
+
+    let mut headers = HashMap::new();
+    headers.insert(
+        "content-type".to_string(),
+        Value::String("application/json".to_string()),
+    );
+    create_response_value(status, headers, body.to_string())
+}
+
 /// Get MIME type based on file extension
 pub fn get_mime_type(path: &str) -> &'static str {
     let extension = std::path::Path::new(path)
@@ -1211,12 +1698,23 @@ mod tests {
     // Route Matching Tests
     // ===========================================
 
+    /// Match a path against a single route
+    fn match_route(path: &str, route: &Route) -> Option<HashMap<String, String>> {
+        let mut router = Router::new();
+        router
+            .insert(&route.method, &route.pattern, &route.segments, 0)
+            .unwrap();
+        router.lookup(&route.method, path).map(|(_, params)| params)
+    }
+
     #[test]
     fn test_match_route_static_exact() {
         let route = Route {
             method: "GET".to_string(),
             pattern: "/users".to_string(),
             segments: parse_route_pattern("/users"),
+            max_body: None,
+            middleware: Vec::new(),
         };
         let result = match_route("/users", &route);
         assert!(result.is_some());
@@ -1229,6 +1727,8 @@ mod tests {
             method: "GET".to_string(),
             pattern: "/users".to_string(),
             segments: parse_route_pattern("/users"),
+            max_body: None,
+            middleware: Vec::new(),
         };
         let result = match_route("/posts", &route);
         assert!(result.is_none());
@@ -1240,6 +1740,8 @@ mod tests {
             method: "GET".to_string(),
             pattern: "/users/{id}".to_string(),
             segments: parse_route_pattern("/users/{id}"),
+            max_body: None,
+            middleware: Vec::new(),
         };
         let result = match_route("/users/123", &route);
         assert!(result.is_some());
@@ -1253,6 +1755,8 @@ mod tests {
             method: "GET".to_string(),
             pattern: "/users/{user_id}/posts/{post_id}".to_string(),
             segments: parse_route_pattern("/users/{user_id}/posts/{post_id}"),
+            max_body: None,
+            middleware: Vec::new(),
         };
         let result = match_route("/users/42/posts/99", &route);
         assert!(result.is_some());
@@ -1267,6 +1771,8 @@ mod tests {
             method: "GET".to_string(),
             pattern: "/users/{id}".to_string(),
             segments: parse_route_pattern("/users/{id}"),
+            max_body: None,
+            middleware: Vec::new(),
         };
         // Too few segments
         let result = match_route("/users", &route);
@@ -1283,6 +1789,8 @@ mod tests {
             method: "GET".to_string(),
             pattern: "/".to_string(),
             segments: parse_route_pattern("/"),
+            max_body: None,
+            middleware: Vec::new(),
         };
         let result = match_route("/", &route);
         assert!(result.is_some());
@@ -1302,14 +1810,16 @@ mod tests {
     #[test]
     fn test_server_state_add_route() {
         let mut state = ServerState::new();
-        state.add_route("GET", "/users", Value::Unit);
+        state.add_route("GET", "/users", Value::Unit).unwrap();
         assert_eq!(state.route_count(), 1);
     }
 
     #[test]
     fn test_server_state_find_route() {
         let mut state = ServerState::new();
-        state.add_route("GET", "/users/{id}", Value::String("handler".to_string()));
+        state
+            .add_route("GET", "/users/{id}", Value::String("handler".to_string()))
+            .unwrap();
 
         let result = state.find_route("GET", "/users/123");
         assert!(result.is_some());
@@ -1321,26 +1831,95 @@ mod tests {
     #[test]
     fn test_server_state_find_route_wrong_method() {
         let mut state = ServerState::new();
-        state.add_route("GET", "/users", Value::Unit);
+        state.add_route("GET", "/users", Value::Unit).unwrap();
 
         let result = state.find_route("POST", "/users");
         assert!(result.is_none());
     }
 
+    #[test]
+    fn test_server_state_allowed_methods_and_conflicts() {
+        let mut state = ServerState::new();
+        state
+            .add_route("GET", "/users/{id:int}", Value::Unit)
+            .unwrap();
+        state
+            .add_route("DELETE", "/users/{id:int}", Value::Unit)
+            .unwrap();
+
+        assert!(state.find_route("HEAD", "/users/7").is_some());
+        assert!(state.find_route("GET", "/users/me").is_none());
+        assert_eq!(
+            state.allowed_methods("/users/7"),
+            vec!["DELETE", "GET", "HEAD", "OPTIONS"]
+        );
+        assert!(state.allowed_methods("/users/me").is_empty());
+        assert!(state
+            .add_route("GET", "/users/{n:int}", Value::Unit)
+            .is_err());
+        assert_eq!(state.route_count(), 2);
+
+        let Value::Map(response) = allow_response("PUT", &state.allowed_methods("/users/7")) else {
+            panic!("Expected Map");
+        };
+        assert_eq!(get_map_int(&response, "status"), 405);
+        let headers = get_map_map(&response, "headers");
+        assert_eq!(
+            get_map_string(&headers, "allow"),
+            "DELETE, GET, HEAD, OPTIONS"
+        );
+        let Value::Map(response) = allow_response("OPTIONS", &[]) else {
+            panic!("Expected Map");
+        };
+        assert_eq!(get_map_int(&response, "status"), 204);
+    }
+
+    #[test]
+    fn test_server_state_sse_route_answers_get() {
+        let mut state = ServerState::new();
+        state.add_route("SSE", "/events", Value::Unit).unwrap();
+
+        let (_, _, index) = state.find_route("GET", "/events").unwrap();
+        assert!(state.is_sse_route(index));
+        assert!(state.find_route("POST", "/events").is_none());
+    }
+
     #[test]
     fn test_server_state_find_route_no_match() {
         let mut state = ServerState::new();
-        state.add_route("GET", "/users", Value::Unit);
+        state.add_route("GET", "/users", Value::Unit).unwrap();
 
         let result = state.find_route("GET", "/posts");
         assert!(result.is_none());
     }
 
+    #[test]
+    fn test_server_state_route_middleware() {
+        let mut state = ServerState::new();
+        state.add_middleware(Value::String("logger".to_string()));
+        state
+            .add_route_with_options(
+                "GET",
+                "/admin/users",
+                Value::Unit,
+                RouteOptions::default(),
+                vec![Value::String("require_admin".to_string())],
+            )
+            .unwrap();
+        state.add_route("GET", "/", Value::Unit).unwrap();
+
+        let chain = state.middleware_for(0);
+        assert_eq!(chain.len(), 2);
+        assert_value_string(&chain[0], "logger");
+        assert_value_string(&chain[1], "require_admin");
+        assert_eq!(state.middleware_for(1).len(), 1);
+    }
+
     #[test]
     fn test_server_state_clear() {
         let mut state = ServerState::new();
-        state.add_route("GET", "/users", Value::Unit);
-        state.add_route("POST", "/users", Value::Unit);
+        state.add_route("GET", "/users", Value::Unit).unwrap();
+        state.add_route("POST", "/users", Value::Unit).unwrap();
         assert_eq!(state.route_count(), 2);
 
         state.clear();
@@ -1350,10 +1929,18 @@ mod tests {
     #[test]
     fn test_server_state_multiple_routes() {
         let mut state = ServerState::new();
-        state.add_route("GET", "/", Value::String("home".to_string()));
-        state.add_route("GET", "/users", Value::String("list_users".to_string()));
-        state.add_route("GET", "/users/{id}", Value::String("get_user".to_string()));
-        state.add_route("POST", "/users", Value::String("create_user".to_string()));
+        state
+            .add_route("GET", "/", Value::String("home".to_string()))
+            .unwrap();
+        state
+            .add_route("GET", "/users", Value::String("list_users".to_string()))
+            .unwrap();
+        state
+            .add_route("GET", "/users/{id}", Value::String("get_user".to_string()))
+            .unwrap();
+        state
+            .add_route("POST", "/users", Value::String("create_user".to_string()))
+            .unwrap();
 
         assert_eq!(state.route_count(), 4);
 
@@ -1809,7 +2396,7 @@ mod tests {
     #[test]
     fn test_server_state_clear_includes_static_dirs() {
         let mut state = ServerState::new();
-        state.add_route("GET", "/", Value::Unit);
+        state.add_route("GET", "/", Value::Unit).unwrap();
         state.add_static_dir("/static".to_string(), "./public".to_string());
         state.add_middleware(Value::Unit);
 
@@ -1925,6 +2512,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_create_contract_error_response() {
+        let mut violation = ContractViolation::new(
+            ContractType::Precondition,
+            "create_user",
+            "len(req.body) > 0",
+        );
+        violation
+            .values
+            .push(("len(req.body)".to_string(), "0".to_string()));
+        let resp = create_contract_error_response(&violation);
+        if let Value::Map(map) = resp {
+            assert!(matches!(map.get("status"), Some(Value::Int(400))));
+            if let Some(Value::String(body)) = map.get("body") {
+                let json: serde_json::Value = serde_json::from_str(body).unwrap();
+                assert_eq!(json["error"], "Bad Request");
+                assert_eq!(json["contract"]["values"][0]["value"], "0");
+            } else {
+                panic!("Expected string body");
+            }
+        } else {
+            panic!("Expected map response");
+        }
+
+        let violation = ContractViolation::new(ContractType::Postcondition, "divide", "result > 0");
+        if let Value::Map(map) = create_contract_error_response(&violation) {
+            assert!(matches!(map.get("status"), Some(Value::Int(500))));
+        }
+    }
+
     #[test]
     fn test_create_error_response_404_not_found() {
         let resp = create_error_response(404, "Not Found: /api/missing");
@@ -1978,4 +2595,31 @@ mod tests {
             panic!("Expected Map response");
         }
     }
+
+    #[test]
+    fn test_parse_size_and_route_options() {
+        let size = |s: &str| parse_size(&Value::String(s.to_string()), "Route option");
+        assert_eq!(parse_size(&Value::Int(512), "x").unwrap(), 512);
+        assert_eq!(size("512kb").unwrap(), 512 * 1024);
+        assert_eq!(size("50MB").unwrap(), 50 * 1024 * 1024);
+        assert!(size("lots").is_err());
+        assert!(size("10tb").is_err());
+        assert!(parse_size(&Value::Int(-1), "x").is_err());
+
+        let mut map = HashMap::new();
+        map.insert("max_body".to_string(), Value::String("2kb".to_string()));
+        let options = RouteOptions::from_value(&Value::Map(map.clone())).unwrap();
+        assert_eq!(options.max_body, Some(2048));
+
+        map.insert("max_bdoy".to_string(), Value::Int(1));
+        assert!(RouteOptions::from_value(&Value::Map(map)).is_err());
+
+        let mut state = ServerState::new();
+        state
+            .add_route_with_options("POST", "/upload", Value::Unit, options, Vec::new())
+            .unwrap();
+        state.add_route("POST", "/other", Value::Unit).unwrap();
+        assert_eq!(state.body_limit(0), 2048);
+        assert_eq!(state.body_limit(1), DEFAULT_MAX_BODY);
+    }
 }
@@ -17,6 +17,8 @@
 //! - High-concurrency via Tokio async runtime
 //! - Static file serving with caching headers
 //! - Request timeouts
+//! - WebSocket routes (`ws()`), pinned to the worker that accepted them
+//! - Streaming bodies for `sse()` routes and `stream()` responses
 //! - Gzip compression
 //! - Graceful shutdown
 //!
@@ -26,30 +28,38 @@
 //! ntnt run server.tnt
 //! ```
 
+use crate::contracts::{ContractConfig, ContractMetrics};
 use crate::error::{IntentError, Result};
 use crate::interpreter::Value;
-use crate::stdlib::http_bridge::{BridgeRequest, BridgeResponse, SharedHandle};
+use crate::stdlib::http_bridge::{BridgeRequest, BridgeResponse, Dispatch, SharedHandle, WsEvent};
+use crate::stdlib::http_multipart::{self, BodyError};
+use crate::stdlib::http_request::{self, TrustedProxies};
+use crate::stdlib::http_router;
+pub use crate::stdlib::http_router::{parse_route_pattern, RouteSegment};
+use crate::stdlib::http_server::{allow_response, parse_size, DEFAULT_MAX_BODY};
+use crate::stdlib::http_stream::{self, StreamKind};
+use crate::stdlib::http_tls::{self, TlsOptions};
+use crate::stdlib::http_ws::{self, Outgoing};
 use axum::{
     body::Body,
-    extract::State,
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    extract::{ConnectInfo, FromRequestParts, State},
     http::{header, Request, StatusCode},
     response::{IntoResponse, Response},
     Router,
 };
 use std::collections::HashMap;
+use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::RwLock;
-use tower_http::{compression::CompressionLayer, timeout::TimeoutLayer, trace::TraceLayer};
-
-/// Route segment for pattern matching (mirrors sync version)
-#[derive(Debug, Clone)]
-pub enum RouteSegment {
-    Static(String),
-    Param(String),
-}
+use std::time::{Duration, SystemTime};
+use tokio::sync::{mpsc, RwLock};
+use tower_http::{
+    compression::{CompressionLayer, DefaultPredicate, Predicate},
+    timeout::TimeoutLayer,
+    trace::TraceLayer,
+};
 
 /// Compiled route with parsed pattern
 #[derive(Debug, Clone)]
@@ -57,6 +67,8 @@ pub struct Route {
     pub method: String,
     pub pattern: String,
     pub segments: Vec<RouteSegment>,
+    /// Body size limit set for this route (`max_body` route option)
+    pub max_body: Option<usize>,
 }
 
 /// Route registration - stores pattern info only (not Values)
@@ -73,67 +85,114 @@ pub struct StaticDir {
     pub fs_path: String,
 }
 
-/// Async server state - thread-safe route registry
-pub struct AsyncServerState {
+/// Routes and static directories the server answers. Hot reload builds a new
+/// table and swaps it in whole, so requests never see a half-registered one.
+#[derive(Debug, Clone, Default)]
+pub struct RouteTable {
     /// Routes with handler names (not actual handlers)
-    pub routes: RwLock<Vec<RouteInfo>>,
+    pub routes: Vec<RouteInfo>,
+    /// Route tree indexing `routes`
+    pub router: http_router::Router,
     /// Static file directories (url_prefix, filesystem_path)
-    pub static_dirs: RwLock<Vec<StaticDir>>,
+    pub static_dirs: Vec<StaticDir>,
+    /// Modification time of the newest source file the table was built from
+    pub version: Option<SystemTime>,
 }
 
-impl AsyncServerState {
+impl RouteTable {
     pub fn new() -> Self {
-        AsyncServerState {
-            routes: RwLock::new(Vec::new()),
-            static_dirs: RwLock::new(Vec::new()),
-        }
+        RouteTable::default()
     }
 
-    /// Register a route pattern
-    pub async fn register_route(&self, method: &str, pattern: &str, handler_name: &str) {
+    /// Register a route pattern (fails on invalid or conflicting patterns)
+    pub fn register_route(
+        &mut self,
+        method: &str,
+        pattern: &str,
+        handler_name: &str,
+        max_body: Option<usize>,
+    ) -> Result<()> {
         let route = Route {
             method: method.to_string(),
             pattern: pattern.to_string(),
             segments: parse_route_pattern(pattern),
+            max_body,
         };
-        let info = RouteInfo {
+        self.router
+            .insert(method, pattern, &route.segments, self.routes.len())?;
+        self.routes.push(RouteInfo {
             route,
             handler_name: handler_name.to_string(),
-        };
-        let mut routes = self.routes.write().await;
-        routes.push(info);
+        });
+        Ok(())
     }
 
     /// Register a static directory
-    pub async fn register_static_dir(&self, url_prefix: &str, fs_path: &str) {
-        let mut dirs = self.static_dirs.write().await;
-        dirs.push(StaticDir {
+    pub fn register_static_dir(&mut self, url_prefix: &str, fs_path: &str) {
+        self.static_dirs.push(StaticDir {
             url_prefix: url_prefix.to_string(),
             fs_path: fs_path.to_string(),
         });
     }
+}
+
+/// Async server state - thread-safe route registry
+pub struct AsyncServerState {
+    table: RwLock<RouteTable>,
+}
+
+impl AsyncServerState {
+    pub fn new() -> Self {
+        AsyncServerState {
+            table: RwLock::new(RouteTable::new()),
+        }
+    }
 
-    /// Find a matching route and return handler name + params
+    /// Register a route pattern (fails on invalid or conflicting patterns)
+    pub async fn register_route(
+        &self,
+        method: &str,
+        pattern: &str,
+        handler_name: &str,
+        max_body: Option<usize>,
+    ) -> Result<()> {
+        self.table
+            .write()
+            .await
+            .register_route(method, pattern, handler_name, max_body)
+    }
+
+    /// Register a static directory
+    pub async fn register_static_dir(&self, url_prefix: &str, fs_path: &str) {
+        self.table
+            .write()
+            .await
+            .register_static_dir(url_prefix, fs_path);
+    }
+
+    /// Find a matching route and return handler name + params + body limit
+    /// (sse() routes, stored as "SSE", answer GET requests, and GET routes
+    /// answer HEAD)
     pub async fn find_route(
         &self,
         method: &str,
         path: &str,
-    ) -> Option<(String, HashMap<String, String>)> {
-        let routes = self.routes.read().await;
-        for info in routes.iter() {
-            if info.route.method == method {
-                if let Some(params) = match_route(path, &info.route) {
-                    return Some((info.handler_name.clone(), params));
-                }
-            }
-        }
-        None
+    ) -> Option<(String, HashMap<String, String>, Option<usize>)> {
+        let table = self.table.read().await;
+        let (index, params) = table.router.lookup(method, path)?;
+        let info = table.routes.get(index)?;
+        Some((info.handler_name.clone(), params, info.route.max_body))
+    }
+
+    /// Methods the routes matching `path` accept (empty if none match)
+    pub async fn allowed_methods(&self, path: &str) -> Vec<String> {
+        self.table.read().await.router.allowed_methods(path)
     }
 
     /// Check if path matches a static directory
     pub async fn find_static_file(&self, path: &str) -> Option<(String, String)> {
-        let dirs = self.static_dirs.read().await;
-        for dir in dirs.iter() {
+        let table = self.table.read().await;
+        for dir in table.static_dirs.iter() {
             if path.starts_with(&dir.url_prefix) {
                 let relative = path.strip_prefix(&dir.url_prefix).unwrap_or("");
                 let relative = relative.trim_start_matches('/');
@@ -151,56 +210,29 @@ impl AsyncServerState {
 
     /// Get route count
     pub async fn route_count(&self) -> usize {
-        self.routes.read().await.len()
+        self.table.read().await.routes.len()
     }
 
     /// Get static dir count
     pub async fn static_dir_count(&self) -> usize {
-        self.static_dirs.read().await.len()
-    }
-
-    /// Clear all routes (for hot-reload)
-    pub async fn clear_routes(&self) {
-        let mut routes = self.routes.write().await;
-        routes.clear();
+        self.table.read().await.static_dirs.len()
     }
 
-    /// Clear all static directories (for hot-reload)
-    pub async fn clear_static_dirs(&self) {
-        let mut dirs = self.static_dirs.write().await;
-        dirs.clear();
-    }
-
-    /// Clear all state (routes and static dirs) for hot-reload
-    pub async fn clear(&self) {
-        self.clear_routes().await;
-        self.clear_static_dirs().await;
-    }
-
-    /// Synchronous version of clear for use from non-async context
-    pub fn clear_blocking(&self, rt: &tokio::runtime::Runtime) {
-        rt.block_on(self.clear());
-    }
-
-    /// Synchronous version of register_route for use from non-async context
-    pub fn register_route_blocking(
-        &self,
-        rt: &tokio::runtime::Runtime,
-        method: &str,
-        pattern: &str,
-        handler_name: &str,
-    ) {
-        rt.block_on(self.register_route(method, pattern, handler_name));
+    /// Swap in a table built for hot reload. Pool workers reload on their
+    /// own, so a table built from older sources than the current one is
+    /// ignored; returns whether the table was installed.
+    pub async fn replace(&self, table: RouteTable) -> bool {
+        let mut current = self.table.write().await;
+        if table.version < current.version {
+            return false;
+        }
+        *current = table;
+        true
     }
 
-    /// Synchronous version of register_static_dir for use from non-async context
-    pub fn register_static_dir_blocking(
-        &self,
-        rt: &tokio::runtime::Runtime,
-        url_prefix: &str,
-        fs_path: &str,
-    ) {
-        rt.block_on(self.register_static_dir(url_prefix, fs_path));
+    /// Synchronous version of replace for use from non-async context
+    pub fn replace_blocking(&self, rt: &tokio::runtime::Runtime, table: RouteTable) -> bool {
+        rt.block_on(self.replace(table))
     }
 }
 
@@ -210,53 +242,6 @@ impl Default for AsyncServerState {
     }
 }
 
-/// Parse a route pattern into segments
-pub fn parse_route_pattern(pattern: &str) -> Vec<RouteSegment> {
-    pattern
-        .split('/')
-        .filter(|s| !s.is_empty())
-        .map(|segment| {
-            if segment.starts_with('{') && segment.ends_with('}') {
-                RouteSegment::Param(segment[1..segment.len() - 1].to_string())
-            } else {
-                RouteSegment::Static(segment.to_string())
-            }
-        })
-        .collect()
-}
-
-/// Match a URL path against a route
-pub fn match_route(path: &str, route: &Route) -> Option<HashMap<String, String>> {
-    let path_segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
-    let route_segments = &route.segments;
-
-    // Handle root path specially
-    if path == "/" && route_segments.is_empty() {
-        return Some(HashMap::new());
-    }
-
-    if path_segments.len() != route_segments.len() {
-        return None;
-    }
-
-    let mut params = HashMap::new();
-
-    for (path_seg, route_seg) in path_segments.iter().zip(route_segments.iter()) {
-        match route_seg {
-            RouteSegment::Static(expected) => {
-                if path_seg != expected {
-                    return None;
-                }
-            }
-            RouteSegment::Param(name) => {
-                params.insert(name.clone(), path_seg.to_string());
-            }
-        }
-    }
-
-    Some(params)
-}
-
 /// State shared between all request handlers
 #[derive(Clone)]
 pub struct AppState {
@@ -264,60 +249,111 @@ pub struct AppState {
     pub interpreter: SharedHandle,
     /// Route registry for matching requests
     pub routes: Arc<AsyncServerState>,
+    /// Contract statistics endpoint, if enabled
+    pub contract_metrics: Option<ContractMetricsEndpoint>,
+    /// Largest request body accepted by routes without their own limit
+    pub max_body: usize,
+    /// Peers whose forwarding headers are believed
+    pub trusted_proxies: Arc<TrustedProxies>,
 }
 
-/// Convert Axum request to BridgeRequest
+/// Convert Axum request to BridgeRequest, reading a body of at most `max_body` bytes
 async fn axum_to_bridge_request(
     req: Request<Body>,
     params: HashMap<String, String>,
-) -> Result<BridgeRequest> {
+    max_body: usize,
+    trusted_proxies: &TrustedProxies,
+) -> std::result::Result<BridgeRequest, BodyError> {
     let method = req.method().to_string();
+    let https = req.extensions().get::<http_tls::Https>().is_some();
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
     let uri = req.uri();
     let path = uri.path().to_string();
     let url = uri.to_string();
     let query = uri.query().unwrap_or("").to_string();
 
-    // Parse query params
-    let mut query_params = HashMap::new();
-    if !query.is_empty() {
-        for pair in query.split('&') {
-            if let Some((key, value)) = pair.split_once('=') {
-                query_params.insert(key.to_string(), value.to_string());
-            }
-        }
-    }
+    let query_params = http_request::Query::parse(&query);
 
-    // Extract headers
-    let mut headers = HashMap::new();
-    let mut client_ip = None;
+    // Extract headers (repeated ones are joined, cookies with "; ")
+    let mut headers: HashMap<String, String> = HashMap::new();
     for (name, value) in req.headers() {
         if let Ok(v) = value.to_str() {
             let key = name.to_string().to_lowercase();
-            if key == "x-forwarded-for" {
-                client_ip = Some(v.split(',').next().unwrap_or(v).trim().to_string());
-            }
-            headers.insert(key, v.to_string());
+            let separator = if key == "cookie" { "; " } else { ", " };
+            headers
+                .entry(key)
+                .and_modify(|existing| {
+                    existing.push_str(separator);
+                    existing.push_str(v);
+                })
+                .or_insert_with(|| v.to_string());
         }
     }
 
-    // Read body
-    let body_bytes = axum::body::to_bytes(req.into_body(), 10 * 1024 * 1024)
-        .await
-        .map_err(|e| IntentError::RuntimeError(format!("Failed to read body: {}", e)))?;
-    let body = String::from_utf8_lossy(&body_bytes).to_string();
+    let ip = http_request::client_ip(peer, &headers, trusted_proxies);
+    let protocol = if https {
+        "https".to_string()
+    } else {
+        http_request::forwarded_proto(peer, &headers, trusted_proxies)
+            .unwrap_or_else(|| "http".to_string())
+    };
+
+    // Read body: multipart bodies are parsed as they arrive (large files go
+    // to disk), anything else is read whole
+    let declared_length = headers
+        .get("content-length")
+        .and_then(|length| length.parse::<usize>().ok());
+    if declared_length.is_some_and(|length| length > max_body) {
+        return Err(BodyError::TooLarge);
+    }
+    let content_type = headers.get("content-type").cloned().unwrap_or_default();
+    let (body, body_bytes, multipart) = match http_multipart::boundary(&content_type) {
+        Some(boundary) => {
+            let stream = req.into_body().into_data_stream();
+            let form = http_multipart::read_form(stream, boundary, max_body).await?;
+            (String::new(), None, Some(form))
+        }
+        None => {
+            let bytes = axum::body::to_bytes(req.into_body(), max_body)
+                .await
+                .map_err(|e| {
+                    let too_large = std::error::Error::source(&e)
+                        .is_some_and(|source| source.is::<http_body_util::LengthLimitError>());
+                    if too_large {
+                        BodyError::TooLarge
+                    } else {
+                        BodyError::Invalid(format!("Failed to read body: {}", e))
+                    }
+                })?;
+            match String::from_utf8(bytes.to_vec()) {
+                Ok(body) => (body, None, None),
+                Err(e) => (
+                    String::from_utf8_lossy(e.as_bytes()).to_string(),
+                    Some(e.into_bytes()),
+                    None,
+                ),
+            }
+        }
+    };
 
     Ok(BridgeRequest {
         method,
         path,
         url,
         query,
-        query_params,
+        query_params: query_params.params,
+        query_all: query_params.all,
         params,
         headers,
         body,
+        body_bytes,
+        multipart,
         id: uuid::Uuid::new_v4().to_string(),
-        ip: client_ip.unwrap_or_else(|| "unknown".to_string()),
-        protocol: "http".to_string(),
+        ip,
+        protocol,
     })
 }
 
@@ -338,7 +374,15 @@ fn bridge_to_axum_response(resp: BridgeResponse) -> Response<Body> {
     // Add server header
     response = response.header("server", "ntnt-async");
 
-    response.body(Body::from(resp.body)).unwrap_or_else(|_| {
+    let body = match resp.stream.and_then(http_stream::take) {
+        Some((kind, rx)) => {
+            response = response.extension(StreamedBody);
+            stream_body(kind, rx)
+        }
+        None => Body::from(resp.body),
+    };
+
+    response.body(body).unwrap_or_else(|_| {
         Response::builder()
             .status(StatusCode::INTERNAL_SERVER_ERROR)
             .body(Body::from("Failed to build response"))
@@ -346,6 +390,36 @@ fn bridge_to_axum_response(resp: BridgeResponse) -> Response<Body> {
     })
 }
 
+/// Marks responses whose body is streamed, so compression leaves them alone
+/// (the gzip encoder would hold chunks back until it has a full block)
+#[derive(Debug, Clone, Copy)]
+struct StreamedBody;
+
+/// Response body fed by a handler's stream
+fn stream_body(kind: StreamKind, rx: mpsc::UnboundedReceiver<String>) -> Body {
+    let keep_alive = (kind == StreamKind::Events).then_some(http_stream::SSE_KEEP_ALIVE);
+    Body::from_stream(stream_chunks(rx, keep_alive))
+}
+
+/// Chunks from a stream channel until the handler finishes or the client goes
+/// away. With `keep_alive`, a comment is sent whenever the stream has been idle
+/// that long, which keeps proxies from closing the connection.
+fn stream_chunks(
+    rx: mpsc::UnboundedReceiver<String>,
+    keep_alive: Option<Duration>,
+) -> impl futures_util::Stream<Item = std::result::Result<String, Infallible>> {
+    futures_util::stream::unfold(rx, move |mut rx| async move {
+        let chunk = match keep_alive {
+            Some(interval) => match tokio::time::timeout(interval, rx.recv()).await {
+                Ok(chunk) => chunk?,
+                Err(_) => ": keep-alive\n\n".to_string(),
+            },
+            None => rx.recv().await?,
+        };
+        Some((Ok(chunk), rx))
+    })
+}
+
 /// Serve a static file with proper headers
 fn serve_static_file(file_path: &str) -> Response<Body> {
     use std::fs;
@@ -406,23 +480,58 @@ async fn handle_request(State(state): State<AppState>, req: Request<Body>) -> im
     let method = req.method().clone();
     let path = req.uri().path().to_string();
 
+    // Contract statistics endpoint (answered without touching the interpreter)
+    if let Some(endpoint) = &state.contract_metrics {
+        if method == axum::http::Method::GET && path == endpoint.path {
+            let body = endpoint.metrics.to_json(&endpoint.config).to_string();
+            return Response::builder()
+                .status(StatusCode::OK)
+                .header("content-type", "application/json")
+                .header("cache-control", "no-cache")
+                .header("server", "ntnt-async")
+                .body(Body::from(body))
+                .unwrap();
+        }
+    }
+
+    // WebSocket upgrades for ws() routes
+    if is_websocket_upgrade(&req) {
+        if let Some((_handler_name, params, _)) = state.routes.find_route("WS", &path).await {
+            return handle_websocket(state, req, params).await;
+        }
+    }
+
     // First, check for dynamic route match
     let route_match = state.routes.find_route(method.as_str(), &path).await;
 
     match route_match {
-        Some((_handler_name, params)) => {
+        Some((_handler_name, params, max_body)) => {
             // Convert request and send to interpreter
-            match axum_to_bridge_request(req, params).await {
-                Ok(bridge_req) => match state.interpreter.call(bridge_req).await {
-                    Ok(response) => bridge_to_axum_response(response),
-                    Err(e) => {
-                        eprintln!("Handler error: {}", e);
-                        bridge_to_axum_response(BridgeResponse::error(
-                            500,
-                            &format!("Internal Server Error: {}", e),
-                        ))
-                    }
-                },
+            let max_body = max_body.unwrap_or(state.max_body);
+            match axum_to_bridge_request(req, params, max_body, &state.trusted_proxies).await {
+                Ok(bridge_req) => {
+                    let temp_files = bridge_req
+                        .multipart
+                        .as_ref()
+                        .map(|form| form.temp_files())
+                        .unwrap_or_default();
+                    let response = match state.interpreter.call(bridge_req).await {
+                        Ok(response) => bridge_to_axum_response(response),
+                        Err(e) => {
+                            eprintln!("Handler error: {}", e);
+                            bridge_to_axum_response(BridgeResponse::error(
+                                500,
+                                &format!("Internal Server Error: {}", e),
+                            ))
+                        }
+                    };
+                    // Uploads the handler did not save are not kept
+                    http_multipart::remove_temp_files(&temp_files);
+                    response
+                }
+                Err(BodyError::TooLarge) => {
+                    bridge_to_axum_response(BridgeResponse::error(413, "Payload Too Large"))
+                }
                 Err(e) => {
                     eprintln!("Request parsing error: {}", e);
                     bridge_to_axum_response(BridgeResponse::error(400, "Bad Request"))
@@ -430,8 +539,15 @@ async fn handle_request(State(state): State<AppState>, req: Request<Body>) -> im
             }
         }
         None => {
-            // No dynamic route - check static files (GET only)
-            if method == axum::http::Method::GET {
+            // The path has routes, but not for this method
+            let allow = state.routes.allowed_methods(&path).await;
+            if !allow.is_empty() {
+                let response = allow_response(method.as_str(), &allow);
+                return bridge_to_axum_response(BridgeResponse::from_value(&response));
+            }
+
+            // No dynamic route - check static files (GET and HEAD only)
+            if method == axum::http::Method::GET || method == axum::http::Method::HEAD {
                 if let Some((file_path, _prefix)) = state.routes.find_static_file(&path).await {
                     return serve_static_file(&file_path);
                 }
@@ -443,6 +559,131 @@ async fn handle_request(State(state): State<AppState>, req: Request<Body>) -> im
     }
 }
 
+/// Whether the request asks to switch to the WebSocket protocol
+fn is_websocket_upgrade(req: &Request<Body>) -> bool {
+    req.headers()
+        .get(header::UPGRADE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.eq_ignore_ascii_case("websocket"))
+        .unwrap_or(false)
+}
+
+/// Accept or reject a WebSocket connection for a ws() route
+///
+/// The interpreter runs the route's handler first; a 101 reply accepts the
+/// upgrade, any other reply (middleware or handler response, error) is sent
+/// back as a normal HTTP response.
+async fn handle_websocket(
+    state: AppState,
+    req: Request<Body>,
+    params: HashMap<String, String>,
+) -> Response<Body> {
+    let (mut parts, body) = req.into_parts();
+    let upgrade = match WebSocketUpgrade::from_request_parts(&mut parts, &state).await {
+        Ok(upgrade) => upgrade,
+        Err(rejection) => return rejection.into_response(),
+    };
+    let request = match axum_to_bridge_request(
+        Request::from_parts(parts, body),
+        params,
+        state.max_body,
+        &state.trusted_proxies,
+    )
+    .await
+    {
+        Ok(request) => request,
+        Err(e) => {
+            eprintln!("Request parsing error: {}", e);
+            return bridge_to_axum_response(BridgeResponse::error(400, "Bad Request"));
+        }
+    };
+
+    let (conn_id, outgoing) = http_ws::register();
+    let interpreter = state.interpreter.clone();
+    match interpreter
+        .call_websocket(None, request.clone(), WsEvent::Open(conn_id))
+        .await
+    {
+        Ok((worker, response)) if response.status == 101 => {
+            let failed_interpreter = interpreter.clone();
+            let failed_request = request.clone();
+            upgrade
+                .on_failed_upgrade(move |e| {
+                    eprintln!("WebSocket upgrade failed: {}", e);
+                    http_ws::unregister(conn_id);
+                    tokio::spawn(async move {
+                        let _ = failed_interpreter
+                            .call_websocket(Some(worker), failed_request, WsEvent::Close(conn_id))
+                            .await;
+                    });
+                })
+                .on_upgrade(move |socket| {
+                    run_websocket(socket, interpreter, worker, conn_id, request, outgoing)
+                })
+        }
+        Ok((_, response)) => {
+            http_ws::unregister(conn_id);
+            bridge_to_axum_response(response)
+        }
+        Err(e) => {
+            http_ws::unregister(conn_id);
+            eprintln!("Handler error: {}", e);
+            bridge_to_axum_response(BridgeResponse::error(
+                500,
+                &format!("Internal Server Error: {}", e),
+            ))
+        }
+    }
+}
+
+/// Pump frames between an open socket and the interpreter that accepted it
+///
+/// Incoming messages are handled one at a time, in order, by the connection's
+/// on_message callback. Frames queued with send()/broadcast() are written as
+/// they arrive.
+async fn run_websocket(
+    mut socket: WebSocket,
+    interpreter: SharedHandle,
+    worker: usize,
+    conn_id: u64,
+    request: BridgeRequest,
+    mut outgoing: tokio::sync::mpsc::UnboundedReceiver<Outgoing>,
+) {
+    loop {
+        tokio::select! {
+            frame = outgoing.recv() => match frame {
+                Some(Outgoing::Text(text)) => {
+                    if socket.send(Message::Text(text)).await.is_err() {
+                        break;
+                    }
+                }
+                Some(Outgoing::Close) | None => {
+                    let _ = socket.send(Message::Close(None)).await;
+                    break;
+                }
+            },
+            incoming = socket.recv() => {
+                let text = match incoming {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Binary(bytes))) => String::from_utf8_lossy(&bytes).to_string(),
+                    Some(Ok(Message::Ping(_))) | Some(Ok(Message::Pong(_))) => continue,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                };
+                let event = WsEvent::Message(conn_id, text);
+                if let Err(e) = interpreter.call_websocket(Some(worker), request.clone(), event).await {
+                    eprintln!("WebSocket handler error: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+
+    http_ws::unregister(conn_id);
+    let _ = interpreter
+        .call_websocket(Some(worker), request, WsEvent::Close(conn_id))
+        .await;
+}
+
 /// Configuration for the async server
 #[derive(Clone)]
 pub struct AsyncServerConfig {
@@ -451,6 +692,24 @@ pub struct AsyncServerConfig {
     pub enable_compression: bool,
     pub request_timeout_secs: u64,
     pub max_connections: usize,
+    /// Serve contract statistics as JSON on this endpoint (from `ntnt.toml` `[contracts] metrics_path`)
+    pub contract_metrics: Option<ContractMetricsEndpoint>,
+    /// Largest request body accepted by routes without their own limit
+    pub max_body: usize,
+    /// Serve HTTPS with this certificate and key
+    pub tls: Option<TlsOptions>,
+    /// Plain-HTTP port that redirects to HTTPS
+    pub redirect_http: Option<u16>,
+    /// Peers whose forwarding headers are believed
+    pub trusted_proxies: TrustedProxies,
+}
+
+/// Contract statistics endpoint configuration
+#[derive(Clone)]
+pub struct ContractMetricsEndpoint {
+    pub path: String,
+    pub metrics: Arc<ContractMetrics>,
+    pub config: ContractConfig,
 }
 
 impl Default for AsyncServerConfig {
@@ -461,7 +720,125 @@ impl Default for AsyncServerConfig {
             enable_compression: true,
             request_timeout_secs: 30,
             max_connections: 10_000,
+            contract_metrics: None,
+            max_body: DEFAULT_MAX_BODY,
+            tls: None,
+            redirect_http: None,
+            trusted_proxies: TrustedProxies::default(),
+        }
+    }
+}
+
+/// Options accepted by `listen(port, options)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListenOptions {
+    /// Number of interpreters handling requests
+    pub workers: usize,
+    /// How requests are assigned to workers
+    pub dispatch: Dispatch,
+    /// Largest request body accepted by routes without their own limit
+    pub max_body: usize,
+    /// Serve HTTPS with this certificate and key
+    pub tls: Option<TlsOptions>,
+    /// Plain-HTTP port that redirects to HTTPS
+    pub redirect_http: Option<u16>,
+    /// Peers whose forwarding headers are believed
+    pub trusted_proxies: TrustedProxies,
+}
+
+impl Default for ListenOptions {
+    fn default() -> Self {
+        ListenOptions {
+            workers: 1,
+            dispatch: Dispatch::default(),
+            max_body: DEFAULT_MAX_BODY,
+            tls: None,
+            redirect_http: None,
+            trusted_proxies: TrustedProxies::default(),
+        }
+    }
+}
+
+impl ListenOptions {
+    /// Read options from the map passed to `listen()`
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let map = match value {
+            Value::Map(map) => map,
+            _ => {
+                return Err(IntentError::TypeError(
+                    "listen() options must be a map".to_string(),
+                ))
+            }
+        };
+
+        let mut options = ListenOptions::default();
+        let mut tls_cert = None;
+        let mut tls_key = None;
+        for (key, value) in map {
+            match (key.as_str(), value) {
+                ("workers", Value::Int(n)) if *n >= 1 => options.workers = *n as usize,
+                ("workers", _) => {
+                    return Err(IntentError::TypeError(
+                        "listen() option 'workers' must be a positive integer".to_string(),
+                    ))
+                }
+                ("dispatch", Value::String(name)) => {
+                    options.dispatch = Dispatch::parse(name).ok_or_else(|| {
+                        IntentError::RuntimeError(format!(
+                            "Unknown dispatch '{}' (expected \"round_robin\" or \"least_busy\")",
+                            name
+                        ))
+                    })?
+                }
+                ("dispatch", _) => {
+                    return Err(IntentError::TypeError(
+                        "listen() option 'dispatch' must be a string".to_string(),
+                    ))
+                }
+                ("max_body", size) => options.max_body = parse_size(size, "listen() option")?,
+                ("tls_cert", Value::String(path)) => tls_cert = Some(PathBuf::from(path)),
+                ("tls_key", Value::String(path)) => tls_key = Some(PathBuf::from(path)),
+                ("tls_cert" | "tls_key", _) => {
+                    return Err(IntentError::TypeError(format!(
+                        "listen() option '{}' must be a file path",
+                        key
+                    )))
+                }
+                ("redirect_http", Value::Int(port)) if (1..=65535).contains(port) => {
+                    options.redirect_http = Some(*port as u16)
+                }
+                ("trusted_proxies", value) => {
+                    options.trusted_proxies = TrustedProxies::from_value(value)?
+                }
+                ("redirect_http", _) => {
+                    return Err(IntentError::TypeError(
+                        "listen() option 'redirect_http' must be a port number".to_string(),
+                    ))
+                }
+                _ => {
+                    return Err(IntentError::RuntimeError(format!(
+                        "Unknown listen() option '{}'",
+                        key
+                    )))
+                }
+            }
+        }
+
+        options.tls = match (tls_cert, tls_key) {
+            (Some(cert), Some(key)) => Some(TlsOptions { cert, key }),
+            (None, None) => None,
+            _ => {
+                return Err(IntentError::RuntimeError(
+                    "listen() options 'tls_cert' and 'tls_key' must be given together".to_string(),
+                ))
+            }
+        };
+        if options.redirect_http.is_some() && options.tls.is_none() {
+            return Err(IntentError::RuntimeError(
+                "listen() option 'redirect_http' requires 'tls_cert' and 'tls_key'".to_string(),
+            ));
         }
+        Ok(options)
     }
 }
 
@@ -479,10 +856,15 @@ pub async fn start_server_with_bridge(
 
     let route_count = routes.route_count().await;
     let static_count = routes.static_dir_count().await;
+    let worker_count = interpreter_handle.worker_count();
+    let dispatch = interpreter_handle.dispatch();
 
     let state = AppState {
         interpreter: interpreter_handle,
         routes,
+        contract_metrics: config.contract_metrics.clone(),
+        max_body: config.max_body,
+        trusted_proxies: Arc::new(config.trusted_proxies.clone()),
     };
 
     // Build the router with catch-all handler
@@ -494,19 +876,37 @@ pub async fn start_server_with_bridge(
         config.request_timeout_secs,
     )));
 
-    // 2. Compression
+    // 2. Compression (not for streamed bodies)
     if config.enable_compression {
-        app = app.layer(CompressionLayer::new());
+        let not_streamed = |_: StatusCode,
+                            _: axum::http::Version,
+                            _: &axum::http::HeaderMap,
+                            extensions: &axum::http::Extensions| {
+            extensions.get::<StreamedBody>().is_none()
+        };
+        app = app.layer(
+            CompressionLayer::new().compress_when(DefaultPredicate::new().and(not_streamed)),
+        );
     }
 
     // 3. Tracing
     app = app.layer(TraceLayer::new_for_http());
 
+    // Load the certificate up front so a bad path or key fails before binding
+    let tls = match &config.tls {
+        Some(options) => {
+            app = app.layer(axum::Extension(http_tls::Https));
+            Some(http_tls::ReloadingCert::load(options)?)
+        }
+        None => None,
+    };
+    let scheme = if tls.is_some() { "https" } else { "http" };
+
     // Show user-friendly URL (0.0.0.0 means all interfaces, so use localhost for display)
     let display_url = if addr.ip().is_unspecified() {
-        format!("http://localhost:{}", addr.port())
+        format!("{}://localhost:{}", scheme, addr.port())
     } else {
-        format!("http://{}", addr)
+        format!("{}://{}", scheme, addr)
     };
 
     println!();
@@ -515,6 +915,12 @@ pub async fn start_server_with_bridge(
         "   Routes: {}  |  Static: {}  |  Hot-reload: enabled",
         route_count, static_count
     );
+    if worker_count > 1 {
+        println!("   Workers: {} ({})", worker_count, dispatch.name());
+    }
+    if let Some(port) = config.redirect_http {
+        println!("   HTTP on port {} redirects to HTTPS", port);
+    }
     println!();
     println!("Press Ctrl+C to stop");
 
@@ -523,11 +929,38 @@ pub async fn start_server_with_bridge(
         .await
         .map_err(|e| IntentError::RuntimeError(format!("Failed to bind: {}", e)))?;
 
-    // Run the server with graceful shutdown
-    axum::serve(listener, app)
+    let Some(cert) = tls else {
+        // Run the server with graceful shutdown
+        return axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
         .with_graceful_shutdown(shutdown_signal())
         .await
-        .map_err(|e| IntentError::RuntimeError(format!("Server error: {}", e)))
+        .map_err(|e| IntentError::RuntimeError(format!("Server error: {}", e)));
+    };
+
+    // HTTP-to-HTTPS redirects, stopped along with the HTTPS server
+    let redirect = match config.redirect_http {
+        Some(port) => {
+            let redirect_addr = SocketAddr::new(addr.ip(), port);
+            let redirect_listener = tokio::net::TcpListener::bind(redirect_addr)
+                .await
+                .map_err(|e| IntentError::RuntimeError(format!("Failed to bind: {}", e)))?;
+            let redirect_app = http_tls::redirect_router(addr.port());
+            Some(tokio::spawn(async move {
+                axum::serve(redirect_listener, redirect_app).await
+            }))
+        }
+        None => None,
+    };
+
+    cert.watch();
+    http_tls::serve(listener, cert.acceptor()?, app, shutdown_signal()).await;
+    if let Some(task) = redirect {
+        task.abort();
+    }
+    Ok(())
 }
 
 /// Signal handler for graceful shutdown
@@ -555,6 +988,9 @@ async fn shutdown_signal() {
     }
 
     println!("\n🛑 Shutdown signal received, stopping server...");
+    // Open WebSocket connections and streams would otherwise keep the server running
+    http_ws::close_all();
+    http_stream::close_all();
 }
 
 // === Helper functions for creating NTNT response Values ===
@@ -599,6 +1035,75 @@ pub fn create_error_response(status: i64, message: &str) -> Value {
 mod tests {
     use super::*;
 
+    /// Match a path against a single route
+    fn match_route(path: &str, route: &Route) -> Option<HashMap<String, String>> {
+        let mut router = http_router::Router::new();
+        router
+            .insert(&route.method, &route.pattern, &route.segments, 0)
+            .unwrap();
+        router.lookup(&route.method, path).map(|(_, params)| params)
+    }
+
+    #[test]
+    fn test_listen_options_from_value() {
+        let mut map = HashMap::new();
+        map.insert("workers".to_string(), Value::Int(4));
+        map.insert(
+            "dispatch".to_string(),
+            Value::String("round_robin".to_string()),
+        );
+        let options = ListenOptions::from_value(&Value::Map(map)).unwrap();
+        assert_eq!(options.workers, 4);
+        assert_eq!(options.dispatch, Dispatch::RoundRobin);
+
+        let mut map = HashMap::new();
+        map.insert("max_body".to_string(), Value::String("2mb".to_string()));
+        let options = ListenOptions::from_value(&Value::Map(map)).unwrap();
+        assert_eq!(options.max_body, 2 * 1024 * 1024);
+
+        let mut map = HashMap::new();
+        map.insert(
+            "tls_cert".to_string(),
+            Value::String("cert.pem".to_string()),
+        );
+        map.insert("tls_key".to_string(), Value::String("key.pem".to_string()));
+        map.insert("redirect_http".to_string(), Value::Int(80));
+        let options = ListenOptions::from_value(&Value::Map(map)).unwrap();
+        assert_eq!(
+            options.tls,
+            Some(TlsOptions {
+                cert: PathBuf::from("cert.pem"),
+                key: PathBuf::from("key.pem"),
+            })
+        );
+        assert_eq!(options.redirect_http, Some(80));
+
+        let mut map = HashMap::new();
+        map.insert("trusted_proxies".to_string(), Value::Array(vec![]));
+        let options = ListenOptions::from_value(&Value::Map(map)).unwrap();
+        assert!(!options
+            .trusted_proxies
+            .contains("127.0.0.1".parse().unwrap()));
+
+        let defaults = ListenOptions::from_value(&Value::Map(HashMap::new())).unwrap();
+        assert_eq!(defaults, ListenOptions::default());
+
+        for (key, value) in [
+            ("workers", Value::Int(0)),
+            ("dispatch", Value::String("random".to_string())),
+            ("wrokers", Value::Int(2)),
+            ("max_body", Value::String("lots".to_string())),
+            ("tls_cert", Value::String("cert.pem".to_string())),
+            ("tls_key", Value::Int(1)),
+            ("redirect_http", Value::Int(80)),
+            ("trusted_proxies", Value::String("*".to_string())),
+        ] {
+            let mut map = HashMap::new();
+            map.insert(key.to_string(), value);
+            assert!(ListenOptions::from_value(&Value::Map(map)).is_err());
+        }
+    }
+
     #[test]
     fn test_parse_route_pattern() {
         let segments = parse_route_pattern("/users/{id}");
@@ -619,6 +1124,7 @@ mod tests {
             method: "GET".to_string(),
             pattern: "/users/{id}".to_string(),
             segments: parse_route_pattern("/users/{id}"),
+            max_body: None,
         };
         let result = match_route("/users/123", &route);
         assert!(result.is_some());
@@ -632,6 +1138,7 @@ mod tests {
             method: "GET".to_string(),
             pattern: "/".to_string(),
             segments: parse_route_pattern("/"),
+            max_body: None,
         };
         let result = match_route("/", &route);
         assert!(result.is_some());
@@ -675,12 +1182,15 @@ mod tests {
         let state = AsyncServerState::new();
         assert_eq!(state.route_count().await, 0);
 
-        state.register_route("GET", "/test", "test_handler").await;
+        state
+            .register_route("GET", "/test", "test_handler", None)
+            .await
+            .unwrap();
         assert_eq!(state.route_count().await, 1);
 
         let found = state.find_route("GET", "/test").await;
         assert!(found.is_some());
-        let (handler_name, params) = found.unwrap();
+        let (handler_name, params, _) = found.unwrap();
         assert_eq!(handler_name, "test_handler");
         assert!(params.is_empty());
 
@@ -688,14 +1198,38 @@ mod tests {
         assert!(not_found.is_none());
     }
 
+    #[tokio::test]
+    async fn test_stream_chunks_keep_alive() {
+        use futures_util::StreamExt;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let chunks = stream_chunks(rx, Some(Duration::from_millis(50)));
+        futures_util::pin_mut!(chunks);
+
+        tx.send("data: 1\n\n".to_string()).unwrap();
+        assert_eq!(chunks.next().await, Some(Ok("data: 1\n\n".to_string())));
+        // Idle streams get a comment so proxies keep the connection open
+        assert_eq!(
+            chunks.next().await,
+            Some(Ok(": keep-alive\n\n".to_string()))
+        );
+
+        // The body ends when the handler finishes
+        drop(tx);
+        assert_eq!(chunks.next().await, None);
+    }
+
     #[tokio::test]
     async fn test_route_with_params() {
         let state = AsyncServerState::new();
-        state.register_route("GET", "/users/{id}", "get_user").await;
+        state
+            .register_route("GET", "/users/{id}", "get_user", None)
+            .await
+            .unwrap();
 
         let found = state.find_route("GET", "/users/42").await;
         assert!(found.is_some());
-        let (handler_name, params) = found.unwrap();
+        let (handler_name, params, _) = found.unwrap();
         assert_eq!(handler_name, "get_user");
         assert_eq!(params.get("id"), Some(&"42".to_string()));
     }
@@ -708,4 +1242,34 @@ mod tests {
         state.register_static_dir("/assets", "./public").await;
         assert_eq!(state.static_dir_count().await, 1);
     }
+
+    #[tokio::test]
+    async fn test_replace_swaps_whole_table() {
+        let state = AsyncServerState::new();
+        state
+            .register_route("GET", "/old", "handler", None)
+            .await
+            .unwrap();
+
+        let now = SystemTime::now();
+        let mut table = RouteTable::new();
+        table
+            .register_route("GET", "/new", "handler", None)
+            .unwrap();
+        table.register_static_dir("/assets", "./public");
+        table.version = Some(now);
+        assert!(state.replace(table).await);
+        assert!(state.find_route("GET", "/old").await.is_none());
+        assert!(state.find_route("GET", "/new").await.is_some());
+        assert_eq!(state.static_dir_count().await, 1);
+
+        // A worker still on older sources can't roll the table back
+        let mut stale = RouteTable::new();
+        stale
+            .register_route("GET", "/old", "handler", None)
+            .unwrap();
+        stale.version = Some(now - Duration::from_secs(1));
+        assert!(!state.replace(stale).await);
+        assert!(state.find_route("GET", "/new").await.is_some());
+    }
 }
@@ -0,0 +1,780 @@
+//! Sessions for `std/http/server`
+//!
+//! `sessions(options)` returns a middleware value for `use_middleware()`:
+//!
+//! ```ntnt
+//! import { sessions, session_get, session_set, rotate_session, text } from "std/http/server"
+//!
+//! use_middleware(sessions(map { "store": "sqlite", "path": "sessions.db", "ttl": "7d" }))
+//!
+//! fn login(req) {
+//!     rotate_session(req)               // new ID for the signed-in session
+//!     session_set(req, "user_id", 42)
+//!     return text("welcome")
+//! }
+//! ```
+//!
+//! When the middleware runs, the session named by the request's cookie is
+//! loaded and attached to the request: `req.session` holds its data as it was
+//! when the request arrived, and `session_get()` / `session_set()` read and
+//! change the live session through the handle stored in it. `req.session` is
+//! read-only: assigning to it, or a later middleware replacing it, is an error
+//! rather than a change that would be lost. Once the handler has returned, the
+//! interpreter calls `commit()`, which saves a changed session and sets the
+//! cookie. Sessions nobody wrote to are never stored, so
+//! anonymous visitors get no cookie.
+//!
+//! Values are stored as JSON. The memory store is shared by every worker of
+//! the process; the SQLite store also survives restarts and can be shared by
+//! several processes.
+
+use crate::error::IntentError;
+use crate::interpreter::Value;
+use crate::stdlib::http_cookies::{self, CookieOptions};
+use crate::stdlib::json::{intent_value_to_json, json_to_intent_value};
+use crate::stdlib::schedule::parse_duration;
+use rand::RngCore;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+
+type Result<T> = std::result::Result<T, IntentError>;
+type SessionData = serde_json::Map<String, serde_json::Value>;
+
+const DEFAULT_COOKIE: &str = "ntnt_session";
+const DEFAULT_DB_PATH: &str = "sessions.db";
+const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How often expired sessions are removed from a store
+const CLEANUP_INTERVAL_MS: i64 = 60_000;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS ntnt_sessions (
+    id TEXT PRIMARY KEY,
+    data TEXT NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS ntnt_sessions_expiry ON ntnt_sessions (expires_at);
+";
+
+/// A stored session: its data and when it expires (Unix milliseconds)
+#[derive(Debug, Clone)]
+struct Stored {
+    data: SessionData,
+    expires_at: i64,
+}
+
+enum Store {
+    /// Process-wide map shared by every memory-backed `sessions()`
+    Memory,
+    Sqlite(Mutex<Connection>),
+}
+
+static MEMORY_STORE: LazyLock<Mutex<HashMap<String, Stored>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Configuration created by one `sessions()` call
+struct SessionManager {
+    store: Store,
+    ttl: Duration,
+    cookie: String,
+    cookie_options: CookieOptions,
+    last_cleanup: Mutex<i64>,
+}
+
+/// A session attached to the request being handled
+struct ActiveSession {
+    manager: Arc<SessionManager>,
+    /// ID from the request cookie; None for a new session
+    id: Option<String>,
+    data: SessionData,
+    expires_at: i64,
+    changed: bool,
+    rotate: bool,
+    destroyed: bool,
+}
+
+static MANAGERS: LazyLock<Mutex<HashMap<u64, Arc<SessionManager>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static ACTIVE: LazyLock<Mutex<HashMap<u64, ActiveSession>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+fn db_error(e: rusqlite::Error) -> IntentError {
+    IntentError::RuntimeError(format!("Session store error: {}", e))
+}
+
+fn lock_error() -> IntentError {
+    IntentError::RuntimeError("Session registry lock poisoned".to_string())
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// 256 random bits, hex encoded
+fn new_session_id() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+impl Store {
+    fn open_sqlite(path: &str) -> Result<Self> {
+        let conn = if path == ":memory:" {
+            Connection::open_in_memory()
+        } else {
+            Connection::open(path)
+        }
+        .map_err(|e| {
+            IntentError::RuntimeError(format!("Failed to open session store '{}': {}", path, e))
+        })?;
+        // Workers (and other processes) may share the file
+        let _ = conn.execute_batch("PRAGMA journal_mode=WAL;");
+        conn.busy_timeout(Duration::from_secs(5))
+            .map_err(db_error)?;
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+        Ok(Store::Sqlite(Mutex::new(conn)))
+    }
+
+    /// Load an unexpired session
+    fn load(&self, id: &str, now: i64) -> Result<Option<Stored>> {
+        match self {
+            Store::Memory => {
+                let sessions = MEMORY_STORE.lock().map_err(|_| lock_error())?;
+                Ok(sessions
+                    .get(id)
+                    .filter(|stored| stored.expires_at > now)
+                    .cloned())
+            }
+            Store::Sqlite(conn) => {
+                let conn = conn.lock().map_err(|_| lock_error())?;
+                let row: Option<(String, i64)> = conn
+                    .query_row(
+                        "SELECT data, expires_at FROM ntnt_sessions WHERE id = ?1 AND expires_at > ?2",
+                        params![id, now],
+                        |row| Ok((row.get(0)?, row.get(1)?)),
+                    )
+                    .optional()
+                    .map_err(db_error)?;
+                Ok(row.map(|(data, expires_at)| Stored {
+                    data: serde_json::from_str(&data).unwrap_or_default(),
+                    expires_at,
+                }))
+            }
+        }
+    }
+
+    fn save(&self, id: &str, stored: &Stored) -> Result<()> {
+        match self {
+            Store::Memory => {
+                let mut sessions = MEMORY_STORE.lock().map_err(|_| lock_error())?;
+                sessions.insert(id.to_string(), stored.clone());
+                Ok(())
+            }
+            Store::Sqlite(conn) => {
+                let conn = conn.lock().map_err(|_| lock_error())?;
+                let data = serde_json::Value::Object(stored.data.clone()).to_string();
+                conn.execute(
+                    "INSERT INTO ntnt_sessions (id, data, expires_at) VALUES (?1, ?2, ?3)
+                     ON CONFLICT(id) DO UPDATE SET data = excluded.data, expires_at = excluded.expires_at",
+                    params![id, data, stored.expires_at],
+                )
+                .map_err(db_error)?;
+                Ok(())
+            }
+        }
+    }
+
+    fn delete(&self, id: &str) -> Result<()> {
+        match self {
+            Store::Memory => {
+                let mut sessions = MEMORY_STORE.lock().map_err(|_| lock_error())?;
+                sessions.remove(id);
+                Ok(())
+            }
+            Store::Sqlite(conn) => {
+                let conn = conn.lock().map_err(|_| lock_error())?;
+                conn.execute("DELETE FROM ntnt_sessions WHERE id = ?1", params![id])
+                    .map_err(db_error)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Remove expired sessions and return how many there were
+    fn remove_expired(&self, now: i64) -> Result<usize> {
+        match self {
+            Store::Memory => {
+                let mut sessions = MEMORY_STORE.lock().map_err(|_| lock_error())?;
+                let before = sessions.len();
+                sessions.retain(|_, stored| stored.expires_at > now);
+                Ok(before - sessions.len())
+            }
+            Store::Sqlite(conn) => {
+                let conn = conn.lock().map_err(|_| lock_error())?;
+                conn.execute(
+                    "DELETE FROM ntnt_sessions WHERE expires_at <= ?1",
+                    params![now],
+                )
+                .map_err(db_error)
+            }
+        }
+    }
+}
+
+impl SessionManager {
+    fn from_value(value: Option<&Value>) -> Result<Self> {
+        let empty = HashMap::new();
+        let map = match value {
+            None => &empty,
+            Some(Value::Map(map)) => map,
+            Some(_) => {
+                return Err(IntentError::TypeError(
+                    "sessions() options must be a map".to_string(),
+                ))
+            }
+        };
+
+        let string = |key: &str| match map.get(key) {
+            None => Ok(None),
+            Some(Value::String(s)) => Ok(Some(s.clone())),
+            Some(_) => Err(IntentError::TypeError(format!(
+                "sessions() option '{}' must be a string",
+                key
+            ))),
+        };
+        let mut cookie_map = HashMap::new();
+        for key in map.keys() {
+            match key.as_str() {
+                "store" | "path" | "ttl" | "cookie" => {}
+                "secure" | "same_site" | "domain" => {
+                    cookie_map.insert(key.clone(), map[key].clone());
+                }
+                _ => {
+                    return Err(IntentError::RuntimeError(format!(
+                        "Unknown sessions() option '{}'",
+                        key
+                    )))
+                }
+            }
+        }
+
+        let store = match string("store")?.as_deref() {
+            None | Some("memory") => {
+                if map.contains_key("path") {
+                    return Err(IntentError::RuntimeError(
+                        "sessions() option 'path' needs \"store\": \"sqlite\"".to_string(),
+                    ));
+                }
+                Store::Memory
+            }
+            Some("sqlite") => {
+                Store::open_sqlite(&string("path")?.unwrap_or_else(|| DEFAULT_DB_PATH.to_string()))?
+            }
+            Some(other) => {
+                return Err(IntentError::RuntimeError(format!(
+                    "sessions() store must be \"memory\" or \"sqlite\", got \"{}\"",
+                    other
+                )))
+            }
+        };
+        let ttl = match map.get("ttl") {
+            Some(ttl) => parse_duration(ttl, "sessions")?,
+            None => DEFAULT_TTL,
+        };
+        if ttl < Duration::from_secs(1) {
+            return Err(IntentError::RuntimeError(
+                "sessions() ttl must be at least one second".to_string(),
+            ));
+        }
+
+        // Session cookies are never readable from scripts
+        cookie_map
+            .entry("same_site".to_string())
+            .or_insert_with(|| Value::String("Lax".to_string()));
+        cookie_map.insert("http_only".to_string(), Value::Bool(true));
+        let mut cookie_options =
+            CookieOptions::from_value(Some(&Value::Map(cookie_map)), "sessions")?;
+        cookie_options.max_age = Some(ttl.as_secs() as i64);
+
+        Ok(SessionManager {
+            store,
+            ttl,
+            cookie: string("cookie")?.unwrap_or_else(|| DEFAULT_COOKIE.to_string()),
+            cookie_options,
+            last_cleanup: Mutex::new(0),
+        })
+    }
+
+    fn ttl_ms(&self) -> i64 {
+        self.ttl.as_millis() as i64
+    }
+
+    /// Remove expired sessions, at most once per CLEANUP_INTERVAL_MS
+    fn cleanup(&self, now: i64) -> Result<()> {
+        {
+            let mut last = self.last_cleanup.lock().map_err(|_| lock_error())?;
+            if now - *last < CLEANUP_INTERVAL_MS {
+                return Ok(());
+            }
+            *last = now;
+        }
+        self.store.remove_expired(now)?;
+        Ok(())
+    }
+}
+
+/// sessions(options?) -> Middleware
+/// Options: store ("memory" or "sqlite"), path (SQLite file), ttl,
+/// cookie (name), secure, same_site, domain
+pub fn sessions(options: Option<&Value>) -> Result<Value> {
+    let manager = SessionManager::from_value(options)?;
+    let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    MANAGERS
+        .lock()
+        .map_err(|_| lock_error())?
+        .insert(id, Arc::new(manager));
+
+    let mut map = HashMap::new();
+    map.insert("_sessions".to_string(), Value::Int(id as i64));
+    Ok(Value::Map(map))
+}
+
+/// The manager ID if `middleware` was created by sessions()
+pub fn middleware_id(middleware: &Value) -> Option<u64> {
+    match middleware {
+        Value::Map(map) => match map.get("_sessions") {
+            Some(Value::Int(id)) => Some(*id as u64),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// `req.session`: the session data plus the handle of the live session
+fn session_value(handle: u64, data: &SessionData) -> Value {
+    let mut map: HashMap<String, Value> = data
+        .iter()
+        .map(|(key, value)| (key.clone(), json_to_intent_value(value)))
+        .collect();
+    map.insert("_session_id".to_string(), Value::Int(handle as i64));
+    Value::Map(map)
+}
+
+/// Load the request's session and attach it as `req.session`.
+/// Returns the request and the handle to commit once the handler returns.
+pub fn attach(manager_id: u64, request: Value) -> Result<(Value, u64)> {
+    let manager = MANAGERS
+        .lock()
+        .map_err(|_| lock_error())?
+        .get(&manager_id)
+        .cloned()
+        .ok_or_else(|| IntentError::RuntimeError("Unknown sessions() middleware".to_string()))?;
+    let Value::Map(mut request) = request else {
+        return Err(IntentError::TypeError(
+            "Session middleware requires a request map".to_string(),
+        ));
+    };
+
+    let cookie = match request.get("cookies") {
+        Some(Value::Map(cookies)) => match cookies.get(&manager.cookie) {
+            Some(Value::String(id)) => Some(id.clone()),
+            _ => None,
+        },
+        _ => None,
+    };
+    let now = now_ms();
+    let loaded = match &cookie {
+        Some(id) => manager.store.load(id, now)?,
+        None => None,
+    };
+    let (id, data, expires_at) = match loaded {
+        Some(stored) => (cookie, stored.data, stored.expires_at),
+        // Unknown or expired IDs are never reused
+        None => (None, SessionData::new(), 0),
+    };
+
+    let handle = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    request.insert("session".to_string(), session_value(handle, &data));
+    ACTIVE.lock().map_err(|_| lock_error())?.insert(
+        handle,
+        ActiveSession {
+            manager,
+            id,
+            data,
+            expires_at,
+            changed: false,
+            rotate: false,
+            destroyed: false,
+        },
+    );
+    Ok((Value::Map(request), handle))
+}
+
+/// Save the session behind `handle` and add its cookie to `response`
+pub fn commit(handle: u64, response: &Value) -> Result<Value> {
+    let Some(session) = ACTIVE.lock().map_err(|_| lock_error())?.remove(&handle) else {
+        return Ok(response.clone());
+    };
+    let manager = &session.manager;
+    let store = &manager.store;
+    let now = now_ms();
+    manager.cleanup(now)?;
+
+    if session.destroyed {
+        let Some(id) = &session.id else {
+            return Ok(response.clone());
+        };
+        store.delete(id)?;
+        let mut options = manager.cookie_options.clone();
+        options.max_age = Some(0);
+        let cookie = http_cookies::format_set_cookie(&manager.cookie, "", &options);
+        return http_cookies::append_header(response, "set-cookie", cookie, "sessions");
+    }
+
+    // Sessions are extended once half their lifetime has passed
+    let refresh = session.id.is_some() && session.expires_at - now < manager.ttl_ms() / 2;
+    let store_new = session.id.is_none() && (session.changed || session.rotate);
+    if !(session.changed || session.rotate || refresh || store_new) {
+        return Ok(response.clone());
+    }
+
+    let id = match (&session.id, session.rotate) {
+        (Some(old), false) => old.clone(),
+        (old, _) => {
+            if let Some(old) = old {
+                store.delete(old)?;
+            }
+            new_session_id()
+        }
+    };
+    store.save(
+        &id,
+        &Stored {
+            data: session.data,
+            expires_at: now + manager.ttl_ms(),
+        },
+    )?;
+    let cookie = http_cookies::format_set_cookie(&manager.cookie, &id, &manager.cookie_options);
+    http_cookies::append_header(response, "set-cookie", cookie, "sessions")
+}
+
+/// Drop a session without saving it (e.g. after a WebSocket handshake)
+pub fn discard(handle: u64) {
+    if let Ok(mut active) = ACTIVE.lock() {
+        active.remove(&handle);
+    }
+}
+
+/// Whether `value` is a `req.session` map
+pub fn is_session(value: &Value) -> bool {
+    matches!(value, Value::Map(map) if matches!(map.get("_session_id"), Some(Value::Int(_))))
+}
+
+/// The error for code that changes `req.session` itself, a copy whose
+/// changes would be lost
+pub fn read_only_error() -> IntentError {
+    IntentError::RuntimeError(
+        "req.session is read-only; change the session with session_set(req, key, value) or session_delete(req, key)"
+            .to_string(),
+    )
+}
+
+/// Check that a middleware returned `request` with `req.session` as it got it
+pub fn check_unchanged(before: &Value, after: &Value) -> Result<()> {
+    let session = |request: &Value| match request {
+        Value::Map(map) => map.get("session").map(intent_value_to_json),
+        _ => None,
+    };
+    let Value::Map(map) = before else {
+        return Ok(());
+    };
+    if map.get("session").is_some_and(is_session) && session(before) != session(after) {
+        return Err(read_only_error());
+    }
+    Ok(())
+}
+
+/// Handle of the live session, from `req` or `req.session`
+fn handle_of(value: &Value, fn_name: &str) -> Result<u64> {
+    let missing = || {
+        IntentError::RuntimeError(format!(
+            "{}() requires a request with a session (add use_middleware(sessions()))",
+            fn_name
+        ))
+    };
+    let Value::Map(map) = value else {
+        return Err(missing());
+    };
+    let session = match map.get("session") {
+        Some(Value::Map(session)) => session,
+        _ => map,
+    };
+    match session.get("_session_id") {
+        Some(Value::Int(handle)) => Ok(*handle as u64),
+        _ => Err(missing()),
+    }
+}
+
+/// Run `f` on the live session of `req`
+fn with_session<T>(
+    req: &Value,
+    fn_name: &str,
+    f: impl FnOnce(&mut ActiveSession) -> T,
+) -> Result<T> {
+    let handle = handle_of(req, fn_name)?;
+    let mut active = ACTIVE.lock().map_err(|_| lock_error())?;
+    let session = active.get_mut(&handle).ok_or_else(|| {
+        IntentError::RuntimeError(format!(
+            "{}(): the session is only available while its request is handled",
+            fn_name
+        ))
+    })?;
+    Ok(f(session))
+}
+
+fn option(value: Option<Value>) -> Value {
+    match value {
+        Some(value) => Value::EnumValue {
+            enum_name: "Option".to_string(),
+            variant: "Some".to_string(),
+            values: vec![value],
+        },
+        None => Value::EnumValue {
+            enum_name: "Option".to_string(),
+            variant: "None".to_string(),
+            values: vec![],
+        },
+    }
+}
+
+/// session_get(req, key) -> Option<Value>
+pub fn session_get(req: &Value, key: &str) -> Result<Value> {
+    with_session(req, "session_get", |session| {
+        option(session.data.get(key).map(json_to_intent_value))
+    })
+}
+
+/// session_set(req, key, value) -> Unit
+pub fn session_set(req: &Value, key: &str, value: &Value) -> Result<Value> {
+    let value = intent_value_to_json(value);
+    with_session(req, "session_set", |session| {
+        session.data.insert(key.to_string(), value);
+        session.changed = true;
+        session.destroyed = false;
+        Value::Unit
+    })
+}
+
+/// session_delete(req, key) -> Bool
+pub fn session_delete(req: &Value, key: &str) -> Result<Value> {
+    with_session(req, "session_delete", |session| {
+        let removed = session.data.remove(key).is_some();
+        session.changed |= removed;
+        Value::Bool(removed)
+    })
+}
+
+/// session_all(req) -> Map
+pub fn session_all(req: &Value) -> Result<Value> {
+    with_session(req, "session_all", |session| {
+        Value::Map(
+            session
+                .data
+                .iter()
+                .map(|(key, value)| (key.clone(), json_to_intent_value(value)))
+                .collect(),
+        )
+    })
+}
+
+/// rotate_session(req) -> Unit
+/// Keeps the data but moves it to a new ID (call it when a user signs in)
+pub fn rotate_session(req: &Value) -> Result<Value> {
+    with_session(req, "rotate_session", |session| {
+        session.rotate = true;
+        Value::Unit
+    })
+}
+
+/// destroy_session(req) -> Unit
+/// Deletes the session from the store and expires its cookie
+pub fn destroy_session(req: &Value) -> Result<Value> {
+    with_session(req, "destroy_session", |session| {
+        session.data.clear();
+        session.changed = false;
+        session.rotate = false;
+        session.destroyed = true;
+        Value::Unit
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(pairs: &[(&str, Value)]) -> Value {
+        Value::Map(
+            pairs
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect(),
+        )
+    }
+
+    fn text(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    /// A request carrying `cookie` as its session cookie
+    fn request(cookie: Option<&str>) -> Value {
+        let mut cookies = HashMap::new();
+        if let Some(cookie) = cookie {
+            cookies.insert("sid".to_string(), text(cookie));
+        }
+        options(&[("cookies", Value::Map(cookies))])
+    }
+
+    /// The session ID in a response's Set-Cookie header
+    fn set_cookie(response: &Value) -> Option<String> {
+        let Value::Map(map) = response else {
+            return None;
+        };
+        let Some(Value::Map(headers)) = map.get("headers") else {
+            return None;
+        };
+        let header = http_cookies::header_values(headers.get("set-cookie")?).pop()?;
+        let value = header.split(';').next()?.strip_prefix("sid=")?;
+        Some(value.to_string())
+    }
+
+    fn handle(manager: &Value, cookie: Option<&str>) -> (Value, u64) {
+        attach(middleware_id(manager).unwrap(), request(cookie)).unwrap()
+    }
+
+    fn round_trip(store: &[(&str, Value)]) {
+        let response = options(&[("status", Value::Int(200))]);
+        let mut config = vec![("cookie", text("sid")), ("ttl", text("1h"))];
+        config.extend(store.iter().cloned());
+        let manager = sessions(Some(&options(&config))).unwrap();
+
+        // Untouched new sessions are not stored and set no cookie
+        let (req, h) = handle(&manager, None);
+        assert!(
+            matches!(session_get(&req, "user").unwrap(), Value::EnumValue { variant, .. } if variant == "None")
+        );
+        assert_eq!(set_cookie(&commit(h, &response).unwrap()), None);
+
+        // A write creates the session
+        let (req, h) = handle(&manager, None);
+        session_set(&req, "user", &Value::Int(7)).unwrap();
+        let id = set_cookie(&commit(h, &response).unwrap()).unwrap();
+        assert_eq!(id.len(), 64);
+
+        // The next request sees the data in req.session and session_get()
+        let (req, h) = handle(&manager, Some(&id));
+        let Value::Map(map) = &req else { panic!() };
+        let Some(Value::Map(session)) = map.get("session") else {
+            panic!()
+        };
+        assert!(matches!(session.get("user"), Some(Value::Int(7))));
+        assert!(
+            matches!(session_get(&req, "user").unwrap(), Value::EnumValue { values, .. } if matches!(values[..], [Value::Int(7)]))
+        );
+        // Reads do not re-send the cookie
+        assert_eq!(set_cookie(&commit(h, &response).unwrap()), None);
+        assert!(session_get(&req, "user").is_err());
+
+        // Rotation moves the data to a new ID and forgets the old one
+        let (req, h) = handle(&manager, Some(&id));
+        rotate_session(&req).unwrap();
+        let rotated = set_cookie(&commit(h, &response).unwrap()).unwrap();
+        assert_ne!(rotated, id);
+        let (req, h) = handle(&manager, Some(&id));
+        assert!(matches!(session_all(&req).unwrap(), Value::Map(m) if m.is_empty()));
+        discard(h);
+        let (req, h) = handle(&manager, Some(&rotated));
+        assert!(matches!(session_all(&req).unwrap(), Value::Map(m) if m.len() == 1));
+
+        // Destroying deletes it and expires the cookie
+        destroy_session(&req).unwrap();
+        let response = commit(h, &response).unwrap();
+        assert_eq!(set_cookie(&response).as_deref(), Some(""));
+        let (req, h) = handle(&manager, Some(&rotated));
+        assert!(matches!(session_all(&req).unwrap(), Value::Map(m) if m.is_empty()));
+        discard(h);
+    }
+
+    #[test]
+    fn test_middleware_cannot_replace_session() {
+        let manager = sessions(Some(&options(&[("cookie", text("sid"))]))).unwrap();
+        let (req, h) = handle(&manager, None);
+        session_set(&req, "user", &Value::Int(7)).unwrap();
+
+        // Passing the request on, or adding other keys, is fine
+        let Value::Map(mut edited) = req.clone() else {
+            panic!()
+        };
+        edited.insert("user".to_string(), Value::Int(7));
+        assert!(check_unchanged(&req, &Value::Map(edited.clone())).is_ok());
+
+        // A rebuilt req.session would never be saved
+        edited.insert(
+            "session".to_string(),
+            options(&[
+                ("_session_id", Value::Int(h as i64)),
+                ("user", Value::Int(8)),
+            ]),
+        );
+        let err = check_unchanged(&req, &Value::Map(edited)).unwrap_err();
+        assert!(err.to_string().contains("session_set"), "{}", err);
+        assert!(check_unchanged(&request(None), &req).is_ok());
+        discard(h);
+    }
+
+    #[test]
+    fn test_memory_store() {
+        round_trip(&[("store", text("memory"))]);
+    }
+
+    #[test]
+    fn test_sqlite_store() {
+        let path = std::env::temp_dir().join(format!("ntnt_sessions_{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        round_trip(&[
+            ("store", text("sqlite")),
+            ("path", text(&path.to_string_lossy())),
+        ]);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_expiry_and_cleanup() {
+        let store = Store::open_sqlite(":memory:").unwrap();
+        let now = now_ms();
+        let stored = |expires_at| Stored {
+            data: SessionData::new(),
+            expires_at,
+        };
+        store.save("old", &stored(now - 1)).unwrap();
+        store.save("new", &stored(now + 60_000)).unwrap();
+        assert!(store.load("old", now).unwrap().is_none());
+        assert!(store.load("new", now).unwrap().is_some());
+        assert_eq!(store.remove_expired(now).unwrap(), 1);
+        assert_eq!(store.remove_expired(now).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_session_options() {
+        assert!(sessions(Some(&options(&[("store", text("redis"))]))).is_err());
+        assert!(sessions(Some(&options(&[("ttl", text("soon"))]))).is_err());
+        assert!(sessions(Some(&options(&[("path", text("x.db"))]))).is_err());
+        assert!(sessions(Some(&options(&[("tll", text("1d"))]))).is_err());
+        assert!(sessions(Some(&options(&[("same_site", text("None"))]))).is_err());
+        assert!(sessions(None).is_ok());
+        assert!(session_get(&request(None), "x").is_err());
+    }
+}
@@ -0,0 +1,332 @@
+//! Streaming response bodies for `sse()` routes and `stream()` responses
+//!
+//! A streaming handler keeps running after the response headers are sent and
+//! writes the body piece by piece:
+//!
+//! ```ntnt
+//! import { send_event } from "std/http/server"
+//! import { sleep_ms } from "std/concurrent"
+//!
+//! fn progress(req, out) {
+//!     for i in 1..11 {
+//!         if !send_event(out, map { "percent": i * 10 }) {
+//!             return  // client went away
+//!         }
+//!         sleep_ms(500)
+//!     }
+//! }
+//!
+//! sse("/progress", progress)
+//! ```
+//!
+//! Each stream is a channel: `write()` and `send_event()` queue chunks on the
+//! sending half, and the async server turns the receiving half into the
+//! response body. When the client disconnects the receiver is dropped, so
+//! writes start returning false. The stream ends when the handler returns.
+
+use crate::error::IntentError;
+use crate::interpreter::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+type Result<T> = std::result::Result<T, IntentError>;
+
+/// How often an idle event stream sends a keep-alive comment
+pub const SSE_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// What a stream carries, which decides how the server sends it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamKind {
+    /// Raw chunks from a `stream()` response
+    Raw,
+    /// Server-Sent Events from an `sse()` route (kept alive while idle)
+    Events,
+}
+
+struct Entry {
+    kind: StreamKind,
+    /// Dropped when the handler finishes, which ends the body
+    tx: Option<mpsc::UnboundedSender<String>>,
+    /// Waiting to be picked up by the server
+    rx: Option<mpsc::UnboundedReceiver<String>>,
+}
+
+static STREAMS: LazyLock<Mutex<HashMap<u64, Entry>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+static NEXT_STREAM_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Open a new stream and return its ID
+pub fn open(kind: StreamKind) -> u64 {
+    let id = NEXT_STREAM_ID.fetch_add(1, Ordering::SeqCst);
+    let (tx, rx) = mpsc::unbounded_channel();
+    if let Ok(mut streams) = STREAMS.lock() {
+        streams.insert(
+            id,
+            Entry {
+                kind,
+                tx: Some(tx),
+                rx: Some(rx),
+            },
+        );
+    }
+    id
+}
+
+/// Hand the receiving half of a stream to the server
+pub fn take(id: u64) -> Option<(StreamKind, mpsc::UnboundedReceiver<String>)> {
+    let mut streams = STREAMS.lock().ok()?;
+    let entry = streams.get_mut(&id)?;
+    let kind = entry.kind;
+    let rx = entry.rx.take()?;
+    if entry.tx.is_none() {
+        streams.remove(&id);
+    }
+    Some((kind, rx))
+}
+
+/// Mark a stream as complete once its handler has returned
+pub fn finish(id: u64) {
+    if let Ok(mut streams) = STREAMS.lock() {
+        if let Some(entry) = streams.get_mut(&id) {
+            entry.tx = None;
+            if entry.rx.is_none() {
+                streams.remove(&id);
+            }
+        }
+    }
+}
+
+/// Finish a stream and return everything written to it as one body
+/// (used by the synchronous server, which cannot stream)
+pub fn collect(id: u64) -> String {
+    finish(id);
+    let mut body = String::new();
+    if let Some((_, mut rx)) = take(id) {
+        while let Ok(chunk) = rx.try_recv() {
+            body.push_str(&chunk);
+        }
+    }
+    body
+}
+
+/// End every open stream (used on server shutdown)
+pub fn close_all() {
+    if let Ok(mut streams) = STREAMS.lock() {
+        for entry in streams.values_mut() {
+            entry.tx = None;
+        }
+        streams.retain(|_, entry| entry.rx.is_some());
+    }
+}
+
+/// Build the stream value passed to streaming handlers
+pub fn stream_value(id: u64) -> Value {
+    let mut map = HashMap::new();
+    map.insert("_stream_id".to_string(), Value::Int(id as i64));
+    Value::Map(map)
+}
+
+/// Stream ID of a stream value
+fn stream_id(value: &Value, fn_name: &str) -> Result<u64> {
+    match value {
+        Value::Map(map) => match map.get("_stream_id") {
+            Some(Value::Int(id)) => Ok(*id as u64),
+            _ => Err(IntentError::TypeError(format!(
+                "{}() requires a response stream",
+                fn_name
+            ))),
+        },
+        _ => Err(IntentError::TypeError(format!(
+            "{}() requires a response stream",
+            fn_name
+        ))),
+    }
+}
+
+/// Response returned for sse() routes before their handler runs
+pub fn sse_response() -> Value {
+    let mut headers = HashMap::new();
+    headers.insert(
+        "content-type".to_string(),
+        Value::String("text/event-stream".to_string()),
+    );
+    headers.insert(
+        "cache-control".to_string(),
+        Value::String("no-cache".to_string()),
+    );
+    // Ask reverse proxies (nginx) not to buffer the stream
+    headers.insert(
+        "x-accel-buffering".to_string(),
+        Value::String("no".to_string()),
+    );
+
+    let mut response = HashMap::new();
+    response.insert("status".to_string(), Value::Int(200));
+    response.insert("headers".to_string(), Value::Map(headers));
+    response.insert("body".to_string(), Value::String(String::new()));
+    Value::Map(response)
+}
+
+/// Strings are written as-is, anything else as JSON
+fn chunk_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => crate::stdlib::json::intent_value_to_json(other).to_string(),
+    }
+}
+
+/// Format one Server-Sent Event. Multi-line data becomes several `data:`
+/// lines; line breaks are removed from the single-line fields.
+pub fn format_event(
+    data: &str,
+    event: Option<&str>,
+    id: Option<&str>,
+    retry: Option<i64>,
+) -> String {
+    let single_line = |s: &str| s.replace(['\r', '\n'], "");
+    let mut frame = String::new();
+    if let Some(event) = event {
+        frame.push_str(&format!("event: {}\n", single_line(event)));
+    }
+    if let Some(id) = id {
+        frame.push_str(&format!("id: {}\n", single_line(id)));
+    }
+    if let Some(retry) = retry {
+        frame.push_str(&format!("retry: {}\n", retry));
+    }
+    for line in data.split('\n') {
+        frame.push_str(&format!("data: {}\n", line.trim_end_matches('\r')));
+    }
+    frame.push('\n');
+    frame
+}
+
+/// Queue a chunk. Returns false once the client has gone or the stream ended.
+fn queue(id: u64, chunk: String) -> Result<bool> {
+    let streams = STREAMS
+        .lock()
+        .map_err(|e| IntentError::RuntimeError(format!("Failed to lock stream registry: {}", e)))?;
+    Ok(streams
+        .get(&id)
+        .and_then(|entry| entry.tx.as_ref())
+        .map(|tx| tx.send(chunk).is_ok())
+        .unwrap_or(false))
+}
+
+/// write(out, chunk) -> Bool
+pub fn write(out: &Value, chunk: &Value) -> Result<Value> {
+    let id = stream_id(out, "write")?;
+    Ok(Value::Bool(queue(id, chunk_text(chunk))?))
+}
+
+/// send_event(out, data, options?) -> Bool
+/// Options: "event" (event name), "id" (last event ID), "retry" (reconnect ms)
+pub fn send_event(out: &Value, data: &Value, options: Option<&Value>) -> Result<Value> {
+    let id = stream_id(out, "send_event")?;
+    let options = match options {
+        None => HashMap::new(),
+        Some(Value::Map(map)) => map.clone(),
+        Some(_) => {
+            return Err(IntentError::TypeError(
+                "send_event() options must be a map".to_string(),
+            ))
+        }
+    };
+    let mut event = None;
+    let mut event_id = None;
+    let mut retry = None;
+    for (key, value) in &options {
+        match (key.as_str(), value) {
+            ("event", Value::String(s)) => event = Some(s.clone()),
+            ("id", Value::String(s)) => event_id = Some(s.clone()),
+            ("id", Value::Int(n)) => event_id = Some(n.to_string()),
+            ("retry", Value::Int(ms)) => retry = Some(*ms),
+            ("event" | "id" | "retry", _) => {
+                return Err(IntentError::TypeError(format!(
+                    "send_event() option '{}' has the wrong type",
+                    key
+                )))
+            }
+            _ => {
+                return Err(IntentError::RuntimeError(format!(
+                    "Unknown send_event() option '{}'",
+                    key
+                )))
+            }
+        }
+    }
+    let frame = format_event(
+        &chunk_text(data),
+        event.as_deref(),
+        event_id.as_deref(),
+        retry,
+    );
+    Ok(Value::Bool(queue(id, frame)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn test_format_event() {
+        assert_eq!(format_event("hi", None, None, None), "data: hi\n\n");
+        assert_eq!(
+            format_event("a\nb", Some("up\ndate"), Some("7"), Some(3000)),
+            "event: update\nid: 7\nretry: 3000\ndata: a\ndata: b\n\n"
+        );
+    }
+
+    #[test]
+    fn test_stream_lifecycle() {
+        let id = open(StreamKind::Raw);
+        let out = stream_value(id);
+        assert!(matches!(
+            write(&out, &text("one ")).unwrap(),
+            Value::Bool(true)
+        ));
+
+        // The server picks up the receiver while the handler is still writing
+        let (kind, mut rx) = take(id).unwrap();
+        assert_eq!(kind, StreamKind::Raw);
+        let mut options = HashMap::new();
+        options.insert("event".to_string(), text("tick"));
+        send_event(&out, &Value::Int(2), Some(&Value::Map(options))).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), "one ");
+        assert_eq!(rx.try_recv().unwrap(), "event: tick\ndata: 2\n\n");
+
+        // Writes fail once the client is gone, and the entry is removed on finish
+        drop(rx);
+        assert!(matches!(
+            write(&out, &text("x")).unwrap(),
+            Value::Bool(false)
+        ));
+        finish(id);
+        assert!(take(id).is_none());
+        assert!(matches!(
+            write(&out, &text("x")).unwrap(),
+            Value::Bool(false)
+        ));
+    }
+
+    #[test]
+    fn test_collect_and_options() {
+        let id = open(StreamKind::Events);
+        let out = stream_value(id);
+        send_event(&out, &text("a"), None).unwrap();
+        write(&out, &text(": comment\n\n")).unwrap();
+        assert_eq!(collect(id), "data: a\n\n: comment\n\n");
+        assert!(take(id).is_none());
+
+        let mut options = HashMap::new();
+        options.insert("evnet".to_string(), text("x"));
+        assert!(send_event(&out, &text("a"), Some(&Value::Map(options))).is_err());
+        assert!(write(&Value::Int(1), &text("a")).is_err());
+    }
+}
@@ -0,0 +1,340 @@
+//! HTTPS for the async server
+//!
+//! `listen()` serves HTTPS when given a certificate and private key in PEM
+//! format, and can also listen on a plain-HTTP port that redirects to it:
+//!
+//! ```ntnt
+//! listen(443, map {
+//!     "tls_cert": "certs/fullchain.pem",
+//!     "tls_key": "certs/privkey.pem",
+//!     "redirect_http": 80
+//! })
+//! ```
+//!
+//! TLS is handled by rustls (with the ring provider) and supports HTTP/2 via
+//! ALPN. The certificate files are checked every `RELOAD_INTERVAL` and
+//! reloaded when they change, so renewed certificates are picked up without
+//! a restart; a pair that fails to load (e.g. a key that doesn't match the
+//! certificate yet) is reported and the current certificate kept.
+
+use crate::error::IntentError;
+use axum::extract::ConnectInfo;
+use axum::http::{header, HeaderMap, Request, StatusCode, Uri};
+use axum::response::{IntoResponse, Redirect};
+use hyper::body::Incoming;
+use hyper::service::service_fn;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use hyper_util::server::graceful::GracefulShutdown;
+use rustls::crypto::CryptoProvider;
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use rustls::ServerConfig;
+use std::future::Future;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tower::Service;
+
+type Result<T> = std::result::Result<T, IntentError>;
+
+/// How often the certificate files are checked for changes
+const RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Certificate and key files from `listen()` options
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlsOptions {
+    /// PEM certificate chain, end-entity certificate first
+    pub cert: PathBuf,
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1)
+    pub key: PathBuf,
+}
+
+/// Request extension marking requests that arrived over TLS
+#[derive(Debug, Clone, Copy)]
+pub struct Https;
+
+/// Size and modification time of both files, to notice when they change
+type FileStamps = Option<[(SystemTime, u64); 2]>;
+
+/// Serves the current certificate and swaps in a new one when the files change
+#[derive(Debug)]
+pub struct ReloadingCert {
+    options: TlsOptions,
+    provider: Arc<CryptoProvider>,
+    current: RwLock<Arc<CertifiedKey>>,
+    stamps: Mutex<FileStamps>,
+}
+
+impl ReloadingCert {
+    /// Load the certificate and key, failing if either can't be used
+    pub fn load(options: &TlsOptions) -> Result<Arc<Self>> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let stamps = file_stamps(options);
+        let key = load_certified_key(options, &provider)?;
+        Ok(Arc::new(ReloadingCert {
+            options: options.clone(),
+            provider,
+            current: RwLock::new(Arc::new(key)),
+            stamps: Mutex::new(stamps),
+        }))
+    }
+
+    /// The certificate handshakes currently use
+    pub fn current(&self) -> Arc<CertifiedKey> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Reload the certificate if either file changed since the last attempt.
+    /// Returns whether a new certificate was loaded.
+    pub fn reload_if_changed(&self) -> Result<bool> {
+        let stamps = file_stamps(&self.options);
+        {
+            let mut last = self.stamps.lock().unwrap();
+            if *last == stamps {
+                return Ok(false);
+            }
+            *last = stamps;
+        }
+        let key = load_certified_key(&self.options, &self.provider)?;
+        *self.current.write().unwrap() = Arc::new(key);
+        Ok(true)
+    }
+
+    /// Check the files for changes every `RELOAD_INTERVAL` on the current runtime
+    pub fn watch(self: &Arc<Self>) {
+        let cert = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                match cert.reload_if_changed() {
+                    Ok(true) => println!(
+                        "🔐 Reloaded TLS certificate from {}",
+                        cert.options.cert.display()
+                    ),
+                    Ok(false) => {}
+                    Err(e) => eprintln!("Keeping the current TLS certificate: {}", e),
+                }
+            }
+        });
+    }
+
+    /// TLS acceptor that always uses the current certificate
+    pub fn acceptor(self: &Arc<Self>) -> Result<TlsAcceptor> {
+        let mut config = ServerConfig::builder_with_provider(self.provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| IntentError::RuntimeError(format!("TLS configuration error: {}", e)))?
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone());
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+}
+
+impl ResolvesServerCert for ReloadingCert {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current())
+    }
+}
+
+fn file_stamps(options: &TlsOptions) -> FileStamps {
+    let stamp = |path: &Path| {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    };
+    Some([stamp(&options.cert)?, stamp(&options.key)?])
+}
+
+/// Read a PEM certificate chain and private key and check that they match
+fn load_certified_key(options: &TlsOptions, provider: &CryptoProvider) -> Result<CertifiedKey> {
+    let open = |path: &Path| {
+        std::fs::File::open(path).map(BufReader::new).map_err(|e| {
+            IntentError::RuntimeError(format!("Cannot read {}: {}", path.display(), e))
+        })
+    };
+
+    let certs = rustls_pemfile::certs(&mut open(&options.cert)?)
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| {
+            IntentError::RuntimeError(format!(
+                "Invalid certificate in {}: {}",
+                options.cert.display(),
+                e
+            ))
+        })?;
+    if certs.is_empty() {
+        return Err(IntentError::RuntimeError(format!(
+            "No certificates found in {}",
+            options.cert.display()
+        )));
+    }
+
+    let key = rustls_pemfile::private_key(&mut open(&options.key)?)
+        .map_err(|e| {
+            IntentError::RuntimeError(format!(
+                "Invalid private key in {}: {}",
+                options.key.display(),
+                e
+            ))
+        })?
+        .ok_or_else(|| {
+            IntentError::RuntimeError(format!("No private key found in {}", options.key.display()))
+        })?;
+
+    CertifiedKey::from_der(certs, key, provider).map_err(|e| {
+        IntentError::RuntimeError(format!(
+            "Cannot use {} with {}: {}",
+            options.key.display(),
+            options.cert.display(),
+            e
+        ))
+    })
+}
+
+/// Serve `app` over TLS until `shutdown` completes, then wait for open
+/// connections to finish
+pub async fn serve(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    app: axum::Router,
+    shutdown: impl Future<Output = ()>,
+) {
+    let graceful = GracefulShutdown::new();
+    tokio::pin!(shutdown);
+
+    loop {
+        let (stream, peer) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    eprintln!("Failed to accept connection: {}", e);
+                    continue;
+                }
+            },
+            _ = &mut shutdown => break,
+        };
+
+        let acceptor = acceptor.clone();
+        // Peer address for handlers, as with axum's into_make_service_with_connect_info
+        let app = app.clone();
+        let service = service_fn(move |mut request: Request<Incoming>| {
+            request.extensions_mut().insert(ConnectInfo(peer));
+            app.clone().call(request)
+        });
+        let watcher = graceful.watcher();
+        tokio::spawn(async move {
+            // Failed handshakes (plain HTTP, untrusted certificate) just close
+            let Ok(stream) = acceptor.accept(stream).await else {
+                return;
+            };
+            let builder = auto::Builder::new(TokioExecutor::new());
+            let connection = builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+            let _ = watcher.watch(connection).await;
+        });
+    }
+
+    drop(listener);
+    graceful.shutdown().await;
+}
+
+/// Plain-HTTP app that redirects every request to the same URL over HTTPS
+pub fn redirect_router(https_port: u16) -> axum::Router {
+    axum::Router::new().fallback(move |headers: HeaderMap, uri: Uri| async move {
+        let host = headers.get(header::HOST).and_then(|h| h.to_str().ok());
+        match host {
+            Some(host) => {
+                let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+                Redirect::permanent(&redirect_location(host, https_port, path)).into_response()
+            }
+            None => (StatusCode::BAD_REQUEST, "Missing Host header").into_response(),
+        }
+    })
+}
+
+/// `https://` URL for `path` on `host` (whose port, if any, is replaced)
+fn redirect_location(host: &str, https_port: u16, path: &str) -> String {
+    let hostname = match host.strip_prefix('[') {
+        // IPv6 literal: [::1]:8080
+        Some(rest) => match rest.split_once(']') {
+            Some((address, _)) => format!("[{}]", address),
+            None => host.to_string(),
+        },
+        None => host.split(':').next().unwrap_or(host).to_string(),
+    };
+    if https_port == 443 {
+        format!("https://{}{}", hostname, path)
+    } else {
+        format!("https://{}:{}{}", hostname, https_port, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_self_signed(dir: &Path, name: &str) -> TlsOptions {
+        let cert = rcgen::generate_simple_self_signed(vec![name.to_string()]).unwrap();
+        let options = TlsOptions {
+            cert: dir.join("cert.pem"),
+            key: dir.join("key.pem"),
+        };
+        std::fs::write(&options.cert, cert.cert.pem()).unwrap();
+        std::fs::write(&options.key, cert.key_pair.serialize_pem()).unwrap();
+        options
+    }
+
+    #[test]
+    fn test_certificate_reloads_when_files_change() {
+        let dir = std::env::temp_dir().join(format!("ntnt_tls_reload_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let options = write_self_signed(&dir, "first.test");
+        let cert = ReloadingCert::load(&options).unwrap();
+        let first = cert.current().cert[0].clone();
+        assert!(!cert.reload_if_changed().unwrap());
+
+        write_self_signed(&dir, "second.test");
+        assert!(cert.reload_if_changed().unwrap());
+        assert_ne!(cert.current().cert[0], first);
+
+        // A key that doesn't match is rejected and the current pair kept
+        let other = rcgen::KeyPair::generate().unwrap();
+        std::fs::write(&options.key, other.serialize_pem()).unwrap();
+        let current = cert.current().cert[0].clone();
+        assert!(cert.reload_if_changed().is_err());
+        assert_eq!(cert.current().cert[0], current);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let missing = TlsOptions {
+            cert: PathBuf::from("/nonexistent/cert.pem"),
+            key: PathBuf::from("/nonexistent/key.pem"),
+        };
+        let err = ReloadingCert::load(&missing).unwrap_err().to_string();
+        assert!(err.contains("Cannot read /nonexistent/cert.pem"), "{}", err);
+    }
+
+    #[test]
+    fn test_redirect_location() {
+        assert_eq!(
+            redirect_location("example.com", 443, "/a?b=1"),
+            "https://example.com/a?b=1"
+        );
+        assert_eq!(
+            redirect_location("example.com:8080", 8443, "/"),
+            "https://example.com:8443/"
+        );
+        assert_eq!(
+            redirect_location("[::1]:8080", 8443, "/x"),
+            "https://[::1]:8443/x"
+        );
+    }
+}
@@ -0,0 +1,375 @@
+//! std/http/ws module - WebSocket connections
+//!
+//! `ws(pattern, handler)` registers a WebSocket route. When a client connects,
+//! the handler is called with a connection value (the upgrade request plus a
+//! `_ws_id` key) and sets up callbacks for the connection:
+//!
+//! ```ntnt
+//! import { on_message, send, join, broadcast } from "std/http/ws"
+//!
+//! fn relay(conn, msg) {
+//!     broadcast(conn.params["room"], msg)
+//! }
+//!
+//! fn chat(conn) {
+//!     join(conn, conn.params["room"])
+//!     send(conn, "welcome")
+//!     on_message(conn, relay)
+//! }
+//!
+//! ws("/chat/{room}", chat)
+//! ```
+//!
+//! Connections and rooms live in a process-wide registry, so `send` and
+//! `broadcast` reach clients served by any interpreter of a worker pool.
+//! Frames are queued on the connection's socket task, which writes them out.
+
+use crate::error::IntentError;
+use crate::interpreter::Value;
+use crate::stdlib::http_bridge::BridgeRequest;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use tokio::sync::mpsc;
+
+type Result<T> = std::result::Result<T, IntentError>;
+
+/// Names of the functions that register callbacks; the interpreter
+/// intercepts calls to them
+pub(crate) const INTERPRETER_FUNCTIONS: [&str; 2] = ["on_message", "on_close"];
+
+/// A frame queued for a connection's socket
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outgoing {
+    Text(String),
+    Close,
+}
+
+#[derive(Default)]
+struct Registry {
+    connections: HashMap<u64, mpsc::UnboundedSender<Outgoing>>,
+    /// Room name -> connection IDs
+    rooms: HashMap<String, BTreeSet<u64>>,
+}
+
+static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(|| Mutex::new(Registry::default()));
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+fn lock_error<E: std::fmt::Display>(e: E) -> IntentError {
+    IntentError::RuntimeError(format!("Failed to lock WebSocket registry: {}", e))
+}
+
+/// Register a new connection; frames sent to it arrive on the returned receiver
+pub fn register() -> (u64, mpsc::UnboundedReceiver<Outgoing>) {
+    let id = NEXT_CONNECTION_ID.fetch_add(1, Ordering::SeqCst);
+    let (tx, rx) = mpsc::unbounded_channel();
+    if let Ok(mut registry) = REGISTRY.lock() {
+        registry.connections.insert(id, tx);
+    }
+    (id, rx)
+}
+
+/// Forget a connection and remove it from all rooms
+pub fn unregister(id: u64) {
+    if let Ok(mut registry) = REGISTRY.lock() {
+        registry.connections.remove(&id);
+        registry.rooms.retain(|_, members| {
+            members.remove(&id);
+            !members.is_empty()
+        });
+    }
+}
+
+/// Ask every open connection to close (used on server shutdown)
+pub fn close_all() {
+    if let Ok(registry) = REGISTRY.lock() {
+        for tx in registry.connections.values() {
+            let _ = tx.send(Outgoing::Close);
+        }
+    }
+}
+
+/// Build the connection value passed to ws() handlers and callbacks
+pub fn connection_value(id: u64, request: &BridgeRequest) -> Value {
+    let mut conn = match request.to_value() {
+        Value::Map(map) => map,
+        _ => HashMap::new(),
+    };
+    conn.insert("_ws_id".to_string(), Value::Int(id as i64));
+    Value::Map(conn)
+}
+
+/// Connection ID of a connection value
+pub fn connection_id(value: &Value, fn_name: &str) -> Result<u64> {
+    match value {
+        Value::Map(map) => match map.get("_ws_id") {
+            Some(Value::Int(id)) => Ok(*id as u64),
+            _ => Err(IntentError::TypeError(format!(
+                "{}() requires a WebSocket connection",
+                fn_name
+            ))),
+        },
+        _ => Err(IntentError::TypeError(format!(
+            "{}() requires a WebSocket connection",
+            fn_name
+        ))),
+    }
+}
+
+/// Strings are sent as-is, anything else as JSON
+fn message_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => crate::stdlib::json::intent_value_to_json(other).to_string(),
+    }
+}
+
+fn room_name<'a>(value: &'a Value, fn_name: &str) -> Result<&'a str> {
+    match value {
+        Value::String(room) => Ok(room),
+        _ => Err(IntentError::TypeError(format!(
+            "{}() requires a room name string",
+            fn_name
+        ))),
+    }
+}
+
+/// Queue a frame for one connection. Returns false if it is no longer open.
+fn queue(id: u64, frame: Outgoing) -> Result<bool> {
+    let registry = REGISTRY.lock().map_err(lock_error)?;
+    Ok(registry
+        .connections
+        .get(&id)
+        .map(|tx| tx.send(frame).is_ok())
+        .unwrap_or(false))
+}
+
+/// send(conn, message) -> Bool
+fn ws_send(conn: &Value, message: &Value) -> Result<Value> {
+    let id = connection_id(conn, "send")?;
+    Ok(Value::Bool(queue(
+        id,
+        Outgoing::Text(message_text(message)),
+    )?))
+}
+
+/// close(conn) -> Unit
+fn ws_close(conn: &Value) -> Result<Value> {
+    let id = connection_id(conn, "close")?;
+    queue(id, Outgoing::Close)?;
+    Ok(Value::Unit)
+}
+
+/// join(conn, room) -> Unit
+fn ws_join(conn: &Value, room: &str) -> Result<Value> {
+    let id = connection_id(conn, "join")?;
+    let mut registry = REGISTRY.lock().map_err(lock_error)?;
+    if registry.connections.contains_key(&id) {
+        registry
+            .rooms
+            .entry(room.to_string())
+            .or_default()
+            .insert(id);
+    }
+    Ok(Value::Unit)
+}
+
+/// leave(conn, room) -> Unit
+fn ws_leave(conn: &Value, room: &str) -> Result<Value> {
+    let id = connection_id(conn, "leave")?;
+    let mut registry = REGISTRY.lock().map_err(lock_error)?;
+    if let Some(members) = registry.rooms.get_mut(room) {
+        members.remove(&id);
+        if members.is_empty() {
+            registry.rooms.remove(room);
+        }
+    }
+    Ok(Value::Unit)
+}
+
+/// broadcast(room, message, except?) -> Int
+/// Sends to every member of the room (except one connection, if given) and
+/// returns how many connections the message was queued for
+fn ws_broadcast(room: &str, message: &Value, except: Option<u64>) -> Result<Value> {
+    let text = message_text(message);
+    let registry = REGISTRY.lock().map_err(lock_error)?;
+    let mut delivered = 0;
+    if let Some(members) = registry.rooms.get(room) {
+        for id in members.iter().filter(|id| Some(**id) != except) {
+            if let Some(tx) = registry.connections.get(id) {
+                if tx.send(Outgoing::Text(text.clone())).is_ok() {
+                    delivered += 1;
+                }
+            }
+        }
+    }
+    Ok(Value::Int(delivered))
+}
+
+/// room_size(room) -> Int
+fn ws_room_size(room: &str) -> Result<Value> {
+    let registry = REGISTRY.lock().map_err(lock_error)?;
+    Ok(Value::Int(
+        registry.rooms.get(room).map(|m| m.len()).unwrap_or(0) as i64,
+    ))
+}
+
+/// Initialize the std/http/ws module
+pub fn init() -> HashMap<String, Value> {
+    let mut module = HashMap::new();
+
+    // on_message(conn, fn) / on_close(conn, fn) - intercepted by the interpreter
+    for name in INTERPRETER_FUNCTIONS {
+        module.insert(
+            name.to_string(),
+            Value::NativeFunction {
+                name: name.to_string(),
+                arity: 2,
+                func: |_args| {
+                    Err(IntentError::RuntimeError(
+                        "WebSocket callbacks must be registered by calling on_message() or on_close() directly".to_string(),
+                    ))
+                },
+            },
+        );
+    }
+
+    // send(conn, message) -> Bool
+    module.insert(
+        "send".to_string(),
+        Value::NativeFunction {
+            name: "send".to_string(),
+            arity: 2,
+            func: |args| ws_send(&args[0], &args[1]),
+        },
+    );
+
+    // close(conn) -> Unit
+    module.insert(
+        "close".to_string(),
+        Value::NativeFunction {
+            name: "close".to_string(),
+            arity: 1,
+            func: |args| ws_close(&args[0]),
+        },
+    );
+
+    // join(conn, room) -> Unit
+    module.insert(
+        "join".to_string(),
+        Value::NativeFunction {
+            name: "join".to_string(),
+            arity: 2,
+            func: |args| ws_join(&args[0], room_name(&args[1], "join")?),
+        },
+    );
+
+    // leave(conn, room) -> Unit
+    module.insert(
+        "leave".to_string(),
+        Value::NativeFunction {
+            name: "leave".to_string(),
+            arity: 2,
+            func: |args| ws_leave(&args[0], room_name(&args[1], "leave")?),
+        },
+    );
+
+    // broadcast(room, message, except?) -> Int
+    module.insert(
+        "broadcast".to_string(),
+        Value::NativeFunction {
+            name: "broadcast".to_string(),
+            arity: 0,
+            func: |args| {
+                let except = match args {
+                    [_, _] => None,
+                    [_, _, conn] => Some(connection_id(conn, "broadcast")?),
+                    _ => {
+                        return Err(IntentError::ArityMismatch {
+                            name: "broadcast".to_string(),
+                            expected: 2,
+                            got: args.len(),
+                        })
+                    }
+                };
+                ws_broadcast(room_name(&args[0], "broadcast")?, &args[1], except)
+            },
+        },
+    );
+
+    // room_size(room) -> Int
+    module.insert(
+        "room_size".to_string(),
+        Value::NativeFunction {
+            name: "room_size".to_string(),
+            arity: 1,
+            func: |args| ws_room_size(room_name(&args[0], "room_size")?),
+        },
+    );
+
+    module
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conn(id: u64) -> Value {
+        let mut map = HashMap::new();
+        map.insert("_ws_id".to_string(), Value::Int(id as i64));
+        Value::Map(map)
+    }
+
+    fn drain(rx: &mut mpsc::UnboundedReceiver<Outgoing>) -> Vec<Outgoing> {
+        let mut frames = Vec::new();
+        while let Ok(frame) = rx.try_recv() {
+            frames.push(frame);
+        }
+        frames
+    }
+
+    #[test]
+    fn test_rooms_and_broadcast() {
+        let (a, mut rx_a) = register();
+        let (b, mut rx_b) = register();
+        let (c, mut rx_c) = register();
+        let room = format!("room-{}", a);
+        ws_join(&conn(a), &room).unwrap();
+        ws_join(&conn(b), &room).unwrap();
+
+        let sent = ws_broadcast(&room, &Value::String("hi".to_string()), Some(a)).unwrap();
+        assert!(matches!(sent, Value::Int(1)));
+        assert!(drain(&mut rx_a).is_empty());
+        assert_eq!(drain(&mut rx_b), vec![Outgoing::Text("hi".to_string())]);
+        assert!(drain(&mut rx_c).is_empty());
+
+        // Non-string messages are sent as JSON
+        let mut data = HashMap::new();
+        data.insert("n".to_string(), Value::Int(1));
+        ws_send(&conn(c), &Value::Map(data)).unwrap();
+        assert_eq!(
+            drain(&mut rx_c),
+            vec![Outgoing::Text("{\"n\":1}".to_string())]
+        );
+
+        ws_leave(&conn(b), &room).unwrap();
+        unregister(a);
+        assert!(matches!(ws_room_size(&room).unwrap(), Value::Int(0)));
+        assert!(matches!(
+            ws_send(&conn(a), &Value::String("gone".to_string())).unwrap(),
+            Value::Bool(false)
+        ));
+
+        ws_close(&conn(c)).unwrap();
+        assert_eq!(drain(&mut rx_c), vec![Outgoing::Close]);
+        unregister(b);
+        unregister(c);
+    }
+
+    #[test]
+    fn test_connection_id_requires_connection() {
+        assert_eq!(connection_id(&conn(7), "send").unwrap(), 7);
+        assert!(connection_id(&Value::Int(7), "send").is_err());
+        assert!(connection_id(&Value::Map(HashMap::new()), "send").is_err());
+    }
+}
@@ -0,0 +1,1252 @@
+//! std/jobs module - Persistent background job queue
+//!
+//! Jobs are stored in SQLite (bundled — no external services needed), so
+//! they survive restarts. Workers run on the interpreter thread: under
+//! `listen()` the queue is polled alongside requests, and `run_jobs()`
+//! drains every due job on demand.
+//!
+//! ```ntnt
+//! import { configure, enqueue, enqueue_at, worker, run_jobs } from "std/jobs"
+//!
+//! fn send_welcome(args) { ... }
+//!
+//! configure(map { "db": "jobs.db" })
+//! worker("send_welcome", send_welcome)
+//!
+//! enqueue("send_welcome", map { "user_id": 42 }, map { "priority": "high" })
+//! enqueue_at("send_welcome", map { "user_id": 7 }, now() + 3600)
+//! ```
+//!
+//! A failed job is retried with exponential backoff (`backoff * 2^(attempt-1)`).
+//! Once it has used `max_attempts` it moves to the dead-letter table, where
+//! `retry_job()` or `ntnt jobs retry <id>` can put it back on the queue.
+//!
+//! A job still marked running after its lease (`configure(map { "lease": ... })`,
+//! default 5 minutes) is assumed lost with a crashed or killed process. The next
+//! claim records that as a failed attempt, so the job is retried or dead-lettered.
+//! While a worker runs, a background thread renews the lease every third of it,
+//! so only a dead process lets it expire. Should a run finish after losing its
+//! job anyway, its result is ignored.
+
+use crate::error::IntentError;
+use crate::interpreter::Value;
+use crate::stdlib::json::{intent_value_to_json, json_to_intent_value};
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{mpsc, LazyLock, Mutex};
+
+type Result<T> = std::result::Result<T, IntentError>;
+
+/// Functions that register or run user callbacks, handled by the interpreter
+pub(crate) const INTERPRETER_FUNCTIONS: &[&str] = &["worker", "run_jobs"];
+
+/// Environment variable overriding the default queue database
+pub const JOBS_DB_ENV: &str = "NTNT_JOBS_DB";
+const DEFAULT_DB_PATH: &str = "ntnt_jobs.db";
+
+/// Most jobs a worker poll runs before yielding to other work (e.g. requests)
+pub(crate) const JOBS_PER_POLL: usize = 100;
+
+const DEFAULT_MAX_ATTEMPTS: i64 = 3;
+const DEFAULT_BACKOFF_MS: i64 = 1000;
+const DEFAULT_LEASE_MS: i64 = 5 * 60 * 1000;
+
+/// How long a job may stay running before it is treated as abandoned
+static LEASE_MS: AtomicI64 = AtomicI64::new(DEFAULT_LEASE_MS);
+
+/// Recorded as the failure of a job whose lease expired
+const LEASE_EXPIRED: &str = "Lease expired: the worker stopped before finishing the job";
+
+/// Process-wide queue connection, opened on first use or by configure()
+static STORE: LazyLock<Mutex<Option<Connection>>> = LazyLock::new(|| Mutex::new(None));
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS ntnt_jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    args TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    priority INTEGER NOT NULL DEFAULT 0,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL,
+    backoff_ms INTEGER NOT NULL,
+    unique_key TEXT,
+    last_error TEXT,
+    run_at INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS ntnt_jobs_due ON ntnt_jobs (status, priority, run_at);
+DROP INDEX IF EXISTS ntnt_jobs_unique;
+-- Queues created before the unique index may hold duplicates; keep the oldest
+UPDATE ntnt_jobs SET unique_key = NULL
+WHERE unique_key IS NOT NULL AND status IN ('pending', 'running') AND id NOT IN (
+    SELECT MIN(id) FROM ntnt_jobs
+    WHERE unique_key IS NOT NULL AND status IN ('pending', 'running')
+    GROUP BY unique_key
+);
+CREATE UNIQUE INDEX IF NOT EXISTS ntnt_jobs_unique_active ON ntnt_jobs (unique_key)
+    WHERE status IN ('pending', 'running');
+CREATE TABLE IF NOT EXISTS ntnt_jobs_dead (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    args TEXT NOT NULL,
+    priority INTEGER NOT NULL,
+    attempts INTEGER NOT NULL,
+    max_attempts INTEGER NOT NULL,
+    backoff_ms INTEGER NOT NULL,
+    unique_key TEXT,
+    last_error TEXT,
+    created_at INTEGER NOT NULL,
+    failed_at INTEGER NOT NULL
+);
+";
+
+fn db_error(e: rusqlite::Error) -> IntentError {
+    IntentError::RuntimeError(format!("Job queue error: {}", e))
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// The queue database used when configure() was not called:
+/// `$NTNT_JOBS_DB`, or `ntnt_jobs.db` in the working directory
+pub fn default_db_path() -> String {
+    std::env::var(JOBS_DB_ENV).unwrap_or_else(|_| DEFAULT_DB_PATH.to_string())
+}
+
+/// Open a queue database, creating the job tables if needed
+pub fn open_store(path: &str) -> Result<Connection> {
+    let conn = if path == ":memory:" {
+        Connection::open_in_memory()
+    } else {
+        Connection::open(path)
+    }
+    .map_err(|e| {
+        IntentError::RuntimeError(format!("Failed to open job queue '{}': {}", path, e))
+    })?;
+    // Several processes (the app and `ntnt jobs`) may share the file
+    let _ = conn.execute_batch("PRAGMA journal_mode=WAL;");
+    conn.busy_timeout(std::time::Duration::from_secs(5))
+        .map_err(db_error)?;
+    conn.execute_batch(SCHEMA).map_err(db_error)?;
+    Ok(conn)
+}
+
+/// Run `f` with the process-wide queue, opening the default database first
+/// if configure() was not called. The lock is not held while a worker runs,
+/// so workers may enqueue follow-up jobs.
+fn with_store<T>(f: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+    let mut store = STORE
+        .lock()
+        .map_err(|_| IntentError::RuntimeError("Job queue lock poisoned".to_string()))?;
+    if store.is_none() {
+        *store = Some(open_store(&default_db_path())?);
+    }
+    f(store.as_ref().expect("job queue opened above"))
+}
+
+/// A row of the queue or the dead-letter table
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobRecord {
+    pub id: i64,
+    pub name: String,
+    /// JSON-encoded arguments
+    pub args: String,
+    /// pending, running, done, cancelled or dead
+    pub status: String,
+    pub priority: i64,
+    pub attempts: i64,
+    pub max_attempts: i64,
+    pub last_error: Option<String>,
+    /// Unix milliseconds the job is due (for dead jobs: when it failed)
+    pub run_at: i64,
+    pub created_at: i64,
+}
+
+impl JobRecord {
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(JobRecord {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            args: row.get("args")?,
+            status: row.get("status")?,
+            priority: row.get("priority")?,
+            attempts: row.get("attempts")?,
+            max_attempts: row.get("max_attempts")?,
+            last_error: row.get("last_error")?,
+            run_at: row.get("run_at")?,
+            created_at: row.get("created_at")?,
+        })
+    }
+
+    fn args_json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.args).unwrap_or(serde_json::Value::Null)
+    }
+
+    /// Decoded job arguments
+    pub fn args_value(&self) -> Value {
+        json_to_intent_value(&self.args_json())
+    }
+
+    /// JSON form used by `ntnt jobs list --format json` (times in Unix seconds)
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "name": self.name,
+            "args": self.args_json(),
+            "status": self.status,
+            "priority": self.priority,
+            "attempts": self.attempts,
+            "max_attempts": self.max_attempts,
+            "last_error": self.last_error,
+            "run_at": self.run_at / 1000,
+            "created_at": self.created_at / 1000,
+        })
+    }
+
+    /// Map form returned by get_job() and list_jobs()
+    pub fn to_value(&self) -> Value {
+        json_to_intent_value(&self.to_json())
+    }
+}
+
+/// Options accepted by enqueue() and enqueue_at()
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnqueueOptions {
+    /// Higher runs first; "low", "normal" and "high" are -10, 0 and 10
+    pub priority: i64,
+    pub max_attempts: i64,
+    pub backoff_ms: i64,
+    pub delay_ms: i64,
+    /// Only one pending or running job may have this key
+    pub unique_key: Option<String>,
+}
+
+impl Default for EnqueueOptions {
+    fn default() -> Self {
+        EnqueueOptions {
+            priority: 0,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            backoff_ms: DEFAULT_BACKOFF_MS,
+            delay_ms: 0,
+            unique_key: None,
+        }
+    }
+}
+
+impl EnqueueOptions {
+    /// Read an options map. `unique: true` derives the key from the job name
+    /// and arguments; a string is used as the key itself.
+    fn from_value(
+        opts: Option<&Value>,
+        name: &str,
+        args_json: &str,
+        fn_name: &str,
+    ) -> Result<Self> {
+        let mut options = EnqueueOptions::default();
+        let map = match opts {
+            None | Some(Value::Unit) => return Ok(options),
+            Some(Value::Map(map)) => map,
+            Some(_) => {
+                return Err(IntentError::TypeError(format!(
+                    "{}() options must be a map",
+                    fn_name
+                )))
+            }
+        };
+        for (key, value) in map {
+            match (key.as_str(), value) {
+                ("priority", Value::Int(p)) => options.priority = *p,
+                ("priority", Value::String(level)) => {
+                    options.priority = match level.as_str() {
+                        "low" => -10,
+                        "normal" => 0,
+                        "high" => 10,
+                        _ => {
+                            return Err(IntentError::TypeError(format!(
+                                "{}() priority must be an Int or \"low\", \"normal\" or \"high\"",
+                                fn_name
+                            )))
+                        }
+                    }
+                }
+                ("max_attempts", Value::Int(n)) if *n >= 1 => options.max_attempts = *n,
+                ("max_attempts", _) => {
+                    return Err(IntentError::TypeError(format!(
+                        "{}() max_attempts must be a positive Int",
+                        fn_name
+                    )))
+                }
+                ("backoff", v) => {
+                    options.backoff_ms =
+                        crate::stdlib::schedule::parse_duration(v, fn_name)?.as_millis() as i64
+                }
+                ("delay", v) => {
+                    options.delay_ms =
+                        crate::stdlib::schedule::parse_duration(v, fn_name)?.as_millis() as i64
+                }
+                ("unique", Value::Bool(true)) => {
+                    options.unique_key = Some(format!("{}:{}", name, args_json))
+                }
+                ("unique", Value::Bool(false)) => {}
+                ("unique", Value::String(key)) => options.unique_key = Some(key.clone()),
+                (other, _) => {
+                    return Err(IntentError::TypeError(format!(
+                        "{}() has no option '{}' (expected priority, max_attempts, backoff, delay or unique)",
+                        fn_name, other
+                    )))
+                }
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Add a job due at `run_at` (Unix ms). A unique job that is already pending
+/// or running is not added again; its existing ID is returned instead.
+pub fn insert_job(
+    conn: &Connection,
+    name: &str,
+    args_json: &str,
+    options: &EnqueueOptions,
+    run_at: i64,
+) -> Result<i64> {
+    // The unique index rejects a duplicate even from another process; the
+    // write lock keeps the existing job from finishing before it is looked up
+    let tx = rusqlite::Transaction::new_unchecked(conn, TransactionBehavior::Immediate)
+        .map_err(db_error)?;
+    let now = now_ms();
+    let inserted = tx.execute(
+        "INSERT INTO ntnt_jobs (name, args, priority, max_attempts, backoff_ms, unique_key, run_at, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)
+         ON CONFLICT DO NOTHING",
+        params![
+            name,
+            args_json,
+            options.priority,
+            options.max_attempts,
+            options.backoff_ms,
+            options.unique_key,
+            run_at,
+            now
+        ],
+    )
+    .map_err(db_error)?;
+    let id = if inserted == 1 {
+        tx.last_insert_rowid()
+    } else {
+        tx.query_row(
+            "SELECT id FROM ntnt_jobs WHERE unique_key = ?1 AND status IN ('pending', 'running')",
+            params![options.unique_key],
+            |row| row.get(0),
+        )
+        .map_err(db_error)?
+    };
+    tx.commit().map_err(db_error)?;
+    Ok(id)
+}
+
+/// Claim the most urgent due job among `names` and mark it running.
+/// The attempt counter of the returned record already includes this run.
+pub fn claim_next(conn: &Connection, names: &[String], now: i64) -> Result<Option<JobRecord>> {
+    if names.is_empty() {
+        return Ok(None);
+    }
+    recover_stale(conn, now, LEASE_MS.load(Ordering::Relaxed))?;
+    let placeholders = vec!["?"; names.len()].join(", ");
+    let sql = format!(
+        "SELECT * FROM ntnt_jobs WHERE status = 'pending' AND run_at <= ? AND name IN ({})
+         ORDER BY priority DESC, run_at ASC, id ASC LIMIT 1",
+        placeholders
+    );
+    let mut bind: Vec<rusqlite::types::Value> = vec![now.into()];
+    bind.extend(names.iter().map(|n| n.clone().into()));
+    loop {
+        let candidate = conn
+            .query_row(
+                &sql,
+                rusqlite::params_from_iter(bind.iter()),
+                JobRecord::from_row,
+            )
+            .optional()
+            .map_err(db_error)?;
+        let Some(mut job) = candidate else {
+            return Ok(None);
+        };
+        // Another process may have claimed it in the meantime
+        let claimed = conn
+            .execute(
+                "UPDATE ntnt_jobs SET status = 'running', attempts = attempts + 1, updated_at = ?2
+                 WHERE id = ?1 AND status = 'pending'",
+                params![job.id, now],
+            )
+            .map_err(db_error)?;
+        if claimed == 1 {
+            job.status = "running".to_string();
+            job.attempts += 1;
+            return Ok(Some(job));
+        }
+    }
+}
+
+/// Mark a claimed job as done. `attempts` is the claimed run's attempt count;
+/// returns false, changing nothing, if that run no longer owns the job (its
+/// lease expired and the job was recovered, retried or cancelled meanwhile).
+pub fn complete_job(conn: &Connection, id: i64, attempts: i64) -> Result<bool> {
+    let completed = conn
+        .execute(
+            "UPDATE ntnt_jobs SET status = 'done', last_error = NULL, updated_at = ?3
+             WHERE id = ?1 AND status = 'running' AND attempts = ?2",
+            params![id, attempts, now_ms()],
+        )
+        .map_err(db_error)?;
+    Ok(completed == 1)
+}
+
+/// What happened to a job after a failed attempt
+#[derive(Debug, Clone, PartialEq)]
+pub enum FailOutcome {
+    /// Back on the queue, due at this Unix ms time
+    Retry(i64),
+    /// Out of attempts; moved to the dead-letter table
+    Dead,
+    /// The run no longer owned the job, so its result was ignored
+    Stale,
+}
+
+/// Record a failed attempt: schedule the retry, or move the job to the
+/// dead-letter table once it has used all its attempts. Like complete_job(),
+/// only the run that claimed attempt number `attempts` may do so.
+pub fn fail_job(
+    conn: &Connection,
+    id: i64,
+    attempts: i64,
+    error: &str,
+    now: i64,
+) -> Result<FailOutcome> {
+    let tx = rusqlite::Transaction::new_unchecked(conn, TransactionBehavior::Immediate)
+        .map_err(db_error)?;
+    let outcome = record_failure(&tx, id, Some(attempts), error, now)?;
+    tx.commit().map_err(db_error)?;
+    Ok(outcome)
+}
+
+/// The body of fail_job(), run inside the caller's transaction. `claimed` is
+/// the attempt of the failing run, or None to fail whichever run holds the job.
+fn record_failure(
+    tx: &Connection,
+    id: i64,
+    claimed: Option<i64>,
+    error: &str,
+    now: i64,
+) -> Result<FailOutcome> {
+    let row = tx
+        .query_row(
+            "SELECT attempts, max_attempts, backoff_ms FROM ntnt_jobs
+             WHERE id = ?1 AND status = 'running' AND (?2 IS NULL OR attempts = ?2)",
+            params![id, claimed],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(db_error)?;
+    let Some((attempts, max_attempts, backoff_ms)): Option<(i64, i64, i64)> = row else {
+        return Ok(FailOutcome::Stale);
+    };
+    let outcome = if attempts >= max_attempts {
+        tx.execute(
+            "INSERT OR REPLACE INTO ntnt_jobs_dead
+                 (id, name, args, priority, attempts, max_attempts, backoff_ms, unique_key, last_error, created_at, failed_at)
+             SELECT id, name, args, priority, attempts, max_attempts, backoff_ms, unique_key, ?2, created_at, ?3
+             FROM ntnt_jobs WHERE id = ?1",
+            params![id, error, now],
+        )
+        .map_err(db_error)?;
+        tx.execute("DELETE FROM ntnt_jobs WHERE id = ?1", params![id])
+            .map_err(db_error)?;
+        FailOutcome::Dead
+    } else {
+        let exponent = (attempts - 1).clamp(0, 30) as u32;
+        let run_at = now.saturating_add(backoff_ms.saturating_mul(1i64 << exponent));
+        tx.execute(
+            "UPDATE ntnt_jobs SET status = 'pending', last_error = ?2, run_at = ?3, updated_at = ?4 WHERE id = ?1",
+            params![id, error, run_at, now],
+        )
+        .map_err(db_error)?;
+        FailOutcome::Retry(run_at)
+    };
+    Ok(outcome)
+}
+
+/// Fail every job that has been running longer than `lease_ms`, as if its
+/// worker had reported an error. Returns how many jobs were recovered.
+pub fn recover_stale(conn: &Connection, now: i64, lease_ms: i64) -> Result<usize> {
+    let cutoff = now.saturating_sub(lease_ms);
+    let stale = |conn: &Connection| -> Result<Vec<i64>> {
+        let mut stmt = conn
+            .prepare("SELECT id FROM ntnt_jobs WHERE status = 'running' AND updated_at <= ?1")
+            .map_err(db_error)?;
+        let ids = stmt
+            .query_map(params![cutoff], |row| row.get(0))
+            .map_err(db_error)?;
+        ids.collect::<rusqlite::Result<Vec<i64>>>()
+            .map_err(db_error)
+    };
+    // Cheap check first so a healthy queue never takes the write lock here
+    if stale(conn)?.is_empty() {
+        return Ok(0);
+    }
+    // Another process may be recovering the same jobs; take the write lock
+    // before reading them again
+    let tx = rusqlite::Transaction::new_unchecked(conn, TransactionBehavior::Immediate)
+        .map_err(db_error)?;
+    let ids = stale(&tx)?;
+    for id in &ids {
+        record_failure(&tx, *id, None, LEASE_EXPIRED, now)?;
+    }
+    tx.commit().map_err(db_error)?;
+    Ok(ids.len())
+}
+
+/// Whether a running job has outlived its lease (see recover_stale)
+const STALE_RUNNING: &str = "status = 'running' AND updated_at <= ?3";
+
+const DEAD_COLUMNS: &str = "id, name, args, 'dead' AS status, priority, attempts, max_attempts, last_error, failed_at AS run_at, created_at";
+
+/// Look up a job in the queue or the dead-letter table
+pub fn find_job(conn: &Connection, id: i64) -> Result<Option<JobRecord>> {
+    let job = conn
+        .query_row(
+            "SELECT * FROM ntnt_jobs WHERE id = ?1",
+            params![id],
+            JobRecord::from_row,
+        )
+        .optional()
+        .map_err(db_error)?;
+    if job.is_some() {
+        return Ok(job);
+    }
+    conn.query_row(
+        &format!("SELECT {} FROM ntnt_jobs_dead WHERE id = ?1", DEAD_COLUMNS),
+        params![id],
+        JobRecord::from_row,
+    )
+    .optional()
+    .map_err(db_error)
+}
+
+/// List jobs ordered by ID, optionally only those with the given status
+/// ("dead" lists the dead-letter table)
+pub fn list_jobs(conn: &Connection, status: Option<&str>) -> Result<Vec<JobRecord>> {
+    let sql = format!(
+        "SELECT * FROM (
+             SELECT id, name, args, status, priority, attempts, max_attempts, last_error, run_at, created_at FROM ntnt_jobs
+             UNION ALL
+             SELECT {} FROM ntnt_jobs_dead
+         ) WHERE ?1 IS NULL OR status = ?1 ORDER BY id",
+        DEAD_COLUMNS
+    );
+    let mut stmt = conn.prepare(&sql).map_err(db_error)?;
+    let rows = stmt
+        .query_map(params![status], JobRecord::from_row)
+        .map_err(db_error)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(db_error)
+}
+
+/// A retried unique job may collide with an identical job queued since
+fn retry_error(id: i64, e: rusqlite::Error) -> IntentError {
+    match e {
+        rusqlite::Error::SqliteFailure(failure, _)
+            if failure.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            IntentError::RuntimeError(format!(
+                "Job {} cannot be retried: a job with the same unique key is pending or running",
+                id
+            ))
+        }
+        e => db_error(e),
+    }
+}
+
+/// Put a dead, cancelled, finished or abandoned (running past its lease) job
+/// back on the queue with a fresh set of attempts. Returns false if there is
+/// no such job or it is still queued.
+pub fn retry_job(conn: &Connection, id: i64) -> Result<bool> {
+    let tx = conn.unchecked_transaction().map_err(db_error)?;
+    let now = now_ms();
+    let revived = tx
+        .execute(
+            "INSERT INTO ntnt_jobs (id, name, args, priority, attempts, max_attempts, backoff_ms, unique_key, last_error, run_at, created_at, updated_at)
+             SELECT id, name, args, priority, 0, max_attempts, backoff_ms, unique_key, last_error, ?2, created_at, ?2
+             FROM ntnt_jobs_dead WHERE id = ?1",
+            params![id, now],
+        )
+        .map_err(|e| retry_error(id, e))?;
+    let changed = if revived == 1 {
+        tx.execute("DELETE FROM ntnt_jobs_dead WHERE id = ?1", params![id])
+            .map_err(db_error)?;
+        1
+    } else {
+        tx.execute(
+            &format!(
+                "UPDATE ntnt_jobs SET status = 'pending', attempts = 0, run_at = ?2, updated_at = ?2
+                 WHERE id = ?1 AND (status IN ('cancelled', 'done') OR {})",
+                STALE_RUNNING
+            ),
+            params![
+                id,
+                now,
+                now.saturating_sub(LEASE_MS.load(Ordering::Relaxed))
+            ],
+        )
+        .map_err(|e| retry_error(id, e))?
+    };
+    tx.commit().map_err(db_error)?;
+    Ok(changed == 1)
+}
+
+/// Cancel a pending or abandoned (running past its lease) job, or discard a
+/// dead one. Returns false if there is no such job or it is running or finished.
+pub fn cancel_job(conn: &Connection, id: i64) -> Result<bool> {
+    let now = now_ms();
+    let cancelled = conn
+        .execute(
+            &format!(
+                "UPDATE ntnt_jobs SET status = 'cancelled', updated_at = ?2
+                 WHERE id = ?1 AND (status = 'pending' OR {})",
+                STALE_RUNNING
+            ),
+            params![
+                id,
+                now,
+                now.saturating_sub(LEASE_MS.load(Ordering::Relaxed))
+            ],
+        )
+        .map_err(db_error)?;
+    if cancelled == 1 {
+        return Ok(true);
+    }
+    let discarded = conn
+        .execute("DELETE FROM ntnt_jobs_dead WHERE id = ?1", params![id])
+        .map_err(db_error)?;
+    Ok(discarded == 1)
+}
+
+/// Claim the next due job for the given worker names (used by the interpreter)
+pub(crate) fn claim(names: &[String]) -> Result<Option<JobRecord>> {
+    with_store(|conn| claim_next(conn, names, now_ms()))
+}
+
+/// Record the result of a worker run (used by the interpreter). A completed
+/// run that no longer owned the job reports FailOutcome::Stale.
+pub(crate) fn finish(job: &JobRecord, error: Option<&str>) -> Result<Option<FailOutcome>> {
+    with_store(|conn| match error {
+        None => complete_job(conn, job.id, job.attempts)
+            .map(|completed| (!completed).then_some(FailOutcome::Stale)),
+        Some(error) => fail_job(conn, job.id, job.attempts, error, now_ms()).map(Some),
+    })
+}
+
+/// Push back the lease of a claimed run. Returns false if the run no longer
+/// owns the job.
+pub fn renew_lease(conn: &Connection, id: i64, attempts: i64, now: i64) -> Result<bool> {
+    let renewed = conn
+        .execute(
+            "UPDATE ntnt_jobs SET updated_at = ?3 WHERE id = ?1 AND status = 'running' AND attempts = ?2",
+            params![id, attempts, now],
+        )
+        .map_err(db_error)?;
+    Ok(renewed == 1)
+}
+
+/// Keeps renewing the lease of a running job from a background thread until
+/// dropped, so a job outlasting the lease is not mistaken for a lost one
+pub(crate) struct LeaseKeeper {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl LeaseKeeper {
+    pub(crate) fn start(job: &JobRecord) -> Self {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let (id, attempts) = (job.id, job.attempts);
+        let every =
+            std::time::Duration::from_millis((LEASE_MS.load(Ordering::Relaxed) / 3).max(1) as u64);
+        let thread = std::thread::Builder::new()
+            .name(format!("ntnt-job-lease-{}", id))
+            .spawn(move || {
+                while let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(every) {
+                    match with_store(|conn| renew_lease(conn, id, attempts, now_ms())) {
+                        Ok(true) => {}
+                        Ok(false) => break,
+                        Err(e) => eprintln!("Job #{}: could not renew its lease: {}", id, e),
+                    }
+                }
+            })
+            .ok();
+        LeaseKeeper {
+            stop: Some(stop),
+            thread,
+        }
+    }
+}
+
+impl Drop for LeaseKeeper {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn job_id(value: &Value, fn_name: &str) -> Result<i64> {
+    match value {
+        Value::Int(id) => Ok(*id),
+        Value::Map(map) => match map.get("id") {
+            Some(Value::Int(id)) => Ok(*id),
+            _ => Err(IntentError::TypeError(format!(
+                "{}() requires a job ID",
+                fn_name
+            ))),
+        },
+        _ => Err(IntentError::TypeError(format!(
+            "{}() requires a job ID",
+            fn_name
+        ))),
+    }
+}
+
+fn job_name(value: &Value, fn_name: &str) -> Result<String> {
+    match value {
+        Value::String(name) if !name.is_empty() => Ok(name.clone()),
+        _ => Err(IntentError::TypeError(format!(
+            "{}() requires a job name string",
+            fn_name
+        ))),
+    }
+}
+
+/// enqueue(name, args, opts?) -> Int
+fn jobs_enqueue(args: &[Value]) -> Result<Value> {
+    let (name, job_args, opts) = match args {
+        [name, job_args] => (name, job_args, None),
+        [name, job_args, opts] => (name, job_args, Some(opts)),
+        _ => {
+            return Err(IntentError::TypeError(
+                "enqueue() requires (name, args) or (name, args, options)".to_string(),
+            ))
+        }
+    };
+    let name = job_name(name, "enqueue")?;
+    let args_json = intent_value_to_json(job_args).to_string();
+    let options = EnqueueOptions::from_value(opts, &name, &args_json, "enqueue")?;
+    let run_at = now_ms() + options.delay_ms;
+    with_store(|conn| insert_job(conn, &name, &args_json, &options, run_at)).map(Value::Int)
+}
+
+/// enqueue_at(name, args, timestamp, opts?) -> Int
+fn jobs_enqueue_at(args: &[Value]) -> Result<Value> {
+    let (name, job_args, when, opts) =
+        match args {
+            [name, job_args, when] => (name, job_args, when, None),
+            [name, job_args, when, opts] => (name, job_args, when, Some(opts)),
+            _ => return Err(IntentError::TypeError(
+                "enqueue_at() requires (name, args, timestamp) or (name, args, timestamp, options)"
+                    .to_string(),
+            )),
+        };
+    let name = job_name(name, "enqueue_at")?;
+    let Value::Int(when) = when else {
+        return Err(IntentError::TypeError(
+            "enqueue_at() requires a Unix timestamp in seconds".to_string(),
+        ));
+    };
+    let args_json = intent_value_to_json(job_args).to_string();
+    let options = EnqueueOptions::from_value(opts, &name, &args_json, "enqueue_at")?;
+    let run_at = when.saturating_mul(1000) + options.delay_ms;
+    with_store(|conn| insert_job(conn, &name, &args_json, &options, run_at)).map(Value::Int)
+}
+
+/// configure(options) -> Unit
+/// Switches the process to another queue database and sets the job lease
+fn jobs_configure(opts: &Value) -> Result<Value> {
+    let Value::Map(map) = opts else {
+        return Err(IntentError::TypeError(
+            "configure() requires an options map".to_string(),
+        ));
+    };
+    for key in map.keys() {
+        if key != "db" && key != "lease" {
+            return Err(IntentError::TypeError(format!(
+                "configure() has no option '{}' (expected db or lease)",
+                key
+            )));
+        }
+    }
+    let lease_ms = match map.get("lease") {
+        Some(v) => crate::stdlib::schedule::parse_duration(v, "configure")?.as_millis() as i64,
+        None => DEFAULT_LEASE_MS,
+    };
+    let path = match map.get("db") {
+        Some(Value::String(path)) => path.clone(),
+        None => default_db_path(),
+        Some(_) => {
+            return Err(IntentError::TypeError(
+                "configure() db must be a file path or \":memory:\"".to_string(),
+            ))
+        }
+    };
+    let conn = open_store(&path)?;
+    LEASE_MS.store(lease_ms, Ordering::Relaxed);
+    *STORE
+        .lock()
+        .map_err(|_| IntentError::RuntimeError("Job queue lock poisoned".to_string()))? =
+        Some(conn);
+    Ok(Value::Unit)
+}
+
+fn some(value: Value) -> Value {
+    Value::EnumValue {
+        enum_name: "Option".to_string(),
+        variant: "Some".to_string(),
+        values: vec![value],
+    }
+}
+
+fn none() -> Value {
+    Value::EnumValue {
+        enum_name: "Option".to_string(),
+        variant: "None".to_string(),
+        values: vec![],
+    }
+}
+
+/// Initialize the std/jobs module
+pub fn init() -> HashMap<String, Value> {
+    let mut module = HashMap::new();
+
+    // worker(name, fn) and run_jobs() keep or call user functions,
+    // so the interpreter handles the call itself
+    for name in INTERPRETER_FUNCTIONS {
+        module.insert(
+            name.to_string(),
+            Value::NativeFunction {
+                name: name.to_string(),
+                arity: 0,
+                func: |_args| {
+                    Err(IntentError::RuntimeError(
+                        "worker() and run_jobs() must be called directly, not stored in a variable"
+                            .to_string(),
+                    ))
+                },
+            },
+        );
+    }
+
+    // configure(options) -> Unit
+    module.insert(
+        "configure".to_string(),
+        Value::NativeFunction {
+            name: "configure".to_string(),
+            arity: 1,
+            func: |args| jobs_configure(&args[0]),
+        },
+    );
+
+    // enqueue(name, args, opts?) -> Int
+    module.insert(
+        "enqueue".to_string(),
+        Value::NativeFunction {
+            name: "enqueue".to_string(),
+            arity: 0,
+            func: |args| jobs_enqueue(args),
+        },
+    );
+
+    // enqueue_at(name, args, timestamp, opts?) -> Int
+    module.insert(
+        "enqueue_at".to_string(),
+        Value::NativeFunction {
+            name: "enqueue_at".to_string(),
+            arity: 0,
+            func: |args| jobs_enqueue_at(args),
+        },
+    );
+
+    // get_job(id) -> Option<Map>
+    module.insert(
+        "get_job".to_string(),
+        Value::NativeFunction {
+            name: "get_job".to_string(),
+            arity: 1,
+            func: |args| {
+                let id = job_id(&args[0], "get_job")?;
+                let job = with_store(|conn| find_job(conn, id))?;
+                Ok(job.map(|job| some(job.to_value())).unwrap_or_else(none))
+            },
+        },
+    );
+
+    // list_jobs(status?) -> Array
+    module.insert(
+        "list_jobs".to_string(),
+        Value::NativeFunction {
+            name: "list_jobs".to_string(),
+            arity: 0,
+            func: |args| {
+                let status = match args {
+                    [] => None,
+                    [Value::String(status)] => Some(status.clone()),
+                    _ => {
+                        return Err(IntentError::TypeError(
+                            "list_jobs() takes an optional status string".to_string(),
+                        ))
+                    }
+                };
+                let jobs = with_store(|conn| list_jobs(conn, status.as_deref()))?;
+                Ok(Value::Array(jobs.iter().map(JobRecord::to_value).collect()))
+            },
+        },
+    );
+
+    // cancel_job(id) -> Bool
+    module.insert(
+        "cancel_job".to_string(),
+        Value::NativeFunction {
+            name: "cancel_job".to_string(),
+            arity: 1,
+            func: |args| {
+                let id = job_id(&args[0], "cancel_job")?;
+                with_store(|conn| cancel_job(conn, id)).map(Value::Bool)
+            },
+        },
+    );
+
+    // retry_job(id) -> Bool
+    module.insert(
+        "retry_job".to_string(),
+        Value::NativeFunction {
+            name: "retry_job".to_string(),
+            arity: 1,
+            func: |args| {
+                let id = job_id(&args[0], "retry_job")?;
+                with_store(|conn| retry_job(conn, id)).map(Value::Bool)
+            },
+        },
+    );
+
+    module
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> Connection {
+        open_store(":memory:").unwrap()
+    }
+
+    fn add(conn: &Connection, name: &str, options: EnqueueOptions, run_at: i64) -> i64 {
+        insert_job(conn, name, "{}", &options, run_at).unwrap()
+    }
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_claims_by_priority_then_due_time() {
+        let conn = store();
+        let low = add(
+            &conn,
+            "mail",
+            EnqueueOptions {
+                priority: -10,
+                ..Default::default()
+            },
+            100,
+        );
+        let normal = add(&conn, "mail", EnqueueOptions::default(), 200);
+        let high = add(
+            &conn,
+            "mail",
+            EnqueueOptions {
+                priority: 10,
+                ..Default::default()
+            },
+            300,
+        );
+        let _later = add(
+            &conn,
+            "mail",
+            EnqueueOptions {
+                priority: 99,
+                ..Default::default()
+            },
+            5000,
+        );
+        let _other = add(&conn, "report", EnqueueOptions::default(), 0);
+
+        let order: Vec<i64> =
+            std::iter::from_fn(|| claim_next(&conn, &names(&["mail"]), 1000).unwrap())
+                .map(|job| job.id)
+                .collect();
+        assert_eq!(order, vec![high, normal, low]);
+        assert_eq!(find_job(&conn, high).unwrap().unwrap().status, "running");
+        assert_eq!(find_job(&conn, high).unwrap().unwrap().attempts, 1);
+    }
+
+    #[test]
+    fn test_failures_back_off_then_dead_letter() {
+        let conn = store();
+        let options = EnqueueOptions {
+            max_attempts: 3,
+            backoff_ms: 1000,
+            ..Default::default()
+        };
+        let id = add(&conn, "sync", options, 0);
+        let workers = names(&["sync"]);
+
+        claim_next(&conn, &workers, 0).unwrap().unwrap();
+        assert_eq!(
+            fail_job(&conn, id, 1, "boom", 10).unwrap(),
+            FailOutcome::Retry(1010)
+        );
+        assert!(claim_next(&conn, &workers, 1009).unwrap().is_none());
+
+        claim_next(&conn, &workers, 1010).unwrap().unwrap();
+        assert_eq!(
+            fail_job(&conn, id, 2, "boom", 2000).unwrap(),
+            FailOutcome::Retry(4000)
+        );
+
+        let job = claim_next(&conn, &workers, 4000).unwrap().unwrap();
+        assert_eq!(job.attempts, 3);
+        assert_eq!(
+            fail_job(&conn, id, 3, "still broken", 4000).unwrap(),
+            FailOutcome::Dead
+        );
+
+        let dead = list_jobs(&conn, Some("dead")).unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].id, id);
+        assert_eq!(dead[0].last_error.as_deref(), Some("still broken"));
+        assert!(list_jobs(&conn, Some("pending")).unwrap().is_empty());
+
+        assert!(retry_job(&conn, id).unwrap());
+        let revived = find_job(&conn, id).unwrap().unwrap();
+        assert_eq!((revived.status.as_str(), revived.attempts), ("pending", 0));
+        assert!(list_jobs(&conn, Some("dead")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_unique_jobs_are_not_duplicated_while_queued() {
+        let conn = store();
+        let unique = || EnqueueOptions {
+            unique_key: Some("invoice:7".to_string()),
+            ..Default::default()
+        };
+        let first = add(&conn, "invoice", unique(), 0);
+        assert_eq!(add(&conn, "invoice", unique(), 0), first);
+
+        claim_next(&conn, &names(&["invoice"]), 0).unwrap();
+        assert_eq!(add(&conn, "invoice", unique(), 0), first);
+
+        assert!(complete_job(&conn, first, 1).unwrap());
+        let second = add(&conn, "invoice", unique(), 0);
+        assert_ne!(second, first);
+
+        // The finished job can't come back while its duplicate is queued
+        let err = retry_job(&conn, first).unwrap_err();
+        assert!(err.to_string().contains("same unique key"), "{}", err);
+        assert_eq!(find_job(&conn, first).unwrap().unwrap().status, "done");
+    }
+
+    #[test]
+    fn test_unique_insert_is_atomic_across_connections() {
+        let path = std::env::temp_dir().join(format!("ntnt_jobs_unique_{}.db", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        let _ = std::fs::remove_file(&path);
+        open_store(&path).unwrap();
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let conn = open_store(&path).unwrap();
+                    let options = EnqueueOptions {
+                        unique_key: Some("report:daily".to_string()),
+                        ..Default::default()
+                    };
+                    insert_job(&conn, "report", "{}", &options, 0).unwrap()
+                })
+            })
+            .collect();
+        let ids: Vec<i64> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        assert!(ids.iter().all(|id| *id == ids[0]), "{:?}", ids);
+
+        let conn = open_store(&path).unwrap();
+        assert_eq!(list_jobs(&conn, Some("pending")).unwrap().len(), 1);
+        drop(conn);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+
+    #[test]
+    fn test_open_store_drops_duplicate_unique_keys() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        conn.execute_batch(
+            "DROP INDEX ntnt_jobs_unique_active;
+             INSERT INTO ntnt_jobs (name, args, max_attempts, backoff_ms, unique_key, run_at, created_at, updated_at)
+             VALUES ('a', '{}', 3, 1000, 'k', 0, 0, 0), ('a', '{}', 3, 1000, 'k', 0, 0, 0);",
+        )
+        .unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        let keys: Vec<Option<String>> = conn
+            .prepare("SELECT unique_key FROM ntnt_jobs ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(keys, vec![Some("k".to_string()), None]);
+    }
+
+    #[test]
+    fn test_cancel_only_pending_jobs() {
+        let conn = store();
+        let pending = add(&conn, "a", EnqueueOptions::default(), 0);
+        let running = add(&conn, "b", EnqueueOptions::default(), 0);
+        claim_next(&conn, &names(&["b"]), now_ms()).unwrap();
+
+        assert!(cancel_job(&conn, pending).unwrap());
+        assert!(!cancel_job(&conn, pending).unwrap());
+        assert!(!cancel_job(&conn, running).unwrap());
+        assert!(!cancel_job(&conn, 999).unwrap());
+        assert!(claim_next(&conn, &names(&["a"]), 0).unwrap().is_none());
+
+        assert!(retry_job(&conn, pending).unwrap());
+        assert_eq!(
+            claim_next(&conn, &names(&["a"]), i64::MAX)
+                .unwrap()
+                .unwrap()
+                .id,
+            pending
+        );
+    }
+
+    #[test]
+    fn test_stale_running_jobs_are_recovered() {
+        let conn = store();
+        let options = EnqueueOptions {
+            max_attempts: 2,
+            backoff_ms: 1000,
+            ..Default::default()
+        };
+        let id = add(&conn, "sync", options, 0);
+        claim_next(&conn, &names(&["sync"]), 0).unwrap().unwrap();
+
+        assert_eq!(
+            recover_stale(&conn, DEFAULT_LEASE_MS - 1, DEFAULT_LEASE_MS).unwrap(),
+            0
+        );
+        assert_eq!(find_job(&conn, id).unwrap().unwrap().status, "running");
+
+        // The lost run counts as a failed attempt and is retried after backoff
+        let now = DEFAULT_LEASE_MS;
+        assert_eq!(recover_stale(&conn, now, DEFAULT_LEASE_MS).unwrap(), 1);
+        let job = find_job(&conn, id).unwrap().unwrap();
+        assert_eq!((job.status.as_str(), job.run_at), ("pending", now + 1000));
+        assert_eq!(job.last_error.as_deref(), Some(LEASE_EXPIRED));
+
+        // Losing the last attempt dead-letters the job
+        claim_next(&conn, &names(&["sync"]), now + 1000)
+            .unwrap()
+            .unwrap();
+        recover_stale(&conn, now + 1000 + DEFAULT_LEASE_MS, DEFAULT_LEASE_MS).unwrap();
+        assert_eq!(find_job(&conn, id).unwrap().unwrap().status, "dead");
+    }
+
+    #[test]
+    fn test_finishing_after_losing_the_lease_is_ignored() {
+        let conn = store();
+        let id = add(&conn, "sync", EnqueueOptions::default(), 0);
+        let workers = names(&["sync"]);
+        let slow = claim_next(&conn, &workers, 0).unwrap().unwrap();
+
+        // Renewing keeps the run from being taken for a lost one
+        assert!(renew_lease(&conn, id, slow.attempts, DEFAULT_LEASE_MS - 1).unwrap());
+        assert_eq!(
+            recover_stale(&conn, DEFAULT_LEASE_MS, DEFAULT_LEASE_MS).unwrap(),
+            0
+        );
+
+        // Without renewal the job is recovered and claimed again
+        let later = 2 * DEFAULT_LEASE_MS;
+        assert_eq!(recover_stale(&conn, later, DEFAULT_LEASE_MS).unwrap(), 1);
+        let retry = claim_next(&conn, &workers, i64::MAX).unwrap().unwrap();
+        assert_eq!(retry.attempts, slow.attempts + 1);
+
+        // The first run's late result leaves the second run's job alone
+        assert!(!renew_lease(&conn, id, slow.attempts, later).unwrap());
+        assert!(!complete_job(&conn, id, slow.attempts).unwrap());
+        assert_eq!(
+            fail_job(&conn, id, slow.attempts, "late", later).unwrap(),
+            FailOutcome::Stale
+        );
+        let job = find_job(&conn, id).unwrap().unwrap();
+        assert_eq!(
+            (job.status.as_str(), job.attempts),
+            ("running", retry.attempts)
+        );
+
+        assert!(complete_job(&conn, id, retry.attempts).unwrap());
+        assert!(!complete_job(&conn, id, retry.attempts).unwrap());
+        assert_eq!(find_job(&conn, id).unwrap().unwrap().status, "done");
+    }
+
+    #[test]
+    fn test_retry_and_cancel_abandoned_jobs() {
+        let conn = store();
+        let abandoned = add(&conn, "a", EnqueueOptions::default(), 0);
+        let cancelled = add(&conn, "b", EnqueueOptions::default(), 0);
+        // Claimed long ago and never finished
+        claim_next(&conn, &names(&["a", "b"]), 0).unwrap();
+        claim_next(&conn, &names(&["a", "b"]), 0).unwrap();
+
+        assert!(retry_job(&conn, abandoned).unwrap());
+        let job = find_job(&conn, abandoned).unwrap().unwrap();
+        assert_eq!((job.status.as_str(), job.attempts), ("pending", 0));
+
+        assert!(cancel_job(&conn, cancelled).unwrap());
+        assert_eq!(
+            find_job(&conn, cancelled).unwrap().unwrap().status,
+            "cancelled"
+        );
+    }
+
+    #[test]
+    fn test_enqueue_options() {
+        let opts = |pairs: Vec<(&str, Value)>| {
+            let map = pairs.into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+            EnqueueOptions::from_value(Some(&Value::Map(map)), "mail", "{\"to\":1}", "enqueue")
+        };
+        let parsed = opts(vec![
+            ("priority", Value::String("high".to_string())),
+            ("backoff", Value::String("30s".to_string())),
+            ("unique", Value::Bool(true)),
+        ])
+        .unwrap();
+        assert_eq!(parsed.priority, 10);
+        assert_eq!(parsed.backoff_ms, 30_000);
+        assert_eq!(parsed.unique_key.as_deref(), Some("mail:{\"to\":1}"));
+        assert!(opts(vec![("priority", Value::String("urgent".to_string()))]).is_err());
+        assert!(opts(vec![("max_attempts", Value::Int(0))]).is_err());
+        assert!(opts(vec![("retries", Value::Int(2))]).is_err());
+    }
+}
@@ -14,14 +14,25 @@ pub mod env;
 pub mod fs;
 pub mod http;
 pub mod http_bridge;
+pub mod http_cookies;
+pub mod http_multipart;
+pub mod http_request;
+pub mod http_router;
 pub mod http_server;
 pub mod http_server_async;
+pub mod http_session;
+pub mod http_stream;
+pub mod http_tls;
+pub mod http_ws;
+pub mod jobs;
 pub mod json;
 pub mod math;
 pub mod path;
 pub mod postgres;
+pub mod schedule;
 pub mod sqlite;
 pub mod string;
+pub mod sync;
 pub mod template;
 pub mod time;
 pub mod url;
@@ -48,9 +59,13 @@ pub fn init_all_modules() -> HashMap<String, StdlibModule> {
     modules.insert("std/url".to_string(), url::init());
     modules.insert("std/http".to_string(), http::init());
     modules.insert("std/http/server".to_string(), http_server::init());
+    modules.insert("std/http/ws".to_string(), http_ws::init());
     modules.insert("std/db/postgres".to_string(), postgres::init());
     modules.insert("std/db/sqlite".to_string(), sqlite::init());
     modules.insert("std/concurrent".to_string(), concurrent::init());
+    modules.insert("std/sync".to_string(), sync::init());
+    modules.insert("std/schedule".to_string(), schedule::init());
+    modules.insert("std/jobs".to_string(), jobs::init());
     modules.insert("std/csv".to_string(), csv::init());
     modules.insert("std/template".to_string(), template::init());
 
@@ -0,0 +1,663 @@
+//! std/schedule module - Timers, tickers and cron jobs
+//!
+//! Jobs are registered with `every`, `after` and `cron` and run on the
+//! interpreter thread. Under `listen()` they are queued through the HTTP
+//! bridge alongside requests; in a plain script the program keeps running
+//! until no jobs are left.
+//!
+//! ```ntnt
+//! import { every, after, cron, cancel } from "std/schedule"
+//!
+//! fn refresh_cache() { ... }
+//! fn purge_sessions() { ... }
+//!
+//! let ticker = every("5m", refresh_cache)
+//! after(30000, warm_up)                         // once, in 30 seconds
+//! cron("0 2 * * *", purge_sessions, "Europe/Berlin")
+//! cancel(ticker)
+//! ```
+//!
+//! A job never overlaps itself: if a run is still queued or in progress when
+//! the next one is due, that tick is skipped.
+
+use crate::error::IntentError;
+use crate::interpreter::Value;
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveDate, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, LazyLock, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+type Result<T> = std::result::Result<T, IntentError>;
+
+// Global job registry. The timer thread sleeps on JOBS_CHANGED until the next
+// job is due or the registry changes.
+static JOBS: LazyLock<Mutex<HashMap<u64, Job>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+static JOBS_CHANGED: Condvar = Condvar::new();
+static JOB_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
+static SCHEDULER_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+/// Names of the functions that register user callbacks; the interpreter
+/// intercepts calls to them
+pub(crate) const INTERPRETER_FUNCTIONS: [&str; 3] = ["every", "after", "cron"];
+
+/// When a job fires
+#[derive(Debug, Clone)]
+pub(crate) enum Trigger {
+    Every(Duration),
+    After(Duration),
+    Cron { expr: CronExpr, tz: Tz },
+}
+
+impl Trigger {
+    /// Build a trigger from the arguments of `every`, `after` or `cron`
+    /// (everything except the callback)
+    pub(crate) fn from_args(kind: &str, spec: &Value, tz: Option<&Value>) -> Result<Self> {
+        match kind {
+            "every" => {
+                let interval = parse_duration(spec, "every")?;
+                if interval.is_zero() {
+                    return Err(IntentError::RuntimeError(
+                        "every() requires a duration greater than zero".to_string(),
+                    ));
+                }
+                Ok(Trigger::Every(interval))
+            }
+            "after" => Ok(Trigger::After(parse_duration(spec, "after")?)),
+            _ => {
+                let Value::String(expr) = spec else {
+                    return Err(IntentError::TypeError(
+                        "cron() requires a cron expression string".to_string(),
+                    ));
+                };
+                let tz = match tz {
+                    None => Tz::UTC,
+                    Some(Value::String(name)) => crate::stdlib::time::parse_timezone(name)?,
+                    Some(_) => {
+                        return Err(IntentError::TypeError(
+                            "cron() timezone must be a string like 'America/New_York'".to_string(),
+                        ))
+                    }
+                };
+                Ok(Trigger::Cron {
+                    expr: CronExpr::parse(expr).map_err(IntentError::RuntimeError)?,
+                    tz,
+                })
+            }
+        }
+    }
+
+    /// The next time this trigger fires after `now`, or None if it never will
+    fn next_after(&self, now: Instant) -> Option<Instant> {
+        match self {
+            Trigger::Every(interval) | Trigger::After(interval) => Some(now + *interval),
+            Trigger::Cron { expr, tz } => {
+                let wall_now = Utc::now();
+                let next = expr.next_after(wall_now, tz)?;
+                let wait = (next - wall_now).to_std().unwrap_or_default();
+                Some(now + wait)
+            }
+        }
+    }
+}
+
+/// A registered job
+struct Job {
+    /// The scheduler (one per interpreter) whose timer runs this job
+    owner: u64,
+    trigger: Trigger,
+    next_run: Instant,
+    /// A run has been handed to the interpreter and has not finished yet
+    running: bool,
+    /// `after` jobs are finished once they have been handed off
+    done: bool,
+}
+
+/// A new scheduler ID. Each interpreter owns the jobs it registers and only
+/// its own timer fires them.
+pub(crate) fn new_scheduler_id() -> u64 {
+    SCHEDULER_ID_COUNTER.fetch_add(1, Ordering::SeqCst)
+}
+
+/// Register a job and return its schedule handle
+pub(crate) fn register(owner: u64, trigger: Trigger) -> Result<Value> {
+    let next_run = trigger.next_after(Instant::now()).ok_or_else(|| {
+        IntentError::RuntimeError("cron() expression never matches a date".to_string())
+    })?;
+    let id = JOB_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
+    JOBS.lock().map_err(lock_error)?.insert(
+        id,
+        Job {
+            owner,
+            trigger,
+            next_run,
+            running: false,
+            done: false,
+        },
+    );
+    JOBS_CHANGED.notify_all();
+    Ok(create_schedule_value(id))
+}
+
+/// A schedule handle that refers to no job (returned when registration is
+/// skipped, e.g. during hot-reload or unit tests)
+pub(crate) fn inert_schedule() -> Value {
+    create_schedule_value(0)
+}
+
+fn create_schedule_value(id: u64) -> Value {
+    let mut handle = HashMap::new();
+    handle.insert("_schedule_id".to_string(), Value::Int(id as i64));
+    handle.insert("type".to_string(), Value::String("Schedule".to_string()));
+    Value::Map(handle)
+}
+
+fn lock_error<E: std::fmt::Display>(e: E) -> IntentError {
+    IntentError::RuntimeError(format!("Failed to lock schedule registry: {}", e))
+}
+
+/// cancel(schedule) -> Bool
+/// Stops a job. Returns false if it had already finished or been cancelled.
+fn schedule_cancel(handle: &Value) -> Result<Value> {
+    let id = match handle {
+        Value::Map(map) => match map.get("_schedule_id") {
+            Some(Value::Int(id)) => *id as u64,
+            _ => return Err(IntentError::TypeError("Expected a Schedule".to_string())),
+        },
+        _ => return Err(IntentError::TypeError("Expected a Schedule".to_string())),
+    };
+    let removed = JOBS.lock().map_err(lock_error)?.remove(&id);
+    JOBS_CHANGED.notify_all();
+    Ok(Value::Bool(removed.is_some()))
+}
+
+/// Cancel every job of a scheduler (used when the server shuts down)
+pub(crate) fn cancel_all(owner: u64) {
+    if let Ok(mut jobs) = JOBS.lock() {
+        jobs.retain(|_, job| job.owner != owner);
+    }
+    JOBS_CHANGED.notify_all();
+}
+
+/// Mark a run as finished. Returns false once the job will not run again, so
+/// the interpreter can drop its callback.
+pub(crate) fn finish_run(id: u64) -> bool {
+    let Ok(mut jobs) = JOBS.lock() else {
+        return false;
+    };
+    let keep = match jobs.get_mut(&id) {
+        Some(job) if !job.done => {
+            job.running = false;
+            true
+        }
+        Some(_) => {
+            jobs.remove(&id);
+            false
+        }
+        None => false,
+    };
+    JOBS_CHANGED.notify_all();
+    keep
+}
+
+/// Whether a scheduler has any jobs left
+pub(crate) fn has_jobs(owner: u64) -> bool {
+    JOBS.lock()
+        .map(|jobs| jobs.values().any(|job| job.owner == owner))
+        .unwrap_or(false)
+}
+
+/// A running timer thread; dropping or stopping it ends the thread
+pub(crate) struct TimerHandle {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl TimerHandle {
+    /// Stop the timer and wait for its thread to exit
+    pub(crate) fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        JOBS_CHANGED.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for TimerHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Start the timer thread for a scheduler's jobs. `fire` is called with the ID
+/// of each due job and returns false when the receiver is gone, which stops
+/// the timer.
+pub(crate) fn start_timer<F>(owner: u64, mut fire: F) -> Result<TimerHandle>
+where
+    F: FnMut(u64) -> bool + Send + 'static,
+{
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_flag = Arc::clone(&stop);
+    let thread = thread::Builder::new()
+        .name("ntnt-schedule".to_string())
+        .spawn(move || {
+            let Ok(mut jobs) = JOBS.lock() else {
+                return;
+            };
+            while !stop_flag.load(Ordering::SeqCst) {
+                let now = Instant::now();
+                let mut due = Vec::new();
+                for (id, job) in jobs.iter_mut().filter(|(_, job)| job.owner == owner) {
+                    if job.done || job.next_run > now {
+                        continue;
+                    }
+                    match &job.trigger {
+                        Trigger::After(_) => job.done = true,
+                        trigger => match trigger.next_after(now) {
+                            Some(next) => job.next_run = next,
+                            None => job.done = true,
+                        },
+                    }
+                    // Skip this tick if the previous run has not finished
+                    if !job.running {
+                        job.running = true;
+                        due.push(*id);
+                    }
+                }
+
+                if !due.is_empty() {
+                    drop(jobs);
+                    due.sort_unstable();
+                    for id in due {
+                        if !fire(id) {
+                            return;
+                        }
+                    }
+                    jobs = match JOBS.lock() {
+                        Ok(jobs) => jobs,
+                        Err(_) => return,
+                    };
+                    continue;
+                }
+
+                let next = jobs
+                    .values()
+                    .filter(|job| job.owner == owner && !job.done)
+                    .map(|job| job.next_run)
+                    .min();
+                jobs = match next {
+                    Some(next) => match JOBS_CHANGED.wait_timeout(jobs, next - now) {
+                        Ok((jobs, _)) => jobs,
+                        Err(_) => return,
+                    },
+                    None => match JOBS_CHANGED.wait(jobs) {
+                        Ok(jobs) => jobs,
+                        Err(_) => return,
+                    },
+                };
+            }
+        })
+        .map_err(|e| IntentError::RuntimeError(format!("Failed to start scheduler: {}", e)))?;
+    Ok(TimerHandle {
+        stop,
+        thread: Some(thread),
+    })
+}
+
+/// Parse a duration given as milliseconds (Int) or a string such as
+/// "500ms", "30s", "5m", "1h30m" or "1d"
+pub(crate) fn parse_duration(value: &Value, fn_name: &str) -> Result<Duration> {
+    let invalid = || {
+        IntentError::TypeError(format!(
+            "{}() requires milliseconds or a duration string like \"30s\", \"5m\" or \"1h30m\"",
+            fn_name
+        ))
+    };
+    match value {
+        Value::Int(ms) if *ms >= 0 => Ok(Duration::from_millis(*ms as u64)),
+        Value::String(text) => {
+            let text = text.trim();
+            if text.is_empty() {
+                return Err(invalid());
+            }
+            let mut total = Duration::ZERO;
+            let mut rest = text;
+            while !rest.is_empty() {
+                let digits = rest
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len());
+                let amount: u64 = rest[..digits].parse().map_err(|_| invalid())?;
+                rest = &rest[digits..];
+                let unit_len = rest
+                    .find(|c: char| c.is_ascii_digit())
+                    .unwrap_or(rest.len());
+                let millis = match &rest[..unit_len] {
+                    "ms" => 1,
+                    "s" => 1_000,
+                    "m" => 60_000,
+                    "h" => 3_600_000,
+                    "d" => 86_400_000,
+                    _ => return Err(invalid()),
+                };
+                rest = &rest[unit_len..];
+                total += Duration::from_millis(amount.saturating_mul(millis));
+            }
+            Ok(total)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// A parsed five-field cron expression: minute hour day-of-month month day-of-week
+#[derive(Debug, Clone)]
+pub(crate) struct CronExpr {
+    minutes: u64,
+    hours: u32,
+    days_of_month: u32,
+    months: u16,
+    days_of_week: u8,
+    /// Day-of-month and day-of-week fields started with `*`; when both are
+    /// restricted a day matches if either does (standard cron behaviour)
+    dom_wildcard: bool,
+    dow_wildcard: bool,
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+impl CronExpr {
+    /// Parse a cron expression. Supports `*`, lists, ranges, steps, month and
+    /// weekday names, and the @hourly/@daily/@weekly/@monthly/@yearly macros.
+    pub(crate) fn parse(expr: &str) -> std::result::Result<Self, String> {
+        let expanded = match expr.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!(
+                "Invalid cron expression '{}': expected 5 fields (minute hour day month weekday)",
+                expr
+            ));
+        }
+        let field = |index: usize, name: &str, min: u32, max: u32, names: &[&str]| {
+            parse_cron_field(fields[index], min, max, names)
+                .map_err(|e| format!("Invalid cron expression '{}': {} field {}", expr, name, e))
+        };
+        let minutes = field(0, "minute", 0, 59, &[])?;
+        let hours = field(1, "hour", 0, 23, &[])?;
+        let days_of_month = field(2, "day-of-month", 1, 31, &[])?;
+        let months = field(3, "month", 1, 12, &MONTH_NAMES)?;
+        // 7 is an alias for Sunday
+        let mut days_of_week = field(4, "day-of-week", 0, 7, &DAY_NAMES)?;
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week |= 1;
+        }
+        Ok(CronExpr {
+            minutes,
+            hours: hours as u32,
+            days_of_month: days_of_month as u32,
+            months: months as u16,
+            days_of_week: (days_of_week & 0x7f) as u8,
+            dom_wildcard: fields[2].starts_with('*'),
+            dow_wildcard: fields[4].starts_with('*'),
+        })
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let dom = self.days_of_month & (1 << date.day()) != 0;
+        let dow = self.days_of_week & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.dom_wildcard, self.dow_wildcard) {
+            (true, true) => true,
+            (true, false) => dow,
+            (false, true) => dom,
+            (false, false) => dom || dow,
+        }
+    }
+
+    /// The first matching minute strictly after `after`, evaluated in `tz`.
+    /// Local times skipped by a DST change are skipped; repeated local times
+    /// fire once, on the earlier occurrence.
+    pub(crate) fn next_after(&self, after: DateTime<Utc>, tz: &Tz) -> Option<DateTime<Utc>> {
+        let local = after.with_timezone(tz).naive_local();
+        let start = local.with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        let mut date = start.date();
+        // Five years covers every valid expression, including Feb 29
+        for _ in 0..(366 * 5) {
+            if self.matches_day(date) {
+                for hour in (0..24).filter(|h| self.hours & (1 << h) != 0) {
+                    for minute in (0..60).filter(|m| self.minutes & (1u64 << m) != 0) {
+                        let candidate = date.and_hms_opt(hour, minute, 0)?;
+                        if candidate < start {
+                            continue;
+                        }
+                        if let Some(time) = tz.from_local_datetime(&candidate).earliest() {
+                            return Some(time.with_timezone(&Utc));
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+}
+
+/// Parse one cron field into a bitmask of allowed values
+fn parse_cron_field(
+    field: &str,
+    min: u32,
+    max: u32,
+    names: &[&str],
+) -> std::result::Result<u64, String> {
+    let value = |text: &str| -> std::result::Result<u32, String> {
+        let lower = text.to_ascii_lowercase();
+        if let Some(pos) = names.iter().position(|n| *n == lower) {
+            // Month names start at 1, weekday names at 0
+            return Ok(pos as u32 + min);
+        }
+        text.parse::<u32>()
+            .ok()
+            .filter(|v| (min..=max).contains(v))
+            .ok_or_else(|| format!("'{}' is out of range {}-{}", text, min, max))
+    };
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| format!("has an invalid step '{}'", step))?;
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (value(a)?, value(b)?)
+        } else {
+            let start = value(range)?;
+            // `a/n` means from a to the end in steps of n
+            (start, if part.contains('/') { max } else { start })
+        };
+        if start > end {
+            return Err(format!("has an empty range '{}'", range));
+        }
+        for v in (start..=end).step_by(step as usize) {
+            mask |= 1 << v;
+        }
+    }
+    Ok(mask)
+}
+
+/// Initialize the std/schedule module
+pub fn init() -> HashMap<String, Value> {
+    let mut module = HashMap::new();
+
+    // every(duration, fn) -> Schedule, after(duration, fn) -> Schedule,
+    // cron(expr, fn, tz) -> Schedule
+    // These keep a reference to the callback, so the interpreter handles the call itself
+    for name in INTERPRETER_FUNCTIONS {
+        module.insert(
+            name.to_string(),
+            Value::NativeFunction {
+                name: name.to_string(),
+                arity: 0,
+                func: |_args| {
+                    Err(IntentError::RuntimeError(
+                        "every(), after() and cron() must be called directly, not stored in a variable"
+                            .to_string(),
+                    ))
+                },
+            },
+        );
+    }
+
+    // cancel(schedule) -> Bool
+    module.insert(
+        "cancel".to_string(),
+        Value::NativeFunction {
+            name: "cancel".to_string(),
+            arity: 1,
+            func: |args| schedule_cancel(&args[0]),
+        },
+    );
+
+    module
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        let ms = |text: &str| parse_duration(&Value::String(text.to_string()), "every").unwrap();
+        assert_eq!(ms("500ms"), Duration::from_millis(500));
+        assert_eq!(ms("5m"), Duration::from_secs(300));
+        assert_eq!(ms("1h30m"), Duration::from_secs(5400));
+        assert_eq!(
+            parse_duration(&Value::Int(250), "after").unwrap(),
+            Duration::from_millis(250)
+        );
+        assert!(parse_duration(&Value::String("5 minutes".to_string()), "every").is_err());
+        assert!(parse_duration(&Value::Int(-1), "every").is_err());
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        let nightly = CronExpr::parse("0 2 * * *").unwrap();
+        assert_eq!(
+            nightly.next_after(utc("2026-03-10T01:59:30Z"), &Tz::UTC),
+            Some(utc("2026-03-10T02:00:00Z"))
+        );
+        assert_eq!(
+            nightly.next_after(utc("2026-03-10T02:00:00Z"), &Tz::UTC),
+            Some(utc("2026-03-11T02:00:00Z"))
+        );
+
+        // Weekdays every 15 minutes during business hours
+        let business = CronExpr::parse("*/15 9-17 * * mon-fri").unwrap();
+        assert_eq!(
+            business.next_after(utc("2026-03-13T17:50:00Z"), &Tz::UTC), // Friday
+            Some(utc("2026-03-16T09:00:00Z"))
+        );
+
+        assert_eq!(
+            CronExpr::parse("@monthly")
+                .unwrap()
+                .next_after(utc("2026-01-31T12:00:00Z"), &Tz::UTC),
+            Some(utc("2026-02-01T00:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_cron_uses_time_zone() {
+        let tz: Tz = "America/New_York".parse().unwrap();
+        let nightly = CronExpr::parse("0 2 * * *").unwrap();
+        // 02:00 EST is 07:00 UTC
+        assert_eq!(
+            nightly.next_after(utc("2026-01-15T00:00:00Z"), &tz),
+            Some(utc("2026-01-15T07:00:00Z"))
+        );
+        // 02:00 does not exist on the spring-forward day; the next run is a day later
+        assert_eq!(
+            nightly.next_after(utc("2026-03-08T05:00:00Z"), &tz),
+            Some(utc("2026-03-09T06:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn test_cron_parse_errors() {
+        assert!(CronExpr::parse("* * * *").is_err());
+        assert!(CronExpr::parse("60 * * * *").is_err());
+        assert!(CronExpr::parse("*/0 * * * *").is_err());
+        assert!(CronExpr::parse("0 0 31 2 *")
+            .unwrap()
+            .next_after(Utc::now(), &Tz::UTC)
+            .is_none());
+    }
+
+    #[test]
+    fn test_timer_fires_and_skips_overlapping_runs() {
+        let owner = new_scheduler_id();
+        let ticker = register(owner, Trigger::Every(Duration::from_millis(10))).unwrap();
+        let once = register(owner, Trigger::After(Duration::from_millis(5))).unwrap();
+        let id = |v: &Value| match v {
+            Value::Map(m) => match m.get("_schedule_id") {
+                Some(Value::Int(id)) => *id as u64,
+                _ => panic!("Expected a schedule"),
+            },
+            _ => panic!("Expected a schedule"),
+        };
+        let (ticker_id, once_id) = (id(&ticker), id(&once));
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let timer = start_timer(owner, move |fired| tx.send(fired).is_ok()).unwrap();
+        let mut fired = Vec::new();
+        while fired.len() < 2 {
+            fired.push(rx.recv_timeout(Duration::from_secs(2)).unwrap());
+        }
+        assert!(fired.contains(&ticker_id) && fired.contains(&once_id));
+
+        // The ticker does not fire again until its run is finished
+        thread::sleep(Duration::from_millis(40));
+        assert!(rx.try_recv().is_err());
+        assert!(finish_run(ticker_id));
+        assert_eq!(rx.recv_timeout(Duration::from_secs(2)).unwrap(), ticker_id);
+
+        // The one-shot job is dropped once its run finishes
+        assert!(!finish_run(once_id));
+        assert!(matches!(
+            schedule_cancel(&ticker).unwrap(),
+            Value::Bool(true)
+        ));
+        assert!(!has_jobs(owner));
+        timer.stop();
+    }
+}
@@ -0,0 +1,802 @@
+//! std/sync module - Shared state across threads
+//!
+//! Provides atomic integers, shared maps and once-cells that can be used from
+//! HTTP handlers, `async fn` tasks and `spawn`ed functions alike. Like channels
+//! in `std/concurrent`, each value is a handle (a map holding an ID), so it can
+//! be copied into other interpreters and still refer to the same state.
+//!
+//! ```ntnt
+//! import { atomic_int, atomic_add, shared_map, shared_update, lazy, force } from "std/sync"
+//!
+//! let hits = atomic_int(0)
+//! atomic_add(hits, 1)
+//!
+//! let counts = shared_map()
+//! fn bump(n) { return n + 1 }
+//! shared_update(counts, "/home", bump, 0)   // atomic per key
+//!
+//! fn load_config() { return read_config() }
+//! let config = lazy(load_config)
+//! let cfg = force(config)                 // runs load_config once
+//! ```
+//!
+//! Passing a name (`atomic_int(0, "hits")`, `shared_map("sessions")`) returns
+//! the same handle to every interpreter in the process that asks for that
+//! name, which is how the HTTP worker pool shares state between workers.
+//!
+//! Handles are copied freely, so the state behind them can't be reclaimed
+//! when a copy goes out of scope: it lives until `free(handle)` is called.
+//! Create unnamed handles once at module level (or use names) rather than
+//! per request, or free them when done.
+
+use crate::error::IntentError;
+use crate::interpreter::Value;
+use crate::stdlib::concurrent::SerializedValue;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, LazyLock, Mutex};
+use std::thread::{self, ThreadId};
+
+type Result<T> = std::result::Result<T, IntentError>;
+
+// Global registries, keyed by handle ID
+static ATOMIC_REGISTRY: LazyLock<Mutex<HashMap<u64, Arc<AtomicI64>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static SHARED_MAP_REGISTRY: LazyLock<Mutex<HashMap<u64, Arc<SharedMap>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static ONCE_REGISTRY: LazyLock<Mutex<HashMap<u64, Arc<OnceCell>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+/// Handle IDs of named atomics and shared maps, keyed by (kind, name)
+static NAMED_HANDLES: LazyLock<Mutex<HashMap<(&'static str, String), u64>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static SYNC_ID_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+/// Names of the functions that call back into user code; the interpreter
+/// intercepts calls to them
+pub(crate) const INTERPRETER_FUNCTIONS: [&str; 3] = ["shared_update", "get_or_init", "force"];
+
+/// A string-keyed map whose entries can be updated atomically one key at a time
+#[derive(Default)]
+struct SharedMap {
+    entries: Mutex<HashMap<String, SerializedValue>>,
+    /// Keys currently being written, with the thread that holds each one
+    busy_keys: Mutex<HashMap<String, ThreadId>>,
+    key_released: Condvar,
+}
+
+/// Holds exclusive access to one key of a SharedMap until dropped
+struct KeyGuard<'a> {
+    map: &'a SharedMap,
+    key: String,
+}
+
+impl SharedMap {
+    /// Wait until no other thread is writing `key`, then claim it. Claiming a
+    /// key the current thread already holds is an error rather than a deadlock.
+    fn lock_key(&self, key: &str) -> Result<KeyGuard<'_>> {
+        let me = thread::current().id();
+        let mut busy = self.busy_keys.lock().map_err(lock_error)?;
+        loop {
+            match busy.get(key) {
+                Some(owner) if *owner == me => {
+                    return Err(IntentError::RuntimeError(format!(
+                        "Key '{}' is already being updated by this thread (shared_update callbacks cannot write their own key)",
+                        key
+                    )))
+                }
+                Some(_) => busy = self.key_released.wait(busy).map_err(lock_error)?,
+                None => break,
+            }
+        }
+        busy.insert(key.to_string(), me);
+        Ok(KeyGuard {
+            map: self,
+            key: key.to_string(),
+        })
+    }
+}
+
+impl Drop for KeyGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut busy) = self.map.busy_keys.lock() {
+            busy.remove(&self.key);
+        }
+        self.map.key_released.notify_all();
+    }
+}
+
+/// A cell that is written at most once; `lazy` cells carry their initializer
+struct OnceCell {
+    slot: Mutex<OnceSlot>,
+    ready: Condvar,
+    init: Option<SerializedValue>,
+}
+
+enum OnceSlot {
+    Empty,
+    Initializing(ThreadId),
+    Ready(SerializedValue),
+}
+
+fn lock_error<E: std::fmt::Display>(e: E) -> IntentError {
+    IntentError::RuntimeError(format!("Failed to lock shared state: {}", e))
+}
+
+fn next_id() -> u64 {
+    SYNC_ID_COUNTER.fetch_add(1, Ordering::SeqCst)
+}
+
+/// ID registered under `name`, or a new one from `create` (called with the
+/// names lock held, so concurrent callers agree on a single ID)
+fn named_id(kind: &'static str, name: &str, create: impl FnOnce() -> Result<u64>) -> Result<u64> {
+    let mut names = NAMED_HANDLES.lock().map_err(lock_error)?;
+    if let Some(id) = names.get(&(kind, name.to_string())) {
+        return Ok(*id);
+    }
+    let id = create()?;
+    names.insert((kind, name.to_string()), id);
+    Ok(id)
+}
+
+/// Optional handle name passed as the last argument
+fn handle_name<'a>(value: Option<&'a Value>, fn_name: &str) -> Result<Option<&'a str>> {
+    match value {
+        None => Ok(None),
+        Some(Value::String(name)) => Ok(Some(name)),
+        Some(_) => Err(IntentError::TypeError(format!(
+            "{}() name must be a string",
+            fn_name
+        ))),
+    }
+}
+
+/// Create a handle value for the given registry ID
+fn create_handle(id_key: &str, id: u64, type_name: &str) -> Value {
+    let mut handle = HashMap::new();
+    handle.insert(id_key.to_string(), Value::Int(id as i64));
+    handle.insert("type".to_string(), Value::String(type_name.to_string()));
+    Value::Map(handle)
+}
+
+/// Look up the shared state behind a handle
+fn lookup<T>(
+    registry: &Mutex<HashMap<u64, Arc<T>>>,
+    handle: &Value,
+    id_key: &str,
+    type_name: &str,
+) -> Result<Arc<T>> {
+    let id = match handle {
+        Value::Map(map) => match map.get(id_key) {
+            Some(Value::Int(id)) => Some(*id as u64),
+            _ => None,
+        },
+        _ => None,
+    };
+    let id = id.ok_or_else(|| IntentError::TypeError(format!("Expected an {}", type_name)))?;
+    registry
+        .lock()
+        .map_err(lock_error)?
+        .get(&id)
+        .cloned()
+        .ok_or_else(|| IntentError::RuntimeError(format!("Invalid or freed {}", type_name)))
+}
+
+/// free(handle) -> Bool
+/// Removes the state behind an AtomicInt, SharedMap or OnceCell handle, and
+/// its name if it has one, so the next call with that name starts fresh.
+/// Every copy of the handle stops working. Returns false if already freed.
+fn sync_free(handle: &Value) -> Result<Value> {
+    let handle_id = |key: &str| match handle {
+        Value::Map(map) => match map.get(key) {
+            Some(Value::Int(id)) => Some(*id as u64),
+            _ => None,
+        },
+        _ => None,
+    };
+    // Held throughout so named_id() can't hand out the ID while it is removed
+    let mut names = NAMED_HANDLES.lock().map_err(lock_error)?;
+    let (id, removed) = if let Some(id) = handle_id("_atomic_id") {
+        let removed = ATOMIC_REGISTRY.lock().map_err(lock_error)?.remove(&id);
+        (id, removed.is_some())
+    } else if let Some(id) = handle_id("_shared_map_id") {
+        let removed = SHARED_MAP_REGISTRY.lock().map_err(lock_error)?.remove(&id);
+        (id, removed.is_some())
+    } else if let Some(id) = handle_id("_once_id") {
+        let removed = ONCE_REGISTRY.lock().map_err(lock_error)?.remove(&id);
+        (id, removed.is_some())
+    } else {
+        return Err(IntentError::TypeError(
+            "free requires an AtomicInt, SharedMap or OnceCell".to_string(),
+        ));
+    };
+    // IDs are unique across kinds
+    names.retain(|_, named| *named != id);
+    Ok(Value::Bool(removed))
+}
+
+fn some(value: Value) -> Value {
+    Value::EnumValue {
+        enum_name: "Option".to_string(),
+        variant: "Some".to_string(),
+        values: vec![value],
+    }
+}
+
+fn none() -> Value {
+    Value::EnumValue {
+        enum_name: "Option".to_string(),
+        variant: "None".to_string(),
+        values: vec![],
+    }
+}
+
+fn expect_int(value: &Value, fn_name: &str) -> Result<i64> {
+    match value {
+        Value::Int(n) => Ok(*n),
+        _ => Err(IntentError::TypeError(format!(
+            "{} requires an integer",
+            fn_name
+        ))),
+    }
+}
+
+fn expect_key<'a>(value: &'a Value, fn_name: &str) -> Result<&'a str> {
+    match value {
+        Value::String(key) => Ok(key),
+        _ => Err(IntentError::TypeError(format!(
+            "{} requires a string key",
+            fn_name
+        ))),
+    }
+}
+
+// ============================================================================
+// AtomicInt
+// ============================================================================
+
+fn atomic(handle: &Value) -> Result<Arc<AtomicI64>> {
+    lookup(&ATOMIC_REGISTRY, handle, "_atomic_id", "AtomicInt")
+}
+
+/// atomic_int(initial, name?) -> AtomicInt
+/// A named atomic is created once; later calls return the same handle
+fn sync_atomic_int(initial: i64, name: Option<&str>) -> Result<Value> {
+    let create = || {
+        let id = next_id();
+        ATOMIC_REGISTRY
+            .lock()
+            .map_err(lock_error)?
+            .insert(id, Arc::new(AtomicI64::new(initial)));
+        Ok(id)
+    };
+    let id = match name {
+        Some(name) => named_id("atomic_int", name, create)?,
+        None => create()?,
+    };
+    Ok(create_handle("_atomic_id", id, "AtomicInt"))
+}
+
+/// atomic_add(atomic, delta) -> Int
+/// Adds `delta` and returns the new value
+fn sync_atomic_add(handle: &Value, delta: i64) -> Result<Value> {
+    let previous = atomic(handle)?.fetch_add(delta, Ordering::SeqCst);
+    Ok(Value::Int(previous.wrapping_add(delta)))
+}
+
+/// compare_and_swap(atomic, expected, new) -> Bool
+/// Stores `new` only if the current value equals `expected`
+fn sync_compare_and_swap(handle: &Value, expected: i64, new: i64) -> Result<Value> {
+    let swapped = atomic(handle)?
+        .compare_exchange(expected, new, Ordering::SeqCst, Ordering::SeqCst)
+        .is_ok();
+    Ok(Value::Bool(swapped))
+}
+
+// ============================================================================
+// SharedMap
+// ============================================================================
+
+fn shared(handle: &Value) -> Result<Arc<SharedMap>> {
+    lookup(&SHARED_MAP_REGISTRY, handle, "_shared_map_id", "SharedMap")
+}
+
+/// shared_map(name?) -> SharedMap
+/// A named map is created once; later calls return the same handle
+fn sync_shared_map(name: Option<&str>) -> Result<Value> {
+    let create = || {
+        let id = next_id();
+        SHARED_MAP_REGISTRY
+            .lock()
+            .map_err(lock_error)?
+            .insert(id, Arc::new(SharedMap::default()));
+        Ok(id)
+    };
+    let id = match name {
+        Some(name) => named_id("shared_map", name, create)?,
+        None => create()?,
+    };
+    Ok(create_handle("_shared_map_id", id, "SharedMap"))
+}
+
+/// shared_get(map, key) -> Option<Value>
+fn sync_shared_get(handle: &Value, key: &str) -> Result<Value> {
+    let map = shared(handle)?;
+    let entries = map.entries.lock().map_err(lock_error)?;
+    Ok(entries
+        .get(key)
+        .map(|value| some(value.to_value()))
+        .unwrap_or_else(none))
+}
+
+/// shared_set(map, key, value) -> Unit
+fn sync_shared_set(handle: &Value, key: &str, value: &Value) -> Result<Value> {
+    let map = shared(handle)?;
+    let value = SerializedValue::from_value(value)?;
+    let _guard = map.lock_key(key)?;
+    map.entries
+        .lock()
+        .map_err(lock_error)?
+        .insert(key.to_string(), value);
+    Ok(Value::Unit)
+}
+
+/// shared_delete(map, key) -> Bool
+fn sync_shared_delete(handle: &Value, key: &str) -> Result<Value> {
+    let map = shared(handle)?;
+    let _guard = map.lock_key(key)?;
+    let removed = map.entries.lock().map_err(lock_error)?.remove(key);
+    Ok(Value::Bool(removed.is_some()))
+}
+
+/// shared_keys(map) -> Array<String>
+fn sync_shared_keys(handle: &Value) -> Result<Value> {
+    let map = shared(handle)?;
+    let mut keys: Vec<String> = map
+        .entries
+        .lock()
+        .map_err(lock_error)?
+        .keys()
+        .cloned()
+        .collect();
+    keys.sort();
+    Ok(Value::Array(keys.into_iter().map(Value::String).collect()))
+}
+
+/// shared_update(map, key, fn, default) -> Value
+/// Replaces the value at `key` with `update(current)`, where `current` is
+/// `default` for a missing key, and returns the new value. Other writers of the
+/// same key wait until the update finishes; other keys are not blocked.
+pub(crate) fn shared_update(
+    handle: &Value,
+    key: &Value,
+    default: Value,
+    update: impl FnOnce(Value) -> Result<Value>,
+) -> Result<Value> {
+    let map = shared(handle)?;
+    let key = expect_key(key, "shared_update")?;
+    let _guard = map.lock_key(key)?;
+    let current = map
+        .entries
+        .lock()
+        .map_err(lock_error)?
+        .get(key)
+        .map(SerializedValue::to_value)
+        .unwrap_or(default);
+    let updated = update(current)?;
+    let stored = SerializedValue::from_value(&updated)?;
+    map.entries
+        .lock()
+        .map_err(lock_error)?
+        .insert(key.to_string(), stored);
+    Ok(updated)
+}
+
+// ============================================================================
+// OnceCell / lazy
+// ============================================================================
+
+fn once(handle: &Value) -> Result<Arc<OnceCell>> {
+    lookup(&ONCE_REGISTRY, handle, "_once_id", "OnceCell")
+}
+
+fn register_once(init: Option<SerializedValue>) -> Result<Value> {
+    let id = next_id();
+    let cell = OnceCell {
+        slot: Mutex::new(OnceSlot::Empty),
+        ready: Condvar::new(),
+        init,
+    };
+    ONCE_REGISTRY
+        .lock()
+        .map_err(lock_error)?
+        .insert(id, Arc::new(cell));
+    Ok(create_handle("_once_id", id, "OnceCell"))
+}
+
+/// once_get(cell) -> Option<Value>
+fn sync_once_get(handle: &Value) -> Result<Value> {
+    let cell = once(handle)?;
+    let slot = cell.slot.lock().map_err(lock_error)?;
+    Ok(match &*slot {
+        OnceSlot::Ready(value) => some(value.to_value()),
+        _ => none(),
+    })
+}
+
+/// The initializer stored by `lazy(fn)`, if the cell has one
+pub(crate) fn lazy_initializer(handle: &Value) -> Result<Option<SerializedValue>> {
+    Ok(once(handle)?.init.clone())
+}
+
+/// Return the cell's value, running `init` to fill it if it is empty. Only one
+/// caller runs `init`; concurrent callers wait for its result. If `init` fails
+/// the cell stays empty and the next caller tries again.
+pub(crate) fn once_get_or_init(
+    handle: &Value,
+    init: impl FnOnce() -> Result<Value>,
+) -> Result<Value> {
+    let cell = once(handle)?;
+    let me = thread::current().id();
+    {
+        let mut slot = cell.slot.lock().map_err(lock_error)?;
+        loop {
+            match &*slot {
+                OnceSlot::Ready(value) => return Ok(value.to_value()),
+                OnceSlot::Initializing(owner) if *owner == me => {
+                    return Err(IntentError::RuntimeError(
+                        "OnceCell initializer tried to read its own cell".to_string(),
+                    ))
+                }
+                OnceSlot::Initializing(_) => slot = cell.ready.wait(slot).map_err(lock_error)?,
+                OnceSlot::Empty => break,
+            }
+        }
+        *slot = OnceSlot::Initializing(me);
+    }
+
+    let result = init().and_then(|value| Ok((SerializedValue::from_value(&value)?, value)));
+    let mut slot = cell.slot.lock().map_err(lock_error)?;
+    let outcome = match result {
+        Ok((stored, value)) => {
+            *slot = OnceSlot::Ready(stored);
+            Ok(value)
+        }
+        Err(e) => {
+            *slot = OnceSlot::Empty;
+            Err(e)
+        }
+    };
+    cell.ready.notify_all();
+    outcome
+}
+
+/// Initialize the std/sync module
+pub fn init() -> HashMap<String, Value> {
+    let mut module = HashMap::new();
+
+    // atomic_int(initial, name?) -> AtomicInt
+    module.insert(
+        "atomic_int".to_string(),
+        Value::NativeFunction {
+            name: "atomic_int".to_string(),
+            arity: 0,
+            func: |args| {
+                if args.is_empty() || args.len() > 2 {
+                    return Err(IntentError::ArityMismatch {
+                        name: "atomic_int".to_string(),
+                        expected: 1,
+                        got: args.len(),
+                    });
+                }
+                sync_atomic_int(
+                    expect_int(&args[0], "atomic_int")?,
+                    handle_name(args.get(1), "atomic_int")?,
+                )
+            },
+        },
+    );
+
+    // atomic_get(atomic) -> Int
+    module.insert(
+        "atomic_get".to_string(),
+        Value::NativeFunction {
+            name: "atomic_get".to_string(),
+            arity: 1,
+            func: |args| Ok(Value::Int(atomic(&args[0])?.load(Ordering::SeqCst))),
+        },
+    );
+
+    // atomic_set(atomic, value) -> Unit
+    module.insert(
+        "atomic_set".to_string(),
+        Value::NativeFunction {
+            name: "atomic_set".to_string(),
+            arity: 2,
+            func: |args| {
+                let value = expect_int(&args[1], "atomic_set")?;
+                atomic(&args[0])?.store(value, Ordering::SeqCst);
+                Ok(Value::Unit)
+            },
+        },
+    );
+
+    // atomic_add(atomic, delta) -> Int
+    module.insert(
+        "atomic_add".to_string(),
+        Value::NativeFunction {
+            name: "atomic_add".to_string(),
+            arity: 2,
+            func: |args| sync_atomic_add(&args[0], expect_int(&args[1], "atomic_add")?),
+        },
+    );
+
+    // compare_and_swap(atomic, expected, new) -> Bool
+    module.insert(
+        "compare_and_swap".to_string(),
+        Value::NativeFunction {
+            name: "compare_and_swap".to_string(),
+            arity: 3,
+            func: |args| {
+                sync_compare_and_swap(
+                    &args[0],
+                    expect_int(&args[1], "compare_and_swap")?,
+                    expect_int(&args[2], "compare_and_swap")?,
+                )
+            },
+        },
+    );
+
+    // shared_map(name?) -> SharedMap
+    module.insert(
+        "shared_map".to_string(),
+        Value::NativeFunction {
+            name: "shared_map".to_string(),
+            arity: 0,
+            func: |args| {
+                if args.len() > 1 {
+                    return Err(IntentError::ArityMismatch {
+                        name: "shared_map".to_string(),
+                        expected: 1,
+                        got: args.len(),
+                    });
+                }
+                sync_shared_map(handle_name(args.first(), "shared_map")?)
+            },
+        },
+    );
+
+    // shared_get(map, key) -> Option<Value>
+    module.insert(
+        "shared_get".to_string(),
+        Value::NativeFunction {
+            name: "shared_get".to_string(),
+            arity: 2,
+            func: |args| sync_shared_get(&args[0], expect_key(&args[1], "shared_get")?),
+        },
+    );
+
+    // shared_set(map, key, value) -> Unit
+    module.insert(
+        "shared_set".to_string(),
+        Value::NativeFunction {
+            name: "shared_set".to_string(),
+            arity: 3,
+            func: |args| sync_shared_set(&args[0], expect_key(&args[1], "shared_set")?, &args[2]),
+        },
+    );
+
+    // shared_delete(map, key) -> Bool
+    module.insert(
+        "shared_delete".to_string(),
+        Value::NativeFunction {
+            name: "shared_delete".to_string(),
+            arity: 2,
+            func: |args| sync_shared_delete(&args[0], expect_key(&args[1], "shared_delete")?),
+        },
+    );
+
+    // shared_keys(map) -> Array<String>
+    module.insert(
+        "shared_keys".to_string(),
+        Value::NativeFunction {
+            name: "shared_keys".to_string(),
+            arity: 1,
+            func: |args| sync_shared_keys(&args[0]),
+        },
+    );
+
+    // once_cell() -> OnceCell
+    module.insert(
+        "once_cell".to_string(),
+        Value::NativeFunction {
+            name: "once_cell".to_string(),
+            arity: 0,
+            func: |_args| register_once(None),
+        },
+    );
+
+    // lazy(fn) -> OnceCell
+    module.insert(
+        "lazy".to_string(),
+        Value::NativeFunction {
+            name: "lazy".to_string(),
+            arity: 1,
+            func: |args| match &args[0] {
+                Value::Function { .. } | Value::NativeFunction { .. } => {
+                    register_once(Some(SerializedValue::capture(&args[0])?))
+                }
+                _ => Err(IntentError::TypeError(
+                    "lazy requires a function".to_string(),
+                )),
+            },
+        },
+    );
+
+    // once_get(cell) -> Option<Value>
+    module.insert(
+        "once_get".to_string(),
+        Value::NativeFunction {
+            name: "once_get".to_string(),
+            arity: 1,
+            func: |args| sync_once_get(&args[0]),
+        },
+    );
+
+    // free(handle) -> Bool
+    module.insert(
+        "free".to_string(),
+        Value::NativeFunction {
+            name: "free".to_string(),
+            arity: 1,
+            func: |args| sync_free(&args[0]),
+        },
+    );
+
+    // shared_update(map, key, fn, default), get_or_init(cell, fn) and force(cell)
+    // call user functions, so the interpreter handles the call itself
+    for name in INTERPRETER_FUNCTIONS {
+        module.insert(
+            name.to_string(),
+            Value::NativeFunction {
+                name: name.to_string(),
+                arity: 0,
+                func: |_args| {
+                    Err(IntentError::RuntimeError(
+                        "shared_update(), get_or_init() and force() must be called directly, not stored in a variable"
+                            .to_string(),
+                    ))
+                },
+            },
+        );
+    }
+
+    module
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_atomic_int_across_threads() {
+        let counter = sync_atomic_int(0, None).unwrap();
+        let id = match &counter {
+            Value::Map(map) => map.get("_atomic_id").cloned().unwrap(),
+            _ => panic!("Expected a handle"),
+        };
+        let Value::Int(id) = id else {
+            panic!("Expected an integer id")
+        };
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                thread::spawn(move || {
+                    let handle = create_handle("_atomic_id", id as u64, "AtomicInt");
+                    for _ in 0..250 {
+                        sync_atomic_add(&handle, 1).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        assert_eq!(atomic(&counter).unwrap().load(Ordering::SeqCst), 1000);
+        assert!(matches!(
+            sync_compare_and_swap(&counter, 1000, 5).unwrap(),
+            Value::Bool(true)
+        ));
+        assert!(matches!(
+            sync_compare_and_swap(&counter, 1000, 6).unwrap(),
+            Value::Bool(false)
+        ));
+    }
+
+    #[test]
+    fn test_named_handles_are_shared() {
+        let first = sync_atomic_int(1, Some("test_named_hits")).unwrap();
+        thread::spawn(|| {
+            let again = sync_atomic_int(100, Some("test_named_hits")).unwrap();
+            sync_atomic_add(&again, 2).unwrap();
+        })
+        .join()
+        .unwrap();
+        assert_eq!(atomic(&first).unwrap().load(Ordering::SeqCst), 3);
+
+        let other = sync_atomic_int(1, Some("test_named_other")).unwrap();
+        assert_eq!(atomic(&other).unwrap().load(Ordering::SeqCst), 1);
+
+        let map = sync_shared_map(Some("test_named_map")).unwrap();
+        sync_shared_set(&map, "k", &Value::Int(7)).unwrap();
+        let same = sync_shared_map(Some("test_named_map")).unwrap();
+        assert!(matches!(
+            sync_shared_get(&same, "k").unwrap(),
+            Value::EnumValue { ref variant, .. } if variant == "Some"
+        ));
+        let unnamed = sync_shared_map(None).unwrap();
+        assert!(matches!(
+            sync_shared_get(&unnamed, "k").unwrap(),
+            Value::EnumValue { ref variant, .. } if variant == "None"
+        ));
+    }
+
+    #[test]
+    fn test_free_removes_state_and_name() {
+        let counter = sync_atomic_int(5, Some("test_free_hits")).unwrap();
+        assert!(matches!(sync_free(&counter).unwrap(), Value::Bool(true)));
+        assert!(matches!(sync_free(&counter).unwrap(), Value::Bool(false)));
+        let err = sync_atomic_add(&counter, 1).unwrap_err();
+        assert!(err.to_string().contains("Invalid or freed AtomicInt"));
+
+        // The name now refers to new state
+        let fresh = sync_atomic_int(0, Some("test_free_hits")).unwrap();
+        assert_eq!(atomic(&fresh).unwrap().load(Ordering::SeqCst), 0);
+
+        let map = sync_shared_map(None).unwrap();
+        let cell = register_once(None).unwrap();
+        assert!(matches!(sync_free(&map).unwrap(), Value::Bool(true)));
+        assert!(matches!(sync_free(&cell).unwrap(), Value::Bool(true)));
+        assert!(shared(&map).is_err());
+        assert!(once(&cell).is_err());
+        assert!(sync_free(&Value::Int(1)).is_err());
+    }
+
+    #[test]
+    fn test_shared_update_uses_default_and_rejects_reentry() {
+        let map = sync_shared_map(None).unwrap();
+        let key = Value::String("hits".to_string());
+        let bump = |v: Value| match v {
+            Value::Int(n) => Ok(Value::Int(n + 1)),
+            _ => panic!("Expected an integer"),
+        };
+        shared_update(&map, &key, Value::Int(10), bump).unwrap();
+        let updated = shared_update(&map, &key, Value::Int(10), bump).unwrap();
+        assert!(matches!(updated, Value::Int(12)));
+
+        let reentrant = shared_update(&map, &key, Value::Int(0), |v| {
+            sync_shared_set(&map, "hits", &Value::Int(0))?;
+            Ok(v)
+        });
+        assert!(reentrant.is_err());
+        // The failed update released the key
+        sync_shared_set(&map, "hits", &Value::Int(1)).unwrap();
+    }
+
+    #[test]
+    fn test_once_cell_runs_init_once() {
+        let cell = register_once(None).unwrap();
+        let first = once_get_or_init(&cell, || Ok(Value::Int(1))).unwrap();
+        let second = once_get_or_init(&cell, || Ok(Value::Int(2))).unwrap();
+        assert!(matches!(first, Value::Int(1)));
+        assert!(matches!(second, Value::Int(1)));
+
+        let failing = register_once(None).unwrap();
+        let err = once_get_or_init(&failing, || {
+            Err(IntentError::RuntimeError("boom".to_string()))
+        });
+        assert!(err.is_err());
+        assert!(matches!(
+            once_get_or_init(&failing, || Ok(Value::Int(3))).unwrap(),
+            Value::Int(3)
+        ));
+    }
+}
@@ -35,7 +35,7 @@ where
 }
 
 /// Parse timezone string to chrono_tz::Tz
-fn parse_timezone(tz: &str) -> Result<Tz, IntentError> {
+pub(crate) fn parse_timezone(tz: &str) -> Result<Tz, IntentError> {
     tz.parse::<Tz>().map_err(|_| {
         IntentError::RuntimeError(format!(
             "Invalid timezone: '{}'. Use IANA format like 'America/New_York'",
@@ -4,7 +4,7 @@
 //! Produces diagnostics (errors/warnings) without blocking execution.
 //! Uses gradual typing: untyped code defaults to `Any`, which is compatible with everything.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::ast::*;
 use crate::types::Type;
@@ -34,6 +34,34 @@ pub struct FunctionSig {
     pub variadic: bool,
 }
 
+/// An enum's variants: [(variant_name, Option<field_types>)]
+type EnumVariants = Vec<(String, Option<Vec<Type>>)>;
+
+/// A trait's method signatures and parent traits
+#[derive(Debug, Clone)]
+struct TraitSig {
+    /// (method name, signature, has default implementation)
+    methods: Vec<(String, FunctionSig, bool)>,
+    supertraits: Vec<String>,
+}
+
+/// A generic function's type parameters and its parameters' declared types
+type GenericParams = (Vec<TypeParam>, Vec<Option<TypeExpr>>);
+
+/// Declarations a file makes available to files that import from it
+#[derive(Debug, Clone, Default)]
+struct ModuleExports {
+    functions: HashMap<String, FunctionSig>,
+    param_type_names: HashMap<String, Vec<Option<String>>>,
+    structs: HashMap<String, Vec<(String, Type)>>,
+    enums: HashMap<String, EnumVariants>,
+    type_aliases: HashMap<String, Type>,
+    refinements: HashMap<String, Vec<(String, Expression)>>,
+    traits: HashMap<String, TraitSig>,
+    trait_impls: HashMap<String, Vec<String>>,
+    generic_params: HashMap<String, GenericParams>,
+}
+
 /// Type checking context with scoped variable bindings
 pub struct TypeContext {
     /// Stack of variable scopes (innermost last)
@@ -43,9 +71,20 @@ pub struct TypeContext {
     /// Struct field types
     structs: HashMap<String, Vec<(String, Type)>>,
     /// Enum variants: enum_name -> [(variant_name, Option<field_types>)]
-    enums: HashMap<String, Vec<(String, Option<Vec<Type>>)>>,
+    enums: HashMap<String, EnumVariants>,
     /// Type aliases
     type_aliases: HashMap<String, Type>,
+    /// Trait definitions
+    traits: HashMap<String, TraitSig>,
+    /// Traits implemented by each type: type_name -> [trait_name]
+    trait_impls: HashMap<String, Vec<String>>,
+    /// Bounded generic functions (for trait bound checks at call sites)
+    generic_params: HashMap<String, GenericParams>,
+    /// Refinement predicates per refined alias, including those inherited from
+    /// refined base aliases: alias -> [(declaring alias, predicate over `self`)]
+    refinements: HashMap<String, Vec<(String, Expression)>>,
+    /// Declared type name of each user function parameter (for refinement checks)
+    param_type_names: HashMap<String, Vec<Option<String>>>,
     /// Builtin and stdlib function signatures
     builtin_sigs: HashMap<String, FunctionSig>,
     /// Return type of current function being checked
@@ -58,12 +97,143 @@ pub struct TypeContext {
     strict_lint: bool,
     /// File path of the current file being checked (for resolving relative imports)
     current_file: Option<String>,
-    /// Cache of already-parsed module signatures (to avoid re-parsing)
-    module_cache: HashMap<String, HashMap<String, FunctionSig>>,
+    /// Cache of already-parsed module declarations (to avoid re-parsing)
+    module_cache: HashMap<String, ModuleExports>,
     /// Set of files currently being resolved (for circular import detection)
     resolving_files: Vec<String>,
 }
 
+/// Struct types registered for HTTP values, which are maps at runtime
+const SYNTHETIC_STRUCTS: &[&str] = &["Request", "Response"];
+
+/// Variables and the types they are narrowed to within a guarded region
+type Narrowings = Vec<(String, Type)>;
+
+/// A compile-time constant, used to check literals against refinement predicates
+#[derive(Debug, Clone, PartialEq)]
+enum ConstValue {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Bool(bool),
+}
+
+impl std::fmt::Display for ConstValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConstValue::Int(n) => write!(f, "{}", n),
+            ConstValue::Float(n) => write!(f, "{}", n),
+            ConstValue::Str(s) => write!(f, "\"{}\"", s),
+            ConstValue::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+impl ConstValue {
+    fn from_literal(expr: &Expression) -> Option<ConstValue> {
+        match expr {
+            Expression::Integer(n) => Some(ConstValue::Int(*n)),
+            Expression::Float(n) => Some(ConstValue::Float(*n)),
+            Expression::String(s) => Some(ConstValue::Str(s.clone())),
+            Expression::Bool(b) => Some(ConstValue::Bool(*b)),
+            Expression::Unary {
+                operator: UnaryOp::Neg,
+                operand,
+            } => match Self::from_literal(operand)? {
+                ConstValue::Int(n) => Some(ConstValue::Int(-n)),
+                ConstValue::Float(n) => Some(ConstValue::Float(-n)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Evaluate a refinement predicate with `self` bound to a constant.
+    /// Returns None for anything that can't be decided statically.
+    fn eval(expr: &Expression, self_value: &ConstValue) -> Option<ConstValue> {
+        use ConstValue::*;
+        match expr {
+            Expression::Identifier(name) if name == "self" => Some(self_value.clone()),
+            Expression::Call {
+                function,
+                arguments,
+            } if arguments.len() == 1
+                && matches!(function.as_ref(), Expression::Identifier(f) if f == "len") =>
+            {
+                match Self::eval(&arguments[0], self_value)? {
+                    Str(s) => Some(Int(s.chars().count() as i64)),
+                    _ => None,
+                }
+            }
+            Expression::Unary { operator, operand } => {
+                match (operator, Self::eval(operand, self_value)?) {
+                    (UnaryOp::Not, Bool(b)) => Some(Bool(!b)),
+                    (UnaryOp::Neg, Int(n)) => Some(Int(-n)),
+                    (UnaryOp::Neg, Float(n)) => Some(Float(-n)),
+                    _ => None,
+                }
+            }
+            Expression::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                // Short-circuit so undecidable right-hand sides don't block a decision
+                let l = Self::eval(left, self_value)?;
+                match (operator, &l) {
+                    (BinaryOp::And, Bool(false)) => return Some(Bool(false)),
+                    (BinaryOp::Or, Bool(true)) => return Some(Bool(true)),
+                    _ => {}
+                }
+                let r = Self::eval(right, self_value)?;
+                Self::binary(*operator, l, r)
+            }
+            _ => Self::from_literal(expr),
+        }
+    }
+
+    fn binary(op: BinaryOp, l: ConstValue, r: ConstValue) -> Option<ConstValue> {
+        use ConstValue::*;
+        let as_f64 = |v: &ConstValue| match v {
+            Int(n) => Some(*n as f64),
+            Float(n) => Some(*n),
+            _ => None,
+        };
+        match (op, &l, &r) {
+            (BinaryOp::And, Bool(a), Bool(b)) => Some(Bool(*a && *b)),
+            (BinaryOp::Or, Bool(a), Bool(b)) => Some(Bool(*a || *b)),
+            (BinaryOp::Eq, _, _) => Some(Bool(
+                l == r || (as_f64(&l).is_some() && as_f64(&l) == as_f64(&r)),
+            )),
+            (BinaryOp::Ne, _, _) => Some(Bool(
+                !(l == r || (as_f64(&l).is_some() && as_f64(&l) == as_f64(&r))),
+            )),
+            (BinaryOp::Add, Int(a), Int(b)) => a.checked_add(*b).map(Int),
+            (BinaryOp::Sub, Int(a), Int(b)) => a.checked_sub(*b).map(Int),
+            (BinaryOp::Mul, Int(a), Int(b)) => a.checked_mul(*b).map(Int),
+            (BinaryOp::Mod, Int(a), Int(b)) if *b != 0 => Some(Int(a % b)),
+            (BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge, Str(a), Str(b)) => {
+                Some(Bool(match op {
+                    BinaryOp::Lt => a < b,
+                    BinaryOp::Le => a <= b,
+                    BinaryOp::Gt => a > b,
+                    _ => a >= b,
+                }))
+            }
+            (BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge, _, _) => {
+                let (a, b) = (as_f64(&l)?, as_f64(&r)?);
+                Some(Bool(match op {
+                    BinaryOp::Lt => a < b,
+                    BinaryOp::Le => a <= b,
+                    BinaryOp::Gt => a > b,
+                    _ => a >= b,
+                }))
+            }
+            _ => None,
+        }
+    }
+}
+
 /// Returns true if NTNT_STRICT mode is enabled
 pub fn is_strict_mode() -> bool {
     std::env::var("NTNT_STRICT").map_or(false, |v| v == "1" || v == "true")
@@ -155,6 +325,11 @@ impl TypeContext {
             structs: HashMap::new(),
             enums: HashMap::new(),
             type_aliases: HashMap::new(),
+            traits: HashMap::new(),
+            trait_impls: HashMap::new(),
+            generic_params: HashMap::new(),
+            refinements: HashMap::new(),
+            param_type_names: HashMap::new(),
             builtin_sigs: HashMap::new(),
             current_return_type: None,
             diagnostics: Vec::new(),
@@ -194,6 +369,14 @@ impl TypeContext {
     // ── Diagnostics ───────────────────────────────────────────────────
 
     fn emit(&mut self, severity: Severity, message: String, line: usize, hint: Option<String>) {
+        // Expressions can be inferred more than once (e.g. a block's trailing expression)
+        if self
+            .diagnostics
+            .iter()
+            .any(|d| d.line == line && d.message == message)
+        {
+            return;
+        }
         self.diagnostics.push(TypeDiagnostic {
             severity,
             message,
@@ -222,7 +405,6 @@ impl TypeContext {
         0
     }
 
-    #[allow(dead_code)]
     fn find_line_after(&self, needle: &str, after: usize) -> usize {
         for (i, line) in self.source_lines.iter().enumerate() {
             if i + 1 > after && line.contains(needle) {
@@ -233,6 +415,16 @@ impl TypeContext {
         self.find_line(needle)
     }
 
+    /// Find the first call to `call` after `after` whose line mentions `arg`
+    fn find_call_line(&self, call: &str, arg: &str, after: usize) -> usize {
+        for (i, line) in self.source_lines.iter().enumerate() {
+            if i + 1 > after && line.contains(call) && line.contains(arg) {
+                return i + 1;
+            }
+        }
+        self.find_line_after(call, after)
+    }
+
     // ── Type resolution ───────────────────────────────────────────────
 
     /// Convert AST TypeExpr to internal Type
@@ -355,9 +547,20 @@ impl TypeContext {
                 name,
                 params,
                 return_type,
-                type_params: _,
+                type_params,
+                is_async,
                 ..
             } => {
+                if type_params.iter().any(|tp| !tp.bounds.is_empty()) {
+                    self.generic_params.insert(
+                        name.clone(),
+                        (
+                            type_params.clone(),
+                            params.iter().map(|p| p.type_annotation.clone()).collect(),
+                        ),
+                    );
+                }
+
                 let param_types: Vec<(String, Type)> = params
                     .iter()
                     .map(|p| {
@@ -374,6 +577,26 @@ impl TypeContext {
                     .as_ref()
                     .map(|t| self.resolve_type_expr(t))
                     .unwrap_or(Type::Any);
+                // Calling an async fn yields a task handle; `await` unwraps it
+                let ret = if *is_async {
+                    Type::Generic {
+                        name: "Task".to_string(),
+                        args: vec![ret],
+                    }
+                } else {
+                    ret
+                };
+
+                self.param_type_names.insert(
+                    name.clone(),
+                    params
+                        .iter()
+                        .map(|p| match &p.type_annotation {
+                            Some(TypeExpr::Named(type_name)) => Some(type_name.clone()),
+                            _ => None,
+                        })
+                        .collect(),
+                );
 
                 self.functions.insert(
                     name.clone(),
@@ -418,15 +641,91 @@ impl TypeContext {
                 name,
                 target,
                 type_params: _,
+                refinement,
             } => {
+                // A refined alias resolves to its base type, so it is a subtype of it;
+                // values flowing in are checked at runtime (or here, for literals).
                 let resolved = self.resolve_type_expr(target);
                 self.type_aliases.insert(name.clone(), resolved);
+
+                let mut predicates = match target {
+                    TypeExpr::Named(base) => {
+                        self.refinements.get(base).cloned().unwrap_or_default()
+                    }
+                    _ => Vec::new(),
+                };
+                if let Some(predicate) = refinement {
+                    predicates.push((name.clone(), predicate.clone()));
+                }
+                if !predicates.is_empty() {
+                    self.refinements.insert(name.clone(), predicates);
+                }
+            }
+            Statement::Trait {
+                name,
+                methods,
+                supertraits,
+                ..
+            } => {
+                let methods = methods
+                    .iter()
+                    .map(|m| {
+                        let sig = FunctionSig {
+                            params: m
+                                .params
+                                .iter()
+                                .map(|p| {
+                                    let typ = p
+                                        .type_annotation
+                                        .as_ref()
+                                        .map(|t| self.resolve_type_expr(t))
+                                        .unwrap_or(Type::Any);
+                                    (p.name.clone(), typ)
+                                })
+                                .collect(),
+                            return_type: m
+                                .return_type
+                                .as_ref()
+                                .map(|t| self.resolve_type_expr(t))
+                                .unwrap_or(Type::Any),
+                            variadic: false,
+                        };
+                        (m.name.clone(), sig, m.default_body.is_some())
+                    })
+                    .collect();
+                self.traits.insert(
+                    name.clone(),
+                    TraitSig {
+                        methods,
+                        supertraits: supertraits.clone(),
+                    },
+                );
             }
-            Statement::Impl { methods, .. } => {
+            Statement::Impl {
+                type_name,
+                trait_name,
+                methods,
+                ..
+            } => {
+                if let Some(trait_name) = trait_name {
+                    self.trait_impls
+                        .entry(type_name.clone())
+                        .or_default()
+                        .push(trait_name.clone());
+                }
                 for method in methods {
                     self.collect_declaration(method);
                 }
             }
+            // Imports are resolved in Pass 1 so imported types are known when
+            // the signatures that mention them are collected
+            Statement::Import {
+                items,
+                source,
+                alias,
+            } => {
+                self.register_import(items, source, alias.as_deref());
+            }
             _ => {}
         }
     }
@@ -449,6 +748,10 @@ impl TypeContext {
 
                 if let Some(ann) = type_annotation {
                     let expected = self.resolve_type_expr(ann);
+                    if let (TypeExpr::Named(type_name), Some(value)) = (ann, value) {
+                        let line = self.find_line(&format!("let {}", name));
+                        self.check_refinement_literal(type_name, value, line);
+                    }
                     if !self.compatible(&inferred, &expected) {
                         let line = self.find_line(&format!("let {}", name));
                         self.error(
@@ -633,14 +936,27 @@ impl TypeContext {
                         None,
                     );
                 }
+                let (when_true, when_false) = self.narrow_condition(condition);
                 self.push_scope();
+                self.apply_narrowings(&when_true);
                 self.check_block(then_branch);
                 self.pop_scope();
                 if let Some(else_b) = else_branch {
                     self.push_scope();
+                    self.apply_narrowings(&when_false);
                     self.check_block(else_b);
                     self.pop_scope();
                 }
+
+                // Early exit: code after `if x == None { return }` only runs when
+                // the condition was false (and vice versa for a diverging else)
+                let then_exits = Self::block_exits(then_branch);
+                let else_exits = else_branch.as_ref().is_some_and(Self::block_exits);
+                if then_exits && !else_exits {
+                    self.apply_narrowings(&when_false);
+                } else if else_exits && !then_exits {
+                    self.apply_narrowings(&when_true);
+                }
             }
 
             Statement::While { condition, body } => {
@@ -689,26 +1005,49 @@ impl TypeContext {
                 self.infer_expression(expr);
             }
 
-            Statement::Import {
-                items,
-                source,
-                alias,
+            Statement::TypeAlias {
+                name,
+                target,
+                refinement: Some(predicate),
+                ..
             } => {
-                self.register_import(items, source, alias.as_deref());
+                // The predicate sees `self` as the base type and must be Bool
+                self.push_scope();
+                let base = self.resolve_type_expr(target);
+                self.bind("self", base);
+                let pred_type = self.infer_expression(predicate);
+                self.pop_scope();
+                if !self.compatible(&pred_type, &Type::Bool) && !matches!(pred_type, Type::Any) {
+                    let line = self.find_line(&format!("type {}", name));
+                    self.error(
+                        format!(
+                            "Refinement of '{}' should be Bool, got {}",
+                            name,
+                            pred_type.name()
+                        ),
+                        line,
+                        Some("where clauses must evaluate to Bool".to_string()),
+                    );
+                }
             }
 
             // Already handled in Pass 1
             Statement::Struct { .. }
             | Statement::Enum { .. }
             | Statement::TypeAlias { .. }
-            | Statement::Trait { .. } => {}
+            | Statement::Trait { .. }
+            | Statement::Import { .. } => {}
 
             Statement::Impl {
                 type_name,
+                trait_name,
                 methods,
                 invariants,
-                ..
             } => {
+                if let Some(trait_name) = trait_name {
+                    self.check_trait_impl(type_name, trait_name, methods);
+                }
+
                 // Type-check invariant expressions
                 if !invariants.is_empty() {
                     self.push_scope();
@@ -811,7 +1150,22 @@ impl TypeContext {
                 right,
             } => {
                 let left_type = self.infer_expression(left);
-                let right_type = self.infer_expression(right);
+                // `x != None && len(x) > 0`: the right side only runs if the left allows it
+                let right_type = match operator {
+                    BinaryOp::And | BinaryOp::Or => {
+                        let (when_true, when_false) = self.narrow_condition(left);
+                        self.push_scope();
+                        self.apply_narrowings(if matches!(operator, BinaryOp::And) {
+                            &when_true
+                        } else {
+                            &when_false
+                        });
+                        let t = self.infer_expression(right);
+                        self.pop_scope();
+                        t
+                    }
+                    _ => self.infer_expression(right),
+                };
                 self.infer_binary_op(operator, &left_type, &right_type)
             }
 
@@ -921,12 +1275,24 @@ impl TypeContext {
                 let obj_type = self.infer_expression(object);
                 match &obj_type {
                     Type::Named(name) => {
-                        if let Some(fields) = self.structs.get(name) {
-                            for (fname, ftype) in fields {
+                        if let Some(fields) = self.structs.get(name).cloned() {
+                            for (fname, ftype) in &fields {
                                 if fname == field {
                                     return ftype.clone();
                                 }
                             }
+                            // Synthetic HTTP structs are maps at runtime and may carry more keys
+                            if SYNTHETIC_STRUCTS.contains(&name.as_str()) {
+                                return Type::Any;
+                            }
+                            let names: Vec<String> = fields.into_iter().map(|(n, _)| n).collect();
+                            let line = self.find_line(&format!(".{}", field));
+                            self.error(
+                                format!("Struct '{}' has no field '{}'", name, field),
+                                line,
+                                crate::error::find_suggestion(field, &names)
+                                    .map(|s| format!("Did you mean '{}'?", s)),
+                            );
                         }
                         Type::Any
                     }
@@ -934,6 +1300,49 @@ impl TypeContext {
                 }
             }
 
+            Expression::OptionalChain { object, access } => {
+                let obj_type = match self.infer_expression(object) {
+                    Type::Optional(inner) => *inner,
+                    t => t,
+                };
+                let inner = match access {
+                    ChainAccess::Field(field) => match &obj_type {
+                        Type::Named(name) => self
+                            .structs
+                            .get(name)
+                            .and_then(|fields| {
+                                fields
+                                    .iter()
+                                    .find(|(n, _)| n == field)
+                                    .map(|(_, t)| t.clone())
+                            })
+                            .unwrap_or(Type::Any),
+                        Type::Map { value_type, .. } => (**value_type).clone(),
+                        _ => Type::Any,
+                    },
+                    ChainAccess::Index(index) => {
+                        self.infer_expression(index);
+                        match &obj_type {
+                            Type::Array(inner) => (**inner).clone(),
+                            Type::Map { value_type, .. } => (**value_type).clone(),
+                            Type::String => Type::String,
+                            _ => Type::Any,
+                        }
+                    }
+                    ChainAccess::Method { method, arguments } => {
+                        self.infer_expression(&Expression::MethodCall {
+                            object: object.clone(),
+                            method: method.clone(),
+                            arguments: arguments.clone(),
+                        })
+                    }
+                };
+                match inner {
+                    Type::Optional(_) => inner,
+                    t => Type::Optional(Box::new(t)),
+                }
+            }
+
             Expression::Index { object, index } => {
                 let obj_type = self.infer_expression(object);
                 let _idx_type = self.infer_expression(index);
@@ -983,7 +1392,35 @@ impl TypeContext {
                 Type::Array(Box::new(Type::Int))
             }
 
-            Expression::InterpolatedString(_) => Type::String,
+            Expression::InterpolatedString(parts) => {
+                for part in parts {
+                    if let crate::ast::StringPart::Formatted { expr, spec } = part {
+                        let ty = self.infer_expression(expr);
+                        let problem = match crate::format::FormatSpec::parse(spec) {
+                            Err(e) => Some(format!("Invalid format spec ':{}': {}", spec, e)),
+                            Ok(parsed) => Self::format_spec_problem(&parsed, &ty).map(|p| {
+                                format!("Format spec ':{}' cannot be used here: {}", spec, p)
+                            }),
+                        };
+                        if let Some(message) = problem {
+                            let needle = match expr {
+                                Expression::Identifier(name) => format!("{{{}:{}}}", name, spec),
+                                _ => format!(":{}}}", spec),
+                            };
+                            let line = self.find_line(&needle);
+                            self.error(
+                                message,
+                                line,
+                                Some(
+                                    "Specs look like {price:.2}, {name:>10} or {count:,}"
+                                        .to_string(),
+                                ),
+                            );
+                        }
+                    }
+                }
+                Type::String
+            }
             Expression::TemplateString(_) => Type::String,
 
             Expression::StructLiteral { name, fields } => {
@@ -1140,13 +1577,21 @@ impl TypeContext {
                 else_branch,
             } => {
                 self.infer_expression(condition);
+                let (when_true, when_false) = self.narrow_condition(condition);
+                self.push_scope();
+                self.apply_narrowings(&when_true);
                 let then_type = self.infer_expression(then_branch);
+                self.pop_scope();
+                self.push_scope();
+                self.apply_narrowings(&when_false);
                 let else_type = self.infer_expression(else_branch);
+                self.pop_scope();
                 self.union_type(&then_type, &else_type)
             }
 
             Expression::Match { scrutinee, arms } => {
                 let scrutinee_type = self.infer_expression(scrutinee);
+                self.check_match_exhaustiveness(scrutinee, &scrutinee_type, arms);
                 let mut result_type: Option<Type> = None;
 
                 for arm in arms {
@@ -1154,6 +1599,8 @@ impl TypeContext {
                     self.bind_pattern(&arm.pattern, &scrutinee_type);
                     if let Some(guard) = &arm.guard {
                         self.infer_expression(guard);
+                        let (when_true, _) = self.narrow_condition(guard);
+                        self.apply_narrowings(&when_true);
                     }
                     let arm_type = self.infer_expression(&arm.body);
                     self.pop_scope();
@@ -1173,11 +1620,36 @@ impl TypeContext {
                 Type::Unit
             }
 
-            Expression::Await(inner) => self.infer_expression(inner),
+            Expression::Await(inner) => {
+                let inner_type = self.infer_expression(inner);
+                Self::task_result_type(&inner_type)
+            }
             Expression::Try(inner) => self.infer_expression(inner),
         }
     }
 
+    /// Return type of a function passed as a value, either a function-typed
+    /// expression or the name of a user-defined function
+    fn callable_return_type(&self, expr: &Expression, typ: &Type) -> Option<Type> {
+        if let Type::Function { return_type, .. } = typ {
+            return Some((**return_type).clone());
+        }
+        match expr {
+            Expression::Identifier(name) if self.lookup(name).is_none() => {
+                self.functions.get(name).map(|sig| sig.return_type.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// The type produced by awaiting a value: `Task<T>` yields `T`, anything else itself
+    fn task_result_type(t: &Type) -> Type {
+        match t {
+            Type::Generic { name, args } if name == "Task" && args.len() == 1 => args[0].clone(),
+            other => other.clone(),
+        }
+    }
+
     /// Infer the result type of a binary operation
     fn infer_binary_op(&self, op: &BinaryOp, left: &Type, right: &Type) -> Type {
         match op {
@@ -1221,9 +1693,99 @@ impl TypeContext {
     }
 
     /// Infer the return type of a function call
+    /// Why a format spec can't be applied to a value of this type, if it can't
+    fn format_spec_problem(spec: &crate::format::FormatSpec, ty: &Type) -> Option<String> {
+        match ty {
+            Type::Float if spec.requires_int() => Some("it requires an Int, got Float".to_string()),
+            Type::String | Type::Bool | Type::Array(_) | Type::Map { .. } => spec
+                .numeric_feature()
+                .map(|feature| format!("{} requires a number, got {}", feature, ty)),
+            _ => None,
+        }
+    }
+
+    /// Check a literal format() template against the argument count and types
+    fn check_format_call(&mut self, template: &str, arg_types: &[Type]) {
+        use crate::format::{argument_count, parse_template, Field, Piece};
+        let line = self.find_call_line("format(", template, 0);
+        let pieces = match parse_template(template) {
+            Ok(pieces) => pieces,
+            Err(e) => {
+                self.error(
+                    format!("Invalid format() template: {}", e),
+                    line,
+                    Some(
+                        "Placeholders are {} or {N}, optionally with a spec like {:>10} or {:.2}"
+                            .to_string(),
+                    ),
+                );
+                return;
+            }
+        };
+        let needed = argument_count(&pieces);
+        if needed != arg_types.len() {
+            self.error(
+                format!(
+                    "format() template uses {} argument{} but {} {} given",
+                    needed,
+                    if needed == 1 { "" } else { "s" },
+                    arg_types.len(),
+                    if arg_types.len() == 1 { "was" } else { "were" }
+                ),
+                line,
+                None,
+            );
+            return;
+        }
+        let mut next = 0;
+        for piece in &pieces {
+            if let Piece::Placeholder { field, spec } = piece {
+                let index = match field {
+                    Field::Next => {
+                        next += 1;
+                        next - 1
+                    }
+                    Field::Index(i) => *i,
+                };
+                if let Some(problem) = Self::format_spec_problem(spec, &arg_types[index]) {
+                    self.error(
+                        format!(
+                            "format() argument {} cannot be formatted: {}",
+                            index, problem
+                        ),
+                        line,
+                        None,
+                    );
+                }
+            }
+        }
+    }
+
     fn infer_call(&mut self, function: &Expression, arguments: &[Expression]) -> Type {
+        // Route patterns keep `{id:int}` as a typed parameter, not a format spec
+        let is_route = match (function, arguments.first()) {
+            (Expression::Identifier(name), Some(Expression::InterpolatedString(parts))) => {
+                [
+                    "get", "post", "put", "delete", "patch", "ws", "sse", "group",
+                ]
+                .contains(&name.as_str())
+                    && matches!(parts.first(), Some(crate::ast::StringPart::Literal(s)) if s.starts_with('/'))
+            }
+            _ => false,
+        };
+
         // Infer argument types
-        let arg_types: Vec<Type> = arguments.iter().map(|a| self.infer_expression(a)).collect();
+        let arg_types: Vec<Type> = arguments
+            .iter()
+            .enumerate()
+            .map(|(i, a)| {
+                if i == 0 && is_route {
+                    Type::String
+                } else {
+                    self.infer_expression(a)
+                }
+            })
+            .collect();
 
         // Get function name for lookup
         let fn_name = match function {
@@ -1231,6 +1793,10 @@ impl TypeContext {
             _ => None,
         };
 
+        if let Some(name) = &fn_name {
+            self.check_trait_bounds(name, &arg_types);
+        }
+
         if let Some(name) = &fn_name {
             // Special built-in constructors and contract functions
             match name.as_str() {
@@ -1285,6 +1851,80 @@ impl TypeContext {
                         return arg_types[0].clone();
                     }
                 }
+                // await_all(Array<Task<T>>) -> Array<T>, await_any(Array<Task<T>>) -> T
+                "await_all" | "await_any" if arguments.len() == 1 => {
+                    if let Type::Array(inner) = &arg_types[0] {
+                        let result = Self::task_result_type(inner);
+                        return if name == "await_all" {
+                            Type::Array(Box::new(result))
+                        } else {
+                            result
+                        };
+                    }
+                }
+                // std/schedule: literal durations and cron expressions are checked up front
+                "every" | "after" | "cron"
+                    if !arguments.is_empty()
+                        && !self.functions.contains_key(name.as_str())
+                        && self.builtin_sigs.contains_key(name.as_str()) =>
+                {
+                    let problem = match &arguments[0] {
+                        Expression::String(expr) if name == "cron" => {
+                            crate::stdlib::schedule::CronExpr::parse(expr).err()
+                        }
+                        Expression::String(text) => crate::stdlib::schedule::parse_duration(
+                            &crate::interpreter::Value::String(text.clone()),
+                            name,
+                        )
+                        .err()
+                        .map(|_| format!("Invalid duration '{}' for {}()", text, name)),
+                        _ => None,
+                    };
+                    if let Some(message) = problem {
+                        let line = self.find_line(&format!("{}(", name));
+                        let hint = if name == "cron" {
+                            "Use five fields: minute hour day-of-month month day-of-week, e.g. \"0 2 * * *\""
+                        } else {
+                            "Use milliseconds or a duration string like \"30s\", \"5m\" or \"1h30m\""
+                        };
+                        self.error(message, line, Some(hint.to_string()));
+                    }
+                }
+                // format(template, args...): literal templates are checked against the arguments
+                "format"
+                    if !self.functions.contains_key("format")
+                        && self.builtin_sigs.get("format").is_some_and(|sig| {
+                            sig.params.first().is_some_and(|(p, _)| p == "template")
+                        }) =>
+                {
+                    if let Some(Expression::String(template)) = arguments.first() {
+                        self.check_format_call(template, &arg_types[1..]);
+                    }
+                    return Type::String;
+                }
+                // join(Task<T>) -> T
+                "join" if arguments.len() == 1 => {
+                    if let Type::Generic { name, .. } = &arg_types[0] {
+                        if name == "Task" {
+                            return Self::task_result_type(&arg_types[0]);
+                        }
+                    }
+                }
+                // spawn(fn() -> T, args) -> Task<T>
+                "spawn" if !arguments.is_empty() => {
+                    if let Some(ret) = self.callable_return_type(&arguments[0], &arg_types[0]) {
+                        return Type::Generic {
+                            name: "Task".to_string(),
+                            args: vec![ret],
+                        };
+                    }
+                }
+                // parallel_map(Array<T>, fn(T) -> U, workers) -> Array<U>
+                "parallel_map" if arguments.len() >= 2 => {
+                    if let Some(ret) = self.callable_return_type(&arguments[1], &arg_types[1]) {
+                        return Type::Array(Box::new(ret));
+                    }
+                }
                 // first(Array<T>) -> T, last(Array<T>) -> T, pop(Array<T>) -> T
                 "first" | "last" | "pop" if !arguments.is_empty() => {
                     if let Type::Array(inner) = &arg_types[0] {
@@ -1394,6 +2034,23 @@ impl TypeContext {
                     return sig.return_type;
                 }
 
+                // Literal arguments to refined parameters can be checked statically
+                if let Some(type_names) = self.param_type_names.get(name).cloned() {
+                    let decl_line = self.find_line(&format!("fn {}(", name));
+                    for (arg, type_name) in arguments.iter().zip(type_names.iter()) {
+                        if let Some(type_name) = type_name {
+                            let call = format!("{}(", name);
+                            let line = match ConstValue::from_literal(arg) {
+                                Some(literal) => {
+                                    self.find_call_line(&call, &literal.to_string(), decl_line)
+                                }
+                                None => self.find_line_after(&call, decl_line),
+                            };
+                            self.check_refinement_literal(type_name, arg, line);
+                        }
+                    }
+                }
+
                 // Check argument types (skip for variadic)
                 if !sig.variadic {
                     for (i, (arg_type, (param_name, param_type))) in
@@ -1428,6 +2085,407 @@ impl TypeContext {
         Type::Any
     }
 
+    /// Report arguments whose types don't implement the trait bounds of the
+    /// generic parameters they are bound to (`fn sort<T: Comparable>(arr: [T])`)
+    fn check_trait_bounds(&mut self, name: &str, arg_types: &[Type]) {
+        let Some((type_params, param_types)) = self.generic_params.get(name).cloned() else {
+            return;
+        };
+        for (ann, arg_type) in param_types.iter().zip(arg_types.iter()) {
+            let Some(ann) = ann else {
+                continue;
+            };
+            let mut bound_types = Vec::new();
+            Self::collect_type_param_types(ann, arg_type, &type_params, &mut bound_types);
+            for (type_param, typ) in bound_types {
+                // Only user-defined types can be checked against impl blocks
+                let Type::Named(type_name) = typ else {
+                    continue;
+                };
+                if !self.structs.contains_key(&type_name) && !self.enums.contains_key(&type_name) {
+                    continue;
+                }
+                for bound in &type_param.bounds {
+                    if self.traits.contains_key(bound) && !self.implements_trait(&type_name, bound)
+                    {
+                        let decl_line = self.find_line(&format!("fn {}", name));
+                        let line = self.find_line_after(&format!("{}(", name), decl_line);
+                        self.error(
+                            format!(
+                                "Type '{}' does not implement trait '{}' required by '{}<{}: {}>'",
+                                type_name,
+                                bound,
+                                name,
+                                type_param.name,
+                                type_param.bounds.join(" + ")
+                            ),
+                            line,
+                            Some(format!("Add `impl {} for {} {{ ... }}`", bound, type_name)),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Pair each generic type parameter in `ann` with the argument type it stands for
+    fn collect_type_param_types(
+        ann: &TypeExpr,
+        typ: &Type,
+        type_params: &[TypeParam],
+        out: &mut Vec<(TypeParam, Type)>,
+    ) {
+        match (ann, typ) {
+            (TypeExpr::Named(n), _) => {
+                if let Some(tp) = type_params.iter().find(|tp| &tp.name == n) {
+                    out.push((tp.clone(), typ.clone()));
+                }
+            }
+            (TypeExpr::Array(inner), Type::Array(elem)) => {
+                Self::collect_type_param_types(inner, elem, type_params, out);
+            }
+            (TypeExpr::Optional(inner), Type::Optional(elem)) => {
+                Self::collect_type_param_types(inner, elem, type_params, out);
+            }
+            (TypeExpr::Optional(inner), _) => {
+                Self::collect_type_param_types(inner, typ, type_params, out);
+            }
+            (TypeExpr::Map { value_type, .. }, Type::Map { value_type: v, .. }) => {
+                Self::collect_type_param_types(value_type, v, type_params, out);
+            }
+            (_, Type::Union(members)) => {
+                for m in members {
+                    Self::collect_type_param_types(ann, m, type_params, out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether `type_name` implements `trait_name`, directly or through a
+    /// trait whose supertraits include it
+    fn implements_trait(&self, type_name: &str, trait_name: &str) -> bool {
+        self.trait_impls
+            .get(type_name)
+            .is_some_and(|impls| impls.iter().any(|t| self.extends_trait(t, trait_name)))
+    }
+
+    /// Whether `trait_name` is `target` or has it among its (transitive) supertraits
+    fn extends_trait(&self, trait_name: &str, target: &str) -> bool {
+        let mut pending = vec![trait_name];
+        let mut seen = std::collections::HashSet::new();
+        while let Some(t) = pending.pop() {
+            if t == target {
+                return true;
+            }
+            if seen.insert(t) {
+                if let Some(sig) = self.traits.get(t) {
+                    pending.extend(sig.supertraits.iter().map(|s| s.as_str()));
+                }
+            }
+        }
+        false
+    }
+
+    /// Report trait methods an `impl Trait for Type` block leaves out
+    fn check_trait_impl(&mut self, type_name: &str, trait_name: &str, methods: &[Statement]) {
+        let Some(trait_sig) = self.traits.get(trait_name).cloned() else {
+            return;
+        };
+        let implemented: Vec<&str> = methods
+            .iter()
+            .filter_map(|m| match m {
+                Statement::Function { name, .. } => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+        let missing: Vec<&str> = trait_sig
+            .methods
+            .iter()
+            .filter(|(name, _, has_default)| !has_default && !implemented.contains(&name.as_str()))
+            .map(|(name, _, _)| name.as_str())
+            .collect();
+        if !missing.is_empty() {
+            let line = self.find_line(&format!("impl {} for {}", trait_name, type_name));
+            self.error(
+                format!(
+                    "impl {} for {} is missing method{} {}",
+                    trait_name,
+                    type_name,
+                    if missing.len() == 1 { "" } else { "s" },
+                    missing
+                        .iter()
+                        .map(|m| format!("'{}'", m))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                line,
+                Some(format!("Trait {} requires these methods", trait_name)),
+            );
+        }
+
+        // `trait Ordered: Comparable` means an Ordered type must also be Comparable
+        for supertrait in &trait_sig.supertraits {
+            let satisfied = self.trait_impls.get(type_name).is_some_and(|impls| {
+                impls
+                    .iter()
+                    .any(|t| t != trait_name && self.extends_trait(t, supertrait))
+            });
+            if self.traits.contains_key(supertrait) && !satisfied {
+                let line = self.find_line(&format!("impl {} for {}", trait_name, type_name));
+                self.error(
+                    format!(
+                        "impl {} for {} requires {} to implement supertrait '{}'",
+                        trait_name, type_name, type_name, supertrait
+                    ),
+                    line,
+                    Some(format!(
+                        "Add `impl {} for {} {{ ... }}`",
+                        supertrait, type_name
+                    )),
+                );
+            }
+        }
+    }
+
+    // ── Flow-sensitive narrowing ──────────────────────────────────────
+
+    /// Narrowed variable types implied by a condition being true and being false
+    fn narrow_condition(&self, cond: &Expression) -> (Narrowings, Narrowings) {
+        match cond {
+            Expression::Unary {
+                operator: UnaryOp::Not,
+                operand,
+            } => {
+                let (when_true, when_false) = self.narrow_condition(operand);
+                (when_false, when_true)
+            }
+            Expression::Binary {
+                left,
+                operator: BinaryOp::And,
+                right,
+            } => {
+                let mut when_true = self.narrow_condition(left).0;
+                when_true.extend(self.narrow_condition(right).0);
+                (when_true, Vec::new())
+            }
+            Expression::Binary {
+                left,
+                operator: BinaryOp::Or,
+                right,
+            } => {
+                let mut when_false = self.narrow_condition(left).1;
+                when_false.extend(self.narrow_condition(right).1);
+                (Vec::new(), when_false)
+            }
+            Expression::Binary {
+                left,
+                operator: operator @ (BinaryOp::Eq | BinaryOp::Ne),
+                right,
+            } => {
+                let (eq_true, eq_false) = self.narrow_equality(left, right);
+                if matches!(operator, BinaryOp::Eq) {
+                    (eq_true, eq_false)
+                } else {
+                    (eq_false, eq_true)
+                }
+            }
+            // x.is_some(), x.is_ok(), ...
+            Expression::MethodCall {
+                object,
+                method,
+                arguments,
+            } if arguments.is_empty() => match object.as_ref() {
+                Expression::Identifier(var) => self.narrow_guard(var, method),
+                _ => (Vec::new(), Vec::new()),
+            },
+            // is_some(x), is_ok(x), ...
+            Expression::Call {
+                function,
+                arguments,
+            } if arguments.len() == 1 => match (function.as_ref(), &arguments[0]) {
+                (Expression::Identifier(guard), Expression::Identifier(var)) => {
+                    self.narrow_guard(var, guard)
+                }
+                _ => (Vec::new(), Vec::new()),
+            },
+            _ => (Vec::new(), Vec::new()),
+        }
+    }
+
+    /// Narrowings for `left == right` being true and being false
+    fn narrow_equality(&self, left: &Expression, right: &Expression) -> (Narrowings, Narrowings) {
+        let (var, other) = match (left, right) {
+            (Expression::Identifier(v), other) if v != "None" => (v, other),
+            (other, Expression::Identifier(v)) if v != "None" => (v, other),
+            // type(x) == "String"
+            (
+                Expression::Call {
+                    function,
+                    arguments,
+                },
+                Expression::String(name),
+            )
+            | (
+                Expression::String(name),
+                Expression::Call {
+                    function,
+                    arguments,
+                },
+            ) if arguments.len() == 1
+                && matches!(function.as_ref(), Expression::Identifier(f) if f == "type") =>
+            {
+                return match &arguments[0] {
+                    Expression::Identifier(var) => self.narrow_type_name(var, name),
+                    _ => (Vec::new(), Vec::new()),
+                };
+            }
+            _ => return (Vec::new(), Vec::new()),
+        };
+        if !matches!(other, Expression::Identifier(n) if n == "None") {
+            return (Vec::new(), Vec::new());
+        }
+        match self.lookup(var) {
+            Some(Type::Optional(inner)) => (Vec::new(), vec![(var.clone(), (**inner).clone())]),
+            _ => (Vec::new(), Vec::new()),
+        }
+    }
+
+    /// Narrowings for `is_some`/`is_none`/`is_ok`/`is_err` guards
+    fn narrow_guard(&self, var: &str, guard: &str) -> (Narrowings, Narrowings) {
+        let Some(current) = self.lookup(var) else {
+            return (Vec::new(), Vec::new());
+        };
+        let narrowed = match (guard, current) {
+            ("is_some" | "is_none", Type::Optional(inner)) => (**inner).clone(),
+            ("is_ok" | "is_err", Type::Any) => Type::Generic {
+                name: "Result".to_string(),
+                args: vec![Type::Any, Type::Any],
+            },
+            ("is_ok" | "is_err", Type::Union(members)) => {
+                match members
+                    .iter()
+                    .find(|m| matches!(m, Type::Generic { name, .. } if name == "Result"))
+                {
+                    Some(result) => result.clone(),
+                    None => return (Vec::new(), Vec::new()),
+                }
+            }
+            _ => return (Vec::new(), Vec::new()),
+        };
+        let narrowing = vec![(var.to_string(), narrowed)];
+        match guard {
+            "is_some" => (narrowing, Vec::new()),
+            "is_none" => (Vec::new(), narrowing),
+            // A value that answers is_ok/is_err either way is a Result
+            _ => (narrowing.clone(), narrowing),
+        }
+    }
+
+    /// Narrowings for `type(var) == name` being true and being false
+    fn narrow_type_name(&self, var: &str, name: &str) -> (Narrowings, Narrowings) {
+        let current = self.lookup(var).cloned().unwrap_or(Type::Any);
+        let members = match &current {
+            Type::Union(members) => members.clone(),
+            other => vec![other.clone()],
+        };
+        let (matching, rest): (Vec<Type>, Vec<Type>) = members
+            .into_iter()
+            .partition(|t| self.runtime_type_name(t).as_deref() == Some(name));
+
+        let when_true = match matching.len() {
+            0 => match name {
+                "Int" => Type::Int,
+                "Float" => Type::Float,
+                "String" => Type::String,
+                "Bool" => Type::Bool,
+                "Unit" => Type::Unit,
+                "Array" => Type::Array(Box::new(Type::Any)),
+                "Map" => Type::Map {
+                    key_type: Box::new(Type::String),
+                    value_type: Box::new(Type::Any),
+                },
+                _ if self.structs.contains_key(name) || self.enums.contains_key(name) => {
+                    Type::Named(name.to_string())
+                }
+                _ => return (Vec::new(), Vec::new()),
+            },
+            1 => matching[0].clone(),
+            _ => Type::Union(matching),
+        };
+        // Only a union can shed members when the comparison fails
+        let when_false = match (&current, rest.len()) {
+            (Type::Union(_), 1) => vec![(var.to_string(), rest[0].clone())],
+            (Type::Union(_), n) if n > 1 => vec![(var.to_string(), Type::Union(rest))],
+            _ => Vec::new(),
+        };
+        (vec![(var.to_string(), when_true)], when_false)
+    }
+
+    /// The name `type()` reports at runtime for values of a static type
+    fn runtime_type_name(&self, typ: &Type) -> Option<String> {
+        match typ {
+            Type::Int => Some("Int".to_string()),
+            Type::Float => Some("Float".to_string()),
+            Type::String => Some("String".to_string()),
+            Type::Bool => Some("Bool".to_string()),
+            Type::Unit => Some("Unit".to_string()),
+            Type::Array(_) => Some("Array".to_string()),
+            Type::Map { .. } => Some("Map".to_string()),
+            Type::Optional(_) => Some("Option".to_string()),
+            Type::Named(name) => Some(name.clone()),
+            Type::Generic { name, .. } => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    fn apply_narrowings(&mut self, narrowings: &Narrowings) {
+        for (name, typ) in narrowings {
+            self.bind(name, typ.clone());
+        }
+    }
+
+    /// Whether a block always leaves the enclosing scope (return/break/continue)
+    fn block_exits(block: &Block) -> bool {
+        matches!(
+            block.statements.last(),
+            Some(Statement::Return(_) | Statement::Break | Statement::Continue)
+        )
+    }
+
+    /// Report a literal that statically violates a refined type's predicate
+    ///
+    /// Only predicates built from literals, `self`, arithmetic, comparisons,
+    /// boolean operators and `len(self)` are evaluated; anything else is left
+    /// to the runtime check.
+    fn check_refinement_literal(&mut self, type_name: &str, value: &Expression, line: usize) {
+        let Some(predicates) = self.refinements.get(type_name).cloned() else {
+            return;
+        };
+        let Some(literal) = ConstValue::from_literal(value) else {
+            return;
+        };
+        for (alias, predicate) in &predicates {
+            if let Some(ConstValue::Bool(false)) = ConstValue::eval(predicate, &literal) {
+                let decl_line = self.find_line(&format!("type {}", alias));
+                let hint = self
+                    .source_lines
+                    .get(decl_line.wrapping_sub(1))
+                    .and_then(|l| l.split_once(" where "))
+                    .map(|(_, pred)| format!("{} requires {}", alias, pred.trim()));
+                self.error(
+                    format!(
+                        "Value {} does not satisfy refinement type '{}'",
+                        literal, type_name
+                    ),
+                    line,
+                    hint,
+                );
+                return;
+            }
+        }
+    }
+
     /// Bind pattern variables with their inferred types
     fn bind_pattern(&mut self, pattern: &Pattern, scrutinee_type: &Type) {
         match pattern {
@@ -1453,7 +2511,45 @@ impl TypeContext {
                     _ => Type::Any,
                 };
                 for p in patterns {
-                    self.bind_pattern(p, &elem_type);
+                    match p {
+                        Pattern::Rest(Some(name)) => {
+                            self.bind(name, Type::Array(Box::new(elem_type.clone())))
+                        }
+                        _ => self.bind_pattern(p, &elem_type),
+                    }
+                }
+            }
+            Pattern::Rest(name) => {
+                if let Some(name) = name {
+                    self.bind(name, scrutinee_type.clone());
+                }
+            }
+            Pattern::Range { .. } => {}
+            Pattern::Or(alternatives) => {
+                for alt in alternatives {
+                    self.bind_pattern(alt, scrutinee_type);
+                }
+            }
+            Pattern::Binding { name, pattern } => {
+                self.bind(name, scrutinee_type.clone());
+                self.bind_pattern(pattern, scrutinee_type);
+            }
+            Pattern::Map(entries) => {
+                let struct_fields = match scrutinee_type {
+                    Type::Named(type_name) => self.structs.get(type_name).cloned(),
+                    _ => None,
+                };
+                for (key, p) in entries {
+                    let value_type = match scrutinee_type {
+                        Type::Map { value_type, .. } => (**value_type).clone(),
+                        _ => struct_fields
+                            .as_ref()
+                            .and_then(|sf| {
+                                sf.iter().find(|(n, _)| n == key).map(|(_, t)| t.clone())
+                            })
+                            .unwrap_or(Type::Any),
+                    };
+                    self.bind_pattern(p, &value_type);
                 }
             }
             Pattern::Struct { name, fields } => {
@@ -1520,9 +2616,22 @@ impl TypeContext {
                     }
                     "None" => {}
                     _ => {
+                        // User enum variants bind their declared field types
+                        let field_types = match scrutinee_type {
+                            Type::Named(enum_name) => self.enums.get(enum_name).and_then(|vs| {
+                                vs.iter()
+                                    .find(|(v, _)| v == variant)
+                                    .and_then(|(_, types)| types.clone())
+                            }),
+                            _ => None,
+                        };
                         if let Some(fields) = fields {
-                            for p in fields {
-                                self.bind_pattern(p, &Type::Any);
+                            for (i, p) in fields.iter().enumerate() {
+                                let t = field_types
+                                    .as_ref()
+                                    .and_then(|ts| ts.get(i).cloned())
+                                    .unwrap_or(Type::Any);
+                                self.bind_pattern(p, &t);
                             }
                         }
                     }
@@ -1531,6 +2640,90 @@ impl TypeContext {
         }
     }
 
+    /// Warn when a match over an enum, Bool, Option or Result leaves cases
+    /// uncovered. Guarded arms never count towards coverage.
+    fn check_match_exhaustiveness(
+        &mut self,
+        scrutinee: &Expression,
+        scrutinee_type: &Type,
+        arms: &[MatchArm],
+    ) {
+        let required: Vec<String> = match scrutinee_type {
+            Type::Bool => vec!["true".into(), "false".into()],
+            Type::Optional(_) => vec!["Some".into(), "None".into()],
+            Type::Generic { name, .. } if name == "Result" => vec!["Ok".into(), "Err".into()],
+            Type::Named(name) => match self.enums.get(name) {
+                Some(variants) => variants.iter().map(|(v, _)| v.clone()).collect(),
+                None => return,
+            },
+            _ => return,
+        };
+
+        let mut covered = HashSet::new();
+        for arm in arms.iter().filter(|arm| arm.guard.is_none()) {
+            if Self::pattern_coverage(&arm.pattern, &mut covered) {
+                return;
+            }
+        }
+
+        let missing: Vec<&str> = required
+            .iter()
+            .filter(|case| !covered.contains(case.as_str()))
+            .map(|case| case.as_str())
+            .collect();
+        if missing.is_empty() {
+            return;
+        }
+        let line = match scrutinee {
+            Expression::Identifier(name) => self.find_line(&format!("match {}", name)),
+            _ => self.find_line("match "),
+        };
+        self.warning(
+            format!(
+                "Non-exhaustive match on {}: missing {}",
+                scrutinee_type,
+                missing.join(", ")
+            ),
+            line,
+            Some("Add arms for the missing cases or a `_ =>` catch-all arm".to_string()),
+        );
+    }
+
+    /// Record which cases a pattern fully covers. Returns true when the
+    /// pattern matches every value.
+    fn pattern_coverage(pattern: &Pattern, covered: &mut HashSet<String>) -> bool {
+        let irrefutable = |p: &Pattern| {
+            matches!(
+                p,
+                Pattern::Wildcard | Pattern::Variable(_) | Pattern::Rest(None)
+            )
+        };
+        match pattern {
+            Pattern::Wildcard | Pattern::Variable(_) => true,
+            Pattern::Binding { pattern, .. } => Self::pattern_coverage(pattern, covered),
+            Pattern::Or(alternatives) => {
+                let mut all = false;
+                for alt in alternatives {
+                    all |= Self::pattern_coverage(alt, covered);
+                }
+                all
+            }
+            Pattern::Literal(Expression::Bool(b)) => {
+                covered.insert(b.to_string());
+                false
+            }
+            Pattern::Variant {
+                variant, fields, ..
+            } => {
+                if fields.as_ref().is_none_or(|fs| fs.iter().all(irrefutable)) {
+                    covered.insert(variant.clone());
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+
     // ── Import resolution ─────────────────────────────────────────────
 
     /// Resolve an import source path to an absolute file path
@@ -1556,11 +2749,8 @@ impl TypeContext {
         Some(path)
     }
 
-    /// Parse a file and extract function signatures (Pass 1 only)
-    fn extract_file_signatures(
-        &mut self,
-        file_path: &std::path::Path,
-    ) -> HashMap<String, FunctionSig> {
+    /// Parse a file and extract its declarations (Pass 1 only)
+    fn extract_file_exports(&mut self, file_path: &std::path::Path) -> ModuleExports {
         use crate::lexer::Lexer;
         use crate::parser::Parser;
 
@@ -1573,13 +2763,13 @@ impl TypeContext {
 
         // Check for circular imports
         if self.resolving_files.contains(&path_str) {
-            return HashMap::new();
+            return ModuleExports::default();
         }
 
         // Read and parse
         let source_code = match std::fs::read_to_string(file_path) {
             Ok(s) => s,
-            Err(_) => return HashMap::new(),
+            Err(_) => return ModuleExports::default(),
         };
 
         let lexer = Lexer::new(&source_code);
@@ -1587,30 +2777,45 @@ impl TypeContext {
         let mut parser = Parser::new(tokens);
         let ast = match parser.parse() {
             Ok(ast) => ast,
-            Err(_) => return HashMap::new(),
+            Err(_) => return ModuleExports::default(),
         };
 
         // Mark as resolving (circular import protection)
         self.resolving_files.push(path_str.clone());
 
-        // Create a temporary context for Pass 1 only
+        // Create a temporary context for Pass 1 only. It shares the cache and the
+        // resolving set so the imported file's own imports are resolved too.
         let mut temp_ctx = TypeContext::new(&source_code);
         temp_ctx.current_file = Some(path_str.clone());
         temp_ctx.register_builtins();
+        temp_ctx.module_cache = std::mem::take(&mut self.module_cache);
+        temp_ctx.resolving_files = std::mem::take(&mut self.resolving_files);
 
         // Run Pass 1 on the imported file to collect declarations
         for stmt in &ast.statements {
             temp_ctx.collect_declaration(stmt);
         }
 
-        // Extract the function signatures
-        let sigs = temp_ctx.functions;
+        self.module_cache = std::mem::take(&mut temp_ctx.module_cache);
+        self.resolving_files = std::mem::take(&mut temp_ctx.resolving_files);
+
+        let exports = ModuleExports {
+            functions: temp_ctx.functions,
+            param_type_names: temp_ctx.param_type_names,
+            structs: temp_ctx.structs,
+            enums: temp_ctx.enums,
+            type_aliases: temp_ctx.type_aliases,
+            refinements: temp_ctx.refinements,
+            traits: temp_ctx.traits,
+            trait_impls: temp_ctx.trait_impls,
+            generic_params: temp_ctx.generic_params,
+        };
 
         // Cache and unmark
-        self.module_cache.insert(path_str.clone(), sigs.clone());
+        self.module_cache.insert(path_str.clone(), exports.clone());
         self.resolving_files.retain(|f| f != &path_str);
 
-        sigs
+        exports
     }
 
     fn register_import(&mut self, items: &[ImportItem], source: &str, alias: Option<&str>) {
@@ -1636,13 +2841,53 @@ impl TypeContext {
 
         // Try user file import
         if let Some(file_path) = self.resolve_import_path(source) {
-            let file_sigs = self.extract_file_signatures(&file_path);
+            let exports = self.extract_file_exports(&file_path);
+            // impl blocks aren't imported by name; they apply wherever the types are used
+            for (type_name, traits) in &exports.trait_impls {
+                let known = self.trait_impls.entry(type_name.clone()).or_default();
+                for t in traits {
+                    if !known.contains(t) {
+                        known.push(t.clone());
+                    }
+                }
+            }
             for item in items {
                 let local_name = item.alias.as_ref().unwrap_or(&item.name);
-                if let Some(sig) = file_sigs.get(&item.name) {
+                let mut found = false;
+                if let Some(sig) = exports.functions.get(&item.name) {
                     self.builtin_sigs.insert(local_name.clone(), sig.clone());
-                } else {
-                    // Function not found in the imported file
+                    if let Some(names) = exports.param_type_names.get(&item.name) {
+                        self.param_type_names
+                            .insert(local_name.clone(), names.clone());
+                    }
+                    if let Some(generic) = exports.generic_params.get(&item.name) {
+                        self.generic_params
+                            .insert(local_name.clone(), generic.clone());
+                    }
+                    found = true;
+                }
+                if let Some(fields) = exports.structs.get(&item.name) {
+                    self.structs.insert(local_name.clone(), fields.clone());
+                    found = true;
+                }
+                if let Some(variants) = exports.enums.get(&item.name) {
+                    self.enums.insert(local_name.clone(), variants.clone());
+                    found = true;
+                }
+                if let Some(target) = exports.type_aliases.get(&item.name) {
+                    self.type_aliases.insert(local_name.clone(), target.clone());
+                    if let Some(predicates) = exports.refinements.get(&item.name) {
+                        self.refinements
+                            .insert(local_name.clone(), predicates.clone());
+                    }
+                    found = true;
+                }
+                if let Some(trait_sig) = exports.traits.get(&item.name) {
+                    self.traits.insert(local_name.clone(), trait_sig.clone());
+                    found = true;
+                }
+                if !found {
+                    // Not declared in the imported file
                     self.bind(local_name, Type::Any);
                 }
             }
@@ -1689,6 +2934,7 @@ impl TypeContext {
         sig!("float", ["value" => Type::Any], Type::Float);
         sig!("bool", ["value" => Type::Any], Type::Bool);
         sig!("type", ["value" => Type::Any], Type::String);
+        sig!("format", ["template" => Type::String], Type::String, variadic);
 
         // Collections
         sig!("len", ["value" => Type::Any], Type::Int);
@@ -1726,12 +2972,19 @@ impl TypeContext {
         sig!("assert", ["condition" => Type::Bool], Type::Unit, variadic);
 
         // HTTP server builtins (global)
-        sig!("get", ["pattern" => Type::String, "handler" => Type::Any], Type::Unit);
-        sig!("post", ["pattern" => Type::String, "handler" => Type::Any], Type::Unit);
-        sig!("put", ["pattern" => Type::String, "handler" => Type::Any], Type::Unit);
-        sig!("patch", ["pattern" => Type::String, "handler" => Type::Any], Type::Unit);
-        sig!("delete", ["pattern" => Type::String, "handler" => Type::Any], Type::Unit);
-        sig!("listen", ["port" => Type::Int], Type::Unit);
+        sig!("get", ["pattern" => Type::String, "handler" => Type::Any], Type::Unit, variadic);
+        sig!("post", ["pattern" => Type::String, "handler" => Type::Any], Type::Unit, variadic);
+        sig!("put", ["pattern" => Type::String, "handler" => Type::Any], Type::Unit, variadic);
+        sig!("patch", ["pattern" => Type::String, "handler" => Type::Any], Type::Unit, variadic);
+        sig!("delete", ["pattern" => Type::String, "handler" => Type::Any], Type::Unit, variadic);
+        sig!("ws", ["pattern" => Type::String, "handler" => Type::Any], Type::Unit);
+        sig!("sse", ["pattern" => Type::String, "handler" => Type::Any], Type::Unit);
+        sig!(
+            "group",
+            ["prefix" => Type::String, "middleware" => Type::Array(Box::new(Type::Any)), "body" => Type::Any],
+            Type::Unit
+        );
+        sig!("listen", ["port" => Type::Int], Type::Unit, variadic);
         sig!("serve_static", ["prefix" => Type::String, "dir" => Type::String], Type::Unit);
         sig!("use_middleware", ["handler" => Type::Any], Type::Unit);
         sig!("on_shutdown", ["handler" => Type::Any], Type::Unit);
@@ -1760,8 +3013,16 @@ impl TypeContext {
                 ("ip".to_string(), Type::String),
                 ("protocol".to_string(), Type::String),
                 ("query_params".to_string(), map_string_string.clone()),
+                (
+                    "query_all".to_string(),
+                    Type::Map {
+                        key_type: Box::new(Type::String),
+                        value_type: Box::new(Type::Array(Box::new(Type::String))),
+                    },
+                ),
                 ("params".to_string(), map_string_string.clone()),
                 ("headers".to_string(), map_string_string.clone()),
+                ("cookies".to_string(), map_string_string.clone()),
             ],
         );
 
@@ -1921,6 +3182,9 @@ fn get_module_signatures(module: &str) -> HashMap<String, FunctionSig> {
             sig!("text", ["content" => Type::String], Type::Named("Response".to_string()));
             sig!("redirect", ["url" => Type::String], Type::Named("Response".to_string()));
             sig!("status", ["code" => Type::Int, "body" => Type::Any], Type::Named("Response".to_string()));
+            sig!("stream", ["producer" => Type::Any], Type::Named("Response".to_string()), variadic);
+            sig!("write", ["out" => Type::Any, "chunk" => Type::Any], Type::Bool);
+            sig!("send_event", ["out" => Type::Any, "data" => Type::Any], Type::Bool, variadic);
             sig!("parse_json", ["req" => Type::Any], Type::Generic {
                 name: "Result".to_string(),
                 args: vec![
@@ -1935,6 +3199,45 @@ fn get_module_signatures(module: &str) -> HashMap<String, FunctionSig> {
                 key_type: Box::new(Type::String),
                 value_type: Box::new(Type::String),
             });
+            sig!("parse_multipart", ["req" => Type::Any], Type::Generic {
+                name: "Result".to_string(),
+                args: vec![
+                    Type::Map {
+                        key_type: Box::new(Type::String),
+                        value_type: Box::new(Type::Any),
+                    },
+                    Type::String,
+                ],
+            });
+            sig!("save_upload", ["file" => Type::Any, "dest" => Type::String], Type::Generic {
+                name: "Result".to_string(),
+                args: vec![Type::String, Type::String],
+            });
+            sig!("set_cookie", ["resp" => Type::Any, "name" => Type::String, "value" => Type::String], Type::Named("Response".to_string()), variadic);
+            sig!("clear_cookie", ["resp" => Type::Any, "name" => Type::String], Type::Named("Response".to_string()), variadic);
+            sig!("set_signed_cookie", ["resp" => Type::Any, "name" => Type::String, "value" => Type::String], Type::Named("Response".to_string()), variadic);
+            sig!("get_signed_cookie", ["req" => Type::Any, "name" => Type::String], Type::Generic {
+                name: "Option".to_string(),
+                args: vec![Type::String],
+            });
+            sig!("set_encrypted_cookie", ["resp" => Type::Any, "name" => Type::String, "value" => Type::String], Type::Named("Response".to_string()), variadic);
+            sig!("get_encrypted_cookie", ["req" => Type::Any, "name" => Type::String], Type::Generic {
+                name: "Option".to_string(),
+                args: vec![Type::String],
+            });
+            sig!("sessions", [], Type::Any, variadic);
+            sig!("session_get", ["req" => Type::Any, "key" => Type::String], Type::Generic {
+                name: "Option".to_string(),
+                args: vec![Type::Any],
+            });
+            sig!("session_set", ["req" => Type::Any, "key" => Type::String, "value" => Type::Any], Type::Unit);
+            sig!("session_delete", ["req" => Type::Any, "key" => Type::String], Type::Bool);
+            sig!("session_all", ["req" => Type::Any], Type::Map {
+                key_type: Box::new(Type::String),
+                value_type: Box::new(Type::Any),
+            });
+            sig!("rotate_session", ["req" => Type::Any], Type::Unit);
+            sig!("destroy_session", ["req" => Type::Any], Type::Unit);
         }
         "std/db/postgres" => {
             sig!("connect", ["url" => Type::String], Type::Any);
@@ -1981,6 +3284,65 @@ fn get_module_signatures(module: &str) -> HashMap<String, FunctionSig> {
             sig!("send", ["ch" => Type::Any, "value" => Type::Any], Type::Unit);
             sig!("recv", ["ch" => Type::Any], Type::Any);
             sig!("sleep_ms", ["ms" => Type::Int], Type::Unit);
+            sig!("select", ["channels" => Type::Array(Box::new(Type::Any))], Type::Optional(Box::new(Type::Map {
+                key_type: Box::new(Type::String),
+                value_type: Box::new(Type::Any),
+            })), variadic);
+            sig!("await_all", ["tasks" => Type::Array(Box::new(Type::Any))], Type::Array(Box::new(Type::Any)));
+            sig!("await_any", ["tasks" => Type::Array(Box::new(Type::Any))], Type::Any);
+            sig!("join", ["task" => Type::Any], Type::Any);
+            sig!("spawn", ["func" => Type::Any], Type::Generic {
+                name: "Task".to_string(),
+                args: vec![Type::Any],
+            }, variadic);
+            sig!("parallel", ["funcs" => Type::Array(Box::new(Type::Any))], Type::Array(Box::new(Type::Any)));
+            sig!("parallel_map", ["arr" => Type::Array(Box::new(Type::Any)), "func" => Type::Any], Type::Array(Box::new(Type::Any)), variadic);
+        }
+        "std/sync" => {
+            sig!("atomic_int", ["initial" => Type::Int], Type::Any, variadic);
+            sig!("atomic_get", ["atomic" => Type::Any], Type::Int);
+            sig!("atomic_set", ["atomic" => Type::Any, "value" => Type::Int], Type::Unit);
+            sig!("atomic_add", ["atomic" => Type::Any, "delta" => Type::Int], Type::Int);
+            sig!("compare_and_swap", ["atomic" => Type::Any, "expected" => Type::Int, "new" => Type::Int], Type::Bool);
+            sig!("shared_map", [], Type::Any, variadic);
+            sig!("shared_get", ["map" => Type::Any, "key" => Type::String], Type::Optional(Box::new(Type::Any)));
+            sig!("shared_set", ["map" => Type::Any, "key" => Type::String, "value" => Type::Any], Type::Unit);
+            sig!("shared_delete", ["map" => Type::Any, "key" => Type::String], Type::Bool);
+            sig!("shared_keys", ["map" => Type::Any], Type::Array(Box::new(Type::String)));
+            sig!("shared_update", ["map" => Type::Any, "key" => Type::String, "func" => Type::Any], Type::Any, variadic);
+            sig!("once_cell", [], Type::Any);
+            sig!("lazy", ["func" => Type::Any], Type::Any);
+            sig!("once_get", ["cell" => Type::Any], Type::Optional(Box::new(Type::Any)));
+            sig!("get_or_init", ["cell" => Type::Any, "func" => Type::Any], Type::Any);
+            sig!("force", ["cell" => Type::Any], Type::Any);
+            sig!("free", ["handle" => Type::Any], Type::Bool);
+        }
+        "std/http/ws" => {
+            sig!("on_message", ["conn" => Type::Any, "func" => Type::Any], Type::Unit);
+            sig!("on_close", ["conn" => Type::Any, "func" => Type::Any], Type::Unit);
+            sig!("send", ["conn" => Type::Any, "message" => Type::Any], Type::Bool);
+            sig!("close", ["conn" => Type::Any], Type::Unit);
+            sig!("join", ["conn" => Type::Any, "room" => Type::String], Type::Unit);
+            sig!("leave", ["conn" => Type::Any, "room" => Type::String], Type::Unit);
+            sig!("broadcast", ["room" => Type::String, "message" => Type::Any], Type::Int, variadic);
+            sig!("room_size", ["room" => Type::String], Type::Int);
+        }
+        "std/schedule" => {
+            sig!("every", ["duration" => Type::Any, "func" => Type::Any], Type::Any);
+            sig!("after", ["duration" => Type::Any, "func" => Type::Any], Type::Any);
+            sig!("cron", ["expr" => Type::String, "func" => Type::Any], Type::Any, variadic);
+            sig!("cancel", ["schedule" => Type::Any], Type::Bool);
+        }
+        "std/jobs" => {
+            sig!("configure", ["options" => Type::Any], Type::Unit);
+            sig!("enqueue", ["name" => Type::String, "args" => Type::Any], Type::Int, variadic);
+            sig!("enqueue_at", ["name" => Type::String, "args" => Type::Any, "timestamp" => Type::Int], Type::Int, variadic);
+            sig!("worker", ["name" => Type::String, "func" => Type::Any], Type::Unit, variadic);
+            sig!("run_jobs", [], Type::Int, variadic);
+            sig!("get_job", ["id" => Type::Int], Type::Optional(Box::new(Type::Any)));
+            sig!("list_jobs", [], Type::Array(Box::new(Type::Any)), variadic);
+            sig!("cancel_job", ["id" => Type::Int], Type::Bool);
+            sig!("retry_job", ["id" => Type::Int], Type::Bool);
         }
         "std/csv" => {
             sig!("parse", ["s" => Type::String], Type::Array(Box::new(Type::Array(Box::new(Type::String)))));
@@ -2325,6 +3687,214 @@ mod tests {
         assert!(diags.is_empty());
     }
 
+    #[test]
+    fn test_match_non_exhaustive_enum_warns() {
+        let diags = check(
+            r#"
+            enum Color { Red, Green, Blue }
+            fn name(c: Color) -> String {
+                return match c {
+                    Red | Green => "warm",
+                }
+            }
+            "#,
+        );
+        assert!(diags
+            .iter()
+            .any(|d| d.message == "Non-exhaustive match on Color: missing Blue"));
+    }
+
+    #[test]
+    fn test_match_exhaustive_patterns_no_warning() {
+        let diags = check(
+            r#"
+            enum Color { Red, Green, Blue }
+            fn name(c: Color) -> String {
+                return match c {
+                    Red | Green => "warm",
+                    other @ Blue => "cool",
+                }
+            }
+            fn flag(b: Bool) -> Int {
+                return match b { true => 1, false => 0 }
+            }
+            fn code(n: Int) -> String {
+                return match n { 200..=299 => "ok", _ => "other" }
+            }
+            "#,
+        );
+        assert!(
+            !diags.iter().any(|d| d.message.contains("Non-exhaustive")),
+            "{:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn test_match_guarded_arm_not_exhaustive() {
+        let diags = check(
+            r#"
+            fn f(b: Bool) -> Int {
+                return match b { true => 1, false if 1 > 2 => 0 }
+            }
+            "#,
+        );
+        assert!(diags
+            .iter()
+            .any(|d| d.message == "Non-exhaustive match on Bool: missing false"));
+    }
+
+    #[test]
+    fn test_match_rest_binds_array() {
+        let diags = check(
+            r#"
+            let xs = [1, 2, 3]
+            let n: String = match xs { [first, ..rest] => rest, _ => [] }
+            "#,
+        );
+        assert!(
+            diags.iter().any(|d| d.message.contains("mismatch")),
+            "{:?}",
+            diags
+        );
+    }
+
+    #[test]
+    fn test_optional_chain_infers_optional() {
+        let diags = check(
+            r#"
+            struct Address { city: String }
+            struct User { address: Address? }
+            fn city(u: User) -> String {
+                let c: String = u.address?.city
+                return u.address?.city ?? "unknown"
+            }
+            "#,
+        );
+        assert_eq!(diags.len(), 1, "{:?}", diags);
+        assert!(diags[0]
+            .message
+            .contains("declared as String but initialized with String?"));
+    }
+
+    #[test]
+    fn test_async_fn_returns_task() {
+        let diags = check(
+            r#"
+            import { await_all } from "std/concurrent"
+            async fn count(n: Int) -> Int {
+                return n + 1
+            }
+            let task = count(1)
+            let a: Int = await task
+            let b: Int = task
+            let all: [String] = await_all([count(1), count(2)])
+            "#,
+        );
+        let messages: Vec<&str> = diags.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Type mismatch: variable 'b' declared as Int but initialized with Task<Int>",
+                "Type mismatch: variable 'all' declared as [String] but initialized with [Int]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spawn_and_parallel_map_types() {
+        let diags = check(
+            r#"
+            import { spawn, join, parallel_map } from "std/concurrent"
+            fn square(n: Int) -> Int {
+                return n * n
+            }
+            let task = spawn(square, [3])
+            let a: Int = join(task)
+            let b: String = await task
+            let all: [String] = parallel_map([1, 2], square, 2)
+            "#,
+        );
+        let messages: Vec<&str> = diags.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Type mismatch: variable 'b' declared as String but initialized with Int",
+                "Type mismatch: variable 'all' declared as [String] but initialized with [Int]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_schedule_literals_validated() {
+        let diags = check(
+            r#"
+            import { every, cron } from "std/schedule"
+            fn tick() { print("tick") }
+            every("5 minutes", tick)
+            cron("0 25 * * *", tick)
+            every("90s", tick)
+            cron("*/15 9-17 * * mon-fri", tick, "Europe/Berlin")
+            "#,
+        );
+        let messages: Vec<&str> = diags.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Invalid duration '5 minutes' for every()",
+                "Invalid cron expression '0 25 * * *': hour field '25' is out of range 0-23",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_strings_validated() {
+        let diags = check(
+            r#"
+            let name = "Widget"
+            let price = 2.5
+            let count = 1200
+            print(format("{:>10} {:.2} {:,}", name, price, count))
+            print("{price:.2} {count:#x} {name:^12}")
+            print(format("{:>10} {:.2}", name))
+            print(format("{:,}", name))
+            print(format("{:x}", price))
+            print(format("{:.2}", "str"))
+            print("{name:,}")
+            print("{price:.2q}")
+            print(format("{0} }", count))
+            "#,
+        );
+        let messages: Vec<&str> = diags.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "format() template uses 2 arguments but 1 was given",
+                "format() argument 0 cannot be formatted: ',' requires a number, got String",
+                "format() argument 0 cannot be formatted: it requires an Int, got Float",
+                "format() argument 0 cannot be formatted: '.2' requires a number, got String",
+                "Format spec ':,' cannot be used here: ',' requires a number, got String",
+                "Invalid format spec ':.2q': unexpected 'q'",
+                "Invalid format() template: unmatched '}' (use '}}' for a literal brace)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_from_std_time_not_checked_as_template() {
+        let diags = check(
+            r#"
+            import { now, format } from "std/time"
+            let stamp = format(now(), "%Y-%m-%d {}")
+            "#,
+        );
+        assert!(
+            diags.is_empty(),
+            "{:?}",
+            diags.iter().map(|d| &d.message).collect::<Vec<_>>()
+        );
+    }
+
     // ── Import resolution ───────────────────────────────────────
 
     #[test]
@@ -3345,4 +4915,265 @@ mod tests {
             path.display()
         );
     }
+
+    #[test]
+    fn test_unknown_struct_field_suggests_name() {
+        let diags = check_errors(
+            r#"
+struct User { name: String, age: Int }
+fn f(u: User) -> String { return u.nmae }
+"#,
+        );
+        assert_eq!(diags.len(), 1, "Diagnostics: {:?}", diags);
+        assert_eq!(diags[0].message, "Struct 'User' has no field 'nmae'");
+        assert_eq!(diags[0].hint.as_deref(), Some("Did you mean 'name'?"));
+    }
+
+    #[test]
+    fn test_trait_impl_missing_method() {
+        let diags = check_errors(
+            r#"
+trait Shape {
+    fn area(self) -> Float
+    fn name(self) -> String { return "shape" }
+}
+struct Square { side: Float }
+impl Shape for Square { }
+"#,
+        );
+        assert_eq!(diags.len(), 1, "Diagnostics: {:?}", diags);
+        assert_eq!(
+            diags[0].message,
+            "impl Shape for Square is missing method 'area'"
+        );
+    }
+
+    // ── Trait bounds ────────────────────────────────────────────
+
+    const TRAITS_PRELUDE: &str = r#"
+trait Comparable { fn compare(self, other) -> Int }
+trait Ordered: Comparable { fn rank(self) -> Int }
+struct User { id: Int }
+struct Tag { name: String }
+struct Score { v: Int }
+impl Comparable for User { fn compare(self, other) -> Int { return self.id - other.id } }
+impl Comparable for Score { fn compare(self, other) -> Int { return 0 } }
+impl Ordered for Score { fn rank(self) -> Int { return self.v } }
+fn sort<T: Comparable>(arr: [T]) -> [T] { return arr }
+fn top<T: Comparable>(x: T?) -> Int { return 0 }
+"#;
+
+    #[test]
+    fn test_trait_bound_satisfied() {
+        let diags = check_errors(&format!(
+            "{}{}",
+            TRAITS_PRELUDE,
+            r#"
+let a = sort([User { id: 1 }])
+let b = sort([Score { v: 1 }])
+let c = sort([3, 1, 2])
+"#
+        ));
+        assert!(diags.is_empty(), "Unexpected errors: {:?}", diags);
+    }
+
+    #[test]
+    fn test_trait_bound_missing_impl() {
+        let diags = check_errors(&format!(
+            "{}{}",
+            TRAITS_PRELUDE,
+            r#"
+let a = sort([Tag { name: "x" }])
+let b = top(Some(Tag { name: "y" }))
+"#
+        ));
+        assert_eq!(diags.len(), 2, "Diagnostics: {:?}", diags);
+        assert_eq!(
+            diags[0].message,
+            "Type 'Tag' does not implement trait 'Comparable' required by 'sort<T: Comparable>'"
+        );
+        assert_eq!(
+            diags[0].hint.as_deref(),
+            Some("Add `impl Comparable for Tag { ... }`")
+        );
+        assert!(diags[1].message.contains("'top<T: Comparable>'"));
+    }
+
+    #[test]
+    fn test_impl_requires_supertrait() {
+        let diags = check_errors(
+            r#"
+trait Comparable { fn compare(self, other) -> Int }
+trait Ordered: Comparable { fn rank(self) -> Int }
+struct Score { v: Int }
+impl Ordered for Score { fn rank(self) -> Int { return self.v } }
+"#,
+        );
+        assert_eq!(diags.len(), 1, "Diagnostics: {:?}", diags);
+        assert_eq!(
+            diags[0].message,
+            "impl Ordered for Score requires Score to implement supertrait 'Comparable'"
+        );
+    }
+
+    // ── Refinement types ────────────────────────────────────────
+
+    #[test]
+    fn test_refined_type_is_subtype_of_base() {
+        let diags = check_errors(
+            r#"
+type Port = Int where self > 0 && self < 65536
+fn double(n: Int) -> Int { return n * 2 }
+let p: Port = 8080
+let d: Int = double(p)
+"#,
+        );
+        assert!(diags.is_empty(), "Unexpected errors: {:?}", diags);
+    }
+
+    #[test]
+    fn test_refined_let_literal_violation() {
+        let diags = check_errors(
+            r#"
+type Port = Int where self > 0 && self < 65536
+let p: Port = 70000
+"#,
+        );
+        assert_eq!(diags.len(), 1);
+        assert!(diags[0].message.contains("'Port'"));
+        assert_eq!(diags[0].line, 3);
+        assert_eq!(
+            diags[0].hint.as_deref(),
+            Some("Port requires self > 0 && self < 65536")
+        );
+    }
+
+    #[test]
+    fn test_refined_param_literal_violation() {
+        let diags = check_errors(
+            r#"
+type Name = String where len(self) > 0
+type Short = Name where len(self) <= 3
+fn greet(n: Short) -> String { return n }
+greet("bob")
+greet("")
+greet("alice")
+"#,
+        );
+        assert_eq!(diags.len(), 2, "Diagnostics: {:?}", diags);
+        assert_eq!(diags[0].line, 6);
+        assert_eq!(diags[1].line, 7);
+    }
+
+    #[test]
+    fn test_refinement_predicate_must_be_bool() {
+        let diags = check_errors("type Bad = Int where self + 1");
+        assert_eq!(diags.len(), 1);
+        assert!(diags[0].message.contains("should be Bool"));
+    }
+
+    // ── Flow-sensitive narrowing ────────────────────────────────
+
+    const NARROWING_PRELUDE: &str = r#"
+struct User { name: String, age: Int }
+fn greet(name: String) -> String { return "hi " + name }
+"#;
+
+    fn check_narrowing(body: &str) -> Vec<TypeDiagnostic> {
+        check_errors(&format!("{}{}", NARROWING_PRELUDE, body))
+    }
+
+    #[test]
+    fn test_narrow_none_check() {
+        let diags = check_narrowing(
+            r#"
+fn f(x: String?) -> String {
+    if x != None {
+        return greet(x)
+    }
+    return "none"
+}
+"#,
+        );
+        assert!(diags.is_empty(), "Unexpected errors: {:?}", diags);
+    }
+
+    #[test]
+    fn test_narrow_does_not_leak_out_of_guard() {
+        let diags = check_narrowing(
+            r#"
+fn f(x: String?) -> String {
+    if x != None {
+        print(x)
+    } else {
+        print(greet(x))
+    }
+    let y: String = x
+    return y
+}
+"#,
+        );
+        // Neither branch exits, so x is String? again after the if
+        assert_eq!(diags.len(), 2, "Diagnostics: {:?}", diags);
+    }
+
+    #[test]
+    fn test_narrow_after_early_return() {
+        let diags = check_narrowing(
+            r#"
+fn f(x: String?) -> String {
+    if x == None {
+        return "none"
+    }
+    return greet(x)
+}
+fn g(u: User?) -> String {
+    if u.is_none() { return "" }
+    let n: Int = u.name
+    return u.name
+}
+"#,
+        );
+        // u is narrowed to User, so u.name is a String, not Int
+        assert_eq!(diags.len(), 1, "Diagnostics: {:?}", diags);
+        assert!(diags[0].message.contains("initialized with String"));
+    }
+
+    #[test]
+    fn test_narrow_is_some_and_short_circuit() {
+        let diags = check_narrowing(
+            r#"
+fn f(x: String?) -> Bool {
+    if is_some(x) && len(greet(x)) > 0 {
+        return true
+    }
+    return x.is_some() && greet(x) != ""
+}
+"#,
+        );
+        assert!(diags.is_empty(), "Unexpected errors: {:?}", diags);
+    }
+
+    #[test]
+    fn test_narrow_type_comparison() {
+        let diags = check_narrowing(
+            r#"
+fn f(v: String | Int) -> String {
+    if type(v) == "String" {
+        return greet(v)
+    }
+    let n: Int = v
+    return str(n)
+}
+fn g(v) -> Int {
+    if type(v) != "Int" { return 0 }
+    let s: String = v
+    return v
+}
+"#,
+        );
+        // Only g's `let s: String = v` is wrong: v is an Int there
+        assert_eq!(diags.len(), 1, "Diagnostics: {:?}", diags);
+        assert!(diags[0].message.contains("Int"));
+    }
 }
@@ -130,6 +130,11 @@ impl Type {
                 types.iter().any(|t| t.is_compatible(other))
             }
             (Type::Named(a), Type::Named(b)) => a == b,
+            (Type::Generic { name: n1, args: a1 }, Type::Generic { name: n2, args: a2 }) => {
+                n1 == n2
+                    && a1.len() == a2.len()
+                    && a1.iter().zip(a2.iter()).all(|(x, y)| x.is_compatible(y))
+            }
             (Type::Tuple(a), Type::Tuple(b)) => {
                 a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| x.is_compatible(y))
             }
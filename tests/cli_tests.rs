@@ -294,6 +294,105 @@ fn test_validate_exits_nonzero_on_syntax_error() {
     assert!(json["summary"]["errors"].as_i64().unwrap() > 0);
 }
 
+#[test]
+fn test_inspect_detects_schedules() {
+    use std::fs;
+
+    let test_file = std::env::temp_dir().join("ntnt_test_schedules.tnt");
+    fs::write(
+        &test_file,
+        r#"import { every, after, cron } from "std/schedule"
+fn refresh() { print("refresh") }
+fn purge() { print("purge") }
+let ticker = every("5m", refresh)
+after(30000, refresh)
+cron("0 2 * * *", purge, "Europe/Berlin")
+"#,
+    )
+    .unwrap();
+
+    let (stdout, _, code) = run_ntnt(&["inspect", test_file.to_str().unwrap()]);
+    fs::remove_file(&test_file).ok();
+    assert_eq!(code, 0);
+
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let schedules = json["schedules"].as_array().unwrap();
+    assert_eq!(schedules.len(), 3);
+    assert_eq!(schedules[0]["kind"], "every");
+    assert_eq!(schedules[0]["schedule"], "5m");
+    assert_eq!(schedules[0]["handler"], "refresh");
+    assert_eq!(schedules[1]["schedule"], "30000ms");
+    assert_eq!(schedules[2]["kind"], "cron");
+    assert_eq!(schedules[2]["timezone"], "Europe/Berlin");
+    assert_eq!(schedules[2]["line"], 6);
+}
+
+#[test]
+fn test_jobs_list_retry_cancel() {
+    use std::fs;
+
+    let dir = std::env::temp_dir();
+    let db = dir.join("ntnt_test_jobs.db");
+    let script = dir.join("ntnt_test_jobs.tnt");
+    for suffix in ["", "-wal", "-shm"] {
+        fs::remove_file(format!("{}{}", db.display(), suffix)).ok();
+    }
+    fs::write(
+        &script,
+        format!(
+            r#"import {{ configure, enqueue }} from "std/jobs"
+configure(map {{ "db": "{}" }})
+enqueue("send_email", map {{ "to": "a@example.com" }})
+enqueue("send_email", map {{ "to": "b@example.com" }}, map {{ "priority": "high" }})
+"#,
+            db.display().to_string().replace('\\', "/")
+        ),
+    )
+    .unwrap();
+    let db_path = db.to_str().unwrap();
+
+    let (_, stderr, code) = run_ntnt(&["run", script.to_str().unwrap()]);
+    fs::remove_file(&script).ok();
+    assert_eq!(code, 0, "stderr: {}", stderr);
+
+    let (stdout, _, code) = run_ntnt(&["jobs", "list", "--db", db_path, "--format", "json"]);
+    assert_eq!(code, 0);
+    let jobs: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let jobs = jobs.as_array().unwrap();
+    assert_eq!(jobs.len(), 2);
+    assert_eq!(jobs[0]["name"], "send_email");
+    assert_eq!(jobs[0]["args"]["to"], "a@example.com");
+    assert_eq!(jobs[0]["status"], "pending");
+    assert_eq!(jobs[1]["priority"], 10);
+    let id = jobs[0]["id"].to_string();
+
+    let (stdout, _, code) = run_ntnt(&["jobs", "cancel", &id, "--db", db_path, "--format", "json"]);
+    assert_eq!(code, 0);
+    let result: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(result["cancelled"], true);
+    assert_eq!(result["status"], "cancelled");
+
+    // Already cancelled: nothing changes and the command fails
+    let (_, _, code) = run_ntnt(&["jobs", "cancel", &id, "--db", db_path, "--format", "json"]);
+    assert_eq!(code, 1);
+
+    let (stdout, _, code) = run_ntnt(&["jobs", "retry", &id, "--db", db_path, "--format", "json"]);
+    assert_eq!(code, 0);
+    let result: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(result["retried"], true);
+    assert_eq!(result["status"], "pending");
+
+    let (stdout, _, code) = run_ntnt(&[
+        "jobs", "list", "--db", db_path, "--status", "dead", "--format", "json",
+    ]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout.trim(), "[]");
+
+    for suffix in ["", "-wal", "-shm"] {
+        fs::remove_file(format!("{}{}", db.display(), suffix)).ok();
+    }
+}
+
 #[test]
 fn test_inspect_handles_invalid_file_gracefully() {
     use std::fs;
@@ -0,0 +1,1191 @@
+//! Integration tests for the async HTTP server
+//!
+//! Each test writes a small .tnt app to a temp directory, starts it with
+//! `ntnt run` on its own port and talks to it over HTTP.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+
+/// Path to the ntnt binary under test
+fn ntnt_binary() -> String {
+    std::env::var("NTNT_TEST_BINARY").unwrap_or_else(|_| env!("CARGO_BIN_EXE_ntnt").to_string())
+}
+
+/// Write `source` to a fresh temp directory and return the app path
+fn write_app(name: &str, source: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("ntnt_http_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("app.tnt");
+    fs::write(&path, source).unwrap();
+    path
+}
+
+/// Start `ntnt run app` on `port`
+fn start_app(app: &Path, port: u16) -> Child {
+    start_app_with_env(app, port, &[])
+}
+
+/// Start `ntnt run app` on `port` with extra environment variables
+fn start_app_with_env(app: &Path, port: u16, env: &[(&str, &str)]) -> Child {
+    let mut cmd = Command::new(ntnt_binary());
+    cmd.args(["run", &app.to_string_lossy()])
+        .env("NTNT_ENV", "production")
+        .envs(env.iter().copied())
+        .env("NTNT_LISTEN_PORT", port.to_string())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    #[cfg(unix)]
+    cmd.process_group(0);
+
+    cmd.spawn().expect("Failed to start ntnt")
+}
+
+/// Wait until the server answers on `url`
+fn wait_for_server(url: &str, timeout: Duration) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if reqwest::blocking::get(url).is_ok() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    false
+}
+
+/// Ask the server to shut down gracefully and collect its output
+#[cfg(unix)]
+fn stop_app(child: Child) -> Output {
+    unsafe {
+        libc::kill(child.id() as i32, libc::SIGTERM);
+    }
+    child.wait_with_output().expect("Failed to wait for ntnt")
+}
+
+fn get_text(url: &str) -> String {
+    reqwest::blocking::get(url)
+        .and_then(|r| r.text())
+        .unwrap_or_else(|e| panic!("GET {} failed: {}", url, e))
+}
+
+#[cfg(unix)]
+#[test]
+fn test_worker_pool_serves_while_a_handler_is_slow() {
+    let app = write_app(
+        "workers",
+        r#"
+import { sleep_ms } from "std/concurrent"
+import { atomic_int, atomic_add } from "std/sync"
+import { text } from "std/http/server"
+
+let hits = atomic_int(0, "hits")
+
+fn slow(req) {
+    sleep_ms(1500)
+    return text("slow")
+}
+
+fn fast(req) {
+    let n = atomic_add(hits, 1)
+    return text("fast {n}")
+}
+
+fn goodbye() {
+    print("worker stopped")
+}
+
+get("/slow", slow)
+get("/fast", fast)
+on_shutdown(goodbye)
+listen(8080, map { "workers": 2 })
+"#,
+    );
+    let port = 19911;
+    let base = format!("http://127.0.0.1:{}", port);
+    let child = start_app(&app, port);
+    assert!(
+        wait_for_server(&format!("{}/fast", base), Duration::from_secs(10)),
+        "server did not start"
+    );
+
+    let slow_url = format!("{}/slow", base);
+    let slow = thread::spawn(move || get_text(&slow_url));
+    thread::sleep(Duration::from_millis(300));
+
+    // The other worker answers while the first is busy
+    let start = Instant::now();
+    let first = get_text(&format!("{}/fast", base));
+    let second = get_text(&format!("{}/fast", base));
+    assert!(
+        start.elapsed() < Duration::from_millis(1000),
+        "fast requests waited for the slow handler"
+    );
+    assert_eq!(slow.join().unwrap(), "slow");
+
+    // Named atomics are shared, so counts continue across workers
+    assert_eq!(first, "fast 2");
+    assert_eq!(second, "fast 3");
+
+    let output = stop_app(child);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Workers: 2 (least_busy)"), "{}", stdout);
+    assert_eq!(stdout.matches("worker stopped").count(), 2, "{}", stdout);
+    fs::remove_dir_all(app.parent().unwrap()).ok();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_hot_reload_keeps_routes_across_workers() {
+    let source = |version: &str, extra: &str| {
+        format!(
+            r#"
+import {{ text }} from "std/http/server"
+
+fn home(req) {{
+    return text("{version}")
+}}
+
+get("/", home)
+{extra}
+listen(8080, map {{ "workers": 4 }})
+"#
+        )
+    };
+    let app = write_app("reload_workers", &source("v1", ""));
+    let port = 19923;
+    let base = format!("http://127.0.0.1:{}", port);
+    let child = start_app_with_env(&app, port, &[("NTNT_ENV", "development")]);
+    assert!(
+        wait_for_server(&format!("{}/", base), Duration::from_secs(10)),
+        "server did not start"
+    );
+
+    // Make sure the new file gets a later modification time
+    thread::sleep(Duration::from_millis(1100));
+    fs::write(
+        &app,
+        source(
+            "v2",
+            "fn extra(req) { return text(\"extra\") }\nget(\"/extra\", extra)",
+        ),
+    )
+    .unwrap();
+
+    // Every worker reloads on its next request; none may answer 404 meanwhile
+    let clients: Vec<_> = (0..4)
+        .map(|_| {
+            let url = format!("{}/", base);
+            thread::spawn(move || {
+                (0..25)
+                    .map(|_| reqwest::blocking::get(&url).unwrap().status().as_u16())
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    for client in clients {
+        let statuses = client.join().unwrap();
+        assert!(statuses.iter().all(|s| *s == 200), "{:?}", statuses);
+    }
+    assert_eq!(get_text(&format!("{}/", base)), "v2");
+    assert_eq!(get_text(&format!("{}/extra", base)), "extra");
+
+    stop_app(child);
+    fs::remove_dir_all(app.parent().unwrap()).ok();
+}
+
+#[test]
+fn test_listen_rejects_unknown_options() {
+    let app = write_app(
+        "listen_options",
+        r#"
+fn home(req) {
+    return "home"
+}
+
+get("/", home)
+listen(8080, map { "wrokers": 2 })
+"#,
+    );
+    let output = Command::new(ntnt_binary())
+        .args(["run", &app.to_string_lossy()])
+        .env("NTNT_LISTEN_PORT", "19912")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("Unknown listen() option 'wrokers'"),
+        "{}",
+        stderr
+    );
+    fs::remove_dir_all(app.parent().unwrap()).ok();
+}
+
+const CHAT_APP: &str = r#"
+import { on_message, on_close, send, join, broadcast, room_size } from "std/http/ws"
+import { status } from "std/http/server"
+
+fn relay(conn, msg) {
+    let room = conn.params["room"]
+    if msg == "who" {
+        send(conn, "members: {room_size(room)}")
+    } else {
+        broadcast(room, msg, conn)
+    }
+}
+
+fn left(conn) {
+    broadcast(conn.params["room"], "someone left")
+}
+
+fn chat(conn) {
+    let room = conn.params["room"]
+    if room == "private" {
+        return status(403, "Forbidden")
+    }
+    join(conn, room)
+    send(conn, "welcome to {room}")
+    on_message(conn, relay)
+    on_close(conn, left)
+}
+
+ws("/chat/{room}", chat)
+listen(8080, map { "workers": 2 })
+"#;
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_websocket_rooms_across_workers() {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let app = write_app("websocket", CHAT_APP);
+    let port = 19913;
+    let child = start_app(&app, port);
+    let url = format!("ws://127.0.0.1:{}/chat/lobby", port);
+
+    // Wait for the server, then connect two clients to the same room
+    let start = Instant::now();
+    let (mut alice, _) = loop {
+        match tokio_tungstenite::connect_async(url.as_str()).await {
+            Ok(connected) => break connected,
+            Err(e) if start.elapsed() > Duration::from_secs(10) => {
+                panic!("could not connect: {}", e)
+            }
+            Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+        }
+    };
+    let (mut bob, _) = tokio_tungstenite::connect_async(url.as_str())
+        .await
+        .unwrap();
+
+    async fn next_text<S>(socket: &mut S) -> String
+    where
+        S: futures_util::Stream<Item = tokio_tungstenite::tungstenite::Result<Message>> + Unpin,
+    {
+        let frame = tokio::time::timeout(Duration::from_secs(5), socket.next())
+            .await
+            .expect("timed out waiting for a message")
+            .expect("connection ended")
+            .expect("websocket error");
+        frame.into_text().unwrap()
+    }
+
+    assert_eq!(next_text(&mut alice).await, "welcome to lobby");
+    assert_eq!(next_text(&mut bob).await, "welcome to lobby");
+
+    // Broadcast skips the sender; send() answers only the sender
+    alice.send(Message::Text("hi bob".into())).await.unwrap();
+    assert_eq!(next_text(&mut bob).await, "hi bob");
+    bob.send(Message::Text("who".into())).await.unwrap();
+    assert_eq!(next_text(&mut bob).await, "members: 2");
+
+    // Closing runs on_close, which tells the rest of the room
+    alice.close(None).await.unwrap();
+    assert_eq!(next_text(&mut bob).await, "someone left");
+
+    // A handler that returns a response rejects the upgrade
+    let private = format!("ws://127.0.0.1:{}/chat/private", port);
+    match tokio_tungstenite::connect_async(private.as_str()).await {
+        Err(tokio_tungstenite::tungstenite::Error::Http(response)) => {
+            assert_eq!(response.status().as_u16(), 403)
+        }
+        other => panic!("expected 403, got {:?}", other.map(|(_, r)| r.status())),
+    }
+
+    // Shutdown closes the remaining connection
+    tokio::task::spawn_blocking(move || stop_app(child))
+        .await
+        .unwrap();
+    let closed = tokio::time::timeout(Duration::from_secs(5), bob.next()).await;
+    assert!(matches!(closed, Ok(Some(Ok(Message::Close(_)))) | Ok(None)));
+    fs::remove_dir_all(app.parent().unwrap()).ok();
+}
+
+const STREAMING_APP: &str = r#"
+import { send_event, stream, text, write } from "std/http/server"
+import { sleep_ms } from "std/concurrent"
+
+fn progress(req, out) {
+    for i in 1..4 {
+        send_event(out, map { "step": i }, map { "id": i })
+        sleep_ms(300)
+    }
+    send_event(out, "done", map { "event": "finished" })
+}
+
+fn forever(req, out) {
+    while send_event(out, "tick") {
+        sleep_ms(50)
+    }
+    print("client left")
+}
+
+fn report(req) {
+    let name = req.query_params["name"]
+    fn lines(out) {
+        write(out, "hello {name}\n")
+        sleep_ms(100)
+        write(out, "bye\n")
+    }
+    return stream(lines)
+}
+
+fn ping(req) {
+    return text("pong")
+}
+
+sse("/progress", progress)
+sse("/forever", forever)
+get("/report", report)
+get("/ping", ping)
+listen(8080)
+"#;
+
+#[cfg(unix)]
+#[test]
+fn test_sse_and_streamed_responses() {
+    use std::io::{BufRead, BufReader};
+
+    let app = write_app("streaming", STREAMING_APP);
+    let port = 19914;
+    let base = format!("http://127.0.0.1:{}", port);
+    let child = start_app(&app, port);
+    assert!(
+        wait_for_server(&format!("{}/report?name=x", base), Duration::from_secs(10)),
+        "server did not start"
+    );
+
+    // Events arrive while the handler is still running
+    let response = reqwest::blocking::get(format!("{}/progress", base)).unwrap();
+    assert_eq!(
+        response.headers()["content-type"].to_str().unwrap(),
+        "text/event-stream"
+    );
+    let mut lines = Vec::new();
+    for line in BufReader::new(response).lines() {
+        lines.push((Instant::now(), line.unwrap()));
+    }
+    let text: Vec<&str> = lines.iter().map(|(_, line)| line.as_str()).collect();
+    assert_eq!(
+        text,
+        [
+            "id: 1",
+            "data: {\"step\":1}",
+            "",
+            "id: 2",
+            "data: {\"step\":2}",
+            "",
+            "id: 3",
+            "data: {\"step\":3}",
+            "",
+            "event: finished",
+            "data: done",
+            ""
+        ]
+    );
+    let first = lines[1].0;
+    let last = lines[10].0;
+    assert!(last.duration_since(first) > Duration::from_millis(500));
+
+    // Other requests are served while an event stream stays open
+    let response = reqwest::blocking::get(format!("{}/forever", base)).unwrap();
+    let mut reader = BufReader::new(response);
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    assert_eq!(line, "data: tick\n");
+    let ping = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap()
+        .get(format!("{}/ping", base))
+        .send()
+        .unwrap();
+    assert_eq!(ping.text().unwrap(), "pong");
+
+    // Disconnecting makes send_event() return false, which ends the stream
+    drop(reader);
+
+    // stream() responses are sent as they are written and never compressed
+    let response = reqwest::blocking::Client::new()
+        .get(format!("{}/report?name=ada", base))
+        .header("accept-encoding", "gzip")
+        .send()
+        .unwrap();
+    assert!(response.headers().get("content-encoding").is_none());
+    assert_eq!(response.text().unwrap(), "hello ada\nbye\n");
+
+    let output = stop_app(child);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("client left"), "{}", stdout);
+    fs::remove_dir_all(app.parent().unwrap()).ok();
+}
+
+const UPLOAD_APP: &str = r#"
+import { parse_multipart, save_upload, json, status } from "std/http/server"
+import { has_key } from "std/collections"
+
+fn upload(req) {
+    match parse_multipart(req) {
+        Ok(form) => {
+            let file = form["files"][0]
+            match save_upload(file, form["fields"]["dest"]) {
+                Ok(path) => {
+                    return json(map {
+                        "field": file["field"],
+                        "filename": file["filename"],
+                        "size": file["size"],
+                        "on_disk": has_key(file, "path")
+                    })
+                },
+                Err(e) => { return status(500, e) }
+            }
+        },
+        Err(e) => { return status(400, e) }
+    }
+}
+
+fn health(req) {
+    return status(200, "ok")
+}
+
+fn raw(req) {
+    return json(map { "bytes": len(req.body_bytes) })
+}
+
+post("/upload", upload, map { "max_body": "1mb" })
+post("/small", upload, map { "max_body": 2000 })
+post("/raw", raw)
+get("/health", health)
+listen(8080, map { "max_body": "4kb" })
+"#;
+
+#[cfg(unix)]
+#[test]
+fn test_multipart_uploads_and_body_limits() {
+    use reqwest::blocking::multipart::{Form, Part};
+
+    let app = write_app("uploads", UPLOAD_APP);
+    let dir = app.parent().unwrap().to_path_buf();
+    let port = 19915;
+    let base = format!("http://127.0.0.1:{}", port);
+    let child = start_app(&app, port);
+    assert!(
+        wait_for_server(&format!("{}/health", base), Duration::from_secs(10)),
+        "server did not start"
+    );
+
+    let client = reqwest::blocking::Client::new();
+    let upload = |route: &str, name: &str, data: &[u8]| {
+        let dest = dir.join(name);
+        let form = Form::new()
+            .text("dest", dest.to_string_lossy().to_string())
+            .part(
+                "document",
+                Part::bytes(data.to_vec())
+                    .file_name(name.to_string())
+                    .mime_str("application/octet-stream")
+                    .unwrap(),
+            );
+        let response = client
+            .post(format!("{}{}", base, route))
+            .multipart(form)
+            .send()
+            .unwrap();
+        (response.status().as_u16(), response.text().unwrap(), dest)
+    };
+
+    // Small uploads stay in memory, large ones are spilled to a temp file;
+    // both are saved byte for byte
+    let small: Vec<u8> = (0..=255).collect();
+    let (code, body, dest) = upload("/upload", "small.bin", &small);
+    assert_eq!(code, 200, "{}", body);
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["field"], "document");
+    assert_eq!(json["filename"], "small.bin");
+    assert_eq!(json["size"], 256);
+    assert_eq!(json["on_disk"], false);
+    assert_eq!(fs::read(dest).unwrap(), small);
+
+    let large: Vec<u8> = (0..300_000u32).map(|i| (i * 7 % 256) as u8).collect();
+    let (code, body, dest) = upload("/upload", "large.bin", &large);
+    assert_eq!(code, 200, "{}", body);
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["size"], 300_000);
+    assert_eq!(json["on_disk"], true);
+    assert_eq!(fs::read(dest).unwrap(), large);
+
+    // Bodies over the route limit, or the listen() limit, are rejected
+    let (code, _, dest) = upload("/small", "big.bin", &[1; 5000]);
+    assert_eq!(code, 413);
+    assert!(!dest.exists());
+    let response = client
+        .post(format!("{}/raw", base))
+        .body(vec![0xff; 8000])
+        .send()
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 413);
+
+    // Non-UTF-8 bodies are available as bytes
+    let response = client
+        .post(format!("{}/raw", base))
+        .body(vec![0xff, 0xfe, 0x00])
+        .send()
+        .unwrap();
+    assert_eq!(response.text().unwrap(), r#"{"bytes":3}"#);
+
+    // parse_multipart() rejects other bodies
+    let response = client
+        .post(format!("{}/upload", base))
+        .body("a=1")
+        .send()
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 400);
+
+    stop_app(child);
+    fs::remove_dir_all(dir).ok();
+}
+
+const COOKIE_APP: &str = r#"
+import { json, text, status, set_cookie, clear_cookie, set_signed_cookie, get_signed_cookie, set_encrypted_cookie, get_encrypted_cookie } from "std/http/server"
+
+fn login(req) {
+    let resp = text("welcome")
+    let resp = set_cookie(resp, "theme", "dark mode", map { "max_age": 3600, "same_site": "Lax" })
+    let resp = set_signed_cookie(resp, "user_id", "42", map { "http_only": true })
+    return set_encrypted_cookie(resp, "cart", "apples=3", map { "http_only": true })
+}
+
+fn me(req) {
+    match get_signed_cookie(req, "user_id") {
+        Some(id) => {
+            return json(map {
+                "id": id,
+                "theme": req.cookies["theme"],
+                "cart": unwrap(get_encrypted_cookie(req, "cart"))
+            })
+        },
+        None => { return status(401, "Not signed in") }
+    }
+}
+
+fn logout(req) {
+    return clear_cookie(text("bye"), "user_id")
+}
+
+fn echo(req) {
+    return json(req.cookies)
+}
+
+get("/login", login)
+get("/me", me)
+get("/logout", logout)
+get("/echo", echo)
+listen(8080)
+"#;
+
+#[cfg(unix)]
+#[test]
+fn test_cookies_and_multiple_set_cookie_headers() {
+    let app = write_app("cookies", COOKIE_APP);
+    let port = 19916;
+    let base = format!("http://127.0.0.1:{}", port);
+    let secret = "test-secret-0123456789abcdef0123456789";
+    let child = start_app_with_env(&app, port, &[("NTNT_COOKIE_SECRET", secret)]);
+    assert!(
+        wait_for_server(&format!("{}/echo", base), Duration::from_secs(10)),
+        "server did not start"
+    );
+
+    // Each cookie is its own Set-Cookie header
+    let response = reqwest::blocking::get(format!("{}/login", base)).unwrap();
+    let mut cookies: Vec<String> = response
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .map(|value| value.to_str().unwrap().to_string())
+        .collect();
+    cookies.sort();
+    assert_eq!(cookies.len(), 3, "{:?}", cookies);
+    assert!(cookies[0].starts_with("cart=") && cookies[0].ends_with("; Path=/; HttpOnly"));
+    assert!(!cookies[0].contains("apples"));
+    assert_eq!(
+        cookies[1],
+        "theme=dark%20mode; Path=/; Max-Age=3600; SameSite=Lax"
+    );
+    assert!(cookies[2].starts_with("user_id=42."), "{}", cookies[2]);
+
+    // The browser sends them back; signed and encrypted values are verified
+    let cookie_header: Vec<&str> = cookies
+        .iter()
+        .map(|cookie| cookie.split(';').next().unwrap())
+        .collect();
+    let client = reqwest::blocking::Client::new();
+    let me = |cookie: &str| {
+        let response = client
+            .get(format!("{}/me", base))
+            .header("cookie", cookie)
+            .send()
+            .unwrap();
+        (response.status().as_u16(), response.text().unwrap())
+    };
+    let (code, body) = me(&cookie_header.join("; "));
+    assert_eq!(code, 200, "{}", body);
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(json["id"], "42");
+    assert_eq!(json["theme"], "dark mode");
+    assert_eq!(json["cart"], "apples=3");
+
+    // A changed value no longer matches its signature
+    let tampered = cookie_header[2].replacen("42", "43", 1);
+    assert_eq!(me(&tampered).0, 401);
+    assert_eq!(me("user_id=42").0, 401);
+
+    // Cookies from repeated Cookie headers are all parsed
+    let response = client
+        .get(format!("{}/echo", base))
+        .header("cookie", "a=1")
+        .header("cookie", "b=2")
+        .send()
+        .unwrap();
+    let json: serde_json::Value = response.json().unwrap();
+    assert_eq!(json, serde_json::json!({ "a": "1", "b": "2" }));
+
+    let response = reqwest::blocking::get(format!("{}/logout", base)).unwrap();
+    assert_eq!(
+        response.headers()["set-cookie"],
+        "user_id=; Path=/; Max-Age=0"
+    );
+
+    stop_app(child);
+    fs::remove_dir_all(app.parent().unwrap()).ok();
+}
+
+const SESSION_APP: &str = r#"
+import { json, text, status, sessions, session_get, session_set, rotate_session, destroy_session } from "std/http/server"
+
+use_middleware(sessions(map { "store": "sqlite", "path": "DB_PATH", "ttl": "1h" }))
+
+fn login(req) {
+    rotate_session(req)
+    session_set(req, "user", "ada")
+    return text("welcome")
+}
+
+fn count(req) {
+    let n = match session_get(req, "count") {
+        Some(n) => n + 1,
+        None => 1
+    }
+    session_set(req, "count", n)
+    return json(map { "count": n, "user": session_get(req, "user") != None })
+}
+
+fn logout(req) {
+    destroy_session(req)
+    return text("bye")
+}
+
+fn health(req) {
+    return text("ok")
+}
+
+get("/login", login)
+get("/count", count)
+get("/logout", logout)
+get("/health", health)
+listen(8080, map { "workers": 2 })
+"#;
+
+#[cfg(unix)]
+#[test]
+fn test_sessions_persist_across_requests_and_workers() {
+    let app = write_app("sessions", SESSION_APP);
+    let db = app.parent().unwrap().join("sessions.db");
+    fs::write(&app, SESSION_APP.replace("DB_PATH", &db.to_string_lossy())).unwrap();
+    let port = 19917;
+    let base = format!("http://127.0.0.1:{}", port);
+    let child = start_app(&app, port);
+    assert!(
+        wait_for_server(&format!("{}/health", base), Duration::from_secs(10)),
+        "server did not start"
+    );
+
+    // Requests that never touch the session get no cookie
+    let response = reqwest::blocking::get(format!("{}/health", base)).unwrap();
+    assert!(response.headers().get("set-cookie").is_none());
+
+    let client = reqwest::blocking::Client::new();
+    let session_cookie = |response: &reqwest::blocking::Response| {
+        let header = response.headers()["set-cookie"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(header.contains("; HttpOnly"), "{}", header);
+        assert!(header.contains("SameSite=Lax"), "{}", header);
+        header.split(';').next().unwrap().to_string()
+    };
+    let count = |cookie: &str| {
+        let response = client
+            .get(format!("{}/count", base))
+            .header("cookie", cookie)
+            .send()
+            .unwrap();
+        response.json::<serde_json::Value>().unwrap()
+    };
+
+    // The first write creates the session; later requests on any worker see it
+    let response = client.get(format!("{}/count", base)).send().unwrap();
+    let anonymous = session_cookie(&response);
+    assert_eq!(anonymous.len(), "ntnt_session=".len() + 64, "{}", anonymous);
+    for expected in 2..6 {
+        let json = count(&anonymous);
+        assert_eq!(json["count"], expected);
+        assert_eq!(json["user"], false);
+    }
+
+    // Signing in moves the data to a new ID and retires the old one
+    let response = client
+        .get(format!("{}/login", base))
+        .header("cookie", &anonymous)
+        .send()
+        .unwrap();
+    let signed_in = session_cookie(&response);
+    assert_ne!(signed_in, anonymous);
+    let json = count(&signed_in);
+    assert_eq!(json["count"], 6);
+    assert_eq!(json["user"], true);
+    assert_eq!(count(&anonymous)["count"], 1);
+
+    // Logging out deletes the session and expires the cookie
+    let response = client
+        .get(format!("{}/logout", base))
+        .header("cookie", &signed_in)
+        .send()
+        .unwrap();
+    assert!(response.headers()["set-cookie"]
+        .to_str()
+        .unwrap()
+        .contains("Max-Age=0"));
+    assert_eq!(count(&signed_in)["count"], 1);
+
+    stop_app(child);
+    fs::remove_dir_all(app.parent().unwrap()).ok();
+}
+
+const ROUTER_APP: &str = r#"
+import { json, text } from "std/http/server"
+
+fn user(req) {
+    return json(map { "route": "user", "id": req.params["id"] })
+}
+
+fn user_by_name(req) {
+    return json(map { "route": "name", "name": req.params["name"] })
+}
+
+fn new_user(req) {
+    return json(map { "route": "new" })
+}
+
+fn create_user(req) {
+    return json(map { "route": "create" })
+}
+
+fn post(req) {
+    return json(map { "route": "post", "slug": req.params["slug"] })
+}
+
+fn file(req) {
+    return json(map { "route": "file", "path": req.params["path"] })
+}
+
+fn health(req) {
+    return text("ok")
+}
+
+get("/users/{name}", user_by_name)
+get("/users/{id:int}", user)
+get("/users/new", new_user)
+post("/users", create_user)
+get("/posts/{slug:[a-z-]+}", post)
+get("/files/{*path}", file)
+get("/health", health)
+listen(8080)
+"#;
+
+#[cfg(unix)]
+#[test]
+fn test_router_priority_typed_params_and_methods() {
+    let app = write_app("router", ROUTER_APP);
+    let port = 19918;
+    let base = format!("http://127.0.0.1:{}", port);
+    let child = start_app(&app, port);
+    assert!(
+        wait_for_server(&format!("{}/health", base), Duration::from_secs(10)),
+        "server did not start"
+    );
+
+    let get_json = |path: &str| -> serde_json::Value {
+        reqwest::blocking::get(format!("{}{}", base, path))
+            .unwrap()
+            .json()
+            .unwrap()
+    };
+
+    // Static beats typed, typed beats plain, catch-all takes the rest
+    assert_eq!(get_json("/users/new")["route"], "new");
+    assert_eq!(
+        get_json("/users/42"),
+        serde_json::json!({ "route": "user", "id": "42" })
+    );
+    assert_eq!(get_json("/users/ada")["route"], "name");
+    assert_eq!(get_json("/posts/hello-world")["slug"], "hello-world");
+    assert_eq!(
+        get_json("/files/docs/guide/intro.md")["path"],
+        "docs/guide/intro.md"
+    );
+    let response = reqwest::blocking::get(format!("{}/posts/Hello", base)).unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+
+    // Known path, wrong method: 405 with the allowed methods
+    let client = reqwest::blocking::Client::new();
+    let response = client.delete(format!("{}/users", base)).send().unwrap();
+    assert_eq!(response.status().as_u16(), 405);
+    assert_eq!(response.headers()["allow"], "OPTIONS, POST");
+
+    // OPTIONS and HEAD are answered automatically
+    let response = client
+        .request(reqwest::Method::OPTIONS, format!("{}/users/42", base))
+        .send()
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(response.headers()["allow"], "GET, HEAD, OPTIONS");
+    let response = client.head(format!("{}/users/42", base)).send().unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["content-type"], "application/json");
+    assert_eq!(response.text().unwrap(), "");
+
+    stop_app(child);
+    fs::remove_dir_all(app.parent().unwrap()).ok();
+}
+
+const GROUPS_APP: &str = r#"
+import { json, text, status } from "std/http/server"
+import { has_key } from "std/collections"
+
+fn block(req) {
+    if has_key(req.headers, "x-block") {
+        return status(401, "Blocked")
+    }
+    return req
+}
+
+fn require_admin(req) {
+    if !has_key(req.headers, "x-admin") {
+        return status(403, "Forbidden")
+    }
+    return req
+}
+
+fn list_users(req) {
+    return json(map { "org": req.params["org"] })
+}
+
+fn health(req) {
+    return text("ok")
+}
+
+use_middleware(block)
+group("/orgs/{org}/admin", [require_admin], fn() {
+    get("/users", list_users)
+    group("/v2", [], fn() {
+        get("/users", list_users)
+    })
+})
+get("/health", health)
+routes("routes")
+listen(8080)
+"#;
+
+#[cfg(unix)]
+#[test]
+fn test_route_groups_and_subtree_middleware() {
+    let app = write_app("groups", GROUPS_APP);
+    let dir = app.parent().unwrap();
+    fs::create_dir_all(dir.join("routes/admin")).unwrap();
+    fs::write(
+        dir.join("routes/admin/_middleware.tnt"),
+        r#"
+import { status } from "std/http/server"
+import { has_key } from "std/collections"
+fn middleware(req) {
+    if !has_key(req.headers, "x-admin") {
+        return status(403, "Forbidden")
+    }
+    return req
+}
+"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.join("routes/admin/stats.tnt"),
+        "import { text } from \"std/http/server\"\nfn get(req) { return text(\"stats\") }\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("routes/about.tnt"),
+        "import { text } from \"std/http/server\"\nfn get(req) { return text(\"about\") }\n",
+    )
+    .unwrap();
+
+    let port = 19919;
+    let base = format!("http://127.0.0.1:{}", port);
+    let child = start_app(&app, port);
+    assert!(
+        wait_for_server(&format!("{}/health", base), Duration::from_secs(10)),
+        "server did not start"
+    );
+
+    let client = reqwest::blocking::Client::new();
+    let status_of = |path: &str, headers: &[(&str, &str)]| -> u16 {
+        let mut request = client.get(format!("{}{}", base, path));
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        request.send().unwrap().status().as_u16()
+    };
+
+    // Group prefix (with a parameter) and group middleware
+    assert_eq!(status_of("/orgs/acme/admin/users", &[]), 403);
+    let body: serde_json::Value = client
+        .get(format!("{}/orgs/acme/admin/v2/users", base))
+        .header("x-admin", "1")
+        .send()
+        .unwrap()
+        .json()
+        .unwrap();
+    assert_eq!(body["org"], "acme");
+    // Global middleware runs first
+    assert_eq!(
+        status_of(
+            "/orgs/acme/admin/users",
+            &[("x-admin", "1"), ("x-block", "1")]
+        ),
+        401
+    );
+    // Routes outside the group are unaffected
+    assert_eq!(status_of("/health", &[]), 200);
+    assert_eq!(status_of("/users", &[]), 404);
+
+    // _middleware.tnt guards its subtree only
+    assert_eq!(status_of("/admin/stats", &[]), 403);
+    assert_eq!(status_of("/admin/stats", &[("x-admin", "1")]), 200);
+    assert_eq!(status_of("/about", &[]), 200);
+
+    stop_app(child);
+
+    // inspect reports the effective middleware stack per route
+    let output = Command::new(ntnt_binary())
+        .args(["inspect", &app.to_string_lossy()])
+        .output()
+        .unwrap();
+    let inspect: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let stack = |path: &str| -> serde_json::Value {
+        inspect["routes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|r| r["path"] == path)
+            .map(|r| r["middleware"].clone())
+            .unwrap_or_else(|| panic!("no route {} in {}", path, inspect["routes"]))
+    };
+    assert_eq!(
+        stack("/orgs/{org}/admin/v2/users"),
+        serde_json::json!(["block", "require_admin"])
+    );
+    assert_eq!(stack("/health"), serde_json::json!(["block"]));
+
+    fs::remove_dir_all(dir).ok();
+}
+
+const HTTPS_APP: &str = r#"
+import { json } from "std/http/server"
+
+fn info(req) {
+    return json(map { "protocol": req.protocol, "path": req.path })
+}
+
+get("/info", info)
+listen(8443, map { "tls_cert": "cert.pem", "tls_key": "key.pem", "redirect_http": 19921 })
+"#;
+
+#[cfg(unix)]
+#[test]
+fn test_https_with_self_signed_certificate() {
+    let app = write_app("https", HTTPS_APP);
+    let dir = app.parent().unwrap();
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    fs::write(dir.join("cert.pem"), cert.cert.pem()).unwrap();
+    fs::write(dir.join("key.pem"), cert.key_pair.serialize_pem()).unwrap();
+
+    let port = 19920;
+    let child = start_app(&app, port);
+    let client = reqwest::blocking::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let url = format!("https://localhost:{}/info?a=1", port);
+    let start = Instant::now();
+    let response = loop {
+        match client.get(&url).send() {
+            Ok(response) => break response,
+            Err(_) if start.elapsed() < Duration::from_secs(10) => {
+                thread::sleep(Duration::from_millis(100))
+            }
+            Err(e) => panic!("server did not start: {}", e),
+        }
+    };
+    let body: serde_json::Value = response.json().unwrap();
+    assert_eq!(body["protocol"], "https");
+    assert_eq!(body["path"], "/info");
+
+    // Plain HTTP on the TLS port fails; the redirect port sends clients to HTTPS
+    assert!(client
+        .get(format!("http://localhost:{}/info", port))
+        .send()
+        .is_err());
+    let response = client
+        .get("http://localhost:19921/info?a=1")
+        .send()
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 308);
+    assert_eq!(
+        response.headers()["location"],
+        format!("https://localhost:{}/info?a=1", port).as_str()
+    );
+
+    stop_app(child);
+    fs::remove_dir_all(dir).ok();
+}
+
+const REQUEST_INFO_APP: &str = r#"
+import { json } from "std/http/server"
+
+fn info(req) {
+    return json(map {
+        "ip": req.ip,
+        "protocol": req.protocol,
+        "query_params": req.query_params,
+        "query_all": req.query_all
+    })
+}
+
+get("/info", info)
+listen(8080, map { "trusted_proxies": ["127.0.0.1", "10.0.0.0/8"] })
+"#;
+
+#[cfg(unix)]
+#[test]
+fn test_query_decoding_and_client_ip() {
+    let app = write_app("request_info", REQUEST_INFO_APP);
+    let port = 19922;
+    let base = format!("http://127.0.0.1:{}", port);
+    let child = start_app(&app, port);
+    assert!(
+        wait_for_server(&format!("{}/info", base), Duration::from_secs(10)),
+        "server did not start"
+    );
+
+    let client = reqwest::blocking::Client::new();
+    let info = |query: &str, headers: &[(&str, &str)]| -> serde_json::Value {
+        let mut request = client.get(format!("{}/info{}", base, query));
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        request.send().unwrap().json().unwrap()
+    };
+
+    // Percent-decoding, repeated keys and flags
+    let body = info("?q=caf%C3%A9+au+lait&tag=a&tag=b&debug", &[]);
+    assert_eq!(body["query_params"]["q"], "café au lait");
+    assert_eq!(body["query_params"]["tag"], "b");
+    assert_eq!(body["query_params"]["debug"], "");
+    assert_eq!(body["query_all"]["tag"], serde_json::json!(["a", "b"]));
+
+    // The peer address, not "unknown"
+    assert_eq!(body["ip"], "127.0.0.1");
+    assert_eq!(body["protocol"], "http");
+
+    // The nearest hop that isn't a trusted proxy
+    let body = info(
+        "",
+        &[
+            ("x-forwarded-for", "198.51.100.1, 203.0.113.7, 10.1.2.3"),
+            ("x-forwarded-proto", "https"),
+        ],
+    );
+    assert_eq!(body["ip"], "203.0.113.7");
+    assert_eq!(body["protocol"], "https");
+    let body = info(
+        "",
+        &[("forwarded", "for=\"[2001:db8::1]:4711\";proto=https")],
+    );
+    assert_eq!(body["ip"], "2001:db8::1");
+
+    stop_app(child);
+    fs::remove_dir_all(app.parent().unwrap()).ok();
+}
+
+#[test]
+fn test_conflicting_routes_are_rejected() {
+    let app = write_app(
+        "router_conflict",
+        r#"
+import { text } from "std/http/server"
+fn a(req) { return text("a") }
+fn b(req) { return text("b") }
+get("/users/{id}", a)
+get("/users/{user_id}", b)
+"#,
+    );
+    let output = Command::new(ntnt_binary())
+        .args(["run", &app.to_string_lossy()])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("Route GET /users/{user_id} conflicts with GET /users/{id}"),
+        "{}",
+        stderr
+    );
+    fs::remove_dir_all(app.parent().unwrap()).ok();
+}
@@ -645,3 +645,102 @@ print(increment(5))
         "Contracts with old() should produce zero type errors"
     );
 }
+
+// ============================================================================
+// Cross-file type propagation
+// ============================================================================
+
+#[test]
+fn test_lint_imported_struct_enum_alias_and_trait() {
+    let dir = std::env::temp_dir().join(format!(
+        "ntnt_typecheck_imports_{}_{}",
+        std::process::id(),
+        TEST_COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    fs::create_dir_all(dir.join("lib")).unwrap();
+
+    // models.tnt and ids.tnt import each other
+    fs::write(
+        dir.join("lib/models.tnt"),
+        r#"import { Id } from "./ids"
+struct User { name: String, id: Id }
+enum Role { Admin, Member(Int) }
+type Email = String where len(self) > 3
+trait Describe { fn describe(self) -> String }
+"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.join("lib/ids.tnt"),
+        r#"import { User } from "./models"
+type Id = Int where self > 0
+"#,
+    )
+    .unwrap();
+    let main = dir.join("main.tnt");
+    fs::write(
+        &main,
+        r#"import { User, Role, Email, Describe } from "./lib/models"
+
+fn show(u: User) -> String {
+    return u.nmae
+}
+
+fn level(r: Role) {
+    match r {
+        Role::Member(n) => {
+            let s: String = n
+        },
+        _ => {}
+    }
+}
+
+let e: Email = "ab"
+
+impl Describe for User { }
+"#,
+    )
+    .unwrap();
+
+    let (stdout, _stderr, _code) = run_ntnt(&["lint", main.to_str().unwrap()]);
+    fs::remove_dir_all(&dir).ok();
+
+    let json: serde_json::Value =
+        serde_json::from_str(&stdout).expect("lint should output valid JSON");
+    let issues = json["files"][0]["issues"].as_array().unwrap();
+    let messages: Vec<&str> = issues
+        .iter()
+        .filter(|i| i["severity"] == "error")
+        .filter_map(|i| i["message"].as_str())
+        .collect();
+
+    assert!(
+        messages.contains(&"Struct 'User' has no field 'nmae'"),
+        "{:?}",
+        messages
+    );
+    assert!(
+        messages
+            .contains(&"Type mismatch: variable 's' declared as String but initialized with Int"),
+        "Enum variant fields should carry imported types: {:?}",
+        messages
+    );
+    assert!(
+        messages.contains(&"Value \"ab\" does not satisfy refinement type 'Email'"),
+        "{:?}",
+        messages
+    );
+    assert!(
+        messages.contains(&"impl Describe for User is missing method 'describe'"),
+        "{:?}",
+        messages
+    );
+    assert!(
+        !issues.iter().any(|i| i["message"]
+            .as_str()
+            .unwrap_or("")
+            .starts_with("Unused import")),
+        "Types used in annotations count as used imports: {:?}",
+        issues
+    );
+}